@@ -0,0 +1,102 @@
+use std::collections::HashMap;
+
+// ============================================================================
+// GATE ALIASES (VENDOR GATE-NAME NORMALIZATION)
+// ============================================================================
+
+/// Names the transpiler uses for standard gates. Vendor spellings that differ
+/// only in case (`CX`, `H`) are folded onto these.
+pub const STANDARD_GATES: &[&str] = &[
+    "id", "x", "y", "z", "h", "s", "sdg", "t", "tdg", "rx", "ry", "rz", "cx", "cy", "cz", "cp",
+    "cu1", "crz", "ccx", "swap", "rxx", "rzz",
+];
+
+/// Translates vendor gate names to the transpiler's own when parsing and,
+/// optionally, back to a vendor's spelling when emitting.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GateAliases {
+    /// Alias to canonical name, keyed in lowercase; lookups ignore case.
+    pub parse: HashMap<String, String>,
+    /// Canonical name to the name written out. Gates without an entry keep
+    /// their canonical name.
+    pub emit: HashMap<String, String>,
+}
+
+impl Default for GateAliases {
+    /// Common alternative spellings: `cnot`, `toffoli`/`ccnot`, `cphase` and
+    /// the Mølmer–Sørensen gate as `ms` or `xx` (read as `rxx`).
+    fn default() -> Self {
+        Self::empty()
+            .with_alias("cnot", "cx")
+            .with_alias("toffoli", "ccx")
+            .with_alias("ccnot", "ccx")
+            .with_alias("cphase", "cp")
+            .with_alias("ms", "rxx")
+            .with_alias("xx", "rxx")
+            .with_alias("molmer_sorensen", "rxx")
+    }
+}
+
+impl GateAliases {
+    /// No aliases; only case is folded.
+    pub fn empty() -> Self {
+        Self {
+            parse: HashMap::new(),
+            emit: HashMap::new(),
+        }
+    }
+
+    /// Reads `alias` as the gate `canonical`.
+    pub fn with_alias(mut self, alias: &str, canonical: &str) -> Self {
+        self.parse
+            .insert(alias.to_ascii_lowercase(), canonical.to_string());
+        self
+    }
+
+    /// Writes the gate `canonical` as `vendor`.
+    pub fn with_emit_name(mut self, canonical: &str, vendor: &str) -> Self {
+        self.emit.insert(canonical.to_string(), vendor.to_string());
+        self
+    }
+
+    /// Name the transpiler uses for the gate spelled `name` in the input.
+    pub fn canonical<'a>(&'a self, name: &'a str) -> &'a str {
+        let lower = name.to_ascii_lowercase();
+        if let Some(canonical) = self.parse.get(&lower) {
+            return canonical;
+        }
+        match STANDARD_GATES.iter().find(|g| **g == lower) {
+            Some(standard) => standard,
+            None => name,
+        }
+    }
+
+    /// Name written out for the gate `canonical`.
+    pub fn vendor<'a>(&'a self, canonical: &'a str) -> &'a str {
+        self.emit.get(canonical).map_or(canonical, String::as_str)
+    }
+
+    /// Adds the entries of an alias table, one per line: `alias = canonical`
+    /// to read `alias` as `canonical`, or `emit canonical = vendor` to write
+    /// `canonical` as `vendor`. Blank lines and `#` comments are ignored.
+    pub fn extend_from_table(mut self, text: &str) -> Result<Self, String> {
+        for (i, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (lhs, rhs) = line
+                .split_once('=')
+                .map(|(l, r)| (l.trim(), r.trim()))
+                .filter(|(l, r)| !l.is_empty() && !r.is_empty())
+                .ok_or_else(|| {
+                    format!("Line {}: expected `alias = canonical`, got '{line}'", i + 1)
+                })?;
+            self = match lhs.strip_prefix("emit ") {
+                Some(canonical) => self.with_emit_name(canonical.trim(), rhs),
+                None => self.with_alias(lhs, rhs),
+            };
+        }
+        Ok(self)
+    }
+}
@@ -0,0 +1,467 @@
+use core::f64::consts::PI;
+use core::fmt;
+
+use crate::prelude::*;
+use crate::Param;
+
+// ============================================================================
+// EXACT ANGLES (RATIONAL MULTIPLES OF PI)
+// ============================================================================
+
+/// Tolerance used by `Param::is_zero` and float comparisons of angles.
+pub const DEFAULT_ANGLE_TOLERANCE: f64 = 1e-10;
+
+/// Reduced fraction with a positive denominator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rational {
+    pub num: i64,
+    pub den: i64,
+}
+
+impl Rational {
+    pub const ZERO: Rational = Rational { num: 0, den: 1 };
+    pub const ONE: Rational = Rational { num: 1, den: 1 };
+
+    /// `num / den` in lowest terms, or `None` if `den` is zero.
+    pub fn new(num: i64, den: i64) -> Option<Self> {
+        if den == 0 {
+            return None;
+        }
+        let g = gcd(num.unsigned_abs(), den.unsigned_abs()).max(1) as i64;
+        let sign = if den < 0 { -1 } else { 1 };
+        Some(Self {
+            num: sign * num / g,
+            den: sign * den / g,
+        })
+    }
+
+    pub fn integer(n: i64) -> Self {
+        Self { num: n, den: 1 }
+    }
+
+    pub fn is_zero(self) -> bool {
+        self.num == 0
+    }
+
+    pub fn to_f64(self) -> f64 {
+        self.num as f64 / self.den as f64
+    }
+
+    /// Exact sum; `None` on overflow, where callers fall back to floats.
+    pub fn checked_add(self, other: Rational) -> Option<Rational> {
+        let num = self
+            .num
+            .checked_mul(other.den)?
+            .checked_add(other.num.checked_mul(self.den)?)?;
+        Rational::new(num, self.den.checked_mul(other.den)?)
+    }
+
+    pub fn checked_mul(self, other: Rational) -> Option<Rational> {
+        Rational::new(
+            self.num.checked_mul(other.num)?,
+            self.den.checked_mul(other.den)?,
+        )
+    }
+
+    pub fn checked_div(self, other: Rational) -> Option<Rational> {
+        Rational::new(
+            self.num.checked_mul(other.den)?,
+            self.den.checked_mul(other.num)?,
+        )
+    }
+
+    /// Closest fraction with denominator at most `max_den` that lies within
+    /// `tolerance` of `x`, preferring small denominators.
+    pub fn approximate(x: f64, max_den: i64, tolerance: f64) -> Option<Rational> {
+        if !x.is_finite() {
+            return None;
+        }
+        (1..=max_den.max(1)).find_map(|den| {
+            let scaled = x * den as f64;
+            if scaled.abs() >= i64::MAX as f64 {
+                return None;
+            }
+            // Rounds half away from zero, as `f64::round` does, without `std`.
+            let num = (scaled + 0.5f64.copysign(scaled)) as i64;
+            if (num as f64 / den as f64 - x).abs() > tolerance {
+                return None;
+            }
+            Rational::new(num, den)
+        })
+    }
+}
+
+fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+impl core::ops::Neg for Rational {
+    type Output = Rational;
+    fn neg(self) -> Rational {
+        Rational {
+            num: -self.num,
+            den: self.den,
+        }
+    }
+}
+
+impl fmt::Display for Rational {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.den == 1 {
+            write!(f, "{}", self.num)
+        } else {
+            write!(f, "{}/{}", self.num, self.den)
+        }
+    }
+}
+
+/// How the parser represents numeric angles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AngleMode {
+    /// Every angle becomes an `f64`.
+    #[default]
+    Float,
+    /// Angles that are rational multiples of pi (written as such, or floats
+    /// within the tolerance of one) are kept exactly as `Param::Pi`.
+    ExactPi,
+}
+
+/// Unit numeric angles are written in. Expressions involving `pi` are always
+/// radians.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AngleUnit {
+    #[default]
+    Radians,
+    Degrees,
+    /// Full turns, 1 being 2*pi.
+    Turns,
+}
+
+impl AngleUnit {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "rad" | "radians" => Some(AngleUnit::Radians),
+            "deg" | "degrees" => Some(AngleUnit::Degrees),
+            "turn" | "turns" => Some(AngleUnit::Turns),
+            _ => None,
+        }
+    }
+
+    /// One unit as a multiple of pi, exact where the unit allows.
+    fn pi_multiple(self) -> Option<Rational> {
+        match self {
+            AngleUnit::Radians => None,
+            AngleUnit::Degrees => Some(Rational { num: 1, den: 180 }),
+            AngleUnit::Turns => Some(Rational::integer(2)),
+        }
+    }
+
+    pub fn to_radians(self, x: f64) -> f64 {
+        self.pi_multiple().map_or(x, |r| x * r.to_f64() * PI)
+    }
+
+    pub fn from_radians(self, x: f64) -> f64 {
+        self.pi_multiple().map_or(x, |r| x / (r.to_f64() * PI))
+    }
+
+    /// `param`, an angle in radians, written in this unit. Symbols are left
+    /// alone: their values are bound in radians.
+    pub fn express(self, param: &Param) -> Param {
+        match (self.pi_multiple(), param) {
+            (None, p) | (_, p @ (Param::Symbol { .. } | Param::Duration(_))) => p.clone(),
+            (Some(unit), Param::Pi(r)) => match r.checked_div(unit) {
+                Some(q) => Param::Value(q.to_f64()),
+                None => Param::Value(self.from_radians(r.to_f64() * PI)),
+            },
+            (Some(_), Param::Value(v)) => Param::Value(self.from_radians(*v)),
+        }
+    }
+
+    /// Reads a parsed number in this unit as radians.
+    fn apply(self, value: AngleValue) -> AngleValue {
+        match (self.pi_multiple(), value) {
+            (Some(unit), AngleValue::Exact { pi: false, coeff }) => match coeff.checked_mul(unit) {
+                Some(coeff) => AngleValue::Exact { pi: true, coeff },
+                None => AngleValue::Float(self.to_radians(coeff.to_f64())),
+            },
+            (Some(_), AngleValue::Float(v)) => AngleValue::Float(self.to_radians(v)),
+            (_, v) => v,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AngleOptions {
+    pub mode: AngleMode,
+    /// Unit of numbers written without `pi`.
+    pub unit: AngleUnit,
+    /// How far a float may be from `k*pi` and still be read as exactly that.
+    pub tolerance: f64,
+    /// Largest denominator considered when recognizing `k*pi` in a float.
+    pub max_denominator: i64,
+}
+
+impl Default for AngleOptions {
+    fn default() -> Self {
+        Self {
+            mode: AngleMode::Float,
+            unit: AngleUnit::Radians,
+            tolerance: 1e-9,
+            max_denominator: 64,
+        }
+    }
+}
+
+impl AngleOptions {
+    pub fn exact_pi() -> Self {
+        Self {
+            mode: AngleMode::ExactPi,
+            ..Self::default()
+        }
+    }
+
+    /// Parses a parameter expression such as `0.5`, `pi/4`, `-3*pi/8`,
+    /// `2*theta` or `(pi + pi/2) / 3`.
+    pub fn parse(&self, text: &str) -> Result<Param, String> {
+        let tokens = tokenize(text)?;
+        let mut parser = AngleParser { tokens, pos: 0 };
+        let value = parser.parse_sum()?;
+        if parser.pos != parser.tokens.len() {
+            return Err(format!("Unexpected trailing input in parameter: {text}"));
+        }
+        Ok(self.finish(self.unit.apply(value)))
+    }
+
+    /// Applies the mode to a float angle.
+    pub fn from_f64(&self, value: f64) -> Param {
+        self.finish(AngleValue::Float(value))
+    }
+
+    fn finish(&self, value: AngleValue) -> Param {
+        match (self.mode, value) {
+            (_, AngleValue::Symbol(name, scale)) => Param::Symbol { name, scale },
+            (AngleMode::ExactPi, AngleValue::Exact { pi: true, coeff }) => Param::Pi(coeff),
+            (AngleMode::ExactPi, v) => {
+                let x = v.to_f64();
+                match Rational::approximate(x / PI, self.max_denominator, self.tolerance / PI) {
+                    Some(coeff) => Param::Pi(coeff),
+                    None => Param::Value(x),
+                }
+            }
+            (AngleMode::Float, v) => Param::Value(v.to_f64()),
+        }
+    }
+}
+
+/// Intermediate value of a parameter expression: exact `coeff` (times pi
+/// if `pi`) for as long as the arithmetic allows, a float otherwise.
+#[derive(Debug, Clone, PartialEq)]
+enum AngleValue {
+    Exact { pi: bool, coeff: Rational },
+    Float(f64),
+    Symbol(String, f64),
+}
+
+impl AngleValue {
+    fn to_f64(&self) -> f64 {
+        match self {
+            AngleValue::Exact { pi, coeff } => coeff.to_f64() * if *pi { PI } else { 1.0 },
+            AngleValue::Float(v) => *v,
+            AngleValue::Symbol(..) => f64::NAN,
+        }
+    }
+
+    fn add(self, other: AngleValue) -> Result<AngleValue, String> {
+        use AngleValue::*;
+        Ok(match (self, other) {
+            (Exact { pi: p1, coeff: a }, Exact { pi: p2, coeff: b }) if p1 == p2 => {
+                match a.checked_add(b) {
+                    Some(coeff) => Exact { pi: p1, coeff },
+                    None => Float((a.to_f64() + b.to_f64()) * if p1 { PI } else { 1.0 }),
+                }
+            }
+            (Symbol(..), _) | (_, Symbol(..)) => {
+                return Err("Symbolic parameters can only be scaled".to_string())
+            }
+            (a, b) => Float(a.to_f64() + b.to_f64()),
+        })
+    }
+
+    fn mul(self, other: AngleValue) -> Result<AngleValue, String> {
+        use AngleValue::*;
+        Ok(match (self, other) {
+            (Exact { pi: p1, coeff: a }, Exact { pi: p2, coeff: b }) if !(p1 && p2) => {
+                match a.checked_mul(b) {
+                    Some(coeff) => Exact {
+                        pi: p1 || p2,
+                        coeff,
+                    },
+                    None => Float(
+                        Exact { pi: p1, coeff: a }.to_f64() * Exact { pi: p2, coeff: b }.to_f64(),
+                    ),
+                }
+            }
+            (Symbol(name, s), other) | (other, Symbol(name, s)) => match other {
+                Symbol(..) => {
+                    return Err("Products of symbolic parameters are not supported".to_string())
+                }
+                v => Symbol(name, s * v.to_f64()),
+            },
+            (a, b) => Float(a.to_f64() * b.to_f64()),
+        })
+    }
+
+    fn div(self, other: AngleValue) -> Result<AngleValue, String> {
+        use AngleValue::*;
+        Ok(match (self, other) {
+            (_, Symbol(..)) => {
+                return Err("Division by a symbolic parameter is not supported".to_string())
+            }
+            (_, Exact { coeff, .. }) if coeff.is_zero() => {
+                return Err("Division by zero in parameter".to_string())
+            }
+            (Exact { pi: p1, coeff: a }, Exact { pi: p2, coeff: b }) if p1 || !p2 => {
+                match a.checked_div(b) {
+                    Some(coeff) => Exact {
+                        pi: p1 && !p2,
+                        coeff,
+                    },
+                    None => Float(a.to_f64() / b.to_f64() * if p1 && !p2 { PI } else { 1.0 }),
+                }
+            }
+            (Symbol(name, s), v) => Symbol(name, s / v.to_f64()),
+            (a, b) => Float(a.to_f64() / b.to_f64()),
+        })
+    }
+
+    fn neg(self) -> AngleValue {
+        match self {
+            AngleValue::Exact { pi, coeff } => AngleValue::Exact { pi, coeff: -coeff },
+            AngleValue::Float(v) => AngleValue::Float(-v),
+            AngleValue::Symbol(name, s) => AngleValue::Symbol(name, -s),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(String),
+    Ident(String),
+    Op(char),
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() || c == '.' {
+            let start = i;
+            while i < chars.len()
+                && (chars[i].is_ascii_digit()
+                    || chars[i] == '.'
+                    || chars[i] == 'e'
+                    || chars[i] == 'E'
+                    || ((chars[i] == '-' || chars[i] == '+') && matches!(chars[i - 1], 'e' | 'E')))
+            {
+                i += 1;
+            }
+            tokens.push(Token::Number(chars[start..i].iter().collect()));
+        } else if c.is_alphabetic() || c == '_' || c == 'π' {
+            let start = i;
+            while i < chars.len()
+                && (chars[i].is_alphanumeric()
+                    || chars[i] == '_'
+                    || chars[i] == '['
+                    || chars[i] == ']')
+            {
+                i += 1;
+            }
+            tokens.push(Token::Ident(chars[start..i].iter().collect()));
+        } else if "+-*/()".contains(c) {
+            tokens.push(Token::Op(c));
+            i += 1;
+        } else {
+            return Err(format!("Unexpected character '{c}' in parameter: {text}"));
+        }
+    }
+    Ok(tokens)
+}
+
+struct AngleParser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl AngleParser {
+    fn peek_op(&self) -> Option<char> {
+        match self.tokens.get(self.pos) {
+            Some(Token::Op(c)) => Some(*c),
+            _ => None,
+        }
+    }
+
+    fn parse_sum(&mut self) -> Result<AngleValue, String> {
+        let mut lhs = self.parse_product()?;
+        while let Some(op @ ('+' | '-')) = self.peek_op() {
+            self.pos += 1;
+            let rhs = self.parse_product()?;
+            lhs = lhs.add(if op == '-' { rhs.neg() } else { rhs })?;
+        }
+        Ok(lhs)
+    }
+
+    fn parse_product(&mut self) -> Result<AngleValue, String> {
+        let mut lhs = self.parse_unary()?;
+        while let Some(op @ ('*' | '/')) = self.peek_op() {
+            self.pos += 1;
+            let rhs = self.parse_unary()?;
+            lhs = if op == '*' {
+                lhs.mul(rhs)?
+            } else {
+                lhs.div(rhs)?
+            };
+        }
+        Ok(lhs)
+    }
+
+    fn parse_unary(&mut self) -> Result<AngleValue, String> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        match token {
+            Some(Token::Op('-')) => Ok(self.parse_unary()?.neg()),
+            Some(Token::Op('+')) => self.parse_unary(),
+            Some(Token::Op('(')) => {
+                let inner = self.parse_sum()?;
+                if self.peek_op() != Some(')') {
+                    return Err("Unbalanced parentheses in parameter".to_string());
+                }
+                self.pos += 1;
+                Ok(inner)
+            }
+            Some(Token::Number(text)) => match text.parse::<i64>() {
+                Ok(n) => Ok(AngleValue::Exact {
+                    pi: false,
+                    coeff: Rational::integer(n),
+                }),
+                Err(_) => text
+                    .parse::<f64>()
+                    .map(AngleValue::Float)
+                    .map_err(|_| format!("Invalid number in parameter: {text}")),
+            },
+            Some(Token::Ident(name)) if name == "pi" || name == "π" => Ok(AngleValue::Exact {
+                pi: true,
+                coeff: Rational::ONE,
+            }),
+            Some(Token::Ident(name)) => Ok(AngleValue::Symbol(name, 1.0)),
+            Some(t) => Err(format!("Unexpected token {t:?} in parameter")),
+            None => Err("Unexpected end of parameter".to_string()),
+        }
+    }
+}
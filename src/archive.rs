@@ -0,0 +1,429 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::calibration::CalibrationSnapshot;
+use crate::json::JsonValue;
+use crate::layout::{Layout, PhysicalCircuit, PhysicalQubit};
+use crate::scheduling::TimingConstraints;
+use crate::{BackendSpec, QuantumCircuit, TranspilationResult, TranspilationStats};
+
+// ============================================================================
+// VERSIONED TRANSPILATION ARCHIVES
+// ============================================================================
+//
+// A JSON file bundling everything needed to audit a transpilation later:
+//
+//   {
+//     "format": "transpilation-archive",
+//     "version": 1,
+//     "crate_version": "0.1.0",
+//     "input_hash": "fnv1a64:…",
+//     "config": { "router": "swap-chain", … },
+//     "backend": { "name": …, "coupling_map": [[0, 1], …], … },
+//     "circuit": "ir 1\nqubits 5\n…",
+//     "initial_layout": [0, 1, 2],
+//     "final_layout": [1, 0, 2],
+//     "stats": { "original_depth": …, … }
+//   }
+//
+// Fields are only ever added, never renamed or removed, and the reader
+// defaults any field a file lacks, so archives of every earlier version stay
+// readable. An archive from a newer version is rejected rather than misread.
+
+pub const ARCHIVE_FORMAT: &str = "transpilation-archive";
+/// Version written by this build; the reader accepts this and all earlier ones.
+pub const ARCHIVE_VERSION: usize = 1;
+
+/// A transpilation as it was run: a hash of the input, the settings, the
+/// backend at the time, and the result.
+pub struct TranspilationArchive {
+    /// Format version the archive was written in.
+    pub version: usize,
+    /// Version of the transpiler that wrote it.
+    pub crate_version: String,
+    /// `fnv1a64:` and the FNV-1a hash of the input source, in hex.
+    pub input_hash: String,
+    /// Settings the transpilation ran with, e.g. `router` or `passes`.
+    pub config: BTreeMap<String, String>,
+    pub backend: BackendSpec,
+    pub circuit: PhysicalCircuit,
+    pub initial_layout: Layout,
+    pub final_layout: Layout,
+    pub stats: TranspilationStats,
+}
+
+/// 64-bit FNV-1a, written out here so hashes never change with the standard
+/// library's hasher.
+pub fn input_hash(source: &str) -> String {
+    let hash = source.bytes().fold(0xcbf2_9ce4_8422_2325u64, |h, b| {
+        (h ^ b as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    });
+    format!("fnv1a64:{hash:016x}")
+}
+
+impl TranspilationArchive {
+    /// Archives `result`, produced from `source` (the QASM text, or for a
+    /// circuit built in memory its `to_ir()`) on `backend`.
+    pub fn new(
+        result: &TranspilationResult,
+        source: &str,
+        backend: &BackendSpec,
+        config: BTreeMap<String, String>,
+    ) -> Self {
+        Self {
+            version: ARCHIVE_VERSION,
+            crate_version: env!("CARGO_PKG_VERSION").to_string(),
+            input_hash: input_hash(source),
+            config,
+            backend: backend.clone(),
+            circuit: result.circuit.clone(),
+            initial_layout: result.initial_layout.clone(),
+            final_layout: result.final_layout.clone(),
+            stats: result.stats.clone(),
+        }
+    }
+
+    /// Whether `source` is the input this archive was made from.
+    pub fn matches_input(&self, source: &str) -> bool {
+        self.input_hash == input_hash(source)
+    }
+
+    pub fn to_json(&self) -> JsonValue {
+        let layout = |l: &Layout| JsonValue::Array(l.iter().map(|(_, p)| p.0.into()).collect());
+        let stats = &self.stats;
+        JsonValue::object([
+            ("format", JsonValue::string(ARCHIVE_FORMAT)),
+            ("version", self.version.into()),
+            (
+                "crate_version",
+                JsonValue::string(self.crate_version.as_str()),
+            ),
+            ("input_hash", JsonValue::string(self.input_hash.as_str())),
+            (
+                "config",
+                JsonValue::object(
+                    self.config
+                        .iter()
+                        .map(|(k, v)| (k.as_str(), JsonValue::string(v.as_str()))),
+                ),
+            ),
+            ("backend", backend_to_json(&self.backend)),
+            ("circuit", JsonValue::string(self.circuit.to_ir())),
+            ("initial_layout", layout(&self.initial_layout)),
+            ("final_layout", layout(&self.final_layout)),
+            (
+                "stats",
+                JsonValue::object([
+                    ("original_depth", stats.original_depth.into()),
+                    ("final_depth", stats.final_depth.into()),
+                    ("original_gate_count", stats.original_gate_count.into()),
+                    ("final_gate_count", stats.final_gate_count.into()),
+                    ("depth_reduction", stats.depth_reduction.into()),
+                    ("gate_reduction", stats.gate_reduction.into()),
+                    ("skipped_passes", stats.skipped_passes.into()),
+                ]),
+            ),
+        ])
+    }
+
+    /// Reads an archive of this or any earlier version.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let json = JsonValue::parse(text)?;
+        if json.get("format").and_then(JsonValue::as_str) != Some(ARCHIVE_FORMAT) {
+            return Err(format!("Not a {ARCHIVE_FORMAT} file"));
+        }
+        let version = json
+            .get("version")
+            .and_then(JsonValue::as_usize)
+            .ok_or("Archive has no version")?;
+        if version > ARCHIVE_VERSION {
+            return Err(format!(
+                "Archive version {version} is newer than the {ARCHIVE_VERSION} this build reads; upgrade to open it"
+            ));
+        }
+        let text_field = |key: &str| {
+            json.get(key)
+                .and_then(JsonValue::as_str)
+                .unwrap_or_default()
+                .to_string()
+        };
+        let config = match json.get("config") {
+            Some(JsonValue::Object(fields)) => fields
+                .iter()
+                .map(|(k, v)| {
+                    (
+                        k.clone(),
+                        v.as_str().map_or_else(|| v.to_string(), str::to_string),
+                    )
+                })
+                .collect(),
+            _ => BTreeMap::new(),
+        };
+        let backend = backend_from_json(json.get("backend").ok_or("Archive has no backend")?)?;
+        // The archived circuit is the transpiler's output, so it is physical.
+        let circuit = QuantumCircuit::from_ir(
+            json.get("circuit")
+                .and_then(JsonValue::as_str)
+                .ok_or("Archive has no circuit")?,
+        )
+        .map(PhysicalCircuit::assume_physical)
+        .map_err(|e| format!("Archived circuit: {e}"))?;
+        let layout = |key: &str| -> Result<Layout, String> {
+            let physical = json
+                .get(key)
+                .and_then(JsonValue::as_array)
+                .ok_or_else(|| format!("Archive has no {key}"))?
+                .iter()
+                .map(|p| {
+                    p.as_usize()
+                        .map(PhysicalQubit)
+                        .ok_or_else(|| format!("Invalid qubit in {key}"))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            Layout::from_physical(physical, backend.num_qubits)
+        };
+        let stats = json.get("stats");
+        let count = |key: &str| {
+            stats
+                .and_then(|s| s.get(key))
+                .and_then(JsonValue::as_usize)
+                .unwrap_or(0)
+        };
+        let ratio = |key: &str| {
+            stats
+                .and_then(|s| s.get(key))
+                .and_then(JsonValue::as_f64)
+                .unwrap_or(0.0)
+        };
+        Ok(Self {
+            version,
+            crate_version: text_field("crate_version"),
+            input_hash: text_field("input_hash"),
+            config,
+            initial_layout: layout("initial_layout")?,
+            final_layout: layout("final_layout")?,
+            backend,
+            circuit,
+            stats: TranspilationStats {
+                original_depth: count("original_depth"),
+                final_depth: count("final_depth"),
+                original_gate_count: count("original_gate_count"),
+                final_gate_count: count("final_gate_count"),
+                depth_reduction: ratio("depth_reduction"),
+                gate_reduction: ratio("gate_reduction"),
+                skipped_passes: count("skipped_passes"),
+            },
+        })
+    }
+}
+
+/// The backend as a JSON object, with sets and maps sorted so equal
+/// backends give equal text.
+fn backend_to_json(backend: &BackendSpec) -> JsonValue {
+    let pair = |(a, b): (usize, usize)| JsonValue::from(vec![a, b]);
+    let mut native: Vec<&str> = backend.native_gates.iter().map(String::as_str).collect();
+    native.sort_unstable();
+    let durations: BTreeMap<&str, u64> = backend
+        .gate_durations
+        .iter()
+        .map(|(k, &v)| (k.as_str(), v))
+        .collect();
+    let timing = backend.timing_constraints;
+    JsonValue::object([
+        ("name", JsonValue::string(backend.name.as_str())),
+        ("num_qubits", backend.num_qubits.into()),
+        (
+            "coupling_map",
+            JsonValue::Array(backend.coupling_map.iter().map(|&e| pair(e)).collect()),
+        ),
+        (
+            "native_gates",
+            JsonValue::Array(native.into_iter().map(JsonValue::string).collect()),
+        ),
+        (
+            "gate_durations",
+            JsonValue::object(durations.into_iter().map(|(k, v)| (k, v.into()))),
+        ),
+        ("dt", backend.dt.map_or(JsonValue::Null, Into::into)),
+        (
+            "timing_constraints",
+            JsonValue::object([
+                ("granularity", timing.granularity.into()),
+                ("min_length", timing.min_length.into()),
+                ("pulse_alignment", timing.pulse_alignment.into()),
+                ("acquire_alignment", timing.acquire_alignment.into()),
+            ]),
+        ),
+        (
+            "crosstalk_pairs",
+            JsonValue::Array(
+                backend
+                    .crosstalk_pairs
+                    .iter()
+                    .map(|&(a, b)| JsonValue::Array(vec![pair(a), pair(b)]))
+                    .collect(),
+            ),
+        ),
+        (
+            "supports_dynamic_circuits",
+            backend.supports_dynamic_circuits.into(),
+        ),
+        (
+            "calibration",
+            backend
+                .calibration
+                .as_ref()
+                .map_or(JsonValue::Null, calibration_to_json),
+        ),
+    ])
+}
+
+fn calibration_to_json(calibration: &CalibrationSnapshot) -> JsonValue {
+    let single: BTreeMap<usize, f64> = calibration
+        .single_qubit_errors
+        .iter()
+        .map(|(&q, &e)| (q, e))
+        .collect();
+    let two: BTreeMap<(usize, usize), f64> = calibration
+        .two_qubit_errors
+        .iter()
+        .map(|(&k, &e)| (k, e))
+        .collect();
+    JsonValue::object([
+        ("timestamp", calibration.timestamp.into()),
+        (
+            "single_qubit_errors",
+            JsonValue::Array(
+                single
+                    .into_iter()
+                    .map(|(q, e)| JsonValue::from(vec![q as f64, e]))
+                    .collect(),
+            ),
+        ),
+        (
+            "two_qubit_errors",
+            JsonValue::Array(
+                two.into_iter()
+                    .map(|((a, b), e)| JsonValue::from(vec![a as f64, b as f64, e]))
+                    .collect(),
+            ),
+        ),
+    ])
+}
+
+/// `[a, b]` as a pair of indices.
+fn index_pair(value: &JsonValue) -> Result<(usize, usize), String> {
+    match value.as_array() {
+        Some([a, b]) => a
+            .as_usize()
+            .zip(b.as_usize())
+            .ok_or_else(|| format!("Invalid qubit pair {value}")),
+        _ => Err(format!("Invalid qubit pair {value}")),
+    }
+}
+
+fn backend_from_json(json: &JsonValue) -> Result<BackendSpec, String> {
+    let array = |key: &str| {
+        json.get(key)
+            .and_then(JsonValue::as_array)
+            .unwrap_or_default()
+    };
+    let timing = json.get("timing_constraints");
+    let timing_field = |key: &str, default: u64| {
+        timing
+            .and_then(|t| t.get(key))
+            .and_then(JsonValue::as_usize)
+            .map_or(default, |v| v as u64)
+    };
+    let defaults = TimingConstraints::default();
+    let gate_durations: HashMap<String, u64> = match json.get("gate_durations") {
+        Some(JsonValue::Object(fields)) => fields
+            .iter()
+            .map(|(k, v)| {
+                v.as_usize()
+                    .map(|d| (k.clone(), d as u64))
+                    .ok_or_else(|| format!("Invalid duration of {k}"))
+            })
+            .collect::<Result<_, _>>()?,
+        _ => HashMap::new(),
+    };
+    Ok(BackendSpec {
+        name: json
+            .get("name")
+            .and_then(JsonValue::as_str)
+            .ok_or("Backend has no name")?
+            .to_string(),
+        num_qubits: json
+            .get("num_qubits")
+            .and_then(JsonValue::as_usize)
+            .ok_or("Backend has no qubit count")?,
+        coupling_map: array("coupling_map")
+            .iter()
+            .map(index_pair)
+            .collect::<Result<_, _>>()?,
+        native_gates: array("native_gates")
+            .iter()
+            .filter_map(JsonValue::as_str)
+            .map(str::to_string)
+            .collect(),
+        gate_durations,
+        dt: json.get("dt").and_then(JsonValue::as_f64),
+        timing_constraints: TimingConstraints {
+            granularity: timing_field("granularity", defaults.granularity),
+            min_length: timing_field("min_length", defaults.min_length),
+            pulse_alignment: timing_field("pulse_alignment", defaults.pulse_alignment),
+            acquire_alignment: timing_field("acquire_alignment", defaults.acquire_alignment),
+        },
+        crosstalk_pairs: array("crosstalk_pairs")
+            .iter()
+            .map(|p| match p.as_array() {
+                Some([a, b]) => Ok((index_pair(a)?, index_pair(b)?)),
+                _ => Err(format!("Invalid crosstalk pair {p}")),
+            })
+            .collect::<Result<_, _>>()?,
+        supports_dynamic_circuits: json
+            .get("supports_dynamic_circuits")
+            .and_then(JsonValue::as_bool)
+            .unwrap_or(false),
+        calibration: match json.get("calibration") {
+            None | Some(JsonValue::Null) => None,
+            Some(c) => Some(calibration_from_json(c)?),
+        },
+    })
+}
+
+fn calibration_from_json(json: &JsonValue) -> Result<CalibrationSnapshot, String> {
+    let timestamp = json
+        .get("timestamp")
+        .and_then(JsonValue::as_f64)
+        .ok_or("Calibration has no timestamp")?;
+    let mut calibration = CalibrationSnapshot::new(timestamp as u64);
+    for entry in json
+        .get("single_qubit_errors")
+        .and_then(JsonValue::as_array)
+        .unwrap_or_default()
+    {
+        match entry.as_array() {
+            Some([q, e]) => match (q.as_usize(), e.as_f64()) {
+                (Some(q), Some(e)) => calibration = calibration.with_single_qubit_error(q, e),
+                _ => return Err(format!("Invalid qubit error {entry}")),
+            },
+            _ => return Err(format!("Invalid qubit error {entry}")),
+        }
+    }
+    for entry in json
+        .get("two_qubit_errors")
+        .and_then(JsonValue::as_array)
+        .unwrap_or_default()
+    {
+        match entry.as_array() {
+            Some([a, b, e]) => match (a.as_usize(), b.as_usize(), e.as_f64()) {
+                (Some(a), Some(b), Some(e)) => {
+                    calibration = calibration.with_two_qubit_error(a, b, e)
+                }
+                _ => return Err(format!("Invalid edge error {entry}")),
+            },
+            _ => return Err(format!("Invalid edge error {entry}")),
+        }
+    }
+    Ok(calibration)
+}
@@ -0,0 +1,301 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use crate::composite::UnrollPass;
+use crate::layout::{Layout, PhysicalQubit, VirtualQubit};
+use crate::{
+    run_pass, BackendSpec, Gate, QuantumCircuit, TranspilationResult, UniversalTranspiler,
+};
+
+// ============================================================================
+// CALIBRATION SNAPSHOTS AND DRIFT
+// ============================================================================
+
+/// Error rates of a device as measured at one point in time. Qubits and
+/// edges without an entry use the default error rates.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct CalibrationSnapshot {
+    /// When the calibration was taken, in seconds since the Unix epoch.
+    pub timestamp: u64,
+    pub single_qubit_errors: HashMap<usize, f64>,
+    /// Keyed by edge with the smaller qubit first.
+    pub two_qubit_errors: HashMap<(usize, usize), f64>,
+}
+
+impl CalibrationSnapshot {
+    pub fn new(timestamp: u64) -> Self {
+        Self {
+            timestamp,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_single_qubit_error(mut self, qubit: usize, error: f64) -> Self {
+        self.single_qubit_errors.insert(qubit, error);
+        self
+    }
+
+    /// Sets the error of two-qubit gates on edge `a`-`b`, in either direction.
+    pub fn with_two_qubit_error(mut self, a: usize, b: usize, error: f64) -> Self {
+        self.two_qubit_errors.insert((a.min(b), a.max(b)), error);
+        self
+    }
+
+    pub fn single_qubit_error(&self, qubit: usize) -> Option<f64> {
+        self.single_qubit_errors.get(&qubit).copied()
+    }
+
+    pub fn two_qubit_error(&self, a: usize, b: usize) -> Option<f64> {
+        self.two_qubit_errors.get(&(a.min(b), a.max(b))).copied()
+    }
+}
+
+/// Qubits and edges a transpiled circuit uses whose error rate grew beyond
+/// the threshold between two snapshots.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DriftReport {
+    pub previous_timestamp: Option<u64>,
+    pub current_timestamp: u64,
+    /// Physical qubit with its previous and current single-qubit error.
+    pub degraded_qubits: Vec<(PhysicalQubit, f64, f64)>,
+    /// Edge with its previous and current two-qubit error.
+    pub degraded_edges: Vec<((PhysicalQubit, PhysicalQubit), f64, f64)>,
+}
+
+impl DriftReport {
+    pub fn is_degraded(&self) -> bool {
+        !self.degraded_qubits.is_empty() || !self.degraded_edges.is_empty()
+    }
+}
+
+impl fmt::Display for DriftReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let previous = self
+            .previous_timestamp
+            .map_or("defaults".to_string(), |t| t.to_string());
+        write!(
+            f,
+            "Calibration drift {previous} -> {}:",
+            self.current_timestamp
+        )?;
+        if !self.is_degraded() {
+            return write!(f, " none beyond threshold");
+        }
+        for (q, before, after) in &self.degraded_qubits {
+            write!(f, "\n  qubit {q}: {before:.2e} -> {after:.2e}")?;
+        }
+        for ((a, b), before, after) in &self.degraded_edges {
+            write!(f, "\n  edge {a}-{b}: {before:.2e} -> {after:.2e}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Calls `f` with every gate, including those in block bodies.
+fn for_each_gate(gates: &[Gate], f: &mut dyn FnMut(&Gate)) {
+    for g in gates {
+        match &g.block {
+            Some(block) => block
+                .bodies()
+                .into_iter()
+                .for_each(|b| for_each_gate(&b.gates, f)),
+            None => f(g),
+        }
+    }
+}
+
+/// Compares the error rates of the qubits and edges `result` runs on under
+/// `previous` (the backend it was transpiled for) and `current`. An error
+/// rate has degraded if it grew by more than `threshold`, relative to its
+/// previous value (0.5 means by more than half).
+pub fn check_drift(
+    result: &TranspilationResult,
+    previous: &BackendSpec,
+    current: &CalibrationSnapshot,
+    threshold: f64,
+) -> DriftReport {
+    let updated = previous.with_calibration(current.clone());
+    let mut qubits = HashSet::new();
+    let mut edges = HashSet::new();
+    for_each_gate(&result.circuit.gates, &mut |g| match g.qubits[..] {
+        [q] => {
+            qubits.insert(q);
+        }
+        [a, b, ..] => {
+            edges.insert((a.min(b), a.max(b)));
+        }
+        [] => {}
+    });
+    let worse = |before: f64, after: f64| after > before * (1.0 + threshold);
+
+    let mut degraded_qubits: Vec<_> = qubits
+        .into_iter()
+        .map(PhysicalQubit)
+        .map(|q| {
+            (
+                q,
+                previous.single_qubit_error(q),
+                updated.single_qubit_error(q),
+            )
+        })
+        .filter(|&(_, before, after)| worse(before, after))
+        .collect();
+    degraded_qubits.sort_by_key(|d| d.0);
+    let mut degraded_edges: Vec<_> = edges
+        .into_iter()
+        .map(|(a, b)| (PhysicalQubit(a), PhysicalQubit(b)))
+        .map(|(a, b)| {
+            (
+                (a, b),
+                previous.two_qubit_error(a, b),
+                updated.two_qubit_error(a, b),
+            )
+        })
+        .filter(|&(_, before, after)| worse(before, after))
+        .collect();
+    degraded_edges.sort_by_key(|d| d.0);
+
+    DriftReport {
+        previous_timestamp: previous.calibration.as_ref().map(|c| c.timestamp),
+        current_timestamp: current.timestamp,
+        degraded_qubits,
+        degraded_edges,
+    }
+}
+
+/// Two-qubit interactions between each pair of virtual qubits.
+fn interaction_counts(circuit: &QuantumCircuit) -> HashMap<(usize, usize), usize> {
+    let mut counts = HashMap::new();
+    for_each_gate(&circuit.gates, &mut |g| {
+        if let [a, b, ..] = g.qubits[..] {
+            *counts.entry((a.min(b), a.max(b))).or_insert(0) += 1;
+        }
+    });
+    counts
+}
+
+/// Moves the virtual qubits placed on degraded qubits, and the less busy end
+/// of each degraded edge, to healthy free qubits close to their interaction
+/// partners. Every other virtual qubit keeps its place.
+fn relayout(
+    circuit: &QuantumCircuit,
+    layout: &Layout,
+    backend: &BackendSpec,
+    report: &DriftReport,
+) -> Result<Layout, String> {
+    let interactions = interaction_counts(circuit);
+    let busyness = |v: VirtualQubit| -> usize {
+        interactions
+            .iter()
+            .filter(|((a, b), _)| *a == v.0 || *b == v.0)
+            .map(|(_, n)| n)
+            .sum()
+    };
+    let bad_qubits: HashSet<PhysicalQubit> = report.degraded_qubits.iter().map(|d| d.0).collect();
+    let bad_edges: HashSet<(PhysicalQubit, PhysicalQubit)> =
+        report.degraded_edges.iter().map(|d| d.0).collect();
+
+    let mut moving = Vec::new();
+    for &(q, ..) in &report.degraded_qubits {
+        moving.extend(layout.virtual_at(q));
+    }
+    for &((a, b), ..) in &report.degraded_edges {
+        let ends: Vec<VirtualQubit> = [a, b]
+            .iter()
+            .filter_map(|&p| layout.virtual_at(p))
+            .collect();
+        if ends.iter().any(|v| moving.contains(v)) {
+            continue;
+        }
+        moving.extend(
+            ends.into_iter()
+                .min_by_key(|&v| (busyness(v), std::cmp::Reverse(v))),
+        );
+    }
+    moving.sort();
+    moving.dedup();
+
+    let mut placement: Vec<Option<PhysicalQubit>> = layout
+        .iter()
+        .map(|(v, p)| (!moving.contains(&v)).then_some(p))
+        .collect();
+    let dist = backend.distance_matrix();
+    for &v in &moving {
+        let taken: HashSet<PhysicalQubit> = placement.iter().flatten().copied().collect();
+        // Interactions over degraded edges first, then distance to partners,
+        // then the qubit's own error in parts per billion.
+        let cost = |p: PhysicalQubit| -> (usize, usize, u64) {
+            let partners: Vec<(PhysicalQubit, usize)> = interactions
+                .iter()
+                .filter_map(|(&(a, b), &n)| match (a == v.0, b == v.0) {
+                    (true, _) => Some((b, n)),
+                    (_, true) => Some((a, n)),
+                    _ => None,
+                })
+                .filter_map(|(partner, n)| placement[partner].map(|q| (q, n)))
+                .collect();
+            let over_bad_edges = partners
+                .iter()
+                .filter(|(q, _)| bad_edges.contains(&(p.min(*q), p.max(*q))))
+                .map(|(_, n)| n)
+                .sum();
+            let distance = partners
+                .iter()
+                .map(|&(q, n)| n.saturating_mul(dist[p.0][q.0]))
+                .fold(0usize, usize::saturating_add);
+            (
+                over_bad_edges,
+                distance,
+                (backend.single_qubit_error(p) * 1e9) as u64,
+            )
+        };
+        let target = (0..backend.num_qubits)
+            .map(PhysicalQubit)
+            .filter(|p| !taken.contains(p) && !bad_qubits.contains(p))
+            .min_by_key(|&p| cost(p))
+            .ok_or_else(|| format!("No healthy free qubit on {} to move {v} to", backend.name))?;
+        placement[v.0] = Some(target);
+    }
+    let physical = placement
+        .into_iter()
+        .map(|p| p.expect("every virtual qubit is placed"))
+        .collect();
+    Layout::from_physical(physical, backend.num_qubits)
+}
+
+impl BackendSpec {
+    /// This backend with its error rates replaced by `snapshot`.
+    pub fn with_calibration(&self, snapshot: CalibrationSnapshot) -> BackendSpec {
+        BackendSpec {
+            calibration: Some(snapshot),
+            ..self.clone()
+        }
+    }
+}
+
+impl UniversalTranspiler {
+    /// Checks whether `cached`, the transpilation of `circuit` for `previous`,
+    /// still holds up under the `current` calibration. If any qubit or edge it
+    /// uses degraded beyond `threshold` (see `check_drift`), only the affected
+    /// virtual qubits are moved and the circuit is transpiled again for the
+    /// recalibrated backend; otherwise the cached result stays valid and
+    /// `None` is returned with the report.
+    pub fn retranspile_on_drift(
+        &self,
+        circuit: &QuantumCircuit,
+        cached: &TranspilationResult,
+        previous: &BackendSpec,
+        current: &CalibrationSnapshot,
+        threshold: f64,
+    ) -> Result<(DriftReport, Option<TranspilationResult>), String> {
+        let report = check_drift(cached, previous, current, threshold);
+        if !report.is_degraded() {
+            return Ok((report, None));
+        }
+        let backend = previous.with_calibration(current.clone());
+        let unrolled = run_pass(&UnrollPass, circuit);
+        let layout = relayout(&unrolled, &cached.initial_layout, &backend, &report)?;
+        let result = self.transpile_circuit_with_layout(circuit.clone(), &backend, layout)?;
+        Ok((report, Some(result)))
+    }
+}
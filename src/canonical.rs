@@ -0,0 +1,178 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use crate::commutation::commute;
+use crate::{Gate, OptimizationPass, QuantumCircuit};
+
+// ============================================================================
+// CANONICAL GATE ORDERING
+// ============================================================================
+
+/// Reorders commuting gates deterministically so that circuits differing only
+/// in the order of commuting gates come out identical (and emit byte-identical
+/// QASM). Gates that don't commute keep their relative order; among the gates
+/// free to go next, the one with the lowest qubit index goes first, then by
+/// gate name, qubits and parameters. Each gate is compared with the gates on
+/// its qubits back to the last one it must follow anyway, so the pass is
+/// linear unless long runs of gates commute.
+pub struct CanonicalOrderPass;
+
+/// Sort key of a gate among those that may be placed next.
+type OrderKey = (usize, String, Vec<usize>, String, usize);
+
+fn touches_classical(g: &Gate) -> bool {
+    g.condition.is_some() || g.block.is_some() || g.name == "measure"
+}
+
+fn order_key(g: &Gate, index: usize) -> OrderKey {
+    let params = g
+        .params
+        .iter()
+        .map(|p| p.to_string())
+        .collect::<Vec<_>>()
+        .join(",");
+    (
+        g.qubits.iter().copied().min().unwrap_or(usize::MAX),
+        g.name.clone(),
+        g.qubits.clone(),
+        params,
+        index,
+    )
+}
+
+impl OptimizationPass for CanonicalOrderPass {
+    fn name(&self) -> &str {
+        "canonical-order"
+    }
+
+    fn optimize(&self, circuit: &QuantumCircuit) -> QuantumCircuit {
+        let gates = &circuit.gates;
+        let n = gates.len();
+
+        // Edge i -> j for every earlier gate i that j doesn't commute with,
+        // unless already implied. Gates conflict only along a wire: a qubit,
+        // or the classical bits as a whole; gates without qubits lie on every
+        // wire and conflict with everything. Each wire lists its gates with
+        // whether all earlier gates on the wire are ordered before them, so
+        // a gate scanning back along a wire stops at the first such gate it
+        // conflicts with rather than going through the whole history.
+        let mut successors: Vec<Vec<usize>> = vec![Vec::new(); n];
+        let mut pending = vec![0usize; n];
+        let mut wires: Vec<Vec<(usize, bool)>> = vec![Vec::new(); circuit.num_qubits + 1];
+        let classical = circuit.num_qubits;
+        // For gate j, whether it conflicts with gate i, once checked.
+        let mut verdict: Vec<Option<(usize, bool)>> = vec![None; n];
+        for j in 0..n {
+            let g = &gates[j];
+            let on: Vec<usize> = if g.qubits.is_empty() {
+                (0..wires.len()).collect()
+            } else {
+                let mut on = g.qubits.clone();
+                if touches_classical(g) {
+                    on.push(classical);
+                }
+                on
+            };
+            for &w in &on {
+                let mut closed = true;
+                for &(i, i_closed) in wires[w].iter().rev() {
+                    let conflicts = match verdict[i] {
+                        Some((k, conflicts)) if k == j => conflicts,
+                        _ => {
+                            let conflicts = g.qubits.is_empty()
+                                || gates[i].qubits.is_empty()
+                                || !commute(&gates[i], g);
+                            if conflicts {
+                                successors[i].push(j);
+                                pending[j] += 1;
+                            }
+                            verdict[i] = Some((j, conflicts));
+                            conflicts
+                        }
+                    };
+                    if conflicts && i_closed {
+                        break;
+                    }
+                    closed &= conflicts;
+                }
+                wires[w].push((j, closed));
+            }
+        }
+
+        let mut ready: BinaryHeap<Reverse<OrderKey>> = (0..n)
+            .filter(|&j| pending[j] == 0)
+            .map(|j| Reverse(order_key(&gates[j], j)))
+            .collect();
+        let mut out = Vec::with_capacity(n);
+        while let Some(Reverse(key)) = ready.pop() {
+            let i = key.4;
+            out.push(gates[i].clone());
+            for &j in &successors[i] {
+                pending[j] -= 1;
+                if pending[j] == 0 {
+                    ready.push(Reverse(order_key(&gates[j], j)));
+                }
+            }
+        }
+        circuit.with_gates(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::classical::{ClassicalBit, ClassicalExpr};
+    use crate::Param;
+
+    fn gate(name: &str, qubits: &[usize]) -> Gate {
+        Gate::new(name, qubits.to_vec(), vec![])
+    }
+
+    fn canonical(gates: Vec<Gate>) -> Vec<Gate> {
+        let circuit = QuantumCircuit::new(3, 1).with_gates(gates);
+        CanonicalOrderPass.optimize(&circuit).gates
+    }
+
+    fn names(gates: &[Gate]) -> Vec<&str> {
+        gates.iter().map(|g| g.name.as_str()).collect()
+    }
+
+    fn placed(gates: &[Gate]) -> Vec<(&str, &[usize])> {
+        gates
+            .iter()
+            .map(|g| (g.name.as_str(), g.qubits.as_slice()))
+            .collect()
+    }
+
+    #[test]
+    fn commuting_orders_come_out_identical() {
+        let rz = Gate::new("rz", vec![0], vec![Param::Value(0.5)]);
+        let a = canonical(vec![gate("cz", &[0, 1]), rz.clone(), gate("x", &[2])]);
+        let b = canonical(vec![gate("x", &[2]), rz, gate("cz", &[0, 1])]);
+        assert_eq!(placed(&a), placed(&b));
+    }
+
+    #[test]
+    fn conflicts_past_a_commuting_gate_keep_their_order() {
+        // rz commutes with the cx control but not with h, so h must stay
+        // after rz even though h would sort first.
+        let rz = Gate::new("rz", vec![0], vec![Param::Value(0.5)]);
+        let out = canonical(vec![rz, gate("cx", &[0, 1]), gate("h", &[0])]);
+        let position = |name| out.iter().position(|g| g.name == name).unwrap();
+        assert!(position("rz") < position("h"));
+        assert!(position("cx") < position("h"));
+    }
+
+    #[test]
+    fn measurements_keep_their_order_with_conditioned_gates() {
+        let bit = ClassicalBit::new("c", 0);
+        let condition = ClassicalExpr::register_equals("c", 1);
+        let out = canonical(vec![
+            Gate::measure(1, bit.clone()),
+            gate("x", &[0]).with_condition(condition),
+            Gate::measure(2, bit),
+        ]);
+        assert_eq!(names(&out), ["measure", "x", "measure"]);
+        assert_eq!(out[0].qubits, [1]);
+    }
+}
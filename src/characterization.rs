@@ -0,0 +1,1004 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::fmt;
+
+use crate::angle::Rational;
+use crate::classical::ClassicalBit;
+use crate::control_flow::ControlFlow;
+use crate::duration::Duration;
+use crate::layout::PhysicalQubit;
+use crate::linalg::{gate_matrix, Complex, Matrix};
+use crate::{BackendSpec, Counts, Gate, Param, QuantumCircuit};
+
+// ============================================================================
+// RANDOMIZED BENCHMARKING AND GATE SET TOMOGRAPHY CIRCUITS
+// ============================================================================
+
+/// The Pauli operator `i^phase X^x Z^z` on at most two qubits, bit `q` of
+/// `x` and `z` belonging to qubit `q`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Pauli {
+    phase: u8,
+    x: u8,
+    z: u8,
+}
+
+impl Pauli {
+    fn mul(self, other: Pauli) -> Pauli {
+        // Moving other's X part left past our Z part picks up a sign per
+        // qubit where both act.
+        let anticommuting = (self.z & other.x).count_ones() as u8;
+        Pauli {
+            phase: (self.phase + other.phase + 2 * anticommuting) % 4,
+            x: self.x ^ other.x,
+            z: self.z ^ other.z,
+        }
+    }
+
+    fn matrix(self, num_qubits: usize) -> Matrix {
+        let (o, l) = (Complex::ZERO, Complex::ONE);
+        let x = Matrix::from_rows(&[&[o, l], &[l, o]]);
+        let z = Matrix::from_rows(&[&[l, o], &[o, -l]]);
+        let mut m = Matrix::identity(1 << num_qubits);
+        for q in (0..num_qubits).filter(|q| self.x >> q & 1 == 1) {
+            m = m.mul(&x.embed(&[q], num_qubits));
+        }
+        for q in (0..num_qubits).filter(|q| self.z >> q & 1 == 1) {
+            m = m.mul(&z.embed(&[q], num_qubits));
+        }
+        let phase = [Complex::ONE, Complex::I, -Complex::ONE, -Complex::I][self.phase as usize];
+        m.data.iter_mut().for_each(|v| *v = phase * *v);
+        m
+    }
+}
+
+/// A Clifford operation, up to global phase, as the images of `X_q` (row
+/// `2q`) and `Z_q` (row `2q + 1`) under conjugation.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Tableau(Vec<Pauli>);
+
+impl Tableau {
+    fn generator(row: usize) -> Pauli {
+        let bit = 1 << (row / 2);
+        match row % 2 {
+            0 => Pauli {
+                phase: 0,
+                x: bit,
+                z: 0,
+            },
+            _ => Pauli {
+                phase: 0,
+                x: 0,
+                z: bit,
+            },
+        }
+    }
+
+    fn identity(num_qubits: usize) -> Self {
+        Tableau((0..2 * num_qubits).map(Self::generator).collect())
+    }
+
+    /// Tableau of `gate` acting on qubits `0..num_qubits`, or `None` if it
+    /// isn't a Clifford gate.
+    fn of_gate(gate: &Gate, num_qubits: usize) -> Option<Tableau> {
+        let u = gate_matrix(gate)?.embed(&gate.qubits, num_qubits);
+        let u_dagger = u.adjoint();
+        let candidates: Vec<Pauli> = (0..4u8)
+            .flat_map(|phase| (0..1u8 << num_qubits).map(move |x| (phase, x)))
+            .flat_map(|(phase, x)| (0..1u8 << num_qubits).map(move |z| Pauli { phase, x, z }))
+            .collect();
+        let rows = (0..2 * num_qubits)
+            .map(|row| {
+                let image = u
+                    .mul(&Self::generator(row).matrix(num_qubits))
+                    .mul(&u_dagger);
+                candidates
+                    .iter()
+                    .copied()
+                    .find(|p| p.matrix(num_qubits).approx_eq(&image, 1e-9))
+            })
+            .collect::<Option<Vec<_>>>()?;
+        Some(Tableau(rows))
+    }
+
+    /// Image of `p` under this operation.
+    fn conjugate(&self, p: Pauli) -> Pauli {
+        let num_qubits = self.0.len() / 2;
+        let mut image = Pauli {
+            phase: p.phase,
+            x: 0,
+            z: 0,
+        };
+        for q in (0..num_qubits).filter(|q| p.x >> q & 1 == 1) {
+            image = image.mul(self.0[2 * q]);
+        }
+        for q in (0..num_qubits).filter(|q| p.z >> q & 1 == 1) {
+            image = image.mul(self.0[2 * q + 1]);
+        }
+        image
+    }
+
+    /// This operation followed by `next`.
+    fn then(&self, next: &Tableau) -> Tableau {
+        Tableau(self.0.iter().map(|&p| next.conjugate(p)).collect())
+    }
+
+    fn inverse(&self) -> Tableau {
+        let num_qubits = self.0.len() / 2;
+        let rows = (0..2 * num_qubits)
+            .map(|row| {
+                let target = Self::generator(row);
+                (0..1u8 << num_qubits)
+                    .flat_map(|x| (0..1u8 << num_qubits).map(move |z| Pauli { phase: 0, x, z }))
+                    .find_map(|p| {
+                        let image = self.conjugate(p);
+                        (image.x == target.x && image.z == target.z).then_some(Pauli {
+                            phase: (4 - image.phase) % 4,
+                            ..p
+                        })
+                    })
+                    .expect("Clifford tableaux are invertible")
+            })
+            .collect();
+        Tableau(rows)
+    }
+}
+
+/// Angles tried for parameterized native gates when looking for Cliffords.
+fn clifford_angles() -> [Param; 3] {
+    [(1, 2), (1, 1), (-1, 2)]
+        .map(|(num, den)| Param::Pi(Rational::new(num, den).expect("nonzero denominator")))
+}
+
+/// The Clifford group on one or two physical qubits, each element written
+/// as the shortest sequence of the backend's native gates implementing it.
+pub struct CliffordGroup {
+    /// Physical qubits; qubit `i` of the group is `qubits[i]`.
+    pub qubits: Vec<usize>,
+    elements: Vec<Tableau>,
+    /// Native gates of each element, on the group's own qubit indices.
+    words: Vec<Vec<Gate>>,
+    index: HashMap<Tableau, usize>,
+}
+
+impl CliffordGroup {
+    /// Enumerates the group generated by the Clifford instances of the
+    /// backend's native gates on `qubits` (`rz` counts with angles that are
+    /// multiples of pi/2). Words use as few two-qubit gates as possible, then
+    /// as few gates as possible.
+    pub fn for_backend(backend: &BackendSpec, qubits: &[usize]) -> Result<Self, String> {
+        let n = qubits.len();
+        if !(1..=2).contains(&n) {
+            return Err(format!(
+                "Clifford groups are supported on 1 or 2 qubits, not {n}"
+            ));
+        }
+        if let Some(&q) = qubits.iter().find(|&&q| q >= backend.num_qubits) {
+            return Err(format!("Qubit {q} is outside backend {}", backend.name));
+        }
+        if n == 2 {
+            let (a, b) = (qubits[0], qubits[1]);
+            if a == b || !backend.are_coupled(PhysicalQubit(a), PhysicalQubit(b)) {
+                return Err(format!(
+                    "Qubits {a} and {b} are not coupled on backend {}",
+                    backend.name
+                ));
+            }
+        }
+
+        let mut names: Vec<&String> = backend.native_gates.iter().collect();
+        names.sort();
+        let placements: Vec<Vec<usize>> = match n {
+            1 => vec![vec![0]],
+            _ => vec![vec![0], vec![1], vec![0, 1], vec![1, 0]],
+        };
+        let identity = Tableau::identity(n);
+        let mut generators: Vec<(Gate, Tableau)> = Vec::new();
+        for name in names {
+            for placement in &placements {
+                let plain = std::iter::once(Vec::new());
+                for params in plain.chain(clifford_angles().into_iter().map(|a| vec![a])) {
+                    let gate = Gate::new(name, placement.clone(), params);
+                    match Tableau::of_gate(&gate, n) {
+                        Some(t) if t != identity && !generators.iter().any(|(_, g)| *g == t) => {
+                            generators.push((gate, t))
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        // Dijkstra from the identity, costing (two-qubit gates, gates).
+        let mut group = Self {
+            qubits: qubits.to_vec(),
+            elements: Vec::new(),
+            words: Vec::new(),
+            index: HashMap::new(),
+        };
+        let mut pending = vec![(identity, Vec::new())];
+        let mut queue = BinaryHeap::from([Reverse(((0usize, 0usize), 0usize))]);
+        while let Some(Reverse((cost, i))) = queue.pop() {
+            let (tableau, word) =
+                std::mem::replace(&mut pending[i], (Tableau(Vec::new()), Vec::new()));
+            if group.index.contains_key(&tableau) {
+                continue;
+            }
+            for (gate, t) in &generators {
+                let next = tableau.then(t);
+                if group.index.contains_key(&next) {
+                    continue;
+                }
+                let mut next_word = word.clone();
+                next_word.push(gate.clone());
+                let next_cost = (cost.0 + usize::from(gate.qubits.len() == 2), cost.1 + 1);
+                queue.push(Reverse((next_cost, pending.len())));
+                pending.push((next, next_word));
+            }
+            group.index.insert(tableau.clone(), group.elements.len());
+            group.elements.push(tableau);
+            group.words.push(word);
+        }
+
+        let expected = if n == 1 { 24 } else { 11520 };
+        if group.order() != expected {
+            return Err(format!(
+                "Native gates of backend {} only generate {} of the {expected} {n}-qubit Cliffords",
+                backend.name,
+                group.order()
+            ));
+        }
+        Ok(group)
+    }
+
+    /// Number of elements: 24 on one qubit, 11520 on two.
+    pub fn order(&self) -> usize {
+        self.elements.len()
+    }
+
+    /// Average number of native gates per Clifford, which turns an error per
+    /// Clifford into an error per gate.
+    pub fn gates_per_clifford(&self) -> f64 {
+        self.words.iter().map(Vec::len).sum::<usize>() as f64 / self.order() as f64
+    }
+
+    /// Native gates of element `i` on the physical qubits.
+    pub fn gates(&self, i: usize) -> Vec<Gate> {
+        self.words[i]
+            .iter()
+            .map(|g| Gate {
+                qubits: g.qubits.iter().map(|&q| self.qubits[q]).collect(),
+                ..g.clone()
+            })
+            .collect()
+    }
+
+    /// Index of the element `gate` (on physical qubits) implements, if it is
+    /// a Clifford on the group's qubits.
+    fn element_of(&self, gate: &Gate) -> Option<usize> {
+        let local = gate
+            .qubits
+            .iter()
+            .map(|q| self.qubits.iter().position(|p| p == q))
+            .collect::<Option<Vec<_>>>()?;
+        let tableau = Tableau::of_gate(
+            &Gate {
+                qubits: local,
+                ..gate.clone()
+            },
+            self.qubits.len(),
+        )?;
+        self.index.get(&tableau).copied()
+    }
+}
+
+/// Small deterministic generator (SplitMix64) so experiments are
+/// reproducible from their seed.
+pub struct SplitMix64(pub u64);
+
+impl SplitMix64 {
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    /// Uniform in `[0, 1)`.
+    pub fn unit(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Circuit running `gates` on `backend`, protected from optimization, then
+/// measuring `qubits` into `c[0..]`.
+fn experiment_circuit(backend: &BackendSpec, qubits: &[usize], gates: Vec<Gate>) -> QuantumCircuit {
+    let mut circuit = QuantumCircuit::new(backend.num_qubits, qubits.len());
+    let body = QuantumCircuit {
+        gates,
+        ..QuantumCircuit::default()
+    };
+    circuit
+        .gates
+        .push(Gate::from_block(ControlFlow::Protected { body }));
+    for (i, &q) in qubits.iter().enumerate() {
+        circuit
+            .gates
+            .push(Gate::measure(q, ClassicalBit::new("c", i)));
+    }
+    circuit
+}
+
+/// Sequence lengths and sampling of a randomized benchmarking experiment.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RbOptions {
+    /// Numbers of random Cliffords per sequence.
+    pub lengths: Vec<usize>,
+    /// Random sequences per length.
+    pub samples: usize,
+    pub seed: u64,
+}
+
+impl Default for RbOptions {
+    fn default() -> Self {
+        Self {
+            lengths: vec![1, 2, 4, 8, 16, 32, 64, 128],
+            samples: 10,
+            seed: 0,
+        }
+    }
+}
+
+/// One randomized benchmarking circuit.
+#[derive(Debug, Clone)]
+pub struct RbSequence {
+    /// Random Cliffords before the recovery Clifford.
+    pub length: usize,
+    /// Which of the samples at this length.
+    pub sample: usize,
+    /// Whether the interleaved gate follows each random Clifford.
+    pub interleaved: bool,
+    /// Random Cliffords, the recovery Clifford inverting them, then a
+    /// measurement of the benchmarked qubits into `c`. Without errors the
+    /// outcome is always all zeros.
+    pub circuit: QuantumCircuit,
+}
+
+/// A batch of randomized benchmarking circuits with what the fit needs to
+/// know about them.
+#[derive(Debug, Clone)]
+pub struct RbExperiment {
+    pub qubits: Vec<usize>,
+    /// The gate characterized by interleaved RB; `None` for standard RB.
+    pub interleaved_gate: Option<Gate>,
+    pub lengths: Vec<usize>,
+    /// See `CliffordGroup::gates_per_clifford`.
+    pub gates_per_clifford: f64,
+    /// Grouped by length, then sample; interleaved RB has a reference and an
+    /// interleaved sequence per sample.
+    pub sequences: Vec<RbSequence>,
+}
+
+/// Standard randomized benchmarking of `qubits` (one or two), with Cliffords
+/// compiled to the backend's native gates.
+pub fn randomized_benchmarking(
+    backend: &BackendSpec,
+    qubits: &[usize],
+    options: &RbOptions,
+) -> Result<RbExperiment, String> {
+    let group = CliffordGroup::for_backend(backend, qubits)?;
+    Ok(rb_experiment(backend, &group, None, options))
+}
+
+/// Interleaved randomized benchmarking of `gate`, a native Clifford gate on
+/// (some of) `qubits`. Each sample yields a reference sequence and one with
+/// `gate` after every random Clifford, both from the same Cliffords.
+pub fn interleaved_randomized_benchmarking(
+    backend: &BackendSpec,
+    qubits: &[usize],
+    gate: &Gate,
+    options: &RbOptions,
+) -> Result<RbExperiment, String> {
+    if !backend.native_gates.contains(&gate.name) {
+        return Err(format!(
+            "{} is not a native gate of backend {}",
+            gate.name, backend.name
+        ));
+    }
+    let group = CliffordGroup::for_backend(backend, qubits)?;
+    let element = group.element_of(gate).ok_or_else(|| {
+        format!(
+            "{} on qubits {:?} is not a Clifford on {qubits:?}",
+            gate.name, gate.qubits
+        )
+    })?;
+    Ok(rb_experiment(
+        backend,
+        &group,
+        Some((gate, element)),
+        options,
+    ))
+}
+
+fn rb_experiment(
+    backend: &BackendSpec,
+    group: &CliffordGroup,
+    interleaved: Option<(&Gate, usize)>,
+    options: &RbOptions,
+) -> RbExperiment {
+    let mut rng = SplitMix64(options.seed);
+    let mut sequences = Vec::new();
+    for &length in &options.lengths {
+        for sample in 0..options.samples {
+            let cliffords: Vec<usize> = (0..length).map(|_| rng.below(group.order())).collect();
+            let variants = match interleaved {
+                Some(target) => vec![None, Some(target)],
+                None => vec![None],
+            };
+            for variant in variants {
+                let mut gates = Vec::new();
+                let mut net = Tableau::identity(group.qubits.len());
+                for &c in &cliffords {
+                    gates.extend(group.gates(c));
+                    net = net.then(&group.elements[c]);
+                    if let Some((gate, element)) = variant {
+                        gates.push(gate.clone());
+                        net = net.then(&group.elements[element]);
+                    }
+                }
+                gates.extend(group.gates(group.index[&net.inverse()]));
+                sequences.push(RbSequence {
+                    length,
+                    sample,
+                    interleaved: variant.is_some(),
+                    circuit: experiment_circuit(backend, &group.qubits, gates),
+                });
+            }
+        }
+    }
+    RbExperiment {
+        qubits: group.qubits.clone(),
+        interleaved_gate: interleaved.map(|(g, _)| g.clone()),
+        lengths: options.lengths.clone(),
+        gates_per_clifford: group.gates_per_clifford(),
+        sequences,
+    }
+}
+
+/// Single-qubit gate set of GST: the idle `Gi` and the pi/2 rotations `Gx`
+/// and `Gy`.
+pub const GST_GATES: [&str; 3] = ["Gi", "Gx", "Gy"];
+
+/// Preparation and measurement fiducials of the standard X/Y/I gate set.
+pub const GST_FIDUCIALS: [&[&str]; 6] = [
+    &[],
+    &["Gx"],
+    &["Gy"],
+    &["Gx", "Gx"],
+    &["Gx", "Gx", "Gx"],
+    &["Gy", "Gy", "Gy"],
+];
+
+/// Germs of the standard X/Y/I gate set, which together amplify every
+/// parameter of the gate set.
+pub const GST_GERMS: [&[&str]; 11] = [
+    &["Gi"],
+    &["Gx"],
+    &["Gy"],
+    &["Gx", "Gy"],
+    &["Gx", "Gy", "Gi"],
+    &["Gx", "Gi", "Gy"],
+    &["Gx", "Gi", "Gi"],
+    &["Gy", "Gi", "Gi"],
+    &["Gx", "Gx", "Gi", "Gy"],
+    &["Gx", "Gy", "Gy", "Gi"],
+    &["Gx", "Gx", "Gy", "Gx", "Gy", "Gy"],
+];
+
+/// One GST circuit: `prep`, then `germ` repeated `power` times, then `meas`.
+#[derive(Debug, Clone)]
+pub struct GstCircuit {
+    pub prep: Vec<&'static str>,
+    pub germ: Vec<&'static str>,
+    pub power: usize,
+    pub meas: Vec<&'static str>,
+    /// Ends by measuring the qubit into `c[0]`.
+    pub circuit: QuantumCircuit,
+}
+
+impl GstCircuit {
+    /// Gate labels in the order they run, e.g. `Gx(GxGy)^2Gy`.
+    pub fn label(&self) -> String {
+        let germ = match self.power {
+            0 => String::new(),
+            _ => format!("({})^{}", self.germ.concat(), self.power),
+        };
+        format!("{}{germ}{}", self.prep.concat(), self.meas.concat())
+    }
+}
+
+/// The circuits of a single-qubit GST experiment, with the native gates
+/// implementing each label.
+#[derive(Debug, Clone)]
+pub struct GstExperiment {
+    pub qubit: usize,
+    /// Native gates of each gate label present on the backend.
+    pub gate_set: Vec<(&'static str, Vec<Gate>)>,
+    pub max_lengths: Vec<usize>,
+    /// The fiducial pairs on their own (power 0), then each fiducial pair
+    /// around every germ repeated to at most each maximum length. Germs using
+    /// a label the backend can't implement are left out.
+    pub circuits: Vec<GstCircuit>,
+}
+
+/// Single-qubit gate set tomography circuits for `qubit` from the standard
+/// X/Y/I fiducials and germs. `Gx` and `Gy` are the shortest native
+/// sequences implementing `rx(pi/2)` and `ry(pi/2)`; `Gi` is the native `id`
+/// gate, or an idle as long as `Gx` if the backend knows its duration.
+pub fn gate_set_tomography(
+    backend: &BackendSpec,
+    qubit: usize,
+    max_lengths: &[usize],
+) -> Result<GstExperiment, String> {
+    let group = CliffordGroup::for_backend(backend, &[qubit])?;
+    let quarter_turn = Param::Pi(Rational::new(1, 2).expect("nonzero denominator"));
+    let rotation = |name: &str| {
+        let element = group
+            .element_of(&Gate::new(name, vec![qubit], vec![quarter_turn.clone()]))
+            .expect("every quarter turn is a Clifford");
+        group.gates(element)
+    };
+    let gx = rotation("rx");
+    let mut gate_set = vec![("Gx", gx.clone()), ("Gy", rotation("ry"))];
+    let gx_duration: Option<u64> = gx.iter().map(|g| backend.gate_duration(g)).sum();
+    if backend.native_gates.contains("id") {
+        gate_set.insert(0, ("Gi", vec![Gate::new("id", vec![qubit], Vec::new())]));
+    } else if let Some(duration) = gx_duration.filter(|&d| d > 0) {
+        gate_set.insert(
+            0,
+            (
+                "Gi",
+                vec![Gate::new(
+                    "delay",
+                    vec![qubit],
+                    vec![Param::Duration(Duration::dt(duration))],
+                )],
+            ),
+        );
+    }
+    let gates_of = |labels: &[&str]| -> Vec<Gate> {
+        labels
+            .iter()
+            .flat_map(|l| {
+                gate_set
+                    .iter()
+                    .find(|(name, _)| name == l)
+                    .map_or(&[][..], |(_, g)| g.as_slice())
+            })
+            .cloned()
+            .collect()
+    };
+
+    let mut circuits = Vec::new();
+    let mut add =
+        |prep: &[&'static str], germ: &[&'static str], power: usize, meas: &[&'static str]| {
+            let mut labels = prep.to_vec();
+            (0..power).for_each(|_| labels.extend(germ));
+            labels.extend(meas);
+            circuits.push(GstCircuit {
+                prep: prep.to_vec(),
+                germ: germ.to_vec(),
+                power,
+                meas: meas.to_vec(),
+                circuit: experiment_circuit(backend, &[qubit], gates_of(&labels)),
+            });
+        };
+    for prep in GST_FIDUCIALS {
+        for meas in GST_FIDUCIALS {
+            add(prep, &[], 0, meas);
+        }
+    }
+    let available = |germ: &[&str]| {
+        germ.iter()
+            .all(|l| gate_set.iter().any(|(name, _)| name == l))
+    };
+    let mut repeated = HashSet::new();
+    for &max_length in max_lengths {
+        for germ in GST_GERMS.into_iter().filter(|g| available(g)) {
+            let power = max_length / germ.len();
+            if power == 0 || !repeated.insert((germ, power)) {
+                continue;
+            }
+            for prep in GST_FIDUCIALS {
+                for meas in GST_FIDUCIALS {
+                    add(prep, germ, power, meas);
+                }
+            }
+        }
+    }
+
+    Ok(GstExperiment {
+        qubit,
+        gate_set,
+        max_lengths: max_lengths.to_vec(),
+        circuits,
+    })
+}
+
+// ============================================================================
+// RB DECAY FITTING
+// ============================================================================
+
+/// Half-width of a 95% confidence interval, in standard errors.
+const Z_95: f64 = 1.96;
+
+/// Least-squares fit of `offset + amplitude * decay^m` to survival
+/// probabilities at sequence lengths `m`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DecayFit {
+    pub amplitude: f64,
+    pub offset: f64,
+    pub decay: f64,
+    /// Standard error of `decay`, from the fit's covariance.
+    pub decay_std_error: f64,
+    /// Sum of squared residuals.
+    pub residual: f64,
+}
+
+/// Best `amplitude` and `offset` for a fixed `decay`, with the residual.
+fn fit_linear(points: &[(usize, f64)], decay: f64) -> (f64, f64, f64) {
+    let n = points.len() as f64;
+    let xs: Vec<f64> = points.iter().map(|&(m, _)| decay.powi(m as i32)).collect();
+    let mean_x = xs.iter().sum::<f64>() / n;
+    let mean_y = points.iter().map(|p| p.1).sum::<f64>() / n;
+    let sxx: f64 = xs.iter().map(|x| (x - mean_x).powi(2)).sum();
+    let sxy: f64 = xs
+        .iter()
+        .zip(points)
+        .map(|(x, p)| (x - mean_x) * (p.1 - mean_y))
+        .sum();
+    let amplitude = if sxx > 0.0 { sxy / sxx } else { 0.0 };
+    let offset = mean_y - amplitude * mean_x;
+    let residual = xs
+        .iter()
+        .zip(points)
+        .map(|(x, p)| (p.1 - offset - amplitude * x).powi(2))
+        .sum();
+    (amplitude, offset, residual)
+}
+
+/// Inverse of a symmetric 3x3 matrix, `None` if singular.
+fn invert_3x3(m: [[f64; 3]; 3]) -> Option<[[f64; 3]; 3]> {
+    let cofactor = |r: usize, c: usize| {
+        let (r0, r1) = ((r + 1) % 3, (r + 2) % 3);
+        let (c0, c1) = ((c + 1) % 3, (c + 2) % 3);
+        m[r0][c0] * m[r1][c1] - m[r0][c1] * m[r1][c0]
+    };
+    let det: f64 = (0..3).map(|c| m[0][c] * cofactor(0, c)).sum();
+    if det.abs() < 1e-300 {
+        return None;
+    }
+    let mut inverse = [[0.0; 3]; 3];
+    for (r, row) in inverse.iter_mut().enumerate() {
+        for (c, v) in row.iter_mut().enumerate() {
+            *v = cofactor(c, r) / det;
+        }
+    }
+    Some(inverse)
+}
+
+/// Fits the RB decay to `(length, survival probability)` points, one per
+/// sequence. The decay is found by a scan over `1 - decay` on a log scale
+/// refined by golden-section search; amplitude and offset are linear given
+/// the decay.
+pub fn fit_decay(points: &[(usize, f64)]) -> Result<DecayFit, String> {
+    let mut lengths: Vec<usize> = points.iter().map(|p| p.0).collect();
+    lengths.sort();
+    lengths.dedup();
+    if lengths.len() < 3 || points.len() < 4 {
+        return Err(format!(
+            "Fitting the decay needs at least 3 sequence lengths and 4 points, got {} and {}",
+            lengths.len(),
+            points.len()
+        ));
+    }
+
+    // Parameterize by t = ln(1 - decay), so decays close to 1 are resolved.
+    let decay_at = |t: f64| 1.0 - t.exp();
+    let residual_at = |t: f64| fit_linear(points, decay_at(t)).2;
+    let (lo, hi) = (1e-7f64.ln(), 0.0);
+    let steps = 400;
+    let grid: Vec<f64> = (0..=steps)
+        .map(|i| lo + (hi - lo) * i as f64 / steps as f64)
+        .collect();
+    let best = (0..grid.len())
+        .min_by(|&a, &b| residual_at(grid[a]).total_cmp(&residual_at(grid[b])))
+        .expect("grid is not empty");
+    let (mut a, mut b) = (grid[best.saturating_sub(1)], grid[(best + 1).min(steps)]);
+    let ratio = (5f64.sqrt() - 1.0) / 2.0;
+    for _ in 0..60 {
+        let (c, d) = (b - ratio * (b - a), a + ratio * (b - a));
+        if residual_at(c) < residual_at(d) {
+            b = d;
+        } else {
+            a = c;
+        }
+    }
+    let decay = decay_at((a + b) / 2.0);
+    let (amplitude, offset, residual) = fit_linear(points, decay);
+
+    // Covariance sigma^2 (J^T J)^-1 over (amplitude, offset, decay).
+    let mut jtj = [[0.0; 3]; 3];
+    for &(m, _) in points {
+        let row = [
+            decay.powi(m as i32),
+            1.0,
+            amplitude * m as f64 * decay.powi(m as i32 - 1),
+        ];
+        for r in 0..3 {
+            for c in 0..3 {
+                jtj[r][c] += row[r] * row[c];
+            }
+        }
+    }
+    let sigma2 = residual / (points.len() - 3).max(1) as f64;
+    let decay_std_error =
+        invert_3x3(jtj).map_or(f64::INFINITY, |inv| (sigma2 * inv[2][2]).max(0.0).sqrt());
+    Ok(DecayFit {
+        amplitude,
+        offset,
+        decay,
+        decay_std_error,
+        residual,
+    })
+}
+
+/// An estimated error rate with its 95% confidence interval.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ErrorEstimate {
+    pub value: f64,
+    pub low: f64,
+    pub high: f64,
+}
+
+impl ErrorEstimate {
+    fn new(value: f64, std_error: f64) -> Self {
+        Self {
+            value,
+            low: (value - Z_95 * std_error).max(0.0),
+            high: value + Z_95 * std_error,
+        }
+    }
+}
+
+impl fmt::Display for ErrorEstimate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:.3e} (95% CI {:.3e} .. {:.3e})",
+            self.value, self.low, self.high
+        )
+    }
+}
+
+/// Outcome of analyzing an RB experiment.
+#[derive(Debug, Clone)]
+pub struct RbAnalysis {
+    pub reference: DecayFit,
+    pub error_per_clifford: ErrorEstimate,
+    /// Error per Clifford spread over its native gates.
+    pub error_per_gate: ErrorEstimate,
+    /// For interleaved RB, the fit of the interleaved sequences and the error
+    /// of the interleaved gate.
+    pub interleaved: Option<(DecayFit, ErrorEstimate)>,
+}
+
+impl fmt::Display for RbAnalysis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Decay {:.6} +- {:.2e}",
+            self.reference.decay, self.reference.decay_std_error
+        )?;
+        writeln!(f, "Error per Clifford: {}", self.error_per_clifford)?;
+        write!(f, "Error per gate: {}", self.error_per_gate)?;
+        if let Some((fit, gate_error)) = &self.interleaved {
+            write!(
+                f,
+                "\nInterleaved decay {:.6} +- {:.2e}",
+                fit.decay, fit.decay_std_error
+            )?;
+            write!(f, "\nInterleaved gate error: {gate_error}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Fraction of shots returning every measured bit to 0.
+fn survival(counts: &Counts) -> Option<f64> {
+    let total: usize = counts.values().sum();
+    let survived: usize = counts
+        .iter()
+        .filter(|(bits, _)| bits.chars().all(|c| c == '0' || c == ' '))
+        .map(|(_, n)| n)
+        .sum();
+    (total > 0).then(|| survived as f64 / total as f64)
+}
+
+/// Fits the decay of `experiment` from `counts[i]`, the measurement results
+/// of `experiment.sequences[i]`, and converts it to an error per Clifford
+/// `(d - 1) / d * (1 - decay)` with `d = 2^qubits`. For interleaved RB the
+/// gate error is `(d - 1) / d * (1 - interleaved / reference)`.
+pub fn analyze_rb(experiment: &RbExperiment, counts: &[Counts]) -> Result<RbAnalysis, String> {
+    if counts.len() != experiment.sequences.len() {
+        return Err(format!(
+            "Got results for {} sequences, the experiment has {}",
+            counts.len(),
+            experiment.sequences.len()
+        ));
+    }
+    let mut reference = Vec::new();
+    let mut interleaved = Vec::new();
+    for (i, (sequence, counts)) in experiment.sequences.iter().zip(counts).enumerate() {
+        let p = survival(counts).ok_or_else(|| format!("No shots for sequence {i}"))?;
+        match sequence.interleaved {
+            true => interleaved.push((sequence.length, p)),
+            false => reference.push((sequence.length, p)),
+        }
+    }
+
+    let d = (1u64 << experiment.qubits.len()) as f64;
+    let scale = (d - 1.0) / d;
+    let fit = fit_decay(&reference)?;
+    let error_per_clifford =
+        ErrorEstimate::new(scale * (1.0 - fit.decay), scale * fit.decay_std_error);
+    let per_gate = experiment.gates_per_clifford.max(1.0);
+    let error_per_gate = ErrorEstimate::new(
+        error_per_clifford.value / per_gate,
+        scale * fit.decay_std_error / per_gate,
+    );
+    let interleaved = match experiment.interleaved_gate {
+        Some(_) => {
+            let gate_fit = fit_decay(&interleaved)?;
+            let ratio = gate_fit.decay / fit.decay;
+            let ratio_error = ratio
+                * ((gate_fit.decay_std_error / gate_fit.decay).powi(2)
+                    + (fit.decay_std_error / fit.decay).powi(2))
+                .sqrt();
+            Some((
+                gate_fit,
+                ErrorEstimate::new(scale * (1.0 - ratio), scale * ratio_error),
+            ))
+        }
+        None => None,
+    };
+    Ok(RbAnalysis {
+        reference: fit,
+        error_per_clifford,
+        error_per_gate,
+        interleaved,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn backend(native: &[&str]) -> BackendSpec {
+        BackendSpec {
+            name: "pair".to_string(),
+            num_qubits: 2,
+            coupling_map: vec![(0, 1)],
+            native_gates: native.iter().map(|s| s.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Unitary of the protected gates of an experiment circuit, on its
+    /// first `num_qubits` qubits.
+    fn unitary(circuit: &QuantumCircuit, num_qubits: usize) -> Matrix {
+        let Some(ControlFlow::Protected { body }) = circuit.gates[0].block.as_deref() else {
+            panic!("experiment circuits start with a protected block");
+        };
+        body.gates
+            .iter()
+            .fold(Matrix::identity(1 << num_qubits), |u, g| {
+                gate_matrix(g)
+                    .expect("native gates have matrices")
+                    .embed(&g.qubits, num_qubits)
+                    .mul(&u)
+            })
+    }
+
+    #[test]
+    fn native_gates_generate_the_clifford_groups() {
+        let backend = backend(&["sx", "rz", "cx"]);
+        let one = CliffordGroup::for_backend(&backend, &[1]).unwrap();
+        assert_eq!(one.order(), 24);
+        assert!(one.gates(0).is_empty());
+        assert!((0..one.order())
+            .flat_map(|i| one.gates(i))
+            .all(|g| g.qubits == [1]));
+        let two = CliffordGroup::for_backend(&backend, &[0, 1]).unwrap();
+        assert_eq!(two.order(), 11520);
+        assert!(two.gates_per_clifford() > one.gates_per_clifford());
+    }
+
+    #[test]
+    fn gates_that_miss_cliffords_are_rejected() {
+        let Err(error) = CliffordGroup::for_backend(&backend(&["rz", "cx"]), &[0]) else {
+            panic!("rz alone only generates the diagonal Cliffords");
+        };
+        assert!(error.contains("only generate"));
+        assert!(CliffordGroup::for_backend(&backend(&["sx", "rz"]), &[0, 0]).is_err());
+    }
+
+    #[test]
+    fn rb_sequences_invert_to_the_identity() {
+        let backend = backend(&["sx", "rz", "cx"]);
+        let options = RbOptions {
+            lengths: vec![1, 5],
+            samples: 3,
+            seed: 11,
+        };
+        let experiment = randomized_benchmarking(&backend, &[0, 1], &options).unwrap();
+        assert_eq!(experiment.sequences.len(), 6);
+        let cx = Gate::new("cx", vec![0, 1], vec![]);
+        let interleaved =
+            interleaved_randomized_benchmarking(&backend, &[0, 1], &cx, &options).unwrap();
+        assert_eq!(interleaved.sequences.len(), 12);
+        for sequence in experiment.sequences.iter().chain(&interleaved.sequences) {
+            let u = unitary(&sequence.circuit, 2);
+            assert!(u.approx_eq_up_to_phase(&Matrix::identity(4), 1e-9));
+        }
+    }
+
+    #[test]
+    fn the_fit_recovers_a_known_decay() {
+        let points: Vec<(usize, f64)> = [1, 2, 4, 8, 16, 32, 64, 128]
+            .iter()
+            .flat_map(|&m| [(m, 0.5 + 0.45 * 0.98f64.powi(m as i32)); 2])
+            .collect();
+        let fit = fit_decay(&points).unwrap();
+        assert!((fit.decay - 0.98).abs() < 1e-6, "{fit:?}");
+        assert!((fit.amplitude - 0.45).abs() < 1e-4);
+        assert!((fit.offset - 0.5).abs() < 1e-4);
+        assert!(fit.residual < 1e-10);
+        assert!(fit_decay(&points[..4]).is_err());
+    }
+
+    #[test]
+    fn analysis_turns_the_decay_into_an_error_per_clifford() {
+        let backend = backend(&["sx", "rz"]);
+        let options = RbOptions {
+            lengths: vec![1, 4, 16, 64, 256],
+            samples: 2,
+            seed: 3,
+        };
+        let experiment = randomized_benchmarking(&backend, &[0], &options).unwrap();
+        let shots = 1_000_000.0;
+        let counts: Vec<Counts> = experiment
+            .sequences
+            .iter()
+            .map(|s| {
+                let survived = ((0.5 + 0.5 * 0.99f64.powi(s.length as i32)) * shots) as usize;
+                Counts::from([
+                    ("0".to_string(), survived),
+                    ("1".to_string(), shots as usize - survived),
+                ])
+            })
+            .collect();
+        let analysis = analyze_rb(&experiment, &counts).unwrap();
+        // (d - 1) / d * (1 - decay) with d = 2.
+        let epc = analysis.error_per_clifford;
+        assert!((epc.value - 0.005).abs() < 1e-5, "{epc}");
+        assert!(epc.low <= epc.value && epc.value <= epc.high);
+        assert!(analysis.interleaved.is_none());
+        assert!(analyze_rb(&experiment, &counts[1..]).is_err());
+    }
+}
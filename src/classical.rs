@@ -0,0 +1,500 @@
+use core::fmt;
+
+use crate::prelude::*;
+use crate::signature::ClassicalType;
+
+// ============================================================================
+// CLASSICAL REGISTERS AND EXPRESSIONS
+// ============================================================================
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClassicalRegister {
+    pub name: String,
+    pub size: usize,
+}
+
+/// One bit of a classical register, e.g. the target of a measurement.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClassicalBit {
+    pub register: String,
+    pub index: usize,
+}
+
+impl ClassicalBit {
+    pub fn new(register: &str, index: usize) -> Self {
+        Self {
+            register: register.to_string(),
+            index,
+        }
+    }
+}
+
+impl fmt::Display for ClassicalBit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}[{}]", self.register, self.index)
+    }
+}
+
+/// Value of a typed classical variable.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ClassicalValue {
+    Bool(bool),
+    Int(i64),
+    Uint(u64),
+    Float(f64),
+}
+
+impl ClassicalValue {
+    /// Reads a literal of type `ty`, checking that it fits the type's width.
+    pub fn parse(ty: ClassicalType, text: &str) -> Result<Self, String> {
+        let text = text.trim();
+        let invalid = || format!("Invalid {ty} value '{text}'");
+        let fits = |bits: Option<u32>, signed: bool, value: i128| match bits {
+            Some(0) => false,
+            Some(w) if w < 64 => {
+                let (low, high) = if signed {
+                    (-(1i128 << (w - 1)), (1i128 << (w - 1)) - 1)
+                } else {
+                    (0, (1i128 << w) - 1)
+                };
+                (low..=high).contains(&value)
+            }
+            _ => true,
+        };
+        match ty {
+            ClassicalType::Bool => match text {
+                "true" => Ok(ClassicalValue::Bool(true)),
+                "false" => Ok(ClassicalValue::Bool(false)),
+                _ => Err(invalid()),
+            },
+            ClassicalType::Int(w) => {
+                let v: i64 = text.parse().map_err(|_| invalid())?;
+                fits(w, true, v as i128)
+                    .then_some(ClassicalValue::Int(v))
+                    .ok_or_else(|| format!("{v} does not fit {ty}"))
+            }
+            ClassicalType::Uint(w) => {
+                let v: u64 = text.parse().map_err(|_| invalid())?;
+                fits(w, false, v as i128)
+                    .then_some(ClassicalValue::Uint(v))
+                    .ok_or_else(|| format!("{v} does not fit {ty}"))
+            }
+            ClassicalType::Float(_) | ClassicalType::Angle(_) => text
+                .parse()
+                .map(ClassicalValue::Float)
+                .map_err(|_| invalid()),
+            ClassicalType::Bit(_) => Err(format!(
+                "Bits are declared as registers, not variables: {text}"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ClassicalValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClassicalValue::Bool(b) => write!(f, "{b}"),
+            ClassicalValue::Int(v) => write!(f, "{v}"),
+            ClassicalValue::Uint(v) => write!(f, "{v}"),
+            ClassicalValue::Float(v) => write!(f, "{v}"),
+        }
+    }
+}
+
+/// A typed classical variable declared in an OpenQASM 3 program, e.g.
+/// `int[32] count = 0;`. Conditions refer to it by name like a register.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClassicalVariable {
+    pub name: String,
+    pub ty: ClassicalType,
+    pub initial: Option<ClassicalValue>,
+}
+
+impl ClassicalVariable {
+    /// Parses a declaration such as `bool done;` or `uint[8] n = 3;`.
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let statement = line.trim().trim_end_matches(';').trim();
+        let (decl, initial) = match statement.split_once('=') {
+            Some((decl, value)) => (decl.trim(), Some(value)),
+            None => (statement, None),
+        };
+        let (ty, name) = decl
+            .rsplit_once(char::is_whitespace)
+            .ok_or_else(|| format!("Malformed classical declaration: {line}"))?;
+        let ty = ClassicalType::parse(ty)?;
+        if ty.bit_size().is_some() {
+            return Err(format!(
+                "Bits are declared as registers, not variables: {line}"
+            ));
+        }
+        Ok(Self {
+            name: name.to_string(),
+            ty,
+            initial: initial.map(|v| ClassicalValue::parse(ty, v)).transpose()?,
+        })
+    }
+}
+
+impl fmt::Display for ClassicalVariable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.ty, self.name)?;
+        if let Some(value) = &self.initial {
+            write!(f, " = {value}")?;
+        }
+        write!(f, ";")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClassicalOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    And,
+    Or,
+    BitAnd,
+    BitOr,
+    BitXor,
+}
+
+impl ClassicalOp {
+    fn symbol(self) -> &'static str {
+        match self {
+            ClassicalOp::Eq => "==",
+            ClassicalOp::Ne => "!=",
+            ClassicalOp::Lt => "<",
+            ClassicalOp::Le => "<=",
+            ClassicalOp::Gt => ">",
+            ClassicalOp::Ge => ">=",
+            ClassicalOp::And => "&&",
+            ClassicalOp::Or => "||",
+            ClassicalOp::BitAnd => "&",
+            ClassicalOp::BitOr => "|",
+            ClassicalOp::BitXor => "^",
+        }
+    }
+
+    /// Binding strength, higher binds tighter (C-like, as in OpenQASM 3).
+    fn precedence(self) -> u8 {
+        match self {
+            ClassicalOp::Or => 1,
+            ClassicalOp::And => 2,
+            ClassicalOp::BitOr => 3,
+            ClassicalOp::BitXor => 4,
+            ClassicalOp::BitAnd => 5,
+            ClassicalOp::Eq | ClassicalOp::Ne => 6,
+            ClassicalOp::Lt | ClassicalOp::Le | ClassicalOp::Gt | ClassicalOp::Ge => 7,
+        }
+    }
+}
+
+/// Condition attached to a gate. Registers and bits are referenced by name so
+/// the expression can be emitted back exactly as written.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClassicalExpr {
+    /// Whole register read as an unsigned integer (bit 0 is least
+    /// significant), or a classical variable.
+    Register(String),
+    /// Single bit `name[index]`.
+    Bit(String, usize),
+    Int(u64),
+    Not(Box<ClassicalExpr>),
+    Binary(ClassicalOp, Box<ClassicalExpr>, Box<ClassicalExpr>),
+}
+
+impl ClassicalExpr {
+    /// `register == value`, the only condition form OpenQASM 2 allows.
+    pub fn register_equals(register: &str, value: u64) -> Self {
+        ClassicalExpr::Binary(
+            ClassicalOp::Eq,
+            Box::new(ClassicalExpr::Register(register.to_string())),
+            Box::new(ClassicalExpr::Int(value)),
+        )
+    }
+
+    /// Parses a condition such as `c == 3` or `c[0] && !(c[1] == 1)`.
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let tokens = tokenize(input)?;
+        let mut parser = ExprParser { tokens, pos: 0 };
+        let expr = parser.parse_binary(0)?;
+        if parser.pos != parser.tokens.len() {
+            return Err(format!("Unexpected trailing tokens in condition: {input}"));
+        }
+        Ok(expr)
+    }
+
+    /// Register name and value if this is the OpenQASM 2 form `reg == int`.
+    pub fn as_register_equals(&self) -> Option<(&str, u64)> {
+        match self {
+            ClassicalExpr::Binary(ClassicalOp::Eq, lhs, rhs) => {
+                match (lhs.as_ref(), rhs.as_ref()) {
+                    (ClassicalExpr::Register(r), ClassicalExpr::Int(v)) => Some((r, *v)),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Registers (or variables) the expression reads, with the bit read or
+    /// `None` for all of them.
+    pub fn references(&self) -> Vec<(String, Option<usize>)> {
+        match self {
+            ClassicalExpr::Register(name) => vec![(name.clone(), None)],
+            ClassicalExpr::Bit(name, index) => vec![(name.clone(), Some(*index))],
+            ClassicalExpr::Int(_) => Vec::new(),
+            ClassicalExpr::Not(inner) => inner.references(),
+            ClassicalExpr::Binary(_, lhs, rhs) => {
+                let mut refs = lhs.references();
+                refs.extend(rhs.references());
+                refs
+            }
+        }
+    }
+
+    /// Copy of the expression with every reference to register `old` renamed.
+    pub fn rename_register(&self, old: &str, new: &str) -> ClassicalExpr {
+        let rename = |name: &String| {
+            if name == old {
+                new.to_string()
+            } else {
+                name.clone()
+            }
+        };
+        match self {
+            ClassicalExpr::Register(name) => ClassicalExpr::Register(rename(name)),
+            ClassicalExpr::Bit(name, index) => ClassicalExpr::Bit(rename(name), *index),
+            ClassicalExpr::Int(v) => ClassicalExpr::Int(*v),
+            ClassicalExpr::Not(inner) => {
+                ClassicalExpr::Not(Box::new(inner.rename_register(old, new)))
+            }
+            ClassicalExpr::Binary(op, lhs, rhs) => ClassicalExpr::Binary(
+                *op,
+                Box::new(lhs.rename_register(old, new)),
+                Box::new(rhs.rename_register(old, new)),
+            ),
+        }
+    }
+
+    /// Evaluates the expression against classical bit values laid out by
+    /// `registers`. Returns `None` if it references an unknown register or bit.
+    pub fn evaluate(&self, registers: &[ClassicalRegister], bits: &[bool]) -> Option<u64> {
+        let offset = |name: &str| -> Option<(usize, usize)> {
+            let mut start = 0;
+            for r in registers {
+                if r.name == name {
+                    return Some((start, r.size));
+                }
+                start += r.size;
+            }
+            None
+        };
+        match self {
+            ClassicalExpr::Register(name) => {
+                let (start, size) = offset(name)?;
+                let mut value = 0u64;
+                for i in (0..size).rev() {
+                    value = (value << 1) | (*bits.get(start + i)? as u64);
+                }
+                Some(value)
+            }
+            ClassicalExpr::Bit(name, index) => {
+                let (start, size) = offset(name)?;
+                if *index >= size {
+                    return None;
+                }
+                bits.get(start + index).map(|&b| b as u64)
+            }
+            ClassicalExpr::Int(v) => Some(*v),
+            ClassicalExpr::Not(inner) => Some((inner.evaluate(registers, bits)? == 0) as u64),
+            ClassicalExpr::Binary(op, lhs, rhs) => {
+                let a = lhs.evaluate(registers, bits)?;
+                let b = rhs.evaluate(registers, bits)?;
+                Some(match op {
+                    ClassicalOp::Eq => (a == b) as u64,
+                    ClassicalOp::Ne => (a != b) as u64,
+                    ClassicalOp::Lt => (a < b) as u64,
+                    ClassicalOp::Le => (a <= b) as u64,
+                    ClassicalOp::Gt => (a > b) as u64,
+                    ClassicalOp::Ge => (a >= b) as u64,
+                    ClassicalOp::And => (a != 0 && b != 0) as u64,
+                    ClassicalOp::Or => (a != 0 || b != 0) as u64,
+                    ClassicalOp::BitAnd => a & b,
+                    ClassicalOp::BitOr => a | b,
+                    ClassicalOp::BitXor => a ^ b,
+                })
+            }
+        }
+    }
+
+    fn fmt_prec(&self, f: &mut fmt::Formatter<'_>, parent: u8) -> fmt::Result {
+        match self {
+            ClassicalExpr::Register(name) => write!(f, "{name}"),
+            ClassicalExpr::Bit(name, index) => write!(f, "{name}[{index}]"),
+            ClassicalExpr::Int(v) => write!(f, "{v}"),
+            ClassicalExpr::Not(inner) => {
+                write!(f, "!")?;
+                inner.fmt_prec(f, u8::MAX)
+            }
+            ClassicalExpr::Binary(op, lhs, rhs) => {
+                let prec = op.precedence();
+                if prec < parent {
+                    write!(f, "(")?;
+                }
+                lhs.fmt_prec(f, prec)?;
+                write!(f, " {} ", op.symbol())?;
+                // Operators are left-associative, so a same-precedence rhs needs parens.
+                rhs.fmt_prec(f, prec + 1)?;
+                if prec < parent {
+                    write!(f, ")")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl fmt::Display for ClassicalExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_prec(f, 0)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Int(u64),
+    Op(String),
+    LParen,
+    RParen,
+    LBracket,
+    RBracket,
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() {
+            let start = i;
+            while i < chars.len() && chars[i].is_ascii_digit() {
+                i += 1;
+            }
+            let text: String = chars[start..i].iter().collect();
+            let value = text
+                .parse()
+                .map_err(|_| format!("Invalid integer in condition: {text}"))?;
+            tokens.push(Token::Int(value));
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push(Token::Ident(chars[start..i].iter().collect()));
+        } else {
+            let two: String = chars[i..(i + 2).min(chars.len())].iter().collect();
+            match two.as_str() {
+                "==" | "!=" | "<=" | ">=" | "&&" | "||" => {
+                    tokens.push(Token::Op(two));
+                    i += 2;
+                    continue;
+                }
+                _ => {}
+            }
+            tokens.push(match c {
+                '(' => Token::LParen,
+                ')' => Token::RParen,
+                '[' => Token::LBracket,
+                ']' => Token::RBracket,
+                '<' | '>' | '&' | '|' | '^' | '!' => Token::Op(c.to_string()),
+                _ => return Err(format!("Unexpected character '{c}' in condition: {input}")),
+            });
+            i += 1;
+        }
+    }
+    Ok(tokens)
+}
+
+struct ExprParser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl ExprParser {
+    fn peek_op(&self) -> Option<ClassicalOp> {
+        match self.tokens.get(self.pos) {
+            Some(Token::Op(op)) => Some(match op.as_str() {
+                "==" => ClassicalOp::Eq,
+                "!=" => ClassicalOp::Ne,
+                "<" => ClassicalOp::Lt,
+                "<=" => ClassicalOp::Le,
+                ">" => ClassicalOp::Gt,
+                ">=" => ClassicalOp::Ge,
+                "&&" => ClassicalOp::And,
+                "||" => ClassicalOp::Or,
+                "&" => ClassicalOp::BitAnd,
+                "|" => ClassicalOp::BitOr,
+                "^" => ClassicalOp::BitXor,
+                _ => return None,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Precedence climbing over left-associative binary operators.
+    fn parse_binary(&mut self, min_prec: u8) -> Result<ClassicalExpr, String> {
+        let mut lhs = self.parse_unary()?;
+        while let Some(op) = self.peek_op() {
+            if op.precedence() < min_prec {
+                break;
+            }
+            self.pos += 1;
+            let rhs = self.parse_binary(op.precedence() + 1)?;
+            lhs = ClassicalExpr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn parse_unary(&mut self) -> Result<ClassicalExpr, String> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        match token {
+            Some(Token::Op(op)) if op == "!" => {
+                Ok(ClassicalExpr::Not(Box::new(self.parse_unary()?)))
+            }
+            Some(Token::Int(v)) => Ok(ClassicalExpr::Int(v)),
+            Some(Token::Ident(name)) if name == "true" => Ok(ClassicalExpr::Int(1)),
+            Some(Token::Ident(name)) if name == "false" => Ok(ClassicalExpr::Int(0)),
+            Some(Token::Ident(name)) => {
+                if self.tokens.get(self.pos) != Some(&Token::LBracket) {
+                    return Ok(ClassicalExpr::Register(name));
+                }
+                match (self.tokens.get(self.pos + 1), self.tokens.get(self.pos + 2)) {
+                    (Some(Token::Int(index)), Some(Token::RBracket)) => {
+                        let index = *index as usize;
+                        self.pos += 3;
+                        Ok(ClassicalExpr::Bit(name, index))
+                    }
+                    _ => Err(format!("Malformed bit reference for register {name}")),
+                }
+            }
+            Some(Token::LParen) => {
+                let inner = self.parse_binary(0)?;
+                if self.tokens.get(self.pos) != Some(&Token::RParen) {
+                    return Err("Unbalanced parentheses in condition".to_string());
+                }
+                self.pos += 1;
+                Ok(inner)
+            }
+            Some(t) => Err(format!("Unexpected token {t:?} in condition")),
+            None => Err("Unexpected end of condition".to_string()),
+        }
+    }
+}
@@ -0,0 +1,803 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+
+use transpiler_arch::aliases::GateAliases;
+use transpiler_arch::angle::{AngleOptions, AngleUnit};
+use transpiler_arch::archive::TranspilationArchive;
+#[cfg(feature = "providers")]
+use transpiler_arch::cost::PricingModel;
+use transpiler_arch::json::JsonValue;
+use transpiler_arch::layout::Layout;
+use transpiler_arch::lint::{AngleInDegrees, LintRule, Linter, SmallAngle};
+use transpiler_arch::objective::OptimizationObjective;
+use transpiler_arch::passes::PassRegistry;
+use transpiler_arch::resources::FtProfile;
+use transpiler_arch::roundtrip::check_roundtrip;
+use transpiler_arch::routing::RouterRegistry;
+use transpiler_arch::{
+    BackendSpec, ParseMode, QASMEmitter, QASMParser, QasmVersion, TranspilationResult,
+    TranspilationStats, TranspileStage, UniversalTranspiler,
+};
+
+// ============================================================================
+// COMMAND LINE INTERFACE
+// ============================================================================
+
+const USAGE: &str = "\
+usage: transpiler_arch <command> [options]
+
+commands:
+  transpile <file.qasm> [--backend NAME] [--output FILE] [--mapping FILE]
+                        [--qasm-version 2|3] [--gate-aliases FILE]
+                        [--angle-unit rad|deg|turn] [--output-angle-unit rad|deg|turn]
+                        [--router swap-chain|teleport|bridge|exact]
+                        [--only-passes NAME,...] [--skip-pass NAME,...] [--list-passes]
+                        [--trace FILE] [--strict] [--until unroll|routing|optimization|scheduling]
+                        [--profile] [--run-all-passes] [--archive FILE]
+      transpile the file, printing the result unless --output is given;
+      --mapping writes a JSON file relating classical bits to physical qubits;
+      --gate-aliases adds `alias = gate` lines to the names read and
+      `emit gate = name` lines to rename gates in the output; angles are
+      read and written in radians unless a unit is given, and angles that look
+      like degrees are reported; --router picks the routing strategy (exact
+      needs the `exact-routing` feature); --only-passes runs just the named
+      passes in the given order, scheduling passes after the optimization
+      ones, --skip-pass leaves passes out,
+      and --list-passes prints the available passes; --trace appends one
+      JSON line per optimization pass (time, gate counts, depth, changes);
+      statements the parser skips are reported as warnings, or with --strict
+      unknown gates and malformed statements are errors; --until stops after
+      a stage, giving the circuit with composite gates expanded onto the
+      initial layout (unroll), routed but not yet optimized (routing) or
+      optimized but not yet scheduled (optimization);
+      passes that a quick check shows can't help are skipped unless
+      --run-all-passes is given, and --profile prints each pass's time and
+      gate counts, or why it was skipped, to stderr; --archive writes a
+      versioned JSON archive of the input hash, settings, backend, output
+      circuit, layouts and stats
+  cost <file.qasm> [--backend NAME] [--shots N] [--pricing per-shot|per-gate]
+                   [--per-task X] [--per-shot X] [--per-1q-gate X] [--per-2q-gate X]
+                   [--max-shots-per-task N]
+      transpile the file and estimate what running it would cost (needs the
+      `providers` feature)
+  estimate-resources <file.qasm> [--profile superconducting|trapped-ion]
+                     [--code-distance D] [--syndrome-cycle-ns X]
+                     [--max-factories N] [--rotation-precision EPS]
+                     [--format text|json]
+      estimate logical qubits, T-factory demand and runtime of the file as a
+      logical circuit on a fault-tolerant architecture
+  lint <file.qasm> [--allow RULE,...] [--small-angle X] [--angle-unit rad|deg|turn]
+      report suspicious patterns in the file, failing if any are found;
+      rules: angle-in-degrees, small-angle (below X, default 1e-6),
+      gate-after-measure, unused-clbit, unmeasured-qubit, unreachable-branch
+  open-archive <archive.json> [--input FILE] [--output FILE]
+      print what an archive written by `transpile --archive` (of any earlier
+      version) records; --input checks that FILE is the archived input and
+      --output writes the archived circuit as OpenQASM 3
+  roundtrip <file.qasm> [--qasm-version 2|3]
+      check that emitting the parsed file and parsing it again gives the same
+      circuit (version defaults to the file's own)
+  transpile-dir <in_dir> <out_dir> [--backend NAME] [--jobs N] [--summary FILE]
+                [--trace FILE]
+      transpile every .qasm file in in_dir in parallel, writing the results to
+      out_dir (each with a .mapping.json qubit mapping) and per-file stats to a
+      CSV summary (default out_dir/summary.csv); --trace appends the pass
+      traces of all files, in file order
+  help
+      show this message
+
+Run without arguments for a demo transpilation.";
+
+/// Backends the CLI knows by name.
+pub fn known_backend(name: &str) -> Option<BackendSpec> {
+    match name {
+        "ibm_demo" => Some(BackendSpec {
+            name: "ibm_demo".to_string(),
+            num_qubits: 5,
+            coupling_map: vec![(0, 1), (1, 2), (2, 3), (3, 4)],
+            native_gates: ["x", "h", "cx", "rz"]
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+            ..Default::default()
+        }),
+        _ => None,
+    }
+}
+
+/// Runs the command named by `args[0]`.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let Some((command, rest)) = args.split_first() else {
+        return Err(USAGE.to_string());
+    };
+    match command.as_str() {
+        "transpile" => transpile_command(rest),
+        #[cfg(feature = "providers")]
+        "cost" => cost_command(rest),
+        #[cfg(not(feature = "providers"))]
+        "cost" => Err("The cost command needs the `providers` feature".to_string()),
+        "estimate-resources" => estimate_resources_command(rest),
+        "lint" => lint_command(rest),
+        "open-archive" => open_archive_command(rest),
+        "roundtrip" => roundtrip_command(rest),
+        "transpile-dir" => transpile_dir_command(rest),
+        "help" | "--help" | "-h" => {
+            println!("{USAGE}");
+            Ok(())
+        }
+        other => Err(format!("Unknown command '{other}'\n\n{USAGE}")),
+    }
+}
+
+/// Positional arguments, `--flag value` options and value-less `--switch`es
+/// of a command.
+struct ParsedArgs {
+    positional: Vec<String>,
+    options: HashMap<String, String>,
+    switches: HashSet<String>,
+}
+
+impl ParsedArgs {
+    fn parse(args: &[String], allowed: &[&str]) -> Result<Self, String> {
+        Self::parse_with_switches(args, allowed, &[])
+    }
+
+    fn parse_with_switches(
+        args: &[String],
+        allowed: &[&str],
+        switches: &[&str],
+    ) -> Result<Self, String> {
+        let mut positional = Vec::new();
+        let mut options = HashMap::new();
+        let mut set = HashSet::new();
+        let mut it = args.iter();
+        while let Some(arg) = it.next() {
+            if let Some(flag) = arg.strip_prefix("--") {
+                if switches.contains(&flag) {
+                    set.insert(flag.to_string());
+                    continue;
+                }
+                if !allowed.contains(&flag) {
+                    return Err(format!("Unknown option --{flag}\n\n{USAGE}"));
+                }
+                let value = it
+                    .next()
+                    .ok_or_else(|| format!("Option --{flag} needs a value"))?;
+                options.insert(flag.to_string(), value.clone());
+            } else {
+                positional.push(arg.clone());
+            }
+        }
+        Ok(Self {
+            positional,
+            options,
+            switches: set,
+        })
+    }
+
+    /// Comma-separated values of `flag`, empty if it wasn't given.
+    fn list(&self, flag: &str) -> Vec<String> {
+        self.options.get(flag).map_or_else(Vec::new, |v| {
+            v.split(',')
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(str::to_string)
+                .collect()
+        })
+    }
+
+    fn get<T: std::str::FromStr>(&self, flag: &str) -> Result<Option<T>, String> {
+        self.options
+            .get(flag)
+            .map(|v| {
+                v.parse()
+                    .map_err(|_| format!("Invalid value '{v}' for --{flag}"))
+            })
+            .transpose()
+    }
+
+    fn backend(&self) -> Result<BackendSpec, String> {
+        let name = self
+            .options
+            .get("backend")
+            .map_or("ibm_demo", String::as_str);
+        known_backend(name).ok_or_else(|| format!("Unknown backend '{name}'"))
+    }
+
+    fn angle_unit(&self, flag: &str) -> Result<AngleUnit, String> {
+        match self.options.get(flag) {
+            Some(name) => AngleUnit::parse(name)
+                .ok_or_else(|| format!("Unknown angle unit '{name}' for --{flag}")),
+            None => Ok(AngleUnit::Radians),
+        }
+    }
+
+    fn single_input(&self) -> Result<&str, String> {
+        match self.positional.as_slice() {
+            [path] => Ok(path),
+            _ => Err(format!("Expected exactly one input file\n\n{USAGE}")),
+        }
+    }
+}
+
+fn read_file(path: &str) -> Result<String, String> {
+    std::fs::read_to_string(path).map_err(|e| format!("Cannot read {path}: {e}"))
+}
+
+/// OpenQASM version a source file declares; files without a version 2
+/// header are treated as OpenQASM 3.
+fn source_version(source: &str) -> QasmVersion {
+    if source.trim_start().starts_with("OPENQASM 2") {
+        QasmVersion::V2
+    } else {
+        QasmVersion::V3
+    }
+}
+
+/// `--qasm-version`, defaulting to the version `source` declares.
+fn output_version(args: &ParsedArgs, source: &str) -> Result<QasmVersion, String> {
+    match args.options.get("qasm-version").map(String::as_str) {
+        Some("2") => Ok(QasmVersion::V2),
+        Some("3") => Ok(QasmVersion::V3),
+        Some(v) => Err(format!("Unsupported OpenQASM version '{v}'")),
+        None => Ok(source_version(source)),
+    }
+}
+
+fn write_file(path: &Path, contents: &str) -> Result<(), String> {
+    std::fs::write(path, contents).map_err(|e| format!("Cannot write {}: {e}", path.display()))
+}
+
+fn append_file(path: &Path, contents: &str) -> Result<(), String> {
+    std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(contents.as_bytes()))
+        .map_err(|e| format!("Cannot write {}: {e}", path.display()))
+}
+
+fn transpile_command(args: &[String]) -> Result<(), String> {
+    let args = ParsedArgs::parse_with_switches(
+        args,
+        &[
+            "backend",
+            "output",
+            "mapping",
+            "qasm-version",
+            "gate-aliases",
+            "angle-unit",
+            "output-angle-unit",
+            "router",
+            "only-passes",
+            "skip-pass",
+            "trace",
+            "until",
+            "archive",
+        ],
+        &["list-passes", "strict", "profile", "run-all-passes"],
+    )?;
+    let registry = PassRegistry::default();
+    if args.switches.contains("list-passes") {
+        for pass in registry.entries() {
+            let default = if pass.default { " (default)" } else { "" };
+            println!("{:<22}{}{default}", pass.name, pass.description);
+        }
+        return Ok(());
+    }
+    let path = args.single_input()?;
+    let backend = args.backend()?;
+    let router = RouterRegistry::default().get(
+        args.options
+            .get("router")
+            .map_or("swap-chain", String::as_str),
+    )?;
+    let only = args
+        .options
+        .contains_key("only-passes")
+        .then(|| args.list("only-passes"));
+    let objective = OptimizationObjective::default();
+    let pipeline = registry.pipeline(
+        &backend,
+        &objective,
+        only.as_deref(),
+        &args.list("skip-pass"),
+    )?;
+    let until = match args.options.get("until") {
+        Some(name) => TranspileStage::parse(name)
+            .ok_or_else(|| format!("Unknown stage '{name}' for --until"))?,
+        None => TranspileStage::default(),
+    };
+    let source = read_file(path)?;
+    let version = output_version(&args, &source)?;
+    let aliases = match args.options.get("gate-aliases") {
+        Some(table) => GateAliases::default()
+            .extend_from_table(&read_file(table)?)
+            .map_err(|e| format!("{table}: {e}"))?,
+        None => GateAliases::default(),
+    };
+    let angles = AngleOptions {
+        unit: args.angle_unit("angle-unit")?,
+        ..AngleOptions::default()
+    };
+    let mode = if args.switches.contains("strict") {
+        ParseMode::Strict
+    } else {
+        ParseMode::Permissive
+    };
+    let parser = QASMParser {
+        angles,
+        aliases: aliases.clone(),
+        mode,
+    };
+    let (circuit, warnings) = parser.parse_with_warnings(&source)?;
+    for warning in &warnings {
+        eprintln!("warning: {path}: {warning}");
+    }
+    for lint in AngleInDegrees.check(&circuit) {
+        eprintln!("warning: {path}: {lint}");
+    }
+
+    let mut config = BTreeMap::from([
+        ("router".to_string(), router.name().to_string()),
+        (
+            "passes".to_string(),
+            pipeline
+                .passes
+                .iter()
+                .map(|p| p.name())
+                .chain(pipeline.scheduling.iter().map(|p| p.name()))
+                .collect::<Vec<_>>()
+                .join(","),
+        ),
+        ("until".to_string(), format!("{until:?}").to_lowercase()),
+        ("parse_mode".to_string(), format!("{mode:?}").to_lowercase()),
+        (
+            "angle_unit".to_string(),
+            format!("{:?}", angles.unit).to_lowercase(),
+        ),
+        (
+            "adaptive_skipping".to_string(),
+            (!args.switches.contains("run-all-passes")).to_string(),
+        ),
+    ]);
+    if let Some(table) = args.options.get("gate-aliases") {
+        config.insert("gate_aliases".to_string(), table.clone());
+    }
+    let transpiler = UniversalTranspiler::new()
+        .with_gate_aliases(aliases.clone())
+        .with_angle_options(angles)
+        .with_parse_mode(mode)
+        .with_router(router)
+        .with_objective(objective)
+        .with_pipeline(pipeline)
+        .with_stop_after(until)
+        .with_adaptive_skipping(!args.switches.contains("run-all-passes"))
+        .with_pass_trace(args.options.contains_key("trace") || args.switches.contains("profile"));
+    let result = transpiler.transpile(&source, &backend)?;
+    if args.switches.contains("profile") {
+        for record in &result.trace {
+            match &record.skipped {
+                Some(reason) => eprintln!("profile: {:<22} skipped: {reason}", record.pass),
+                None => eprintln!(
+                    "profile: {:<22} {:>9.3} ms  gates {} -> {}  depth {} -> {}{}",
+                    record.pass,
+                    record.wall_time_ms,
+                    record.gate_count_before,
+                    record.gate_count,
+                    record.depth_before,
+                    record.depth,
+                    if record.accepted { "" } else { "  (rejected)" }
+                ),
+            }
+        }
+    }
+    if let Some(trace) = args.options.get("trace") {
+        let run = [
+            ("file", JsonValue::string(path)),
+            ("backend", JsonValue::string(backend.name.as_str())),
+        ];
+        append_file(Path::new(trace), &result.trace_jsonl(&run))?;
+    }
+    let emitter = QASMEmitter {
+        aliases,
+        angle_unit: args.angle_unit("output-angle-unit")?,
+        ..QASMEmitter::new(version)
+    };
+    let text = emitter.emit(&result.circuit)?;
+    if let Some(mapping) = args.options.get("mapping") {
+        write_file(Path::new(mapping), &result.mapping_json(&backend).pretty())?;
+    }
+    if let Some(archive) = args.options.get("archive") {
+        let archived = TranspilationArchive::new(&result, &source, &backend, config);
+        write_file(Path::new(archive), &archived.to_json().pretty())?;
+    }
+    match args.options.get("output") {
+        Some(output) => {
+            write_file(Path::new(output), &text)?;
+            println!(
+                "{path} on {}: depth {} -> {}, gates {} -> {}; wrote {output}",
+                backend.name,
+                result.stats.original_depth,
+                result.stats.final_depth,
+                result.stats.original_gate_count,
+                result.stats.final_gate_count
+            );
+        }
+        None => print!("{text}"),
+    }
+    Ok(())
+}
+
+#[cfg(feature = "providers")]
+fn cost_command(args: &[String]) -> Result<(), String> {
+    let args = ParsedArgs::parse(
+        args,
+        &[
+            "backend",
+            "shots",
+            "pricing",
+            "per-task",
+            "per-shot",
+            "per-1q-gate",
+            "per-2q-gate",
+            "max-shots-per-task",
+        ],
+    )?;
+    let path = args.single_input()?;
+    let backend = args.backend()?;
+    let shots = args.get("shots")?.unwrap_or(1000);
+
+    let pricing_name = args
+        .options
+        .get("pricing")
+        .map_or("per-shot", String::as_str);
+    let mut pricing = PricingModel::preset(pricing_name)
+        .ok_or_else(|| format!("Unknown pricing model '{pricing_name}'"))?;
+    if let Some(v) = args.get("per-task")? {
+        pricing.per_task = v;
+    }
+    if let Some(v) = args.get("per-shot")? {
+        pricing.per_shot = v;
+    }
+    if let Some(v) = args.get("per-1q-gate")? {
+        pricing.per_single_qubit_gate = v;
+    }
+    if let Some(v) = args.get("per-2q-gate")? {
+        pricing.per_two_qubit_gate = v;
+    }
+    if let Some(v) = args.get("max-shots-per-task")? {
+        pricing.max_shots_per_task = Some(v);
+    }
+
+    let result = UniversalTranspiler::new().transpile(&read_file(path)?, &backend)?;
+    println!(
+        "{path} on {}: {} -> {} gates, depth {} -> {}",
+        backend.name,
+        result.stats.original_gate_count,
+        result.stats.final_gate_count,
+        result.stats.original_depth,
+        result.stats.final_depth
+    );
+    println!("{}", pricing.estimate(&result.circuit, shots));
+    Ok(())
+}
+
+fn estimate_resources_command(args: &[String]) -> Result<(), String> {
+    let args = ParsedArgs::parse(
+        args,
+        &[
+            "profile",
+            "code-distance",
+            "syndrome-cycle-ns",
+            "max-factories",
+            "rotation-precision",
+            "format",
+        ],
+    )?;
+    let path = args.single_input()?;
+    let profile_name = args
+        .options
+        .get("profile")
+        .map_or("superconducting", String::as_str);
+    let mut profile = FtProfile::preset(profile_name)
+        .ok_or_else(|| format!("Unknown resource profile '{profile_name}'"))?;
+    if let Some(d) = args.get("code-distance")? {
+        profile.code_distance = d;
+    }
+    if let Some(ns) = args.get("syndrome-cycle-ns")? {
+        profile.syndrome_cycle_ns = ns;
+    }
+    if let Some(n) = args.get("max-factories")? {
+        profile.max_t_factories = Some(n);
+    }
+    if let Some(eps) = args.get::<f64>("rotation-precision")? {
+        if !(eps > 0.0 && eps < 1.0) {
+            return Err(format!(
+                "--rotation-precision must be between 0 and 1, got {eps}"
+            ));
+        }
+        profile.rotation_precision = eps;
+    }
+
+    let circuit = QASMParser::default().parse(&read_file(path)?)?;
+    let estimate = profile.estimate(&circuit);
+    match args.options.get("format").map_or("text", String::as_str) {
+        "text" => println!("{path}: {estimate}"),
+        "json" => println!("{}", estimate.to_json().pretty()),
+        other => return Err(format!("Unknown format '{other}', expected text or json")),
+    }
+    Ok(())
+}
+
+fn lint_command(args: &[String]) -> Result<(), String> {
+    let args = ParsedArgs::parse(args, &["allow", "small-angle", "angle-unit"])?;
+    let path = args.single_input()?;
+    let mut linter = Linter::new();
+    if let Some(threshold) = args.get("small-angle")? {
+        linter.rules.retain(|r| r.name() != "small-angle");
+        linter = linter.with_rule(Box::new(SmallAngle { threshold }));
+    }
+    for rule in args
+        .options
+        .get("allow")
+        .into_iter()
+        .flat_map(|a| a.split(','))
+    {
+        linter = linter.allow(rule.trim())?;
+    }
+    let parser = QASMParser {
+        angles: AngleOptions {
+            unit: args.angle_unit("angle-unit")?,
+            ..AngleOptions::default()
+        },
+        ..QASMParser::default()
+    };
+
+    let lints = linter.check(&parser.parse(&read_file(path)?)?);
+    for lint in &lints {
+        println!("{path}: {lint}");
+    }
+    match lints.len() {
+        0 => Ok(()),
+        n => Err(format!("{path}: {n} lint(s) found")),
+    }
+}
+
+fn open_archive_command(args: &[String]) -> Result<(), String> {
+    let args = ParsedArgs::parse(args, &["input", "output"])?;
+    let path = args.single_input()?;
+    let archive =
+        TranspilationArchive::parse(&read_file(path)?).map_err(|e| format!("{path}: {e}"))?;
+    let stats = &archive.stats;
+    println!(
+        "{path}: archive version {} written by {}, input {}",
+        archive.version,
+        if archive.crate_version.is_empty() {
+            "an unknown version"
+        } else {
+            &archive.crate_version
+        },
+        archive.input_hash
+    );
+    println!(
+        "backend {} ({} qubits): depth {} -> {}, gates {} -> {}",
+        archive.backend.name,
+        archive.backend.num_qubits,
+        stats.original_depth,
+        stats.final_depth,
+        stats.original_gate_count,
+        stats.final_gate_count
+    );
+    for (key, value) in &archive.config {
+        println!("  {key} = {value}");
+    }
+    let layout = |l: &Layout| {
+        l.iter()
+            .map(|(v, p)| format!("{v}->{p}"))
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    println!("initial layout: {}", layout(&archive.initial_layout));
+    println!("final layout: {}", layout(&archive.final_layout));
+    if let Some(input) = args.options.get("input") {
+        if !archive.matches_input(&read_file(input)?) {
+            return Err(format!(
+                "{input} is not the archived input ({})",
+                archive.input_hash
+            ));
+        }
+        println!("{input} matches the archived input");
+    }
+    if let Some(output) = args.options.get("output") {
+        write_file(
+            Path::new(output),
+            &QASMEmitter::new(QasmVersion::V3).emit(&archive.circuit)?,
+        )?;
+    }
+    Ok(())
+}
+
+fn roundtrip_command(args: &[String]) -> Result<(), String> {
+    let args = ParsedArgs::parse(args, &["qasm-version"])?;
+    let path = args.single_input()?;
+    let source = read_file(path)?;
+    let version = output_version(&args, &source)?;
+    let circuit = QASMParser::default().parse(&source)?;
+    check_roundtrip(&circuit, version)?;
+    println!(
+        "{path}: round trip through {version:?} OK ({} gates)",
+        circuit.gates.len()
+    );
+    Ok(())
+}
+
+/// One row of the `transpile-dir` CSV summary.
+struct FileReport {
+    file: String,
+    outcome: Result<TranspilationStats, String>,
+    elapsed_ms: f64,
+    /// Pass trace lines, if tracing.
+    trace: String,
+}
+
+impl FileReport {
+    const CSV_HEADER: &'static str = "file,status,original_depth,final_depth,original_gate_count,final_gate_count,depth_reduction,gate_reduction,elapsed_ms,error";
+
+    fn csv_row(&self) -> String {
+        match &self.outcome {
+            Ok(s) => format!(
+                "{},ok,{},{},{},{},{:.2},{:.2},{:.3},",
+                csv_field(&self.file),
+                s.original_depth,
+                s.final_depth,
+                s.original_gate_count,
+                s.final_gate_count,
+                s.depth_reduction,
+                s.gate_reduction,
+                self.elapsed_ms
+            ),
+            Err(e) => format!(
+                "{},error,,,,,,,{:.3},{}",
+                csv_field(&self.file),
+                self.elapsed_ms,
+                csv_field(e)
+            ),
+        }
+    }
+}
+
+/// Quotes a CSV field if it contains a separator, quote or newline.
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+fn transpile_dir_command(args: &[String]) -> Result<(), String> {
+    let args = ParsedArgs::parse(args, &["backend", "jobs", "summary", "trace"])?;
+    let [in_dir, out_dir] = args.positional.as_slice() else {
+        return Err(format!(
+            "Expected an input and an output directory\n\n{USAGE}"
+        ));
+    };
+    let backend = args.backend()?;
+    let jobs = match args.get::<usize>("jobs")? {
+        Some(0) => return Err("--jobs must be at least 1".to_string()),
+        Some(n) => n,
+        None => std::thread::available_parallelism().map_or(1, |n| n.get()),
+    };
+    let summary_path = args
+        .options
+        .get("summary")
+        .map_or_else(|| Path::new(out_dir).join("summary.csv"), PathBuf::from);
+
+    let mut inputs: Vec<PathBuf> = std::fs::read_dir(in_dir)
+        .map_err(|e| format!("Cannot read directory {in_dir}: {e}"))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| p.is_file() && p.extension().is_some_and(|ext| ext == "qasm"))
+        .collect();
+    inputs.sort();
+    std::fs::create_dir_all(out_dir)
+        .map_err(|e| format!("Cannot create directory {out_dir}: {e}"))?;
+
+    // Workers pull files off a shared index; each owns its transpiler since
+    // passes aren't required to be thread-safe.
+    let next = AtomicUsize::new(0);
+    let reports = Mutex::new(Vec::with_capacity(inputs.len()));
+    std::thread::scope(|scope| {
+        for _ in 0..jobs.min(inputs.len()) {
+            scope.spawn(|| {
+                let transpiler =
+                    UniversalTranspiler::new().with_pass_trace(args.options.contains_key("trace"));
+                loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(input) = inputs.get(i) else {
+                        break;
+                    };
+                    let started = Instant::now();
+                    let file = input
+                        .file_name()
+                        .map_or_else(String::new, |n| n.to_string_lossy().into_owned());
+                    let (outcome, trace) =
+                        match transpile_file(&transpiler, input, Path::new(out_dir), &backend) {
+                            Ok(result) => {
+                                let run = [
+                                    ("file", JsonValue::string(file.as_str())),
+                                    ("backend", JsonValue::string(backend.name.as_str())),
+                                ];
+                                let trace = result.trace_jsonl(&run);
+                                (Ok(result.stats), trace)
+                            }
+                            Err(e) => (Err(e), String::new()),
+                        };
+                    let report = FileReport {
+                        file,
+                        outcome,
+                        elapsed_ms: started.elapsed().as_secs_f64() * 1000.0,
+                        trace,
+                    };
+                    reports.lock().unwrap().push(report);
+                }
+            });
+        }
+    });
+
+    let mut reports = reports.into_inner().unwrap();
+    reports.sort_by(|a, b| a.file.cmp(&b.file));
+    let mut csv = String::from(FileReport::CSV_HEADER);
+    csv.push('\n');
+    for r in &reports {
+        csv.push_str(&r.csv_row());
+        csv.push('\n');
+    }
+    write_file(&summary_path, &csv)?;
+    if let Some(trace) = args.options.get("trace") {
+        append_file(
+            Path::new(trace),
+            &reports.iter().map(|r| r.trace.as_str()).collect::<String>(),
+        )?;
+    }
+
+    let failed = reports.iter().filter(|r| r.outcome.is_err()).count();
+    println!(
+        "Transpiled {} of {} files on {} with {jobs} job(s); summary in {}",
+        reports.len() - failed,
+        reports.len(),
+        backend.name,
+        summary_path.display()
+    );
+    if failed > 0 {
+        return Err(format!(
+            "{failed} file(s) failed, see the summary for details"
+        ));
+    }
+    Ok(())
+}
+
+/// Transpiles one file into `out_dir`, keeping its name and OpenQASM version,
+/// with its qubit mapping next to it.
+fn transpile_file(
+    transpiler: &UniversalTranspiler,
+    input: &Path,
+    out_dir: &Path,
+    backend: &BackendSpec,
+) -> Result<TranspilationResult, String> {
+    let source = read_file(&input.to_string_lossy())?;
+    let result = transpiler.transpile(&source, backend)?;
+    let text = QASMEmitter::new(source_version(&source)).emit(&result.circuit)?;
+    let name = input.file_name().unwrap_or_default();
+    write_file(&out_dir.join(name), &text)?;
+    let stem = input.file_stem().unwrap_or_default().to_string_lossy();
+    write_file(
+        &out_dir.join(format!("{stem}.mapping.json")),
+        &result.mapping_json(backend).pretty(),
+    )?;
+    Ok(result)
+}
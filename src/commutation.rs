@@ -0,0 +1,143 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+use crate::linalg::{gate_matrix, Matrix};
+use crate::Gate;
+
+// ============================================================================
+// GATE COMMUTATION ANALYSIS
+// ============================================================================
+
+/// Largest number of distinct qubits the matrix check will multiply out.
+const MAX_MATRIX_QUBITS: usize = 4;
+const COMMUTATION_TOLERANCE: f64 = 1e-9;
+
+/// Gates that are diagonal in the computational basis for every parameter
+/// value, so they commute with each other even when symbolic.
+pub const DIAGONAL_GATES: &[&str] = &[
+    "id", "i", "z", "s", "sdg", "t", "tdg", "p", "u1", "rz", "cz", "cp", "cu1", "crz", "rzz",
+];
+
+/// Cache key: both gates' names and parameter bit patterns, plus the qubits
+/// of each renumbered by first appearance so equivalent placements share an
+/// entry.
+type CommutationKey = (String, Vec<u64>, Vec<usize>, String, Vec<u64>, Vec<usize>);
+
+/// True if applying `g1` then `g2` is the same operation as `g2` then `g1`.
+/// Conservative: returns false whenever commutation can't be established.
+pub fn commute(g1: &Gate, g2: &Gate) -> bool {
+    static CHECKER: OnceLock<CommutationChecker> = OnceLock::new();
+    CHECKER
+        .get_or_init(CommutationChecker::default)
+        .commute(g1, g2)
+}
+
+/// Commutation oracle with its own result cache. `commute` uses a shared
+/// instance; passes that want an isolated cache can hold one of these.
+#[derive(Debug, Default)]
+pub struct CommutationChecker {
+    cache: Mutex<HashMap<CommutationKey, bool>>,
+}
+
+impl CommutationChecker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn commute(&self, g1: &Gate, g2: &Gate) -> bool {
+        if g1.qubits.iter().all(|q| !g2.qubits.contains(q)) {
+            // Disjoint gates still conflict through classical bits.
+            return !(touches_classical(g1) && touches_classical(g2));
+        }
+        // Conditions, control flow and opaque composites may act on state the
+        // qubit lists don't describe.
+        let opaque = |g: &Gate| g.condition.is_some() || g.block.is_some() || g.composite.is_some();
+        if opaque(g1) || opaque(g2) || is_non_unitary(g1) || is_non_unitary(g2) {
+            return false;
+        }
+        if DIAGONAL_GATES.contains(&g1.name.as_str()) && DIAGONAL_GATES.contains(&g2.name.as_str())
+        {
+            return true;
+        }
+        if g1.name == g2.name && g1.qubits == g2.qubits && g1.params == g2.params {
+            return true;
+        }
+
+        let Some(key) = cache_key(g1, g2) else {
+            return false;
+        };
+        if let Some(&hit) = self.cache.lock().unwrap().get(&key) {
+            return hit;
+        }
+        let result = matrices_commute(g1, g2);
+        self.cache.lock().unwrap().insert(key, result);
+        result
+    }
+
+    /// Number of gate pairs whose commutation has been computed.
+    pub fn cached_pairs(&self) -> usize {
+        self.cache.lock().unwrap().len()
+    }
+}
+
+/// True if the gate reads or writes classical bits.
+fn touches_classical(g: &Gate) -> bool {
+    g.condition.is_some() || g.block.is_some() || g.name == "measure"
+}
+
+fn is_non_unitary(g: &Gate) -> bool {
+    matches!(g.name.as_str(), "measure" | "reset" | "barrier" | "delay")
+}
+
+fn cache_key(g1: &Gate, g2: &Gate) -> Option<CommutationKey> {
+    let params = |g: &Gate| {
+        g.params
+            .iter()
+            .map(|p| p.value().map(f64::to_bits))
+            .collect::<Option<Vec<_>>>()
+    };
+    let mut order: Vec<usize> = Vec::new();
+    let mut relative = |qubits: &[usize]| {
+        qubits
+            .iter()
+            .map(|q| match order.iter().position(|o| o == q) {
+                Some(i) => i,
+                None => {
+                    order.push(*q);
+                    order.len() - 1
+                }
+            })
+            .collect::<Vec<_>>()
+    };
+    let (q1, q2) = (relative(&g1.qubits), relative(&g2.qubits));
+    Some((
+        g1.name.clone(),
+        params(g1)?,
+        q1,
+        g2.name.clone(),
+        params(g2)?,
+        q2,
+    ))
+}
+
+fn matrices_commute(g1: &Gate, g2: &Gate) -> bool {
+    let mut support: Vec<usize> = g1.qubits.iter().chain(&g2.qubits).copied().collect();
+    support.sort_unstable();
+    support.dedup();
+    if support.len() > MAX_MATRIX_QUBITS {
+        return false;
+    }
+    let (Some(m1), Some(m2)) = (gate_matrix(g1), gate_matrix(g2)) else {
+        return false;
+    };
+    let embed = |m: &Matrix, g: &Gate| {
+        let positions: Vec<usize> = g
+            .qubits
+            .iter()
+            .map(|q| support.iter().position(|s| s == q).unwrap())
+            .collect();
+        m.embed(&positions, support.len())
+    };
+    let (a, b) = (embed(&m1, g1), embed(&m2, g2));
+    a.mul(&b).approx_eq(&b.mul(&a), COMMUTATION_TOLERANCE)
+}
@@ -0,0 +1,224 @@
+use alloc::sync::Arc;
+
+use crate::classical::{ClassicalExpr, ClassicalOp};
+use crate::control_flow::ControlFlow;
+use crate::prelude::*;
+use crate::{Gate, OptimizationPass, QuantumCircuit};
+
+// ============================================================================
+// COMPOSITE GATES (LAZILY EXPANDED SUBCIRCUITS)
+// ============================================================================
+
+/// A named subcircuit over local qubits `0..definition.num_qubits`. Instances
+/// share the definition through an `Arc`, so a circuit with a million Trotter
+/// steps stores the step body once.
+#[derive(Debug)]
+pub struct CompositeGate {
+    pub name: String,
+    pub definition: QuantumCircuit,
+}
+
+impl CompositeGate {
+    /// Fails if a gate of `definition`, or of a block inside it, acts on a
+    /// qubit outside `0..definition.num_qubits`.
+    pub fn new(name: &str, definition: QuantumCircuit) -> Result<Arc<Self>, String> {
+        fn check(gates: &[Gate], num_qubits: usize, name: &str) -> Result<(), String> {
+            for g in gates {
+                if let Some(&q) = g.qubits.iter().find(|&&q| q >= num_qubits) {
+                    return Err(format!(
+                        "{} in the definition of {name} uses qubit {q}, but {name} acts on {num_qubits} qubits",
+                        g.name
+                    ));
+                }
+                if let Some(block) = &g.block {
+                    for body in block.bodies() {
+                        check(&body.gates, num_qubits, name)?;
+                    }
+                }
+            }
+            Ok(())
+        }
+        check(&definition.gates, definition.num_qubits, name)?;
+        Ok(Arc::new(Self {
+            name: name.to_string(),
+            definition,
+        }))
+    }
+
+    /// Gate count after full expansion, without expanding anything.
+    pub fn expanded_len(&self) -> usize {
+        self.definition
+            .gates
+            .iter()
+            .map(|g| g.composite.as_ref().map_or(1, |c| c.expanded_len()))
+            .sum()
+    }
+}
+
+impl Gate {
+    /// Instance of `composite` where local qubit `i` maps to `qubits[i]`.
+    pub fn from_composite(
+        composite: &Arc<CompositeGate>,
+        qubits: Vec<usize>,
+    ) -> Result<Self, String> {
+        if qubits.len() != composite.definition.num_qubits {
+            return Err(format!(
+                "{} acts on {} qubits, got {}",
+                composite.name,
+                composite.definition.num_qubits,
+                qubits.len()
+            ));
+        }
+        Ok(Gate {
+            composite: Some(Arc::clone(composite)),
+            ..Gate::new(&composite.name, qubits, Vec::new())
+        })
+    }
+}
+
+/// Replaces every composite instance by its (recursively expanded) definition,
+/// remapping local qubits, inside control-flow bodies too, and conditioning
+/// each inner gate on the instance's condition as well as its own.
+pub struct UnrollPass;
+
+impl UnrollPass {
+    /// Expands `gate` into `out`; `top` gives block bodies their registers.
+    fn expand(gate: &Gate, top: &QuantumCircuit, out: &mut Vec<Gate>) {
+        let Some(composite) = &gate.composite else {
+            out.push(gate.clone());
+            return;
+        };
+        for inner in &composite.definition.gates {
+            let mut mapped = Self::remap(inner, &gate.qubits, top);
+            mapped = match (&gate.condition, &inner.condition) {
+                (None, _) => mapped,
+                // A block has no condition of its own, so it is nested in an `if`.
+                (Some(outer), _) if mapped.block.is_some() => {
+                    Gate::from_block(ControlFlow::IfElse {
+                        condition: outer.clone(),
+                        true_body: top.with_gates(vec![mapped]),
+                        false_body: None,
+                    })
+                }
+                (Some(outer), None) => mapped.with_condition(outer.clone()),
+                (Some(outer), Some(own)) => mapped.with_condition(ClassicalExpr::Binary(
+                    ClassicalOp::And,
+                    Box::new(outer.clone()),
+                    Box::new(own.clone()),
+                )),
+            };
+            Self::expand(&mapped, top, out);
+        }
+    }
+
+    /// `gate` with local qubit `i` renamed to `qubits[i]`, including in the
+    /// bodies of a block, whose composites are expanded on the way.
+    fn remap(gate: &Gate, qubits: &[usize], top: &QuantumCircuit) -> Gate {
+        let mut mapped = gate.clone();
+        mapped.qubits = gate.qubits.iter().map(|&q| qubits[q]).collect();
+        if let Some(block) = &gate.block {
+            mapped.block = Some(Box::new(block.map_bodies(&mut |body| {
+                let mut out = Vec::with_capacity(body.gates.len());
+                for g in &body.gates {
+                    Self::expand(&Self::remap(g, qubits, top), top, &mut out);
+                }
+                top.with_gates(out)
+            })));
+        }
+        mapped
+    }
+}
+
+impl OptimizationPass for UnrollPass {
+    fn name(&self) -> &str {
+        "unroll"
+    }
+
+    fn optimize(&self, circuit: &QuantumCircuit) -> QuantumCircuit {
+        let mut out = Vec::with_capacity(circuit.gates.len());
+        for g in &circuit.gates {
+            Self::expand(g, circuit, &mut out);
+        }
+        circuit.with_gates(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::run_pass;
+
+    fn definition(num_qubits: usize, gates: Vec<Gate>) -> QuantumCircuit {
+        QuantumCircuit {
+            gates,
+            ..QuantumCircuit::new(num_qubits, 0)
+        }
+    }
+
+    fn bell() -> Arc<CompositeGate> {
+        let gates = vec![
+            Gate::new("h", vec![0], vec![]),
+            Gate::new("cx", vec![0, 1], vec![]),
+        ];
+        CompositeGate::new("bell", definition(2, gates)).unwrap()
+    }
+
+    #[test]
+    fn definitions_outside_their_qubits_are_rejected() {
+        let gates = vec![Gate::new("cx", vec![0, 2], vec![])];
+        assert!(CompositeGate::new("bad", definition(2, gates)).is_err());
+        let body = definition(2, vec![Gate::new("x", vec![3], vec![])]);
+        let block = Gate::from_block(ControlFlow::Protected { body });
+        assert!(CompositeGate::new("bad", definition(2, vec![block])).is_err());
+    }
+
+    #[test]
+    fn instances_need_one_qubit_per_local_qubit() {
+        assert!(Gate::from_composite(&bell(), vec![0]).is_err());
+        assert!(Gate::from_composite(&bell(), vec![0, 1]).is_ok());
+    }
+
+    #[test]
+    fn unrolling_remaps_qubits_and_ands_conditions() {
+        let flag = ClassicalExpr::Bit("c".to_string(), 0);
+        let inner = ClassicalExpr::Bit("c".to_string(), 1);
+        let gates = vec![Gate::new("x", vec![1], vec![]).with_condition(inner.clone())];
+        let composite = CompositeGate::new("cond_x", definition(2, gates)).unwrap();
+        let instance = Gate::from_composite(&composite, vec![3, 2])
+            .unwrap()
+            .with_condition(flag.clone());
+        let circuit = QuantumCircuit::new(4, 2).with_gates(vec![instance]);
+        let unrolled = run_pass(&UnrollPass, &circuit);
+        assert_eq!(unrolled.gates.len(), 1);
+        assert_eq!(unrolled.gates[0].qubits, vec![2]);
+        let expected = ClassicalExpr::Binary(ClassicalOp::And, Box::new(flag), Box::new(inner));
+        assert_eq!(unrolled.gates[0].condition, Some(expected));
+    }
+
+    #[test]
+    fn unrolling_remaps_qubits_inside_blocks() {
+        let body = definition(2, vec![Gate::new("cx", vec![0, 1], vec![])]);
+        let block = Gate::from_block(ControlFlow::Protected { body });
+        let composite = CompositeGate::new("guarded", definition(2, vec![block])).unwrap();
+        let instance = Gate::from_composite(&composite, vec![2, 0]).unwrap();
+        let circuit = QuantumCircuit::new(3, 0).with_gates(vec![instance]);
+        let unrolled = run_pass(&UnrollPass, &circuit);
+        let bodies = unrolled.gates[0].block.as_ref().unwrap().bodies();
+        assert_eq!(bodies[0].gates[0].qubits, vec![2, 0]);
+    }
+
+    #[test]
+    fn expanded_len_counts_nested_instances() {
+        let twice = definition(
+            2,
+            vec![
+                Gate::from_composite(&bell(), vec![0, 1]).unwrap(),
+                Gate::from_composite(&bell(), vec![1, 0]).unwrap(),
+            ],
+        );
+        assert_eq!(
+            CompositeGate::new("twice", twice).unwrap().expanded_len(),
+            4
+        );
+    }
+}
@@ -0,0 +1,242 @@
+use alloc::collections::BTreeSet;
+use core::fmt;
+
+use crate::classical::ClassicalExpr;
+use crate::prelude::*;
+use crate::QuantumCircuit;
+
+// ============================================================================
+// STRUCTURED CONTROL FLOW
+// ============================================================================
+
+/// A structured control-flow block. Bodies are full circuits over the same
+/// qubit and classical bit indices as the enclosing circuit.
+#[derive(Debug, Clone)]
+pub enum ControlFlow {
+    IfElse {
+        condition: ClassicalExpr,
+        true_body: QuantumCircuit,
+        false_body: Option<QuantumCircuit>,
+    },
+    While {
+        condition: ClassicalExpr,
+        body: QuantumCircuit,
+    },
+    /// `for variable in [start:step:end]`, with `end` inclusive as in OpenQASM 3.
+    For {
+        variable: String,
+        start: i64,
+        step: i64,
+        end: i64,
+        body: QuantumCircuit,
+    },
+    /// Region the optimizer must leave exactly as written (echo sequences,
+    /// calibrated composite pulses), delimited in source by `no-opt` pragmas.
+    Protected { body: QuantumCircuit },
+}
+
+/// Comment directives recognised by the parser.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pragma {
+    /// `// pragma: no-opt begin`
+    NoOptBegin,
+    /// `// pragma: no-opt end`
+    NoOptEnd,
+}
+
+impl Pragma {
+    /// Recognises a pragma comment line, tolerating extra whitespace.
+    pub fn parse(line: &str) -> Option<Pragma> {
+        let directive = line
+            .trim()
+            .strip_prefix("//")?
+            .trim()
+            .strip_prefix("pragma:")?;
+        match directive.split_whitespace().collect::<Vec<_>>()[..] {
+            ["no-opt", "begin"] => Some(Pragma::NoOptBegin),
+            ["no-opt", "end"] => Some(Pragma::NoOptEnd),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Pragma {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Pragma::NoOptBegin => write!(f, "// pragma: no-opt begin"),
+            Pragma::NoOptEnd => write!(f, "// pragma: no-opt end"),
+        }
+    }
+}
+
+impl ControlFlow {
+    /// Gate name used for the block in the enclosing circuit.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ControlFlow::IfElse { .. } => "if_else",
+            ControlFlow::While { .. } => "while_loop",
+            ControlFlow::For { .. } => "for_loop",
+            ControlFlow::Protected { .. } => "protected",
+        }
+    }
+
+    /// True for regions that optimization passes must not look into.
+    pub fn is_protected(&self) -> bool {
+        matches!(self, ControlFlow::Protected { .. })
+    }
+
+    pub fn bodies(&self) -> Vec<&QuantumCircuit> {
+        match self {
+            ControlFlow::IfElse {
+                true_body,
+                false_body,
+                ..
+            } => core::iter::once(true_body)
+                .chain(false_body.as_ref())
+                .collect(),
+            ControlFlow::While { body, .. }
+            | ControlFlow::For { body, .. }
+            | ControlFlow::Protected { body } => {
+                vec![body]
+            }
+        }
+    }
+
+    /// Rebuilds the block with every body passed through `f`.
+    pub fn map_bodies(&self, f: &mut dyn FnMut(&QuantumCircuit) -> QuantumCircuit) -> ControlFlow {
+        match self {
+            ControlFlow::IfElse {
+                condition,
+                true_body,
+                false_body,
+            } => ControlFlow::IfElse {
+                condition: condition.clone(),
+                true_body: f(true_body),
+                false_body: false_body.as_ref().map(&mut *f),
+            },
+            ControlFlow::While { condition, body } => ControlFlow::While {
+                condition: condition.clone(),
+                body: f(body),
+            },
+            ControlFlow::For {
+                variable,
+                start,
+                step,
+                end,
+                body,
+            } => ControlFlow::For {
+                variable: variable.clone(),
+                start: *start,
+                step: *step,
+                end: *end,
+                body: f(body),
+            },
+            ControlFlow::Protected { body } => ControlFlow::Protected { body: f(body) },
+        }
+    }
+
+    /// Sorted set of qubits touched anywhere inside the block.
+    pub fn qubits(&self) -> Vec<usize> {
+        fn collect(circuit: &QuantumCircuit, out: &mut BTreeSet<usize>) {
+            for g in &circuit.gates {
+                out.extend(g.qubits.iter().copied());
+                if let Some(block) = &g.block {
+                    for body in block.bodies() {
+                        collect(body, out);
+                    }
+                }
+            }
+        }
+        let mut qubits = BTreeSet::new();
+        for body in self.bodies() {
+            collect(body, &mut qubits);
+        }
+        qubits.into_iter().collect()
+    }
+
+    /// Loop iteration values, for passes that want to unroll a `for` block.
+    pub fn iterations(&self) -> Option<Vec<i64>> {
+        match self {
+            ControlFlow::For {
+                start, step, end, ..
+            } if *step != 0 => {
+                let mut values = Vec::new();
+                let mut v = Some(*start);
+                while let Some(value) =
+                    v.filter(|&v| (*step > 0 && v <= *end) || (*step < 0 && v >= *end))
+                {
+                    values.push(value);
+                    v = value.checked_add(*step);
+                }
+                Some(values)
+            }
+            _ => None,
+        }
+    }
+
+    /// Number of iterations of a `for` block, without listing them; counts
+    /// too large for `usize` saturate.
+    pub fn iteration_count(&self) -> Option<usize> {
+        match self {
+            ControlFlow::For {
+                start, step, end, ..
+            } if *step != 0 => {
+                let (start, step, end) = (*start as i128, *step as i128, *end as i128);
+                let span = if step > 0 { end - start } else { start - end };
+                let count = if span < 0 { 0 } else { span / step.abs() + 1 };
+                Some(usize::try_from(count).unwrap_or(usize::MAX))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn for_loop(start: i64, step: i64, end: i64) -> ControlFlow {
+        ControlFlow::For {
+            variable: "i".to_string(),
+            start,
+            step,
+            end,
+            body: QuantumCircuit::new(1, 0),
+        }
+    }
+
+    #[test]
+    fn iterations_include_the_end() {
+        assert_eq!(for_loop(0, 2, 4).iterations(), Some(vec![0, 2, 4]));
+        assert_eq!(for_loop(3, -1, 1).iterations(), Some(vec![3, 2, 1]));
+        assert_eq!(for_loop(1, 1, 0).iterations(), Some(vec![]));
+        assert_eq!(for_loop(0, 0, 4).iterations(), None);
+    }
+
+    #[test]
+    fn iterations_stop_at_the_ends_of_i64() {
+        assert_eq!(
+            for_loop(i64::MAX - 1, 1, i64::MAX).iterations(),
+            Some(vec![i64::MAX - 1, i64::MAX])
+        );
+        assert_eq!(
+            for_loop(i64::MIN + 1, -1, i64::MIN).iterations(),
+            Some(vec![i64::MIN + 1, i64::MIN])
+        );
+    }
+
+    #[test]
+    fn iteration_count_matches_iterations_without_listing_them() {
+        for (start, step, end) in [(0, 2, 4), (0, 2, 5), (3, -1, 1), (1, 1, 0), (-5, 3, 7)] {
+            let block = for_loop(start, step, end);
+            assert_eq!(
+                block.iteration_count(),
+                block.iterations().map(|it| it.len())
+            );
+        }
+        assert_eq!(
+            for_loop(i64::MIN, 1, i64::MAX).iteration_count(),
+            Some(usize::MAX)
+        );
+    }
+}
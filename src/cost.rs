@@ -0,0 +1,156 @@
+use std::fmt;
+
+use crate::QuantumCircuit;
+
+// ============================================================================
+// COST ESTIMATION
+// ============================================================================
+
+/// How a provider bills a job. Each term is in the same currency; unused
+/// terms are zero. Gate charges are per gate per shot, as trapped-ion
+/// providers bill.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PricingModel {
+    pub name: String,
+    pub per_task: f64,
+    pub per_shot: f64,
+    pub per_single_qubit_gate: f64,
+    pub per_two_qubit_gate: f64,
+    /// Jobs with more shots are split into several tasks.
+    pub max_shots_per_task: Option<usize>,
+    /// Smallest amount billed for a job.
+    pub minimum_charge: f64,
+}
+
+impl PricingModel {
+    /// Task fee plus a flat price per shot.
+    pub fn per_shot(name: &str, per_task: f64, per_shot: f64) -> Self {
+        Self {
+            name: name.to_string(),
+            per_task,
+            per_shot,
+            per_single_qubit_gate: 0.0,
+            per_two_qubit_gate: 0.0,
+            max_shots_per_task: None,
+            minimum_charge: 0.0,
+        }
+    }
+
+    /// Task fee plus a price per gate executed, for every shot.
+    pub fn per_gate(
+        name: &str,
+        per_task: f64,
+        per_single_qubit_gate: f64,
+        per_two_qubit_gate: f64,
+    ) -> Self {
+        Self {
+            per_single_qubit_gate,
+            per_two_qubit_gate,
+            ..Self::per_shot(name, per_task, 0.0)
+        }
+    }
+
+    /// Built-in models with illustrative list prices in USD. Real contracts
+    /// differ; pass explicit rates for budgeting.
+    pub fn presets() -> Vec<PricingModel> {
+        vec![
+            Self::per_shot("per-shot", 0.30, 0.00035),
+            Self::per_gate("per-gate", 0.30, 0.00003, 0.0003),
+        ]
+    }
+
+    pub fn preset(name: &str) -> Option<PricingModel> {
+        Self::presets().into_iter().find(|p| p.name == name)
+    }
+
+    /// Cost of running `circuit` (already transpiled) for `shots` shots.
+    pub fn estimate(&self, circuit: &QuantumCircuit, shots: usize) -> CostEstimate {
+        let (single_qubit_gates, two_qubit_gates) = count_billable_gates(circuit);
+        let tasks = match self.max_shots_per_task {
+            Some(max) if max > 0 => shots.div_ceil(max).max(1),
+            _ => 1,
+        };
+        let task_cost = self.per_task * tasks as f64;
+        let shot_cost = self.per_shot * shots as f64;
+        let gate_cost = shots as f64
+            * (self.per_single_qubit_gate * single_qubit_gates as f64
+                + self.per_two_qubit_gate * two_qubit_gates as f64);
+        CostEstimate {
+            model: self.name.clone(),
+            shots,
+            tasks,
+            single_qubit_gates,
+            two_qubit_gates,
+            task_cost,
+            shot_cost,
+            gate_cost,
+            total: (task_cost + shot_cost + gate_cost).max(self.minimum_charge),
+        }
+    }
+}
+
+/// Itemized cost of one job.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CostEstimate {
+    pub model: String,
+    pub shots: usize,
+    pub tasks: usize,
+    /// Gates executed per shot; loop bodies count once per iteration.
+    pub single_qubit_gates: usize,
+    /// Two-qubit gates executed per shot, counting a `swap` as three.
+    pub two_qubit_gates: usize,
+    pub task_cost: f64,
+    pub shot_cost: f64,
+    pub gate_cost: f64,
+    pub total: f64,
+}
+
+impl fmt::Display for CostEstimate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Cost estimate ({} pricing, {} shots in {} task(s)):",
+            self.model, self.shots, self.tasks
+        )?;
+        writeln!(
+            f,
+            "  gates per shot: {} single-qubit, {} two-qubit",
+            self.single_qubit_gates, self.two_qubit_gates
+        )?;
+        writeln!(f, "  tasks: {:>12.4}", self.task_cost)?;
+        writeln!(f, "  shots: {:>12.4}", self.shot_cost)?;
+        writeln!(f, "  gates: {:>12.4}", self.gate_cost)?;
+        write!(f, "  total: {:>12.4}", self.total)
+    }
+}
+
+/// (single-qubit, two-qubit) gates one shot executes. Barriers, delays and
+/// measurements aren't billed as gates.
+fn count_billable_gates(circuit: &QuantumCircuit) -> (usize, usize) {
+    let mut counts: (usize, usize) = (0, 0);
+    for g in &circuit.gates {
+        if let Some(block) = &g.block {
+            let repeats = block.iteration_count().unwrap_or(1);
+            // Only one branch of an if/else runs; bill the more expensive one.
+            let body = block
+                .bodies()
+                .into_iter()
+                .map(count_billable_gates)
+                .max_by_key(|&(one, two)| (two, one))
+                .unwrap_or((0, 0));
+            counts.0 = counts.0.saturating_add(body.0.saturating_mul(repeats));
+            counts.1 = counts.1.saturating_add(body.1.saturating_mul(repeats));
+            continue;
+        }
+        if matches!(g.name.as_str(), "barrier" | "delay" | "measure" | "reset") {
+            continue;
+        }
+        match g.qubits.len() {
+            0 => {}
+            1 => counts.0 += 1,
+            _ if g.name == "swap" => counts.1 += 3,
+            _ => counts.1 += 1,
+        }
+    }
+    counts
+}
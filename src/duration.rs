@@ -0,0 +1,105 @@
+use core::fmt;
+
+use crate::prelude::*;
+
+// ============================================================================
+// DURATIONS WITH UNITS (DT AND SI)
+// ============================================================================
+
+/// Unit a duration is written in: samples of the backend's `dt`, or time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DurationUnit {
+    Dt,
+    Ns,
+    Us,
+    Ms,
+    S,
+}
+
+impl DurationUnit {
+    pub fn suffix(self) -> &'static str {
+        match self {
+            DurationUnit::Dt => "dt",
+            DurationUnit::Ns => "ns",
+            DurationUnit::Us => "us",
+            DurationUnit::Ms => "ms",
+            DurationUnit::S => "s",
+        }
+    }
+
+    /// Seconds in one unit; `None` for `dt`, whose length is the backend's.
+    pub fn seconds(self) -> Option<f64> {
+        match self {
+            DurationUnit::Dt => None,
+            DurationUnit::Ns => Some(1e-9),
+            DurationUnit::Us => Some(1e-6),
+            DurationUnit::Ms => Some(1e-3),
+            DurationUnit::S => Some(1.0),
+        }
+    }
+}
+
+/// A length of time as written, e.g. the `100ns` of `delay[100ns] q[0];`.
+/// It is converted to `dt` only against a backend, so a program may mix
+/// units with the backend's `dt`-based gate durations.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Duration {
+    pub value: f64,
+    pub unit: DurationUnit,
+}
+
+impl Duration {
+    pub fn dt(samples: u64) -> Self {
+        Self {
+            value: samples as f64,
+            unit: DurationUnit::Dt,
+        }
+    }
+
+    /// Parses a number followed by a unit: `dt`, `ns`, `us` (or `µs`), `ms`
+    /// or `s`.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let text = text.trim();
+        let units = [
+            ("dt", DurationUnit::Dt),
+            ("ns", DurationUnit::Ns),
+            ("us", DurationUnit::Us),
+            ("µs", DurationUnit::Us),
+            ("ms", DurationUnit::Ms),
+            ("s", DurationUnit::S),
+        ];
+        let (number, unit) = units
+            .iter()
+            .find_map(|&(suffix, unit)| text.strip_suffix(suffix).map(|number| (number, unit)))
+            .ok_or_else(|| format!("Duration '{text}' has no unit (dt, ns, us, ms or s)"))?;
+        let value: f64 = number
+            .trim()
+            .parse()
+            .map_err(|_| format!("Invalid duration '{text}'"))?;
+        if !value.is_finite() || value < 0.0 {
+            return Err(format!("Duration '{text}' must be a non-negative number"));
+        }
+        Ok(Self { value, unit })
+    }
+
+    /// The duration in seconds, given the backend's `dt` in seconds.
+    pub fn seconds(&self, dt: Option<f64>) -> Option<f64> {
+        self.unit.seconds().or(dt).map(|unit| self.value * unit)
+    }
+
+    /// The duration in whole `dt`, rounded to the nearest sample; `None` for
+    /// a time in SI units when the backend's `dt` is unknown.
+    pub fn to_dt(&self, dt: Option<f64>) -> Option<u64> {
+        let samples = match self.unit.seconds() {
+            None => self.value,
+            Some(unit) => self.value * unit / dt.filter(|&dt| dt > 0.0)?,
+        };
+        Some((samples.max(0.0) + 0.5) as u64)
+    }
+}
+
+impl fmt::Display for Duration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.value, self.unit.suffix())
+    }
+}
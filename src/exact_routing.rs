@@ -0,0 +1,508 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+use crate::layout::{Layout, PhysicalCircuit, PhysicalQubit, VirtualQubit};
+use crate::objective::OptimizationObjective;
+use crate::routing::RoutingStrategy;
+use crate::{BackendSpec, Gate, QuantumCircuit, RoutedCircuit, RoutingReport, SwapDecision};
+
+// ============================================================================
+// EXACT (MINIMUM-SWAP) LAYOUT AND ROUTING
+// ============================================================================
+
+/// Chooses the initial layout and the swaps together so that the circuit
+/// runs with the fewest swaps possible, keeping its gate order. The search
+/// is A* over (next two-qubit gate, placement) states, so it is only meant
+/// for small devices and circuits, e.g. to measure how far the heuristic
+/// router is from optimal. Its report has one decision per swap, for the
+/// gate the swap was inserted before.
+///
+/// The bound it proves is narrower than a SAT or ILP encoding's:
+/// - two-qubit gates run in the order they are listed, so commuting or
+///   independent gates are never reordered to save a swap;
+/// - swaps count one each, whatever their error rates;
+/// - a `None` from `route` because `max_states` ran out proves nothing.
+///
+/// Within that, the swap count is minimal: every swap moves each of a
+/// gate's qubits one step at most, so the distance to the next gate never
+/// overestimates the swaps still needed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExactRouter {
+    /// Largest device searched; all of its placements are considered.
+    pub max_physical_qubits: usize,
+    pub max_two_qubit_gates: usize,
+    /// Search states after which the search gives up.
+    pub max_states: usize,
+}
+
+impl Default for ExactRouter {
+    fn default() -> Self {
+        Self {
+            max_physical_qubits: 8,
+            max_two_qubit_gates: 64,
+            max_states: 2_000_000,
+        }
+    }
+}
+
+/// Virtual qubit on each physical qubit, `EMPTY` for none, and the index of
+/// the next two-qubit gate to run.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct State {
+    next: usize,
+    placement: Vec<u8>,
+}
+
+const EMPTY: u8 = u8::MAX;
+
+/// A searched state and how it was reached.
+struct Node {
+    state: State,
+    parent: Option<usize>,
+    /// Swap applied to the parent to get here, with the gate it was for.
+    swap: Option<(usize, usize, usize)>,
+}
+
+impl ExactRouter {
+    /// Routes `circuit` with the minimum number of swaps, or returns `None`
+    /// if it is outside the configured limits (too large, control flow, or
+    /// gates on more than two qubits) or the search gives up.
+    pub fn route(
+        &self,
+        circuit: &QuantumCircuit,
+        backend: &BackendSpec,
+    ) -> Result<Option<RoutedCircuit>, String> {
+        let p = backend.num_qubits;
+        if circuit.num_qubits > p {
+            return Err(format!(
+                "Circuit needs {} qubits but backend {} only has {p}",
+                circuit.num_qubits, backend.name
+            ));
+        }
+        let unsupported = circuit
+            .gates
+            .iter()
+            .any(|g| g.block.is_some() || g.qubits.len() > 2);
+        if p > self.max_physical_qubits.min(EMPTY as usize) || unsupported {
+            return Ok(None);
+        }
+        let pairs: Vec<(usize, usize)> = circuit
+            .gates
+            .iter()
+            .filter_map(|g| match g.qubits[..] {
+                [a, b] => Some((a, b)),
+                _ => None,
+            })
+            .collect();
+        if pairs.len() > self.max_two_qubit_gates {
+            return Ok(None);
+        }
+        let dist = backend.distance_matrix();
+        if let Some(&(a, b)) = pairs
+            .iter()
+            .find(|&&(a, b)| a >= circuit.num_qubits || b >= circuit.num_qubits)
+        {
+            return Err(format!(
+                "Gate on qubits {a} and {b} is outside the {}-qubit circuit",
+                circuit.num_qubits
+            ));
+        }
+        let mut edges: Vec<(usize, usize)> = backend
+            .coupling_map
+            .iter()
+            .filter(|&&(a, b)| a != b && a < p && b < p)
+            .map(|&(a, b)| (a.min(b), a.max(b)))
+            .collect();
+        edges.sort();
+        edges.dedup();
+
+        let Some(nodes) = self.search(circuit.num_qubits, p, &pairs, &edges, &dist) else {
+            return Ok(None);
+        };
+        Ok(Some(Self::build(circuit, backend, &nodes)?))
+    }
+
+    /// A* from every initial placement; returns the path of nodes to a goal.
+    fn search(
+        &self,
+        num_virtual: usize,
+        num_physical: usize,
+        pairs: &[(usize, usize)],
+        edges: &[(usize, usize)],
+        dist: &[Vec<usize>],
+    ) -> Option<Vec<Node>> {
+        let position = |placement: &[u8], v: usize| {
+            placement
+                .iter()
+                .position(|&x| x as usize == v)
+                .expect("placed")
+        };
+        // Runs every gate already adjacent, so states are compared after it.
+        let advance = |state: &mut State| {
+            while let Some(&(a, b)) = pairs.get(state.next) {
+                if dist[position(&state.placement, a)][position(&state.placement, b)] > 1 {
+                    break;
+                }
+                state.next += 1;
+            }
+        };
+        // Every swap moves each qubit one step at most.
+        let estimate = |state: &State| match pairs.get(state.next) {
+            Some(&(a, b)) => {
+                dist[position(&state.placement, a)][position(&state.placement, b)].saturating_sub(1)
+            }
+            None => 0,
+        };
+
+        let mut nodes: Vec<Node> = Vec::new();
+        let mut best: HashMap<State, usize> = HashMap::new();
+        let mut queue = BinaryHeap::new();
+        for placement in placements(num_virtual, num_physical) {
+            let mut state = State { next: 0, placement };
+            advance(&mut state);
+            if best.insert(state.clone(), 0).is_some() {
+                continue;
+            }
+            if pairs.get(state.next).is_some_and(|&(a, b)| {
+                dist[position(&state.placement, a)][position(&state.placement, b)] == usize::MAX
+            }) {
+                continue;
+            }
+            queue.push(Reverse((estimate(&state), 0, nodes.len())));
+            nodes.push(Node {
+                state,
+                parent: None,
+                swap: None,
+            });
+        }
+
+        while let Some(Reverse((_, swaps, id))) = queue.pop() {
+            if best.get(&nodes[id].state) != Some(&swaps) {
+                continue;
+            }
+            if nodes[id].state.next == pairs.len() {
+                let mut path = Vec::new();
+                let mut at = Some(id);
+                while let Some(i) = at {
+                    at = nodes[i].parent;
+                    path.push(i);
+                }
+                let mut taken: Vec<Option<Node>> = nodes.into_iter().map(Some).collect();
+                return Some(
+                    path.into_iter()
+                        .rev()
+                        .map(|i| taken[i].take().expect("on path once"))
+                        .collect(),
+                );
+            }
+            if nodes.len() > self.max_states {
+                return None;
+            }
+            let gate = nodes[id].state.next;
+            for &(x, y) in edges {
+                let placement = &nodes[id].state.placement;
+                if placement[x] == EMPTY && placement[y] == EMPTY {
+                    continue;
+                }
+                let mut next = nodes[id].state.clone();
+                next.placement.swap(x, y);
+                advance(&mut next);
+                if best.get(&next).is_some_and(|&s| s <= swaps + 1) {
+                    continue;
+                }
+                best.insert(next.clone(), swaps + 1);
+                queue.push(Reverse((
+                    swaps + 1 + estimate(&next),
+                    swaps + 1,
+                    nodes.len(),
+                )));
+                nodes.push(Node {
+                    state: next,
+                    parent: Some(id),
+                    swap: Some((x, y, gate)),
+                });
+            }
+        }
+        None
+    }
+
+    /// The circuit with the swaps of `path` inserted before the gates they
+    /// serve.
+    fn build(
+        circuit: &QuantumCircuit,
+        backend: &BackendSpec,
+        path: &[Node],
+    ) -> Result<RoutedCircuit, String> {
+        let start = &path[0].state.placement;
+        let physical = (0..circuit.num_qubits)
+            .map(|v| PhysicalQubit(start.iter().position(|&x| x as usize == v).expect("placed")))
+            .collect();
+        let initial_layout = Layout::from_physical(physical, backend.num_qubits)?;
+        let mut layout = initial_layout.clone();
+        let mut swaps = path.iter().filter_map(|n| n.swap).peekable();
+        let mut report = RoutingReport::default();
+        let mut gates = Vec::with_capacity(circuit.gates.len() + path.len());
+        let mut pair_index = 0;
+        for g in &circuit.gates {
+            if let [a, b] = g.qubits[..] {
+                while let Some((x, y, _)) = swaps.next_if(|&(_, _, gate)| gate == pair_index) {
+                    let (x, y) = (PhysicalQubit(x), PhysicalQubit(y));
+                    layout.swap_physical(x, y);
+                    gates.push(Gate::new("swap", vec![x.0, y.0], vec![]));
+                    let error = 1.0 - (1.0 - backend.two_qubit_error(x, y)).powi(3);
+                    report.decisions.push(SwapDecision {
+                        gate: g.name.clone(),
+                        qubits: (VirtualQubit(a), VirtualQubit(b)),
+                        path: vec![x, y],
+                        alternatives: 1,
+                        error,
+                        worst_error: error,
+                    });
+                }
+                pair_index += 1;
+            }
+            let mut mapped = g.clone();
+            mapped.qubits = g
+                .qubits
+                .iter()
+                .map(|&q| layout.physical(VirtualQubit(q)).0)
+                .collect();
+            gates.push(mapped);
+        }
+        let mut routed = circuit.with_gates(gates);
+        routed.num_qubits = backend.num_qubits;
+        Ok(RoutedCircuit {
+            circuit: PhysicalCircuit::assume_physical(routed),
+            initial_layout,
+            final_layout: layout,
+            report,
+        })
+    }
+}
+
+/// Every injective placement of `num_virtual` qubits on `num_physical`.
+fn placements(num_virtual: usize, num_physical: usize) -> Vec<Vec<u8>> {
+    fn extend(
+        v: usize,
+        num_virtual: usize,
+        current: &mut Vec<u8>,
+        used: &mut HashSet<usize>,
+        out: &mut Vec<Vec<u8>>,
+    ) {
+        if v == num_virtual {
+            out.push(current.clone());
+            return;
+        }
+        for p in 0..current.len() {
+            if used.insert(p) {
+                current[p] = v as u8;
+                extend(v + 1, num_virtual, current, used, out);
+                current[p] = EMPTY;
+                used.remove(&p);
+            }
+        }
+    }
+    let mut out = Vec::new();
+    extend(
+        0,
+        num_virtual,
+        &mut vec![EMPTY; num_physical],
+        &mut HashSet::new(),
+        &mut out,
+    );
+    out
+}
+
+impl RoutingStrategy for ExactRouter {
+    fn name(&self) -> &str {
+        "exact"
+    }
+
+    /// Fails for circuits outside the limits; the layout is always the
+    /// router's own choice. The objective is not consulted: the result has
+    /// the fewest swaps whatever it weighs.
+    fn route(
+        &self,
+        circuit: &QuantumCircuit,
+        backend: &BackendSpec,
+        initial_layout: Option<Layout>,
+        _: &OptimizationObjective,
+    ) -> Result<RoutedCircuit, String> {
+        if initial_layout.is_some() {
+            return Err("The exact router chooses its own initial layout".to_string());
+        }
+        ExactRouter::route(self, circuit, backend)?.ok_or_else(|| {
+            format!(
+                "Circuit is outside the exact router's limits ({} physical qubits, {} two-qubit gates, {} states)",
+                self.max_physical_qubits, self.max_two_qubit_gates, self.max_states
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SimpleRouter;
+
+    fn line(num_qubits: usize) -> BackendSpec {
+        BackendSpec {
+            name: "line".to_string(),
+            num_qubits,
+            coupling_map: (1..num_qubits).map(|q| (q - 1, q)).collect(),
+            native_gates: ["cx", "h", "swap"].iter().map(|s| s.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    fn cx(a: usize, b: usize) -> Gate {
+        Gate::new("cx", vec![a, b], vec![])
+    }
+
+    /// Number of swaps, after checking every two-qubit gate is on coupled
+    /// qubits.
+    fn swaps(routed: &RoutedCircuit, backend: &BackendSpec) -> usize {
+        for g in &routed.circuit.gates {
+            if let [a, b] = g.qubits[..] {
+                assert!(
+                    backend.are_coupled(PhysicalQubit(a), PhysicalQubit(b)),
+                    "{} on uncoupled {a} and {b}",
+                    g.name
+                );
+            }
+        }
+        routed
+            .circuit
+            .gates
+            .iter()
+            .filter(|g| g.name == "swap")
+            .count()
+    }
+
+    #[test]
+    fn a_path_of_interactions_needs_no_swaps() {
+        // 2-0-3-1 is a path, so it fits the line once laid out along it,
+        // though the trivial layout would need swaps.
+        let backend = line(4);
+        let circuit = QuantumCircuit::new(4, 0).with_gates(vec![cx(2, 0), cx(0, 3), cx(3, 1)]);
+        let routed = ExactRouter::default()
+            .route(&circuit, &backend)
+            .unwrap()
+            .unwrap();
+        assert_eq!(swaps(&routed, &backend), 0);
+        assert!(routed.report.decisions.is_empty());
+        assert_eq!(routed.circuit.gates.len(), circuit.gates.len());
+    }
+
+    #[test]
+    fn a_triangle_on_a_line_needs_one_swap() {
+        let backend = line(3);
+        let circuit = QuantumCircuit::new(3, 0).with_gates(vec![
+            Gate::new("h", vec![0], vec![]),
+            cx(0, 1),
+            cx(1, 2),
+            cx(2, 0),
+        ]);
+        let routed = ExactRouter::default()
+            .route(&circuit, &backend)
+            .unwrap()
+            .unwrap();
+        assert_eq!(swaps(&routed, &backend), 1);
+        assert_eq!(routed.report.decisions.len(), 1);
+        assert_eq!(
+            routed.report.decisions[0].qubits,
+            (VirtualQubit(2), VirtualQubit(0))
+        );
+        assert_eq!(routed.circuit.gates.len(), circuit.gates.len() + 1);
+    }
+
+    #[test]
+    fn gates_run_in_their_order_on_the_tracked_layout() {
+        let backend = line(4);
+        let circuit = QuantumCircuit::new(4, 0).with_gates(vec![
+            cx(0, 1),
+            cx(2, 3),
+            cx(0, 3),
+            Gate::new("h", vec![1], vec![]),
+            cx(1, 2),
+            cx(0, 2),
+        ]);
+        let routed = ExactRouter::default()
+            .route(&circuit, &backend)
+            .unwrap()
+            .unwrap();
+        let mut layout = routed.initial_layout.clone();
+        let mut input = circuit.gates.iter();
+        for g in &routed.circuit.gates {
+            if g.name == "swap" {
+                layout.swap_physical(PhysicalQubit(g.qubits[0]), PhysicalQubit(g.qubits[1]));
+                continue;
+            }
+            let original = input.next().expect("no extra gates");
+            let placed: Vec<usize> = original
+                .qubits
+                .iter()
+                .map(|&q| layout.physical(VirtualQubit(q)).0)
+                .collect();
+            assert_eq!((&g.name, &g.qubits), (&original.name, &placed));
+        }
+        assert!(input.next().is_none());
+        assert_eq!(layout, routed.final_layout);
+    }
+
+    #[test]
+    fn never_more_swaps_than_the_heuristic_router() {
+        let backend = line(5);
+        let mut seed = 7u64;
+        let mut next = |n: u64| {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            ((seed >> 33) % n) as usize
+        };
+        for _ in 0..10 {
+            let gates = (0..8)
+                .map(|_| {
+                    let a = next(5);
+                    cx(a, (a + 1 + next(4)) % 5)
+                })
+                .collect();
+            let circuit = QuantumCircuit::new(5, 0).with_gates(gates);
+            let exact = ExactRouter::default()
+                .route(&circuit, &backend)
+                .unwrap()
+                .unwrap();
+            let heuristic = SimpleRouter::default().route(&circuit, &backend).unwrap();
+            assert!(swaps(&exact, &backend) <= swaps(&heuristic, &backend));
+        }
+    }
+
+    #[test]
+    fn circuits_outside_the_limits_are_left_to_the_heuristic_router() {
+        let backend = line(4);
+        let toffoli =
+            QuantumCircuit::new(3, 0).with_gates(vec![Gate::new("ccx", vec![0, 1, 2], vec![])]);
+        assert!(ExactRouter::default()
+            .route(&toffoli, &backend)
+            .unwrap()
+            .is_none());
+
+        let small = ExactRouter {
+            max_physical_qubits: 3,
+            ..Default::default()
+        };
+        let circuit = QuantumCircuit::new(2, 0).with_gates(vec![cx(0, 1)]);
+        assert!(small.route(&circuit, &backend).unwrap().is_none());
+        assert!(RoutingStrategy::route(
+            &small,
+            &circuit,
+            &backend,
+            None,
+            &OptimizationObjective::default()
+        )
+        .is_err());
+
+        let too_wide = QuantumCircuit::new(5, 0);
+        assert!(ExactRouter::default().route(&too_wide, &backend).is_err());
+    }
+}
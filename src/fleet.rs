@@ -0,0 +1,149 @@
+use std::fmt;
+
+use crate::cost::PricingModel;
+use crate::objective::CircuitMetrics;
+use crate::{BackendSpec, QuantumCircuit, TranspilationResult, UniversalTranspiler};
+
+// ============================================================================
+// BACKEND SELECTION ACROSS A FLEET
+// ============================================================================
+
+/// A device the user can submit to.
+#[derive(Debug, Clone)]
+pub struct FleetMember {
+    pub backend: BackendSpec,
+    /// How the provider bills; `None` if running is free or unknown.
+    pub pricing: Option<PricingModel>,
+    /// Expected wait before the job starts.
+    pub queue_seconds: f64,
+}
+
+impl FleetMember {
+    pub fn new(backend: BackendSpec) -> Self {
+        Self {
+            backend,
+            pricing: None,
+            queue_seconds: 0.0,
+        }
+    }
+}
+
+/// How backends are compared. A backend's score is the weighted sum of its
+/// estimated error probability, its price for `shots` shots and its queue
+/// time in hours; lower is better.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SelectionCriteria {
+    pub shots: usize,
+    pub error_weight: f64,
+    /// Weight per unit of currency.
+    pub cost_weight: f64,
+    /// Weight per hour of queueing.
+    pub queue_weight: f64,
+}
+
+impl Default for SelectionCriteria {
+    /// Picks the backend with the best estimated fidelity.
+    fn default() -> Self {
+        Self {
+            shots: 1000,
+            error_weight: 1.0,
+            cost_weight: 0.0,
+            queue_weight: 0.0,
+        }
+    }
+}
+
+/// How one backend fared.
+pub struct BackendScore {
+    pub backend: String,
+    /// The circuit transpiled for this backend, ready to submit.
+    pub result: TranspilationResult,
+    pub metrics: CircuitMetrics,
+    pub cost: Option<f64>,
+    pub queue_seconds: f64,
+    pub score: f64,
+}
+
+impl BackendScore {
+    /// Probability that no gate fails.
+    pub fn fidelity(&self) -> f64 {
+        1.0 - self.metrics.estimated_error
+    }
+}
+
+/// Backends ordered best first, and those the circuit couldn't be transpiled
+/// for.
+pub struct FleetRanking {
+    pub scores: Vec<BackendScore>,
+    /// Backend name and the transpilation error.
+    pub failures: Vec<(String, String)>,
+}
+
+impl FleetRanking {
+    pub fn recommended(&self) -> Option<&BackendScore> {
+        self.scores.first()
+    }
+}
+
+impl fmt::Display for FleetRanking {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Backend ranking (best first):")?;
+        for (i, s) in self.scores.iter().enumerate() {
+            let cost = s.cost.map_or("-".to_string(), |c| format!("{c:.4}"));
+            writeln!(
+                f,
+                "  {}. {}: score {:.6}, fidelity {:.6}, cost {cost}, queue {:.0} s",
+                i + 1,
+                s.backend,
+                s.score,
+                s.fidelity(),
+                s.queue_seconds
+            )?;
+        }
+        for (name, error) in &self.failures {
+            writeln!(f, "  -- {name}: {error}")?;
+        }
+        Ok(())
+    }
+}
+
+impl UniversalTranspiler {
+    /// Transpiles `circuit` for every backend in `fleet` and ranks them by
+    /// `criteria`. Backends the circuit doesn't fit on are listed as failures.
+    pub fn rank_backends(
+        &self,
+        circuit: &QuantumCircuit,
+        fleet: &[FleetMember],
+        criteria: &SelectionCriteria,
+    ) -> FleetRanking {
+        let mut scores = Vec::new();
+        let mut failures = Vec::new();
+        for member in fleet {
+            let result = match self.transpile_circuit(circuit.clone(), &member.backend) {
+                Ok(result) => result,
+                Err(e) => {
+                    failures.push((member.backend.name.clone(), e));
+                    continue;
+                }
+            };
+            let metrics = CircuitMetrics::on(&result.circuit, &member.backend);
+            let cost = member
+                .pricing
+                .as_ref()
+                .map(|p| p.estimate(&result.circuit, criteria.shots).total);
+            let score = criteria.error_weight * metrics.estimated_error
+                + criteria.cost_weight * cost.unwrap_or(0.0)
+                + criteria.queue_weight * member.queue_seconds / 3600.0;
+            scores.push(BackendScore {
+                backend: member.backend.name.clone(),
+                result,
+                metrics,
+                cost,
+                queue_seconds: member.queue_seconds,
+                score,
+            });
+        }
+        scores.sort_by(|a, b| a.score.total_cmp(&b.score));
+        FleetRanking { scores, failures }
+    }
+}
@@ -0,0 +1,301 @@
+use std::sync::mpsc;
+
+use wgpu::util::DeviceExt;
+
+use crate::composite::UnrollPass;
+use crate::linalg::{Complex, Matrix};
+use crate::simulator::{for_each_unitary, SimulatorBackend};
+use crate::{run_pass, QuantumCircuit};
+
+// ============================================================================
+// GPU STATE-VECTOR SIMULATION
+// ============================================================================
+
+/// Most qubits a gate may act on; the shader gathers `2^k` amplitudes.
+const MAX_GATE_QUBITS: usize = 4;
+const WORKGROUP_SIZE: u32 = 64;
+/// Largest workgroup count of one dispatch dimension.
+const MAX_GROUPS_PER_DIMENSION: u32 = 65535;
+
+/// One invocation per group of `2^k` amplitudes a gate mixes: it spreads its
+/// index around the gate's (sorted) qubits to find the group's first
+/// amplitude, then multiplies the group by the gate's matrix in place.
+const SHADER: &str = r#"
+struct Params {
+    count: u32,
+    k: u32,
+    dim: u32,
+    pad: u32,
+    qubits: vec4<u32>,
+    sorted: vec4<u32>,
+}
+
+@group(0) @binding(0) var<storage, read_write> state: array<vec2<f32>>;
+@group(0) @binding(1) var<storage, read> matrix: array<vec2<f32>>;
+@group(0) @binding(2) var<uniform> params: Params;
+
+fn offset(local: u32) -> u32 {
+    var out = 0u;
+    for (var i = 0u; i < params.k; i++) {
+        out |= ((local >> i) & 1u) << params.qubits[i];
+    }
+    return out;
+}
+
+@compute @workgroup_size(64)
+fn main(@builtin(global_invocation_id) id: vec3<u32>,
+        @builtin(num_workgroups) groups: vec3<u32>) {
+    let b = id.x + id.y * groups.x * 64u;
+    if (b >= params.count) {
+        return;
+    }
+    var base = b;
+    for (var t = 0u; t < params.k; t++) {
+        let q = params.sorted[t];
+        base = ((base >> q) << (q + 1u)) | (base & ((1u << q) - 1u));
+    }
+    var amplitudes: array<vec2<f32>, 16>;
+    for (var c = 0u; c < params.dim; c++) {
+        amplitudes[c] = state[base + offset(c)];
+    }
+    for (var r = 0u; r < params.dim; r++) {
+        var acc = vec2<f32>(0.0, 0.0);
+        for (var c = 0u; c < params.dim; c++) {
+            let m = matrix[r * params.dim + c];
+            let a = amplitudes[c];
+            acc += vec2<f32>(m.x * a.x - m.y * a.y, m.x * a.y + m.y * a.x);
+        }
+        state[base + offset(r)] = acc;
+    }
+}
+"#;
+
+/// State-vector simulation on a GPU through wgpu (Vulkan, Metal, DX12 or
+/// WebGPU), for circuits beyond what `StateVectorSimulator` runs
+/// comfortably. Amplitudes are single precision, as few GPUs offer `f64` in
+/// shaders, so they agree with the CPU simulator to about `1e-6`. The state
+/// must fit one storage buffer of the adapter: 8 bytes times 2^qubits, which
+/// sets `max_qubits`.
+pub struct GpuStateVectorSimulator {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    pipeline: wgpu::ComputePipeline,
+    max_qubits: usize,
+}
+
+impl GpuStateVectorSimulator {
+    /// Opens the system's preferred high-performance adapter, or fails if
+    /// there is none.
+    pub fn new() -> Result<Self, String> {
+        let instance = wgpu::Instance::default();
+        let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            ..Default::default()
+        }))
+        .map_err(|e| format!("No GPU adapter for simulation: {e}"))?;
+        let limits = adapter.limits();
+        let (device, queue) = pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor {
+            label: Some("statevector"),
+            required_limits: limits.clone(),
+            ..Default::default()
+        }))
+        .map_err(|e| format!("Cannot open GPU {}: {e}", adapter.get_info().name))?;
+
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("apply-gate"),
+            source: wgpu::ShaderSource::Wgsl(SHADER.into()),
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("apply-gate"),
+            layout: None,
+            module: &module,
+            entry_point: Some("main"),
+            compilation_options: Default::default(),
+            cache: None,
+        });
+
+        // Indices are u32 in the shader, so 31 qubits at most.
+        let bytes = limits
+            .max_buffer_size
+            .min(limits.max_storage_buffer_binding_size);
+        let max_qubits = (bytes / 8).checked_ilog2().unwrap_or(0).min(31) as usize;
+        Ok(Self {
+            device,
+            queue,
+            pipeline,
+            max_qubits,
+        })
+    }
+
+    /// Records `u` acting on `qubits` of the state in `encoder`.
+    fn record(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        state: &wgpu::Buffer,
+        num_qubits: usize,
+        u: &Matrix,
+        qubits: &[usize],
+    ) -> Result<(), String> {
+        if qubits.len() > MAX_GATE_QUBITS {
+            return Err(format!(
+                "The GPU simulator applies gates on at most {MAX_GATE_QUBITS} qubits, not {}",
+                qubits.len()
+            ));
+        }
+        let mut sorted = qubits.to_vec();
+        sorted.sort_unstable();
+        let count = 1u32 << (num_qubits - qubits.len());
+        let word = |i: usize, list: &[usize]| list.get(i).copied().unwrap_or(0) as u32;
+        let mut params = vec![count, qubits.len() as u32, u.dim as u32, 0];
+        params.extend((0..4).map(|i| word(i, qubits)));
+        params.extend((0..4).map(|i| word(i, &sorted)));
+        let params: Vec<u8> = params.iter().flat_map(|w| w.to_le_bytes()).collect();
+        let matrix: Vec<u8> = u
+            .data
+            .iter()
+            .flat_map(|z| [z.re as f32, z.im as f32])
+            .flat_map(f32::to_le_bytes)
+            .collect();
+
+        let buffer = |contents: &[u8], usage| {
+            self.device
+                .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: None,
+                    contents,
+                    usage,
+                })
+        };
+        let matrix = buffer(&matrix, wgpu::BufferUsages::STORAGE);
+        let params = buffer(&params, wgpu::BufferUsages::UNIFORM);
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &self.pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: state.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: matrix.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: params.as_entire_binding(),
+                },
+            ],
+        });
+
+        let groups = count.div_ceil(WORKGROUP_SIZE);
+        let x = groups.min(MAX_GROUPS_PER_DIMENSION);
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor::default());
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &bind_group, &[]);
+        pass.dispatch_workgroups(x, groups.div_ceil(x), 1);
+        Ok(())
+    }
+}
+
+impl SimulatorBackend for GpuStateVectorSimulator {
+    fn name(&self) -> &'static str {
+        "gpu-statevector"
+    }
+
+    fn max_qubits(&self) -> usize {
+        self.max_qubits
+    }
+
+    fn statevector(&self, circuit: &QuantumCircuit) -> Result<Vec<Complex>, String> {
+        if circuit.num_qubits > self.max_qubits {
+            return Err(format!(
+                "Circuit has {} qubits; the {} simulator handles at most {}",
+                circuit.num_qubits,
+                self.name(),
+                self.max_qubits
+            ));
+        }
+        let circuit = run_pass(&UnrollPass, circuit);
+        let size = 8u64 << circuit.num_qubits;
+        let state = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("state"),
+            size,
+            usage: wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::COPY_DST
+                | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        // Buffers start zeroed, so |0...0> only needs its first amplitude.
+        let one: Vec<u8> = [1.0f32, 0.0].iter().flat_map(|x| x.to_le_bytes()).collect();
+        self.queue.write_buffer(&state, 0, &one);
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        let mut recorded = Ok(());
+        for_each_unitary(&circuit.gates, &mut |u, qubits| {
+            if recorded.is_ok() {
+                recorded = self.record(&mut encoder, &state, circuit.num_qubits, u, qubits);
+            }
+        })?;
+        recorded?;
+
+        let readback = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("readback"),
+            size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        encoder.copy_buffer_to_buffer(&state, 0, &readback, 0, size);
+        self.queue.submit([encoder.finish()]);
+
+        let (sender, receiver) = mpsc::channel();
+        readback.map_async(wgpu::MapMode::Read, .., move |mapped| {
+            let _ = sender.send(mapped);
+        });
+        self.device
+            .poll(wgpu::PollType::wait_indefinitely())
+            .map_err(|e| format!("GPU simulation failed: {e}"))?;
+        receiver
+            .recv()
+            .map_err(|e| format!("GPU simulation failed: {e}"))?
+            .map_err(|e| format!("Cannot read the GPU state back: {e}"))?;
+        let bytes = readback.get_mapped_range(..);
+        let floats: Vec<f32> = bytes
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect();
+        Ok(floats
+            .chunks_exact(2)
+            .map(|z| Complex::new(f64::from(z[0]), f64::from(z[1])))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulator::StateVectorSimulator;
+    use crate::{Gate, Param};
+
+    #[test]
+    fn matches_the_cpu_simulator() {
+        // Machines without a GPU (or a software adapter) have nothing to test.
+        let Ok(gpu) = GpuStateVectorSimulator::new() else {
+            return;
+        };
+        let circuit = QuantumCircuit::new(4, 0).with_gates(vec![
+            Gate::new("h", vec![0], vec![]),
+            Gate::new("cx", vec![0, 3], vec![]),
+            Gate::new("ry", vec![2], vec![Param::Value(0.7)]),
+            Gate::new("ccx", vec![3, 2, 1], vec![]),
+            Gate::new("rz", vec![1], vec![Param::Value(-1.1)]),
+        ]);
+        let expected = StateVectorSimulator::default()
+            .statevector(&circuit)
+            .unwrap();
+        let actual = gpu.statevector(&circuit).unwrap();
+        for (a, e) in actual.iter().zip(&expected) {
+            assert!((*a - *e).abs() < 1e-5, "{a:?} != {e:?}");
+        }
+    }
+}
@@ -0,0 +1,94 @@
+#[cfg(feature = "std")]
+use crate::commutation::DIAGONAL_GATES;
+#[cfg(feature = "std")]
+use crate::prelude::*;
+#[cfg(feature = "std")]
+use crate::{Gate, OptimizationPass, QuantumCircuit};
+
+// ============================================================================
+// INITIAL STATE ASSUMPTION AND |0> SIMPLIFICATION
+// ============================================================================
+
+/// What the circuit may assume about its qubits when it starts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InitialState {
+    /// Every qubit starts in |0>, as on hardware after initialization.
+    #[default]
+    Zero,
+    /// Qubits may hold anything, e.g. a subroutine run mid-program.
+    Unknown,
+}
+
+/// Removes gates that act trivially because the qubits they touch are still
+/// in |0>: diagonal gates (a global phase), controlled gates with a |0>
+/// control, swaps of two |0> qubits and resets of a |0> qubit. A qubit is
+/// back in |0> after an unconditional `reset`. Does nothing unless the
+/// circuit's initial state is `InitialState::Zero`.
+#[cfg(feature = "std")]
+pub struct InitialStateOptimizationPass;
+
+/// Positions of the qubits that must be |1> for the gate to do anything.
+#[cfg(feature = "std")]
+fn controls(name: &str) -> &'static [usize] {
+    match name {
+        "cx" | "cnot" | "cy" | "crz" => &[0],
+        // Symmetric: either qubit acts as the control.
+        "cz" | "cp" | "cu1" => &[0, 1],
+        "ccx" => &[0, 1],
+        _ => &[],
+    }
+}
+
+#[cfg(feature = "std")]
+fn is_trivial(g: &Gate, zero: &[bool]) -> bool {
+    if g.qubits.is_empty() {
+        return false;
+    }
+    let all_zero = g.qubits.iter().all(|&q| zero[q]);
+    match g.name.as_str() {
+        "reset" | "swap" => all_zero,
+        name if controls(name)
+            .iter()
+            .any(|&i| g.qubits.get(i).is_some_and(|&q| zero[q])) =>
+        {
+            true
+        }
+        name => DIAGONAL_GATES.contains(&name) && all_zero,
+    }
+}
+
+#[cfg(feature = "std")]
+impl OptimizationPass for InitialStateOptimizationPass {
+    fn name(&self) -> &str {
+        "initial-state"
+    }
+
+    fn optimize(&self, circuit: &QuantumCircuit) -> QuantumCircuit {
+        if circuit.initial_state != InitialState::Zero {
+            return circuit.clone();
+        }
+        let mut zero = vec![true; circuit.num_qubits];
+        let mut out = Vec::with_capacity(circuit.gates.len());
+        for g in &circuit.gates {
+            let opaque = g.block.is_some() || g.composite.is_some();
+            if !opaque && is_trivial(g, &zero) {
+                continue;
+            }
+            match g.name.as_str() {
+                _ if opaque => g.qubits.iter().for_each(|&q| zero[q] = false),
+                // Measuring |0> leaves it in |0>.
+                "measure" | "barrier" | "delay" => {}
+                "reset" if g.condition.is_none() => g.qubits.iter().for_each(|&q| zero[q] = true),
+                name if DIAGONAL_GATES.contains(&name) => {}
+                _ => g.qubits.iter().for_each(|&q| zero[q] = false),
+            }
+            out.push(g.clone());
+        }
+        circuit.with_gates(out)
+    }
+
+    /// Block bodies don't start at the beginning of the circuit.
+    fn recurse_into_blocks(&self) -> bool {
+        false
+    }
+}
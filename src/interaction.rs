@@ -0,0 +1,177 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::composite::UnrollPass;
+use crate::layout::{Layout, PhysicalQubit};
+use crate::{run_pass, BackendSpec, Gate, QuantumCircuit};
+
+// ============================================================================
+// INTERACTION GRAPH AND ITS EMBEDDING IN THE COUPLING MAP
+// ============================================================================
+
+/// Which qubits of a circuit act together, and how often. A circuit whose
+/// interaction graph embeds in a backend's coupling map runs there without
+/// a single swap.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct InteractionGraph {
+    pub num_qubits: usize,
+    /// Number of gates on each pair `(a, b)` with `a < b`; a gate on more
+    /// than two qubits counts for every pair of them.
+    pub edges: BTreeMap<(usize, usize), usize>,
+}
+
+/// Placements tried before the embedding search gives up.
+const MAX_EMBEDDING_STEPS: usize = 1_000_000;
+
+impl QuantumCircuit {
+    /// The interaction graph, with composite gates unrolled and the bodies of
+    /// control-flow blocks included. Barriers are not interactions.
+    pub fn interaction_graph(&self) -> InteractionGraph {
+        fn add(gates: &[Gate], edges: &mut BTreeMap<(usize, usize), usize>) {
+            for g in gates {
+                if let Some(block) = &g.block {
+                    block
+                        .bodies()
+                        .into_iter()
+                        .for_each(|b| add(&b.gates, edges));
+                    continue;
+                }
+                if g.name == "barrier" {
+                    continue;
+                }
+                for (i, &a) in g.qubits.iter().enumerate() {
+                    for &b in &g.qubits[i + 1..] {
+                        if a != b {
+                            *edges.entry((a.min(b), a.max(b))).or_insert(0) += 1;
+                        }
+                    }
+                }
+            }
+        }
+        let mut edges = BTreeMap::new();
+        add(&run_pass(&UnrollPass, self).gates, &mut edges);
+        InteractionGraph {
+            num_qubits: self.num_qubits,
+            edges,
+        }
+    }
+}
+
+impl InteractionGraph {
+    /// Qubits each qubit interacts with.
+    pub fn neighbors(&self) -> Vec<BTreeSet<usize>> {
+        let mut neighbors = vec![BTreeSet::new(); self.num_qubits];
+        for &(a, b) in self.edges.keys() {
+            neighbors[a].insert(b);
+            neighbors[b].insert(a);
+        }
+        neighbors
+    }
+
+    /// Whether every interacting pair can sit on coupled qubits at once.
+    pub fn embeds_in(&self, backend: &BackendSpec) -> bool {
+        self.embedding(backend).is_some()
+    }
+
+    /// A layout putting every interacting pair on coupled physical qubits,
+    /// so routing from it needs no swaps; `None` if there is none, or if the
+    /// search gives up after `MAX_EMBEDDING_STEPS` placements. Qubits that
+    /// interact with nothing take the lowest free physical qubits.
+    pub fn embedding(&self, backend: &BackendSpec) -> Option<Layout> {
+        if self.num_qubits > backend.num_qubits {
+            return None;
+        }
+        let mut coupled = vec![BTreeSet::new(); backend.num_qubits];
+        for &(a, b) in &backend.coupling_map {
+            if a != b && a < backend.num_qubits && b < backend.num_qubits {
+                coupled[a].insert(b);
+                coupled[b].insert(a);
+            }
+        }
+        let neighbors = self.neighbors();
+        let mut search = EmbeddingSearch {
+            order: search_order(&neighbors),
+            neighbors,
+            coupled,
+            placement: vec![None; self.num_qubits],
+            used: vec![false; backend.num_qubits],
+            steps: 0,
+        };
+        if !search.extend(0) {
+            return None;
+        }
+        let physical = search
+            .placement
+            .into_iter()
+            .map(|p| PhysicalQubit(p.expect("placed")))
+            .collect();
+        Layout::from_physical(physical, backend.num_qubits).ok()
+    }
+}
+
+/// Qubits in the order they are placed: next is the one with the most
+/// neighbors already placed, then the busiest, so every placement after the
+/// first of a connected group is narrowed to the neighbors of a placed one.
+fn search_order(neighbors: &[BTreeSet<usize>]) -> Vec<usize> {
+    let mut order = Vec::with_capacity(neighbors.len());
+    let mut placed = vec![false; neighbors.len()];
+    while order.len() < neighbors.len() {
+        let next = (0..neighbors.len())
+            .filter(|&v| !placed[v])
+            .max_by_key(|&v| {
+                let placed_neighbors = neighbors[v].iter().filter(|&&u| placed[u]).count();
+                (placed_neighbors, neighbors[v].len(), std::cmp::Reverse(v))
+            })
+            .expect("unplaced qubit left");
+        placed[next] = true;
+        order.push(next);
+    }
+    order
+}
+
+/// Backtracking search for an injective placement that maps interaction
+/// edges onto coupling edges.
+struct EmbeddingSearch {
+    order: Vec<usize>,
+    neighbors: Vec<BTreeSet<usize>>,
+    coupled: Vec<BTreeSet<usize>>,
+    placement: Vec<Option<usize>>,
+    used: Vec<bool>,
+    steps: usize,
+}
+
+impl EmbeddingSearch {
+    /// Places `order[i..]`, returning whether it succeeded.
+    fn extend(&mut self, i: usize) -> bool {
+        let Some(&v) = self.order.get(i) else {
+            return true;
+        };
+        let placed: Vec<usize> = self.neighbors[v]
+            .iter()
+            .filter_map(|&u| self.placement[u])
+            .collect();
+        let candidates: Vec<usize> = match placed.first() {
+            Some(&p) => self.coupled[p].iter().copied().collect(),
+            None => (0..self.used.len()).collect(),
+        };
+        for p in candidates {
+            if self.used[p]
+                || self.coupled[p].len() < self.neighbors[v].len()
+                || !placed.iter().all(|q| self.coupled[p].contains(q))
+            {
+                continue;
+            }
+            self.steps += 1;
+            if self.steps > MAX_EMBEDDING_STEPS {
+                return false;
+            }
+            self.placement[v] = Some(p);
+            self.used[p] = true;
+            if self.extend(i + 1) {
+                return true;
+            }
+            self.placement[v] = None;
+            self.used[p] = false;
+        }
+        false
+    }
+}
@@ -0,0 +1,616 @@
+use std::collections::{HashMap, HashSet};
+use std::iter::Peekable;
+use std::sync::Arc;
+
+use crate::angle::{AngleMode, AngleOptions};
+use crate::classical::{ClassicalBit, ClassicalRegister, ClassicalVariable};
+use crate::composite::CompositeGate;
+use crate::control_flow::ControlFlow;
+use crate::duration::Duration;
+use crate::initial_state::InitialState;
+use crate::pulse::extract_calibrations;
+use crate::scheduling::Schedule;
+use crate::signature::CircuitSignature;
+use crate::timing::Timing;
+use crate::{Gate, Param, QASMParser, QuantumCircuit};
+
+// ============================================================================
+// TEXTUAL IR (STABLE DEBUG DUMP AND TEST FIXTURE FORMAT)
+// ============================================================================
+//
+// One instruction per line, qubits written as `$n`, nesting indented by two
+// spaces. `#` starts a comment line. Example:
+//
+//   ir 1
+//   qubits 3
+//   initial_state unknown
+//   timing fixed
+//   input float theta
+//   creg c[2]
+//   var int[8] n = 3
+//   defcal x $0 {
+//     play(drive($0), gaussian(160dt, 0.2, 40dt));
+//   }
+//   gate bell 2 {
+//     h $0
+//     cx $0, $1
+//   }
+//   @0+35 rz(pi/4) $0
+//   bell $1, $2
+//   measure $2 -> c[0]
+//   if (c[0] == 1) x $1
+//   for i in [0:1:3] {
+//     h $0
+//   }
+//
+// `initial_state unknown` is only written for circuits that may not start in
+// |0>, and `timing fixed` only for circuits whose timing must be kept. The
+// `@start+duration` prefix (in `dt`) appears only when a schedule is given and
+// is ignored when parsing, since timing is derived from a backend. OpenPulse
+// `defcalgrammar`, `cal` and `defcal` sections are copied verbatim.
+
+const IR_VERSION: &str = "ir 1";
+
+impl QuantumCircuit {
+    /// Dumps the circuit in the textual IR format.
+    pub fn to_ir(&self) -> String {
+        IrPrinter { schedule: None }.print(self)
+    }
+
+    /// Dumps the circuit with each top-level instruction's start time and
+    /// duration from `schedule`.
+    pub fn to_ir_with_schedule(&self, schedule: &Schedule) -> String {
+        IrPrinter {
+            schedule: Some(schedule),
+        }
+        .print(self)
+    }
+
+    /// Reads a circuit back from the textual IR format.
+    pub fn from_ir(text: &str) -> Result<QuantumCircuit, String> {
+        IrReader::default().read(text)
+    }
+}
+
+struct IrPrinter<'a> {
+    schedule: Option<&'a Schedule>,
+}
+
+impl IrPrinter<'_> {
+    fn print(&self, circuit: &QuantumCircuit) -> String {
+        let mut out = format!("{IR_VERSION}\nqubits {}\n", circuit.num_qubits);
+        if circuit.initial_state == InitialState::Unknown {
+            out.push_str("initial_state unknown\n");
+        }
+        if circuit.timing == Timing::Fixed {
+            out.push_str("timing fixed\n");
+        }
+        for decl in &circuit.signature.inputs {
+            out.push_str(&format!("input {} {}\n", decl.ty, decl.name));
+        }
+        for r in &circuit.cregs {
+            match circuit.signature.output(&r.name) {
+                Some(decl) => out.push_str(&format!("output {} {}\n", decl.ty, decl.name)),
+                None => out.push_str(&format!("creg {}[{}]\n", r.name, r.size)),
+            }
+        }
+        for decl in circuit
+            .signature
+            .outputs
+            .iter()
+            .filter(|d| d.ty.bit_size().is_none())
+        {
+            out.push_str(&format!("output {} {}\n", decl.ty, decl.name));
+        }
+        for variable in &circuit.variables {
+            out.push_str(&format!(
+                "var {}\n",
+                variable.to_string().trim_end_matches(';')
+            ));
+        }
+        for block in &circuit.calibrations.blocks {
+            out.push_str(block.text());
+            out.push('\n');
+        }
+        Self::print_definitions(&circuit.gates, &mut HashSet::new(), &mut out);
+        self.print_gates(&circuit.gates, 0, true, &mut out);
+        out
+    }
+
+    /// Composite definitions used anywhere in `gates`, innermost first.
+    fn print_definitions(gates: &[Gate], defined: &mut HashSet<String>, out: &mut String) {
+        for g in gates {
+            if let Some(block) = &g.block {
+                for body in block.bodies() {
+                    Self::print_definitions(&body.gates, defined, out);
+                }
+            }
+            let Some(composite) = &g.composite else {
+                continue;
+            };
+            if !defined.insert(composite.name.clone()) {
+                continue;
+            }
+            let body = &composite.definition;
+            Self::print_definitions(&body.gates, defined, out);
+            out.push_str(&format!("gate {} {} {{\n", composite.name, body.num_qubits));
+            IrPrinter { schedule: None }.print_gates(&body.gates, 1, false, out);
+            out.push_str("}\n");
+        }
+    }
+
+    fn print_gates(&self, gates: &[Gate], indent: usize, top_level: bool, out: &mut String) {
+        let pad = "  ".repeat(indent);
+        for (index, g) in gates.iter().enumerate() {
+            let timing = match self.schedule.filter(|_| top_level) {
+                Some(s) => s
+                    .gates
+                    .iter()
+                    .find(|sg| sg.index == index)
+                    .map_or(String::new(), |sg| {
+                        format!("@{}+{} ", sg.start, sg.duration)
+                    }),
+                None => String::new(),
+            };
+            if let Some(block) = &g.block {
+                let header = match block.as_ref() {
+                    ControlFlow::IfElse { condition, .. } => format!("if ({condition})"),
+                    ControlFlow::While { condition, .. } => format!("while ({condition})"),
+                    ControlFlow::For {
+                        variable,
+                        start,
+                        step,
+                        end,
+                        ..
+                    } => format!("for {variable} in [{start}:{step}:{end}]"),
+                    ControlFlow::Protected { .. } => "protected".to_string(),
+                };
+                out.push_str(&format!("{pad}{timing}{header} {{\n"));
+                for (i, body) in block.bodies().into_iter().enumerate() {
+                    if i > 0 {
+                        out.push_str(&format!("{pad}}} else {{\n"));
+                    }
+                    self.print_gates(&body.gates, indent + 1, false, out);
+                }
+                out.push_str(&format!("{pad}}}\n"));
+                continue;
+            }
+            let condition = g
+                .condition
+                .as_ref()
+                .map_or(String::new(), |c| format!("if ({c}) "));
+            out.push_str(&format!(
+                "{pad}{timing}{condition}{}\n",
+                Self::instruction(g)
+            ));
+        }
+    }
+
+    fn instruction(g: &Gate) -> String {
+        let mut text = g.name.clone();
+        if !g.params.is_empty() {
+            let params = g
+                .params
+                .iter()
+                .map(|p| p.to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+            text.push_str(&format!("({params})"));
+        }
+        if !g.qubits.is_empty() {
+            let qubits = g
+                .qubits
+                .iter()
+                .map(|q| format!("${q}"))
+                .collect::<Vec<_>>()
+                .join(", ");
+            text.push_str(&format!(" {qubits}"));
+        }
+        if !g.clbits.is_empty() {
+            let bits = g
+                .clbits
+                .iter()
+                .map(|b| b.to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+            text.push_str(&format!(" -> {bits}"));
+        }
+        text
+    }
+}
+
+#[derive(Default)]
+struct IrReader {
+    composites: HashMap<String, Arc<CompositeGate>>,
+}
+
+impl IrReader {
+    fn read(&mut self, text: &str) -> Result<QuantumCircuit, String> {
+        let (text, calibrations) = extract_calibrations(text)?;
+        let mut lines = text
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+            .peekable();
+        if lines.next() != Some(IR_VERSION) {
+            return Err(format!("IR must start with `{IR_VERSION}`"));
+        }
+
+        let mut circuit = QuantumCircuit::default();
+        let mut signature = CircuitSignature::default();
+        while let Some(line) = lines.peek().copied() {
+            if let Some(n) = line.strip_prefix("qubits ") {
+                circuit.num_qubits = n
+                    .trim()
+                    .parse()
+                    .map_err(|_| format!("Invalid qubit count: {line}"))?;
+            } else if let Some(state) = line.strip_prefix("initial_state ") {
+                circuit.initial_state = match state.trim() {
+                    "zero" => InitialState::Zero,
+                    "unknown" => InitialState::Unknown,
+                    _ => return Err(format!("Initial state must be zero or unknown: {line}")),
+                };
+            } else if let Some(timing) = line.strip_prefix("timing ") {
+                circuit.timing = match timing.trim() {
+                    "flexible" => Timing::Flexible,
+                    "fixed" => Timing::Fixed,
+                    _ => return Err(format!("Timing must be flexible or fixed: {line}")),
+                };
+            } else if let Some(spec) = line.strip_prefix("creg ") {
+                circuit.cregs.push(Self::parse_creg(spec)?);
+            } else if let Some(spec) = line.strip_prefix("var ") {
+                circuit.variables.push(ClassicalVariable::parse(spec)?);
+            } else if line.starts_with("input ") || line.starts_with("output ") {
+                let (is_input, decl) = CircuitSignature::parse_declaration(line)?;
+                if let Some(reg) = signature.declare(is_input, decl)? {
+                    circuit.cregs.push(reg);
+                }
+            } else if line.starts_with("gate ") {
+                lines.next();
+                self.read_definition(line, &mut lines)?;
+                continue;
+            } else {
+                break;
+            }
+            lines.next();
+        }
+        circuit.num_clbits = circuit.cregs.iter().map(|r| r.size).sum();
+        circuit.signature = signature;
+        circuit.calibrations = calibrations;
+
+        let (gates, close) = self.read_gates(&mut lines, false)?;
+        if let Some(close) = close {
+            return Err(format!("Unmatched '{close}'"));
+        }
+        circuit.gates = gates;
+        if let Some(g) = Self::first_out_of_range(&circuit.gates, circuit.num_qubits) {
+            return Err(format!(
+                "Instruction {} acts outside the {} declared qubits",
+                g.name, circuit.num_qubits
+            ));
+        }
+        Ok(QASMParser::share_registers(&circuit, &circuit))
+    }
+
+    fn first_out_of_range(gates: &[Gate], num_qubits: usize) -> Option<&Gate> {
+        gates.iter().find_map(|g| match &g.block {
+            Some(block) => block
+                .bodies()
+                .into_iter()
+                .find_map(|body| Self::first_out_of_range(&body.gates, num_qubits)),
+            None => g.qubits.iter().any(|&q| q >= num_qubits).then_some(g),
+        })
+    }
+
+    fn parse_creg(spec: &str) -> Result<ClassicalRegister, String> {
+        let (name, size) = spec
+            .trim()
+            .trim_end_matches(']')
+            .split_once('[')
+            .ok_or_else(|| format!("Malformed register: creg {spec}"))?;
+        Ok(ClassicalRegister {
+            name: name.trim().to_string(),
+            size: size
+                .trim()
+                .parse()
+                .map_err(|_| format!("Malformed register: creg {spec}"))?,
+        })
+    }
+
+    /// `gate name num_qubits {` followed by the body and `}`.
+    fn read_definition<'a, I: Iterator<Item = &'a str>>(
+        &mut self,
+        header: &str,
+        lines: &mut Peekable<I>,
+    ) -> Result<(), String> {
+        let parts: Vec<&str> = header.trim_end_matches('{').split_whitespace().collect();
+        let (name, num_qubits) = match parts[..] {
+            ["gate", name, n] => (
+                name,
+                n.parse()
+                    .map_err(|_| format!("Malformed definition: {header}"))?,
+            ),
+            _ => return Err(format!("Malformed definition: {header}")),
+        };
+        let (gates, close) = self.read_gates(lines, true)?;
+        if close != Some("}") {
+            return Err(format!("Definition of {name} must end with a plain '}}'"));
+        }
+        let definition = QuantumCircuit {
+            gates,
+            ..QuantumCircuit::new(num_qubits, 0)
+        };
+        self.composites
+            .insert(name.to_string(), CompositeGate::new(name, definition)?);
+        Ok(())
+    }
+
+    /// Reads instructions until end of input or, when `nested`, until a
+    /// closing line, which is returned so `} else {` can be handled.
+    fn read_gates<'a, I: Iterator<Item = &'a str>>(
+        &self,
+        lines: &mut Peekable<I>,
+        nested: bool,
+    ) -> Result<(Vec<Gate>, Option<&'a str>), String> {
+        let mut gates = Vec::new();
+        while let Some(line) = lines.next() {
+            if line.starts_with('}') {
+                if nested {
+                    return Ok((gates, Some(line)));
+                }
+                return Err(format!("Unmatched '{line}'"));
+            }
+            let line = Self::strip_timing(line)?;
+            if line.ends_with('{') {
+                gates.push(self.read_block(line, lines)?);
+            } else if line.starts_with("if") {
+                let (condition, rest) = QASMParser::split_condition(line)?;
+                gates.push(self.read_instruction(rest)?.with_condition(condition));
+            } else {
+                gates.push(self.read_instruction(line)?);
+            }
+        }
+        if nested {
+            return Err("Unterminated block: missing '}'".to_string());
+        }
+        Ok((gates, None))
+    }
+
+    fn strip_timing(line: &str) -> Result<&str, String> {
+        let Some(rest) = line.strip_prefix('@') else {
+            return Ok(line);
+        };
+        let (timing, rest) = rest
+            .split_once(char::is_whitespace)
+            .ok_or_else(|| format!("Missing instruction after timing: {line}"))?;
+        let valid = timing.split_once('+').is_some_and(|(start, duration)| {
+            start.parse::<u64>().is_ok() && duration.parse::<u64>().is_ok()
+        });
+        if !valid {
+            return Err(format!(
+                "Malformed timing `@{timing}`, expected @start+duration"
+            ));
+        }
+        Ok(rest.trim())
+    }
+
+    fn read_block<'a, I: Iterator<Item = &'a str>>(
+        &self,
+        header: &str,
+        lines: &mut Peekable<I>,
+    ) -> Result<Gate, String> {
+        let body = |lines: &mut Peekable<I>| -> Result<(QuantumCircuit, &'a str), String> {
+            let (gates, close) = self.read_gates(lines, true)?;
+            let circuit = QuantumCircuit {
+                gates,
+                ..QuantumCircuit::default()
+            };
+            Ok((circuit, close.unwrap_or("}")))
+        };
+        let spec = header.trim_end_matches('{').trim();
+        let block = if spec == "protected" {
+            ControlFlow::Protected {
+                body: body(lines)?.0,
+            }
+        } else if let Some(spec) = spec.strip_prefix("for ") {
+            let (variable, range) = spec
+                .split_once(" in ")
+                .ok_or_else(|| format!("Malformed for loop: {header}"))?;
+            let bounds = range
+                .trim()
+                .trim_start_matches('[')
+                .trim_end_matches(']')
+                .split(':')
+                .map(|b| b.trim().parse::<i64>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|_| format!("Malformed for-loop range: {range}"))?;
+            let [start, step, end] = bounds[..] else {
+                return Err(format!("For-loop range must be [start:step:end]: {range}"));
+            };
+            ControlFlow::For {
+                variable: variable.trim().to_string(),
+                start,
+                step,
+                end,
+                body: body(lines)?.0,
+            }
+        } else if spec.starts_with("while") {
+            let (condition, _) = QASMParser::split_condition(spec)?;
+            ControlFlow::While {
+                condition,
+                body: body(lines)?.0,
+            }
+        } else if spec.starts_with("if") {
+            let (condition, _) = QASMParser::split_condition(spec)?;
+            let (true_body, close) = body(lines)?;
+            let false_body = if close.contains("else") {
+                Some(body(lines)?.0)
+            } else {
+                None
+            };
+            ControlFlow::IfElse {
+                condition,
+                true_body,
+                false_body,
+            }
+        } else {
+            return Err(format!("Unknown block: {header}"));
+        };
+        Ok(Gate::from_block(block))
+    }
+
+    /// `name(params) $a, $b -> reg[i]`, with parameters and operands optional.
+    fn read_instruction(&self, text: &str) -> Result<Gate, String> {
+        let (operation, clbits) = match text.split_once("->") {
+            Some((operation, bits)) => (operation.trim(), Self::read_clbits(bits)?),
+            None => (text.trim(), Vec::new()),
+        };
+        let (head, operands) = match operation.rfind(')') {
+            Some(close) => (&operation[..=close], operation[close + 1..].trim()),
+            None => match operation.split_once(char::is_whitespace) {
+                Some((head, operands)) => (head, operands.trim()),
+                None => (operation, ""),
+            },
+        };
+        let (name, params) = match head.split_once('(') {
+            Some((name, params)) => {
+                // Only exact multiples of pi are printed with `pi`, so the
+                // text alone says whether to read an angle as exact.
+                let params = params
+                    .trim_end_matches(')')
+                    .split(',')
+                    .map(|p| {
+                        // Delays written without a unit count `dt`, as plain numbers.
+                        if let (Ok(d), "delay") = (Duration::parse(p), name.trim()) {
+                            return Ok(Param::Duration(d));
+                        }
+                        let mode = if p.contains("pi") {
+                            AngleMode::ExactPi
+                        } else {
+                            AngleMode::Float
+                        };
+                        AngleOptions {
+                            mode,
+                            ..AngleOptions::default()
+                        }
+                        .parse(p.trim())
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                (name.trim(), params)
+            }
+            None => (head.trim(), Vec::new()),
+        };
+        if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+            return Err(format!("Malformed instruction: {text}"));
+        }
+        let qubits = operands
+            .split(',')
+            .map(str::trim)
+            .filter(|q| !q.is_empty())
+            .map(|q| {
+                q.strip_prefix('$')
+                    .and_then(|n| n.parse().ok())
+                    .ok_or_else(|| format!("Expected a qubit like $0, got `{q}` in: {text}"))
+            })
+            .collect::<Result<Vec<usize>, _>>()?;
+        let mut gate = match self.composites.get(name) {
+            Some(composite) => Gate::from_composite(composite, qubits)?,
+            None => Gate::new(name, qubits, params),
+        };
+        gate.clbits = clbits;
+        Ok(gate)
+    }
+
+    fn read_clbits(text: &str) -> Result<Vec<ClassicalBit>, String> {
+        text.split(',')
+            .map(|b| {
+                let b = b.trim();
+                let (register, index) = b
+                    .trim_end_matches(']')
+                    .split_once('[')
+                    .ok_or_else(|| format!("Expected a classical bit like c[0], got `{b}`"))?;
+                let index = index
+                    .parse()
+                    .map_err(|_| format!("Invalid bit index in `{b}`"))?;
+                Ok(ClassicalBit::new(register.trim(), index))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::roundtrip::structural_diff;
+
+    /// OpenQASM 3 with control-flow blocks, an input/output signature,
+    /// classical registers addressed by bit and a typed variable.
+    const QASM3: &str = "OPENQASM 3.0;
+include \"stdgates.inc\";
+input float theta;
+input int[8] reps;
+output bit[2] result;
+qubit[2] q;
+bit[1] flag;
+int[8] n = 3;
+h q[0];
+rz(theta) q[1];
+flag[0] = measure q[0];
+if (flag[0] == 1) {
+  x q[1];
+} else {
+  z q[1];
+}
+while (flag[0] == 1) {
+  h q[0];
+  flag[0] = measure q[0];
+}
+for int i in [0:2] {
+  cx q[0], q[1];
+}
+result[0] = measure q[0];
+result[1] = measure q[1];
+";
+
+    #[test]
+    fn qasm3_blocks_signature_and_registers_survive_the_ir() {
+        let circuit = QASMParser::default().parse(QASM3).unwrap();
+        let ir = circuit.to_ir();
+        let back = QuantumCircuit::from_ir(&ir).unwrap();
+        assert_eq!(structural_diff(&circuit, &back), None);
+        assert_eq!(back.to_ir(), ir);
+    }
+
+    #[test]
+    fn ir_fixture_prints_back_verbatim() {
+        let ir = "ir 1
+qubits 3
+initial_state unknown
+input angle[20] phi
+output bit flag
+creg c[2]
+var uint[4] k = 2
+var bool done = false
+rz(phi) $0
+measure $0 -> c[0]
+measure $1 -> c[1]
+if (c[0] == 1) x $2
+if (c == 3) {
+  h $2
+}
+while (!done) {
+  measure $2 -> flag[0]
+}
+measure $2 -> flag[0]
+";
+        assert_eq!(QuantumCircuit::from_ir(ir).unwrap().to_ir(), ir);
+    }
+
+    #[test]
+    fn zero_width_types_are_rejected() {
+        let ir = "ir 1\nqubits 1\nvar int[0] n = 0\n";
+        assert!(QuantumCircuit::from_ir(ir).is_err());
+        let qasm = "OPENQASM 3.0;\nqubit[1] q;\ninput uint[0] n;\n";
+        assert!(QASMParser::default().parse(qasm).is_err());
+    }
+}
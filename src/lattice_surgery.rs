@@ -0,0 +1,653 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+
+use crate::QuantumCircuit;
+
+// ============================================================================
+// LATTICE SURGERY LOWERING (EXPERIMENTAL)
+// ============================================================================
+//
+// Lowers a Clifford+T circuit to merge/split operations on surface-code
+// patches laid out on a tile grid. The instruction set, layouts and timing
+// model are deliberately simple and may change; this is a starting point for
+// prototyping fault-tolerant layouts, not a compiler for real devices.
+
+/// Grid position `(row, column)` of a surface-code patch.
+pub type Tile = (usize, usize);
+
+/// Which logical operator a patch boundary exposes to a merge or measurement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatchBasis {
+    X,
+    Z,
+}
+
+/// State a fresh patch is prepared in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatchState {
+    Zero,
+    Plus,
+    /// `T|+>`, delivered by a magic-state factory.
+    Magic,
+}
+
+/// Tile roles: one data patch per logical qubit, bus tiles that merges are
+/// routed through and magic-state ports next to the bus.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PatchLayout {
+    pub rows: usize,
+    pub cols: usize,
+    /// Tile of each logical qubit's patch, by qubit index.
+    pub data: Vec<Tile>,
+    pub bus: Vec<Tile>,
+    pub magic_ports: Vec<Tile>,
+}
+
+impl PatchLayout {
+    /// Data patches in a row with a bus row beneath them, and a magic-state
+    /// port at the end of the data row:
+    ///
+    /// ```text
+    /// q0 q1 q2 .. M
+    /// -- -- -- -- --
+    /// ```
+    pub fn linear(num_qubits: usize) -> Self {
+        Self {
+            rows: 2,
+            cols: num_qubits + 1,
+            data: (0..num_qubits).map(|c| (0, c)).collect(),
+            bus: (0..=num_qubits).map(|c| (1, c)).collect(),
+            magic_ports: vec![(0, num_qubits)],
+        }
+    }
+
+    fn neighbours(&self, (r, c): Tile) -> impl Iterator<Item = Tile> + '_ {
+        [
+            (r.wrapping_sub(1), c),
+            (r + 1, c),
+            (r, c.wrapping_sub(1)),
+            (r, c + 1),
+        ]
+        .into_iter()
+        .filter(|&(r, c)| r < self.rows && c < self.cols)
+    }
+
+    /// Shortest chain of bus tiles from one next to `from` to one next to
+    /// `to`, avoiding `busy` tiles.
+    fn route(&self, from: Tile, to: Tile, busy: &HashSet<Tile>) -> Option<Vec<Tile>> {
+        let bus: HashSet<Tile> = self
+            .bus
+            .iter()
+            .copied()
+            .filter(|t| !busy.contains(t))
+            .collect();
+        let mut parent: HashMap<Tile, Option<Tile>> = HashMap::new();
+        let mut queue = VecDeque::new();
+        for t in self.neighbours(from).filter(|t| bus.contains(t)) {
+            parent.insert(t, None);
+            queue.push_back(t);
+        }
+        while let Some(t) = queue.pop_front() {
+            if self.neighbours(t).any(|n| n == to) {
+                let mut path = vec![t];
+                while let Some(Some(p)) = parent.get(&path[path.len() - 1]) {
+                    path.push(*p);
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for n in self.neighbours(t) {
+                if bus.contains(&n) && !parent.contains_key(&n) {
+                    parent.insert(n, Some(t));
+                    queue.push_back(n);
+                }
+            }
+        }
+        None
+    }
+}
+
+/// One lattice-surgery operation. Measurement outcomes are numbered in
+/// program order.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LsOp {
+    Init {
+        tile: Tile,
+        state: PatchState,
+    },
+    /// Joint Pauli measurement of the operands through the `route` tiles.
+    Merge {
+        operands: Vec<(Tile, PatchBasis)>,
+        route: Vec<Tile>,
+        outcome: usize,
+    },
+    /// Separates the patches of the preceding merge again.
+    Split {
+        tiles: Vec<Tile>,
+    },
+    Measure {
+        tile: Tile,
+        basis: PatchBasis,
+        outcome: usize,
+    },
+    /// Clifford applied within one patch (`h`, `s`, `sdg`, or a Pauli that
+    /// only updates the frame).
+    Local {
+        tile: Tile,
+        gate: String,
+    },
+    /// `gate` applied if the parity of `outcomes` is odd.
+    Conditional {
+        tile: Tile,
+        gate: String,
+        outcomes: Vec<usize>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LsInstruction {
+    /// Time slot, each roughly `d` code cycles long.
+    pub step: usize,
+    pub op: LsOp,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LatticeSurgeryProgram {
+    pub layout: PatchLayout,
+    pub instructions: Vec<LsInstruction>,
+    pub num_steps: usize,
+    pub num_outcomes: usize,
+}
+
+fn fmt_tiles(tiles: &[Tile]) -> String {
+    tiles
+        .iter()
+        .map(|(r, c)| format!("({r},{c})"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+impl fmt::Display for LsOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LsOp::Init { tile, state } => write!(f, "init {state:?} {}", fmt_tiles(&[*tile])),
+            LsOp::Merge {
+                operands,
+                route,
+                outcome,
+            } => {
+                let ops = operands
+                    .iter()
+                    .map(|(t, b)| format!("{b:?}{}", fmt_tiles(&[*t])))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                write!(f, "m{outcome} = merge {ops} via [{}]", fmt_tiles(route))
+            }
+            LsOp::Split { tiles } => write!(f, "split {}", fmt_tiles(tiles)),
+            LsOp::Measure {
+                tile,
+                basis,
+                outcome,
+            } => write!(f, "m{outcome} = measure {basis:?}{}", fmt_tiles(&[*tile])),
+            LsOp::Local { tile, gate } => write!(f, "{gate} {}", fmt_tiles(&[*tile])),
+            LsOp::Conditional {
+                tile,
+                gate,
+                outcomes,
+            } => {
+                let parity = outcomes
+                    .iter()
+                    .map(|o| format!("m{o}"))
+                    .collect::<Vec<_>>()
+                    .join(" ^ ");
+                write!(f, "if ({parity}) {gate} {}", fmt_tiles(&[*tile]))
+            }
+        }
+    }
+}
+
+impl fmt::Display for LatticeSurgeryProgram {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Lattice surgery program: {} instructions in {} steps on a {}x{} tile grid",
+            self.instructions.len(),
+            self.num_steps,
+            self.layout.rows,
+            self.layout.cols
+        )?;
+        for i in &self.instructions {
+            writeln!(f, "  [{:>4}] {}", i.step, i.op)?;
+        }
+        Ok(())
+    }
+}
+
+/// Greedy scheduler: each operation takes the first step at which its
+/// patches are free and a route through idle bus tiles exists.
+struct Lowering<'a> {
+    layout: &'a PatchLayout,
+    instructions: Vec<LsInstruction>,
+    /// Tiles in use at each step.
+    busy: Vec<HashSet<Tile>>,
+    /// First step each data patch is free again.
+    ready: Vec<usize>,
+    outcomes: usize,
+}
+
+impl Lowering<'_> {
+    fn outcome(&mut self) -> usize {
+        self.outcomes += 1;
+        self.outcomes - 1
+    }
+
+    fn emit(&mut self, step: usize, op: LsOp) {
+        self.instructions.push(LsInstruction { step, op });
+    }
+
+    fn reserve(&mut self, step: usize, tiles: impl IntoIterator<Item = Tile>) {
+        if self.busy.len() <= step {
+            self.busy.resize_with(step + 1, HashSet::new);
+        }
+        self.busy[step].extend(tiles);
+    }
+
+    fn is_free(&self, step: usize, tile: Tile) -> bool {
+        self.busy.get(step).is_none_or(|b| !b.contains(&tile))
+    }
+
+    /// First step at or after `from` with a bus route from `a` to `b` free
+    /// for `steps` consecutive steps.
+    fn find_route(
+        &self,
+        from: usize,
+        a: Tile,
+        b: Tile,
+        steps: usize,
+    ) -> Result<(usize, Vec<Tile>), String> {
+        let horizon = from + self.busy.len() + steps + 1;
+        for start in from..horizon {
+            let mut blocked = HashSet::new();
+            for s in start..start + steps {
+                if let Some(b) = self.busy.get(s) {
+                    blocked.extend(b.iter().copied());
+                }
+            }
+            if blocked.contains(&a) || blocked.contains(&b) {
+                continue;
+            }
+            if let Some(route) = self.layout.route(a, b, &blocked) {
+                return Ok((start, route));
+            }
+        }
+        Err(format!(
+            "No bus route between patches {} and {}",
+            fmt_tiles(&[a]),
+            fmt_tiles(&[b])
+        ))
+    }
+
+    fn local(&mut self, q: usize, gate: &str) {
+        let tile = self.layout.data[q];
+        if matches!(gate, "x" | "y" | "z" | "id") {
+            // Paulis are tracked in software and take no time.
+            self.emit(
+                self.ready[q],
+                LsOp::Local {
+                    tile,
+                    gate: gate.to_string(),
+                },
+            );
+            return;
+        }
+        let mut step = self.ready[q];
+        while !self.is_free(step, tile) {
+            step += 1;
+        }
+        self.reserve(step, [tile]);
+        self.emit(
+            step,
+            LsOp::Local {
+                tile,
+                gate: gate.to_string(),
+            },
+        );
+        self.ready[q] = step + 1;
+    }
+
+    /// CNOT through an ancilla on the bus: Z⊗Z with the control, X⊗X with
+    /// the target, then Z measurement of the ancilla.
+    fn cnot(&mut self, c: usize, t: usize) -> Result<(), String> {
+        let (ct, tt) = (self.layout.data[c], self.layout.data[t]);
+        let from = self.ready[c].max(self.ready[t]);
+        let (step, route) = self.find_route(from, ct, tt, 2)?;
+        let ancilla = route[0];
+        for s in step..step + 2 {
+            self.reserve(s, route.iter().copied().chain([ct, tt]));
+        }
+        self.emit(
+            step,
+            LsOp::Init {
+                tile: ancilla,
+                state: PatchState::Plus,
+            },
+        );
+        let m1 = self.outcome();
+        self.emit(
+            step,
+            LsOp::Merge {
+                operands: vec![(ct, PatchBasis::Z), (ancilla, PatchBasis::Z)],
+                route: Vec::new(),
+                outcome: m1,
+            },
+        );
+        self.emit(
+            step,
+            LsOp::Split {
+                tiles: vec![ct, ancilla],
+            },
+        );
+        let m2 = self.outcome();
+        self.emit(
+            step + 1,
+            LsOp::Merge {
+                operands: vec![(ancilla, PatchBasis::X), (tt, PatchBasis::X)],
+                route: route[1..].to_vec(),
+                outcome: m2,
+            },
+        );
+        self.emit(
+            step + 1,
+            LsOp::Split {
+                tiles: vec![ancilla, tt],
+            },
+        );
+        let m3 = self.outcome();
+        self.emit(
+            step + 1,
+            LsOp::Measure {
+                tile: ancilla,
+                basis: PatchBasis::Z,
+                outcome: m3,
+            },
+        );
+        self.emit(
+            step + 1,
+            LsOp::Conditional {
+                tile: ct,
+                gate: "z".to_string(),
+                outcomes: vec![m2],
+            },
+        );
+        self.emit(
+            step + 1,
+            LsOp::Conditional {
+                tile: tt,
+                gate: "x".to_string(),
+                outcomes: vec![m1, m3],
+            },
+        );
+        self.ready[c] = step + 2;
+        self.ready[t] = step + 2;
+        Ok(())
+    }
+
+    /// T by magic-state injection: Z⊗Z between the patch and a magic state,
+    /// X measurement of the magic patch, then S and Z fix-ups.
+    fn t_gate(&mut self, q: usize) -> Result<(), String> {
+        let tile = self.layout.data[q];
+        let port = *self
+            .layout
+            .magic_ports
+            .first()
+            .ok_or("Layout has no magic-state port")?;
+        let (step, route) = self.find_route(self.ready[q], tile, port, 1)?;
+        self.reserve(step, route.iter().copied().chain([tile, port]));
+        self.emit(
+            step,
+            LsOp::Init {
+                tile: port,
+                state: PatchState::Magic,
+            },
+        );
+        let m1 = self.outcome();
+        self.emit(
+            step,
+            LsOp::Merge {
+                operands: vec![(tile, PatchBasis::Z), (port, PatchBasis::Z)],
+                route,
+                outcome: m1,
+            },
+        );
+        self.emit(
+            step,
+            LsOp::Split {
+                tiles: vec![tile, port],
+            },
+        );
+        let m2 = self.outcome();
+        self.emit(
+            step,
+            LsOp::Measure {
+                tile: port,
+                basis: PatchBasis::X,
+                outcome: m2,
+            },
+        );
+        self.emit(
+            step,
+            LsOp::Conditional {
+                tile,
+                gate: "s".to_string(),
+                outcomes: vec![m1],
+            },
+        );
+        self.emit(
+            step,
+            LsOp::Conditional {
+                tile,
+                gate: "z".to_string(),
+                outcomes: vec![m2],
+            },
+        );
+        self.ready[q] = step + 1;
+        Ok(())
+    }
+
+    fn measure(&mut self, q: usize) {
+        let tile = self.layout.data[q];
+        let mut step = self.ready[q];
+        while !self.is_free(step, tile) {
+            step += 1;
+        }
+        self.reserve(step, [tile]);
+        let outcome = self.outcome();
+        self.emit(
+            step,
+            LsOp::Measure {
+                tile,
+                basis: PatchBasis::Z,
+                outcome,
+            },
+        );
+        self.ready[q] = step + 1;
+    }
+}
+
+/// Lowers a Clifford+T circuit (`h`, `s`, `sdg`, Paulis, `cx`, `t`, `tdg`,
+/// `measure`) onto `layout`. Other gates, classical control and composites
+/// must be decomposed first.
+pub fn lower_to_lattice_surgery(
+    circuit: &QuantumCircuit,
+    layout: &PatchLayout,
+) -> Result<LatticeSurgeryProgram, String> {
+    if layout.data.len() < circuit.num_qubits {
+        return Err(format!(
+            "Layout has {} data patches but the circuit needs {}",
+            layout.data.len(),
+            circuit.num_qubits
+        ));
+    }
+    let mut lowering = Lowering {
+        layout,
+        instructions: Vec::new(),
+        busy: Vec::new(),
+        ready: vec![0; circuit.num_qubits],
+        outcomes: 0,
+    };
+    for g in &circuit.gates {
+        if g.condition.is_some() || g.block.is_some() || g.composite.is_some() {
+            return Err(format!(
+                "Cannot lower {} with classical control or a definition; flatten it first",
+                g.name
+            ));
+        }
+        match (g.name.as_str(), g.qubits.as_slice()) {
+            ("barrier", _) => {}
+            ("h" | "s" | "sdg" | "x" | "y" | "z" | "id", [q]) => lowering.local(*q, &g.name),
+            ("cx" | "cnot", [c, t]) => lowering.cnot(*c, *t)?,
+            ("t", [q]) => lowering.t_gate(*q)?,
+            // T† = S† T, with both diagonal.
+            ("tdg", [q]) => {
+                lowering.t_gate(*q)?;
+                lowering.local(*q, "sdg");
+            }
+            ("measure", [q]) => lowering.measure(*q),
+            _ => {
+                return Err(format!(
+                    "{} is not a Clifford+T gate; decompose it before lowering",
+                    g.name
+                ))
+            }
+        }
+    }
+    // Stable, so operations within a step keep program order.
+    let mut instructions = lowering.instructions;
+    instructions.sort_by_key(|i| i.step);
+    let num_steps = instructions.last().map_or(0, |i| i.step + 1);
+    Ok(LatticeSurgeryProgram {
+        layout: layout.clone(),
+        instructions,
+        num_steps,
+        num_outcomes: lowering.outcomes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::classical::ClassicalExpr;
+    use crate::{Gate, Param};
+
+    fn lower(num_qubits: usize, gates: Vec<Gate>) -> Result<LatticeSurgeryProgram, String> {
+        let circuit = QuantumCircuit::new(num_qubits, 0).with_gates(gates);
+        lower_to_lattice_surgery(&circuit, &PatchLayout::linear(num_qubits))
+    }
+
+    fn gate(name: &str, qubits: Vec<usize>) -> Gate {
+        Gate::new(name, qubits, vec![])
+    }
+
+    /// Steps of the merges, in program order.
+    fn merge_steps(program: &LatticeSurgeryProgram) -> Vec<usize> {
+        program
+            .instructions
+            .iter()
+            .filter(|i| matches!(i.op, LsOp::Merge { .. }))
+            .map(|i| i.step)
+            .collect()
+    }
+
+    #[test]
+    fn a_cnot_is_two_merges_through_a_bus_ancilla() {
+        let program = lower(2, vec![gate("cx", vec![0, 1])]).unwrap();
+        assert_eq!(program.num_steps, 2);
+        assert_eq!(program.num_outcomes, 3);
+        let ops: Vec<&LsOp> = program.instructions.iter().map(|i| &i.op).collect();
+        assert_eq!(
+            ops[0],
+            &LsOp::Init {
+                tile: (1, 0),
+                state: PatchState::Plus
+            }
+        );
+        assert_eq!(
+            ops[1],
+            &LsOp::Merge {
+                operands: vec![((0, 0), PatchBasis::Z), ((1, 0), PatchBasis::Z)],
+                route: Vec::new(),
+                outcome: 0,
+            }
+        );
+        assert_eq!(
+            ops[3],
+            &LsOp::Merge {
+                operands: vec![((1, 0), PatchBasis::X), ((0, 1), PatchBasis::X)],
+                route: vec![(1, 1)],
+                outcome: 1,
+            }
+        );
+        // The target's X fix-up depends on the ZZ and the ancilla outcome.
+        assert_eq!(
+            ops[7],
+            &LsOp::Conditional {
+                tile: (0, 1),
+                gate: "x".to_string(),
+                outcomes: vec![0, 2],
+            }
+        );
+        assert!(program
+            .to_string()
+            .contains("m1 = merge X(1,0) X(0,1) via [(1,1)]"));
+    }
+
+    #[test]
+    fn cnots_share_a_step_only_on_disjoint_bus_tiles() {
+        let program = lower(
+            4,
+            vec![
+                gate("cx", vec![0, 1]),
+                gate("cx", vec![2, 3]),
+                gate("cx", vec![0, 3]),
+            ],
+        )
+        .unwrap();
+        assert_eq!(merge_steps(&program), [0, 0, 1, 1, 2, 3]);
+        assert_eq!(program.num_steps, 4);
+    }
+
+    #[test]
+    fn paulis_take_no_time_and_t_uses_the_magic_port() {
+        let program = lower(1, vec![gate("x", vec![0]), gate("h", vec![0])]).unwrap();
+        assert_eq!(program.num_steps, 1);
+
+        let program = lower(1, vec![gate("tdg", vec![0])]).unwrap();
+        assert_eq!(
+            program.instructions[0].op,
+            LsOp::Init {
+                tile: (0, 1),
+                state: PatchState::Magic,
+            }
+        );
+        assert_eq!(program.num_outcomes, 2);
+        let last = program.instructions.last().unwrap();
+        assert_eq!(last.step, 1);
+        assert_eq!(
+            last.op,
+            LsOp::Local {
+                tile: (0, 0),
+                gate: "sdg".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn circuits_outside_clifford_t_are_rejected() {
+        assert!(lower(1, vec![Gate::new("rz", vec![0], vec![Param::Value(0.3)])]).is_err());
+        let circuit = QuantumCircuit::new(3, 0);
+        assert!(lower_to_lattice_surgery(&circuit, &PatchLayout::linear(2)).is_err());
+        let conditioned =
+            gate("x", vec![0]).with_condition(ClassicalExpr::parse("c == 1").unwrap());
+        assert!(lower(1, vec![conditioned]).is_err());
+    }
+}
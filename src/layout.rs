@@ -0,0 +1,151 @@
+use core::fmt;
+use core::ops::Deref;
+
+use crate::prelude::*;
+use crate::{Gate, QuantumCircuit};
+
+// ============================================================================
+// VIRTUAL / PHYSICAL QUBITS AND LAYOUTS
+// ============================================================================
+
+// A `QuantumCircuit`'s gate indices are virtual qubits. Routing, and
+// `QuantumCircuit::apply_layout`, produce a `PhysicalCircuit` instead, whose
+// indices are physical qubits; code that asks the backend about gate qubits
+// (coupling, calibrated errors, crosstalk) takes one, so an unrouted circuit
+// can't reach it.
+
+/// A qubit of the input program, before placement on hardware.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct VirtualQubit(pub usize);
+
+/// A qubit of the device, as numbered by the backend's coupling map.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PhysicalQubit(pub usize);
+
+impl fmt::Display for VirtualQubit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "v{}", self.0)
+    }
+}
+
+impl fmt::Display for PhysicalQubit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "p{}", self.0)
+    }
+}
+
+/// Bijection between the virtual qubits of a circuit and a subset of the
+/// physical qubits of a device.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Layout {
+    virtual_to_physical: Vec<PhysicalQubit>,
+    physical_to_virtual: Vec<Option<VirtualQubit>>,
+}
+
+impl Layout {
+    /// Virtual qubit `i` on physical qubit `i`.
+    pub fn trivial(num_virtual: usize, num_physical: usize) -> Result<Self, String> {
+        Self::from_physical((0..num_virtual).map(PhysicalQubit).collect(), num_physical)
+    }
+
+    /// Layout placing virtual qubit `i` on `physical[i]`.
+    pub fn from_physical(
+        physical: Vec<PhysicalQubit>,
+        num_physical: usize,
+    ) -> Result<Self, String> {
+        let mut physical_to_virtual = vec![None; num_physical];
+        for (v, &p) in physical.iter().enumerate() {
+            let slot = physical_to_virtual.get_mut(p.0).ok_or_else(|| {
+                format!("Physical qubit {p} does not exist on a {num_physical}-qubit device")
+            })?;
+            if slot.is_some() {
+                return Err(format!("Physical qubit {p} is assigned twice"));
+            }
+            *slot = Some(VirtualQubit(v));
+        }
+        Ok(Self {
+            virtual_to_physical: physical,
+            physical_to_virtual,
+        })
+    }
+
+    pub fn num_virtual(&self) -> usize {
+        self.virtual_to_physical.len()
+    }
+
+    pub fn num_physical(&self) -> usize {
+        self.physical_to_virtual.len()
+    }
+
+    pub fn physical(&self, v: VirtualQubit) -> PhysicalQubit {
+        self.virtual_to_physical[v.0]
+    }
+
+    pub fn virtual_at(&self, p: PhysicalQubit) -> Option<VirtualQubit> {
+        self.physical_to_virtual.get(p.0).copied().flatten()
+    }
+
+    /// Exchanges whatever lives on `a` and `b`, as a SWAP gate would.
+    pub fn swap_physical(&mut self, a: PhysicalQubit, b: PhysicalQubit) {
+        let (va, vb) = (self.physical_to_virtual[a.0], self.physical_to_virtual[b.0]);
+        self.physical_to_virtual[a.0] = vb;
+        self.physical_to_virtual[b.0] = va;
+        if let Some(v) = va {
+            self.virtual_to_physical[v.0] = b;
+        }
+        if let Some(v) = vb {
+            self.virtual_to_physical[v.0] = a;
+        }
+    }
+
+    /// `(virtual, physical)` pairs in virtual qubit order.
+    pub fn iter(&self) -> impl Iterator<Item = (VirtualQubit, PhysicalQubit)> + '_ {
+        self.virtual_to_physical
+            .iter()
+            .enumerate()
+            .map(|(v, &p)| (VirtualQubit(v), p))
+    }
+}
+
+/// A circuit whose gate indices are physical qubits of a device. It derefs to
+/// the circuit, which is all most code needs; `qubit` and `gate_qubits`
+/// give the indices as `PhysicalQubit`s.
+#[derive(Debug, Clone)]
+pub struct PhysicalCircuit(QuantumCircuit);
+
+impl PhysicalCircuit {
+    /// Takes `circuit`'s indices to be physical qubits already, e.g. for a
+    /// program written against a device or the body of a block inside a
+    /// physical circuit.
+    pub fn assume_physical(circuit: QuantumCircuit) -> Self {
+        Self(circuit)
+    }
+
+    pub fn into_circuit(self) -> QuantumCircuit {
+        self.0
+    }
+
+    /// `f` applied to the circuit, for changes that keep every gate on the
+    /// qubits it had, such as optimization passes.
+    pub fn map(&self, f: impl FnOnce(&QuantumCircuit) -> QuantumCircuit) -> Self {
+        Self(f(&self.0))
+    }
+
+    /// The physical qubit behind gate index `index`.
+    pub fn qubit(&self, index: usize) -> PhysicalQubit {
+        PhysicalQubit(index)
+    }
+
+    /// The physical qubits `gate`, a gate of this circuit, acts on.
+    pub fn gate_qubits(&self, gate: &Gate) -> Vec<PhysicalQubit> {
+        gate.qubits.iter().map(|&q| self.qubit(q)).collect()
+    }
+}
+
+impl Deref for PhysicalCircuit {
+    type Target = QuantumCircuit;
+
+    fn deref(&self) -> &QuantumCircuit {
+        &self.0
+    }
+}
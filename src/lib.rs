@@ -0,0 +1,3045 @@
+//! Circuit model, optimization passes, routing and the OpenQASM front end.
+//! The `transpiler_arch` binary is a thin command line over this library;
+//! other crates can add routers (`routing::RouterRegistry`), passes
+//! (`passes::PassRegistry`) and linear algebra backends
+//! (`linalg::set_backend`).
+//!
+//! Without the default `std` feature only the circuit model and the basic
+//! passes are built, as `no_std` code that needs `alloc`.
+
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::sync::Arc;
+use core::fmt;
+#[cfg(feature = "std")]
+use std::collections::{HashMap, HashSet};
+#[cfg(feature = "parser")]
+use std::iter::Peekable;
+
+use prelude::*;
+
+/// What the standard prelude provides beyond `core`, for modules that also
+/// build without `std`.
+mod prelude {
+    pub use alloc::boxed::Box;
+    pub use alloc::string::{String, ToString};
+    pub use alloc::vec::Vec;
+    pub use alloc::{format, vec};
+}
+
+#[cfg(feature = "parser")]
+pub mod aliases;
+pub mod angle;
+#[cfg(feature = "parser")]
+pub mod archive;
+#[cfg(feature = "std")]
+pub mod calibration;
+#[cfg(feature = "std")]
+pub mod canonical;
+#[cfg(feature = "std")]
+pub mod characterization;
+pub mod classical;
+#[cfg(feature = "std")]
+pub mod commutation;
+pub mod composite;
+pub mod control_flow;
+#[cfg(feature = "providers")]
+pub mod cost;
+pub mod duration;
+#[cfg(feature = "exact-routing")]
+pub mod exact_routing;
+#[cfg(feature = "providers")]
+pub mod fleet;
+#[cfg(feature = "gpu")]
+pub mod gpu;
+pub mod initial_state;
+#[cfg(feature = "std")]
+pub mod interaction;
+#[cfg(feature = "parser")]
+pub mod ir;
+#[cfg(feature = "std")]
+pub mod json;
+#[cfg(feature = "std")]
+pub mod lattice_surgery;
+pub mod layout;
+#[cfg(feature = "std")]
+pub mod library;
+#[cfg(feature = "std")]
+pub mod linalg;
+#[cfg(feature = "parser")]
+pub mod lint;
+#[cfg(feature = "std")]
+pub mod mapping;
+#[cfg(feature = "std")]
+pub mod moments;
+#[cfg(feature = "std")]
+pub mod objective;
+#[cfg(feature = "std")]
+pub mod passes;
+#[cfg(feature = "std")]
+pub mod pauli_frame;
+pub mod pulse;
+#[cfg(feature = "std")]
+pub mod qaoa;
+#[cfg(feature = "std")]
+pub mod qft;
+#[cfg(feature = "std")]
+pub mod relabel;
+#[cfg(feature = "std")]
+pub mod resources;
+#[cfg(feature = "parser")]
+pub mod roundtrip;
+#[cfg(feature = "std")]
+pub mod routing;
+#[cfg(feature = "std")]
+pub mod scheduling;
+#[cfg(feature = "std")]
+pub mod shots;
+pub mod signature;
+#[cfg(feature = "simulator")]
+pub mod simulator;
+#[cfg(feature = "router")]
+pub mod teleport;
+pub mod timing;
+#[cfg(feature = "std")]
+pub mod trace;
+#[cfg(feature = "std")]
+pub mod unobservable;
+#[cfg(feature = "viz")]
+pub mod viz;
+
+#[cfg(feature = "parser")]
+use aliases::GateAliases;
+#[cfg(feature = "parser")]
+use angle::{AngleOptions, AngleUnit};
+use angle::{Rational, DEFAULT_ANGLE_TOLERANCE};
+#[cfg(feature = "std")]
+use calibration::CalibrationSnapshot;
+use classical::{ClassicalBit, ClassicalExpr, ClassicalRegister, ClassicalVariable};
+use composite::CompositeGate;
+#[cfg(feature = "std")]
+use composite::UnrollPass;
+use control_flow::ControlFlow;
+#[cfg(feature = "parser")]
+use control_flow::Pragma;
+use duration::Duration;
+#[cfg(feature = "parser")]
+use duration::DurationUnit;
+use initial_state::InitialState;
+#[cfg(feature = "std")]
+use initial_state::InitialStateOptimizationPass;
+#[cfg(feature = "std")]
+use layout::{Layout, PhysicalCircuit, PhysicalQubit, VirtualQubit};
+#[cfg(feature = "std")]
+use objective::{CircuitMetrics, OptimizationObjective, StoppingCriterion};
+#[cfg(feature = "std")]
+use passes::Pipeline;
+use pulse::PulseCalibrations;
+#[cfg(feature = "std")]
+use routing::RoutingStrategy;
+#[cfg(feature = "std")]
+use scheduling::{SchedulingPass, TimingConstraints};
+use signature::CircuitSignature;
+use timing::Timing;
+#[cfg(feature = "std")]
+use trace::PassRecord;
+
+// ============================================================================
+// CORE DATA STRUCTURES
+// ============================================================================
+
+#[derive(Debug, Clone, Default)]
+pub struct QuantumCircuit {
+    pub num_qubits: usize,
+    pub num_clbits: usize,
+    pub gates: Vec<Gate>,
+    /// Classical registers in declaration order; their sizes sum to `num_clbits`.
+    pub cregs: Vec<ClassicalRegister>,
+    /// Typed classical variables (OpenQASM 3), in declaration order.
+    pub variables: Vec<ClassicalVariable>,
+    /// Declared `input`/`output` variables (OpenQASM 3).
+    pub signature: CircuitSignature,
+    /// Whether the qubits may be assumed to start in |0>.
+    pub initial_state: InitialState,
+    /// Whether gate timing must be kept as written.
+    pub timing: Timing,
+    /// Pulse-level calibrations passed through from the source program.
+    pub calibrations: PulseCalibrations,
+}
+
+#[derive(Debug, Clone)]
+pub struct Gate {
+    pub name: String,
+    /// Virtual qubits, or physical ones in a `layout::PhysicalCircuit`.
+    pub qubits: Vec<usize>,
+    pub params: Vec<Param>,
+    /// Classical condition gating execution (`if (c == 3) x q[0];`).
+    pub condition: Option<ClassicalExpr>,
+    /// Nested bodies when this "gate" is an if/while/for block.
+    pub block: Option<Box<ControlFlow>>,
+    /// Shared subcircuit definition when this gate is a composite instance.
+    pub composite: Option<Arc<CompositeGate>>,
+    /// Classical bits written by the gate, one per qubit of a `measure`.
+    pub clbits: Vec<ClassicalBit>,
+}
+
+impl Gate {
+    pub fn new(name: &str, qubits: Vec<usize>, params: Vec<Param>) -> Self {
+        Self {
+            name: name.to_string(),
+            qubits,
+            params,
+            condition: None,
+            block: None,
+            composite: None,
+            clbits: Vec::new(),
+        }
+    }
+
+    /// Wraps a control-flow block as a gate acting on every qubit its bodies touch.
+    pub fn from_block(block: ControlFlow) -> Self {
+        Self {
+            name: block.name().to_string(),
+            qubits: block.qubits(),
+            params: Vec::new(),
+            condition: None,
+            block: Some(Box::new(block)),
+            composite: None,
+            clbits: Vec::new(),
+        }
+    }
+
+    pub fn with_condition(mut self, condition: ClassicalExpr) -> Self {
+        self.condition = Some(condition);
+        self
+    }
+
+    /// Measurement of `qubit` into `bit`.
+    pub fn measure(qubit: usize, bit: ClassicalBit) -> Self {
+        Self {
+            clbits: vec![bit],
+            ..Self::new("measure", vec![qubit], Vec::new())
+        }
+    }
+}
+
+/// A gate parameter: either a concrete angle or a symbolic one that is
+/// resolved later via `QuantumCircuit::bind_parameters`, or the duration
+/// of a `delay`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Param {
+    Value(f64),
+    /// Exact rational multiple of pi, kept so that angles like `pi/4`
+    /// survive merging and round trips without floating-point drift.
+    Pi(Rational),
+    /// `scale * name`, e.g. the `2*gamma` of a QAOA cost layer.
+    Symbol {
+        name: String,
+        scale: f64,
+    },
+    /// Length of a `delay`, in the unit it was written in.
+    Duration(Duration),
+}
+
+impl Param {
+    pub fn symbol(name: &str) -> Self {
+        Param::Symbol {
+            name: name.to_string(),
+            scale: 1.0,
+        }
+    }
+
+    pub fn value(&self) -> Option<f64> {
+        match self {
+            Param::Value(v) => Some(*v),
+            Param::Pi(r) => Some(r.to_f64() * core::f64::consts::PI),
+            Param::Symbol { .. } | Param::Duration(_) => None,
+        }
+    }
+
+    /// Sum of two parameters, if it can be expressed as a single `Param`.
+    pub fn add(&self, other: &Param) -> Option<Param> {
+        match (self, other) {
+            (Param::Value(a), Param::Value(b)) => Some(Param::Value(a + b)),
+            (Param::Pi(a), Param::Pi(b)) => Some(match a.checked_add(*b) {
+                Some(sum) => Param::Pi(sum),
+                None => Param::Value((a.to_f64() + b.to_f64()) * core::f64::consts::PI),
+            }),
+            (Param::Value(_) | Param::Pi(_), Param::Value(_) | Param::Pi(_)) => {
+                Some(Param::Value(self.value()? + other.value()?))
+            }
+            (
+                Param::Symbol {
+                    name: n1,
+                    scale: s1,
+                },
+                Param::Symbol {
+                    name: n2,
+                    scale: s2,
+                },
+            ) if n1 == n2 => Some(Param::Symbol {
+                name: n1.clone(),
+                scale: s1 + s2,
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.is_zero_within(DEFAULT_ANGLE_TOLERANCE)
+    }
+
+    /// Zero test for float angles; exact angles must be exactly zero.
+    pub fn is_zero_within(&self, tolerance: f64) -> bool {
+        match self {
+            Param::Value(v) => v.abs() <= tolerance,
+            Param::Pi(r) => r.is_zero(),
+            Param::Symbol { scale, .. } => scale.abs() <= tolerance,
+            Param::Duration(d) => d.value.abs() <= tolerance,
+        }
+    }
+
+    fn bind(&self, values: &BTreeMap<String, f64>) -> Result<Param, String> {
+        match self {
+            Param::Value(_) | Param::Pi(_) | Param::Duration(_) => Ok(self.clone()),
+            Param::Symbol { name, scale } => values
+                .get(name)
+                .map(|v| Param::Value(scale * v))
+                .ok_or_else(|| format!("No value bound for parameter {name}")),
+        }
+    }
+}
+
+impl fmt::Display for Param {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Param::Value(v) => write!(f, "{v}"),
+            Param::Pi(r) => match (r.num, r.den) {
+                (0, _) => write!(f, "0"),
+                (1, 1) => write!(f, "pi"),
+                (-1, 1) => write!(f, "-pi"),
+                (n, 1) => write!(f, "{n}*pi"),
+                (1, d) => write!(f, "pi/{d}"),
+                (-1, d) => write!(f, "-pi/{d}"),
+                (n, d) => write!(f, "{n}*pi/{d}"),
+            },
+            Param::Symbol { name, scale } if *scale == 1.0 => write!(f, "{name}"),
+            Param::Symbol { name, scale } => write!(f, "{scale}*{name}"),
+            Param::Duration(d) => write!(f, "{d}"),
+        }
+    }
+}
+
+impl QuantumCircuit {
+    /// Empty circuit; any classical bits live in a single register `c`.
+    pub fn new(num_qubits: usize, num_clbits: usize) -> Self {
+        let cregs = if num_clbits > 0 {
+            vec![ClassicalRegister {
+                name: "c".to_string(),
+                size: num_clbits,
+            }]
+        } else {
+            Vec::new()
+        };
+        Self {
+            num_qubits,
+            num_clbits,
+            gates: Vec::new(),
+            cregs,
+            variables: Vec::new(),
+            signature: CircuitSignature::default(),
+            initial_state: InitialState::default(),
+            timing: Timing::default(),
+            calibrations: PulseCalibrations::default(),
+        }
+    }
+
+    /// Copy of this circuit's registers and metadata with a new gate list,
+    /// which is what most passes produce.
+    pub fn with_gates(&self, gates: Vec<Gate>) -> QuantumCircuit {
+        QuantumCircuit {
+            num_qubits: self.num_qubits,
+            num_clbits: self.num_clbits,
+            gates,
+            cregs: self.cregs.clone(),
+            variables: self.variables.clone(),
+            signature: self.signature.clone(),
+            initial_state: self.initial_state,
+            timing: self.timing,
+            calibrations: self.calibrations.clone(),
+        }
+    }
+
+    /// Position of `bit` among all classical bits, registers laid out in
+    /// declaration order.
+    pub fn clbit_index(&self, bit: &ClassicalBit) -> Option<usize> {
+        let mut offset = 0;
+        for r in &self.cregs {
+            if r.name == bit.register {
+                return (bit.index < r.size).then_some(offset + bit.index);
+            }
+            offset += r.size;
+        }
+        None
+    }
+
+    /// Names of the unbound symbolic parameters, in order of first use.
+    pub fn parameters(&self) -> Vec<String> {
+        let mut seen = BTreeSet::new();
+        let mut names = Vec::new();
+        for g in &self.gates {
+            for p in &g.params {
+                if let Param::Symbol { name, .. } = p {
+                    if seen.insert(name.clone()) {
+                        names.push(name.clone());
+                    }
+                }
+            }
+        }
+        names
+    }
+
+    /// Returns a copy of the circuit with every symbolic parameter replaced by
+    /// its value from `values`.
+    pub fn bind_parameters(
+        &self,
+        values: &BTreeMap<String, f64>,
+    ) -> Result<QuantumCircuit, String> {
+        let mut gates = Vec::with_capacity(self.gates.len());
+        for g in &self.gates {
+            let params = g
+                .params
+                .iter()
+                .map(|p| p.bind(values))
+                .collect::<Result<Vec<_>, _>>()?;
+            gates.push(Gate {
+                params,
+                ..g.clone()
+            });
+        }
+        Ok(self.with_gates(gates))
+    }
+}
+
+/// Measurement histogram keyed by bitstring. Qubit 0 is the rightmost
+/// character, following the usual OpenQASM convention.
+#[cfg(feature = "std")]
+pub type Counts = HashMap<String, usize>;
+
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Default)]
+pub struct BackendSpec {
+    pub name: String,
+    pub num_qubits: usize,
+    pub coupling_map: Vec<(usize, usize)>,
+    pub native_gates: HashSet<String>,
+    /// Gate durations in units of the device sample time `dt`.
+    pub gate_durations: HashMap<String, u64>,
+    /// Length of `dt` in seconds, to convert durations given in time units
+    /// (e.g. `delay[100ns]`); without it only `dt` durations can be scheduled.
+    pub dt: Option<f64>,
+    pub timing_constraints: TimingConstraints,
+    /// Pairs of coupling-map edges whose two-qubit gates degrade each other
+    /// when run at the same time; the crosstalk-aware scheduler serializes them.
+    pub crosstalk_pairs: Vec<((usize, usize), (usize, usize))>,
+    /// Whether the device supports mid-circuit measurement with classical
+    /// feedforward, which teleportation-based routing relies on.
+    pub supports_dynamic_circuits: bool,
+    /// Measured error rates; without one, default rates are assumed.
+    pub calibration: Option<CalibrationSnapshot>,
+}
+
+#[cfg(feature = "std")]
+impl BackendSpec {
+    pub fn are_coupled(&self, a: PhysicalQubit, b: PhysicalQubit) -> bool {
+        self.coupling_map.contains(&(a.0, b.0)) || self.coupling_map.contains(&(b.0, a.0))
+    }
+
+    /// All-pairs shortest path lengths over the undirected coupling graph;
+    /// `usize::MAX` marks unreachable pairs.
+    pub fn distance_matrix(&self) -> Vec<Vec<usize>> {
+        let n = self.num_qubits;
+        let mut adjacency = vec![Vec::new(); n];
+        for &(a, b) in &self.coupling_map {
+            if a < n && b < n {
+                adjacency[a].push(b);
+                adjacency[b].push(a);
+            }
+        }
+        (0..n)
+            .map(|src| {
+                let mut dist = vec![usize::MAX; n];
+                dist[src] = 0;
+                let mut queue = std::collections::VecDeque::from([src]);
+                while let Some(u) = queue.pop_front() {
+                    for &v in &adjacency[u] {
+                        if dist[v] == usize::MAX {
+                            dist[v] = dist[u] + 1;
+                            queue.push_back(v);
+                        }
+                    }
+                }
+                dist
+            })
+            .collect()
+    }
+
+    pub fn single_qubit_error(&self, qubit: PhysicalQubit) -> f64 {
+        self.calibration
+            .as_ref()
+            .and_then(|c| c.single_qubit_error(qubit.0))
+            .unwrap_or(objective::DEFAULT_SINGLE_QUBIT_ERROR)
+    }
+
+    pub fn two_qubit_error(&self, a: PhysicalQubit, b: PhysicalQubit) -> f64 {
+        self.calibration
+            .as_ref()
+            .and_then(|c| c.two_qubit_error(a.0, b.0))
+            .unwrap_or(objective::DEFAULT_TWO_QUBIT_ERROR)
+    }
+}
+
+// ============================================================================
+// SIMPLE QASM PARSER (MINIMAL BUT ROBUST ENOUGH FOR DEMO)
+// ============================================================================
+
+/// How the parser treats input it cannot represent.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ParseMode {
+    /// Unknown gates, wrong parameter or qubit counts, malformed gate
+    /// statements and declarations, and redeclared registers are errors.
+    Strict,
+    /// Such input is read as well as possible, or skipped, with a warning.
+    #[default]
+    Permissive,
+}
+
+/// Something the parser skipped or had to guess at.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseWarning {
+    pub statement: String,
+    pub message: String,
+}
+
+#[cfg(feature = "std")]
+impl fmt::Display for ParseWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.message, self.statement)
+    }
+}
+
+/// Qubit and parameter counts of the gates the parser knows, by canonical
+/// name.
+#[cfg(feature = "parser")]
+const GATE_ARITIES: &[(&str, usize, usize)] = &[
+    ("id", 1, 0),
+    ("x", 1, 0),
+    ("y", 1, 0),
+    ("z", 1, 0),
+    ("h", 1, 0),
+    ("s", 1, 0),
+    ("sdg", 1, 0),
+    ("t", 1, 0),
+    ("tdg", 1, 0),
+    ("sx", 1, 0),
+    ("rx", 1, 1),
+    ("ry", 1, 1),
+    ("rz", 1, 1),
+    ("p", 1, 1),
+    ("u1", 1, 1),
+    ("u2", 1, 2),
+    ("u", 1, 3),
+    ("u3", 1, 3),
+    ("cx", 2, 0),
+    ("cy", 2, 0),
+    ("cz", 2, 0),
+    ("cp", 2, 1),
+    ("cu1", 2, 1),
+    ("crz", 2, 1),
+    ("swap", 2, 0),
+    ("rxx", 2, 1),
+    ("rzz", 2, 1),
+    ("ccx", 3, 0),
+];
+
+/// Statements the parser reads past without representing them.
+#[cfg(feature = "parser")]
+const SKIPPED_STATEMENTS: &[&str] = &["gate", "opaque", "def", "extern", "let", "const"];
+
+#[cfg(feature = "parser")]
+#[derive(Debug, Clone, Default)]
+pub struct QASMParser {
+    /// How numeric gate parameters are represented.
+    pub angles: AngleOptions,
+    /// Vendor gate names read as the transpiler's own.
+    pub aliases: GateAliases,
+    pub mode: ParseMode,
+}
+
+/// A declared quantum register, placed after the ones before it.
+#[cfg(feature = "parser")]
+struct QuantumRegister {
+    name: String,
+    offset: usize,
+    size: usize,
+}
+
+/// Declarations and warnings gathered while parsing.
+#[cfg(feature = "parser")]
+#[derive(Default)]
+struct ParseState {
+    num_qubits: usize,
+    qregs: Vec<QuantumRegister>,
+    cregs: Vec<ClassicalRegister>,
+    variables: Vec<ClassicalVariable>,
+    signature: CircuitSignature,
+    warnings: Vec<ParseWarning>,
+}
+
+#[cfg(feature = "parser")]
+impl QASMParser {
+    pub fn parse(&self, input: &str) -> Result<QuantumCircuit, String> {
+        self.parse_with_warnings(input).map(|(circuit, _)| circuit)
+    }
+
+    /// Parses `input`, also returning what was skipped or guessed at. In
+    /// strict mode anything that would warn in permissive mode is an error,
+    /// except statements the parser skips by design, such as `gate` definitions.
+    pub fn parse_with_warnings(
+        &self,
+        input: &str,
+    ) -> Result<(QuantumCircuit, Vec<ParseWarning>), String> {
+        let mut state = ParseState::default();
+        let (input, calibrations) = pulse::extract_calibrations(input)?;
+        let calibrated = calibrations.gate_names();
+        let mut lines = input
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty() && (!l.starts_with("//") || Pragma::parse(l).is_some()))
+            .peekable();
+
+        let (gates, _) = self.parse_statements(&mut lines, &mut state, &calibrated, false)?;
+
+        let circuit = QuantumCircuit {
+            num_qubits: state.num_qubits,
+            num_clbits: state.cregs.iter().map(|r| r.size).sum(),
+            gates,
+            cregs: state.cregs,
+            variables: state.variables,
+            signature: state.signature,
+            initial_state: InitialState::default(),
+            timing: Timing::default(),
+            calibrations,
+        };
+        Ok((Self::share_registers(&circuit, &circuit), state.warnings))
+    }
+
+    /// Fails in strict mode, records a warning otherwise.
+    fn reject(
+        &self,
+        state: &mut ParseState,
+        statement: &str,
+        message: String,
+    ) -> Result<(), String> {
+        match self.mode {
+            ParseMode::Strict => Err(format!("{message}: {statement}")),
+            ParseMode::Permissive => {
+                state.warnings.push(ParseWarning {
+                    statement: statement.to_string(),
+                    message,
+                });
+                Ok(())
+            }
+        }
+    }
+
+    /// Whether `line` applies a known gate, or one the program calibrates
+    /// itself, which is passed through. Permissive mode also takes names
+    /// starting like a few common gates, as it always has, with a warning.
+    fn is_gate_statement(
+        &self,
+        line: &str,
+        state: &mut ParseState,
+        calibrated: &BTreeSet<String>,
+    ) -> Result<bool, String> {
+        let name = self.aliases.canonical(Self::gate_name(line));
+        if SKIPPED_STATEMENTS.contains(&name) {
+            state.warnings.push(ParseWarning {
+                statement: line.to_string(),
+                message: format!("Skipped {name} statement, which the parser does not represent"),
+            });
+            return Ok(false);
+        }
+        if ["OPENQASM", "include"].contains(&name) {
+            return Ok(false);
+        }
+        if GATE_ARITIES.iter().any(|&(known, ..)| known == name) || calibrated.contains(name) {
+            return Ok(true);
+        }
+        let guessed = self.mode == ParseMode::Permissive && Self::is_supported_gate(name);
+        let message = if guessed {
+            format!("Unknown gate '{name}' kept as is")
+        } else if ["qubit", "bit", "duration"].contains(&name) {
+            "Unsupported declaration skipped".to_string()
+        } else if !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+            format!("Unknown gate '{name}' skipped")
+        } else {
+            "Unsupported statement skipped".to_string()
+        };
+        self.reject(state, line, message)?;
+        Ok(guessed)
+    }
+
+    /// Checks a parsed gate against its known qubit and parameter counts and
+    /// the declared qubits.
+    fn check_gate(&self, g: &Gate, line: &str, state: &mut ParseState) -> Result<(), String> {
+        if let Some(&(_, qubits, params)) = GATE_ARITIES.iter().find(|&&(name, ..)| name == g.name)
+        {
+            if g.qubits.len() != qubits || g.params.len() != params {
+                let message = format!(
+                    "Gate {} takes {qubits} qubit(s) and {params} parameter(s), not {} and {}",
+                    g.name,
+                    g.qubits.len(),
+                    g.params.len()
+                );
+                self.reject(state, line, message)?;
+            }
+        }
+        if let Some(&q) = g.qubits.iter().find(|&&q| q >= state.num_qubits) {
+            self.reject(
+                state,
+                line,
+                format!("Qubit {q} is not declared ({} qubits)", state.num_qubits),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Parses statements until end of input or, when `nested`, until the
+    /// closing `}` line, which is returned so `} else {` can be handled.
+    fn parse_statements<'a, I: Iterator<Item = &'a str>>(
+        &self,
+        lines: &mut Peekable<I>,
+        state: &mut ParseState,
+        calibrated: &BTreeSet<String>,
+        nested: bool,
+    ) -> Result<(Vec<Gate>, Option<&'a str>), String> {
+        let mut gates = Vec::new();
+
+        while let Some(line) = lines.next() {
+            if line.starts_with('}') || Pragma::parse(line) == Some(Pragma::NoOptEnd) {
+                if nested {
+                    return Ok((gates, Some(line)));
+                }
+                return Err(format!("Unmatched '{line}'"));
+            }
+
+            if Pragma::parse(line) == Some(Pragma::NoOptBegin) {
+                let (body, close) = self.parse_statements(lines, state, calibrated, true)?;
+                if close.and_then(Pragma::parse) != Some(Pragma::NoOptEnd) {
+                    return Err(format!(
+                        "Block closed by '{}' inside a no-opt region",
+                        close.unwrap_or("")
+                    ));
+                }
+                let body = QuantumCircuit {
+                    gates: body,
+                    ..QuantumCircuit::default()
+                };
+                gates.push(Gate::from_block(ControlFlow::Protected { body }));
+                continue;
+            }
+
+            if line.starts_with("qreg") || line.starts_with("qubit[") {
+                // e.g. qreg q[3];  /  qubit[3] q;
+                match Self::parse_qreg(line) {
+                    Some((name, _)) if state.qregs.iter().any(|r| r.name == name) => self.reject(
+                        state,
+                        line,
+                        format!("Quantum register '{name}' is already declared"),
+                    )?,
+                    Some((name, size)) => {
+                        state.qregs.push(QuantumRegister {
+                            name,
+                            offset: state.num_qubits,
+                            size,
+                        });
+                        state.num_qubits += size;
+                    }
+                    None => self.reject(
+                        state,
+                        line,
+                        "Malformed qubit declaration skipped".to_string(),
+                    )?,
+                }
+            } else if line.starts_with("input ") || line.starts_with("output ") {
+                // e.g. input float theta;  /  output bit[2] result;
+                if nested {
+                    return Err(format!("Input/output declarations must be global: {line}"));
+                }
+                let (is_input, decl) = CircuitSignature::parse_declaration(line)?;
+                if let Some(reg) = state.signature.declare(is_input, decl)? {
+                    state.cregs.push(reg);
+                }
+            } else if line.starts_with("creg")
+                || line.starts_with("bit[")
+                || line.starts_with("bit ")
+            {
+                // e.g. creg c[3];  /  bit[3] c;  /  bit[2] c = measure q;
+                let (decl, init) = match line.split_once('=') {
+                    Some((decl, init)) => (decl, Some(init)),
+                    None => (line, None),
+                };
+                match Self::parse_creg(decl) {
+                    Some(reg) => {
+                        let name = reg.name.clone();
+                        state.cregs.push(reg);
+                        if let Some(init) = init {
+                            self.parse_measure_into(&format!("{name} ={init}"), state, &mut gates)?;
+                        }
+                    }
+                    None => self.reject(
+                        state,
+                        line,
+                        "Malformed classical register declaration skipped".to_string(),
+                    )?,
+                }
+            } else if ["int", "uint", "float", "angle", "bool"].contains(&Self::declared_type(line))
+            {
+                // e.g. int[32] count = 0;  /  bool done;
+                match ClassicalVariable::parse(line) {
+                    Ok(variable) => state.variables.push(variable),
+                    Err(e) => self.reject(state, line, e)?,
+                }
+            } else if line.starts_with("measure") || line.contains("= measure") {
+                // e.g. measure q[0] -> c[0];  /  c = measure q;
+                self.parse_measure_into(line, state, &mut gates)?;
+            } else if line.starts_with("reset")
+                || line.starts_with("barrier")
+                || line.starts_with("delay")
+            {
+                // e.g. reset q[0];  /  barrier q;
+                match Self::parse_directive(line, state) {
+                    Ok(directive) => gates.extend(directive),
+                    Err(e) => self.reject(state, line, e)?,
+                }
+            } else if line.starts_with("while")
+                || line.starts_with("for ")
+                || (line.starts_with("if") && line.ends_with('{'))
+            {
+                gates.push(self.parse_block(line, lines, state, calibrated)?);
+            } else if line.starts_with("if") {
+                // e.g. if(c==3) x q[0];  /  if (c[0] && !c[1]) { reset q[0]; }
+                let (condition, body) = Self::split_condition(line)?;
+                let body = body.trim_start_matches('{').trim_end_matches('}').trim();
+                let mut conditioned = Vec::new();
+                if body.starts_with("measure") || body.contains("= measure") {
+                    self.parse_measure_into(body, state, &mut conditioned)?;
+                } else if body.starts_with("reset") || body.starts_with("delay") {
+                    match Self::parse_directive(body, state) {
+                        Ok(directive) => conditioned.extend(directive),
+                        Err(e) => self.reject(state, line, e)?,
+                    }
+                } else if self.is_gate_statement(body, state, calibrated)? {
+                    conditioned.extend(self.parse_checked_gate(body, line, state)?);
+                }
+                gates.extend(
+                    conditioned
+                        .into_iter()
+                        .map(|g| g.with_condition(condition.clone())),
+                );
+            } else if self.is_gate_statement(line, state, calibrated)? {
+                gates.extend(self.parse_checked_gate(line, line, state)?);
+            }
+        }
+
+        if nested {
+            return Err("Unterminated block: missing '}' or no-opt end pragma".to_string());
+        }
+        Ok((gates, None))
+    }
+
+    fn parse_block<'a, I: Iterator<Item = &'a str>>(
+        &self,
+        header: &str,
+        lines: &mut Peekable<I>,
+        state: &mut ParseState,
+        calibrated: &BTreeSet<String>,
+    ) -> Result<Gate, String> {
+        // Examples:
+        //   if (c == 1) {   ...   } else {   ...   }
+        //   while (c[0]) {   ...   }
+        //   for uint i in [0:2:10] {   ...   }
+        let mut body = |lines: &mut Peekable<I>| -> Result<(QuantumCircuit, &'a str), String> {
+            let (gates, close) = self.parse_statements(lines, state, calibrated, true)?;
+            if let Some(pragma) = close.and_then(Pragma::parse) {
+                return Err(format!(
+                    "'{pragma}' has no matching begin inside this block"
+                ));
+            }
+            let circuit = QuantumCircuit {
+                gates,
+                ..QuantumCircuit::default()
+            };
+            Ok((circuit, close.unwrap_or("}")))
+        };
+
+        let block = if let Some(spec) = header.strip_prefix("for ") {
+            let spec = spec.trim_end_matches('{').trim();
+            let (decl, range) = spec
+                .split_once(" in ")
+                .ok_or_else(|| format!("Malformed for loop: {header}"))?;
+            let variable = decl.split_whitespace().last().unwrap_or("i").to_string();
+            let bounds = range
+                .trim()
+                .trim_start_matches('[')
+                .trim_end_matches(']')
+                .split(':')
+                .map(|b| b.trim().parse::<i64>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|_| format!("Unsupported for-loop range: {range}"))?;
+            let (start, step, end) = match bounds[..] {
+                [start, end] => (start, 1, end),
+                [start, step, end] => (start, step, end),
+                _ => return Err(format!("Unsupported for-loop range: {range}")),
+            };
+            let (body, _) = body(lines)?;
+            ControlFlow::For {
+                variable,
+                start,
+                step,
+                end,
+                body,
+            }
+        } else {
+            let (condition, _) = Self::split_condition(header)?;
+            if header.starts_with("while") {
+                let (body, _) = body(lines)?;
+                ControlFlow::While { condition, body }
+            } else {
+                let (true_body, close) = body(lines)?;
+                let has_else = if close.contains("else") {
+                    true
+                } else if lines.peek().is_some_and(|l| l.starts_with("else")) {
+                    lines.next();
+                    true
+                } else {
+                    false
+                };
+                let false_body = if has_else { Some(body(lines)?.0) } else { None };
+                ControlFlow::IfElse {
+                    condition,
+                    true_body,
+                    false_body,
+                }
+            }
+        };
+        Ok(Gate::from_block(block))
+    }
+
+    /// Gives nested block bodies the register layout of the top-level circuit.
+    fn share_registers(circuit: &QuantumCircuit, top: &QuantumCircuit) -> QuantumCircuit {
+        let gates = circuit
+            .gates
+            .iter()
+            .map(|g| match &g.block {
+                Some(block) => Gate {
+                    block: Some(Box::new(block.map_bodies(&mut |body| {
+                        let body = QuantumCircuit {
+                            gates: body.gates.clone(),
+                            ..top.with_gates(Vec::new())
+                        };
+                        Self::share_registers(&body, top)
+                    }))),
+                    ..g.clone()
+                },
+                None => g.clone(),
+            })
+            .collect();
+        circuit.with_gates(gates)
+    }
+
+    /// Name and size of `qreg q[3];` or `qubit[3] q;`.
+    fn parse_qreg(line: &str) -> Option<(String, usize)> {
+        let parts: Vec<&str> = line.split(&['[', ']'][..]).collect();
+        if parts.len() < 3 {
+            return None;
+        }
+        let size = parts[1].trim().parse().ok()?;
+        let name = if line.starts_with("qreg") {
+            parts[0].trim_start_matches("qreg").trim()
+        } else {
+            parts[2].trim().trim_end_matches(';').trim()
+        };
+        (!name.is_empty()).then(|| (name.to_string(), size))
+    }
+
+    fn parse_creg(line: &str) -> Option<ClassicalRegister> {
+        if let Some(name) = line.strip_prefix("bit ") {
+            // A single bit, `bit b;`.
+            return Some(ClassicalRegister {
+                name: name.trim().trim_end_matches(';').trim().to_string(),
+                size: 1,
+            });
+        }
+        let parts: Vec<&str> = line.split(&['[', ']'][..]).collect();
+        if parts.len() < 3 {
+            return None;
+        }
+        let size = parts[1].parse().ok()?;
+        let name = if line.starts_with("creg") {
+            parts[0].trim_start_matches("creg").trim()
+        } else {
+            parts[2].trim().trim_end_matches(';').trim()
+        };
+        Some(ClassicalRegister {
+            name: name.to_string(),
+            size,
+        })
+    }
+
+    /// Type keyword a declaration starts with, e.g. `int` in `int[8] n;`.
+    fn declared_type(line: &str) -> &str {
+        line.split(|c: char| c == '[' || c.is_whitespace())
+            .next()
+            .unwrap_or("")
+    }
+
+    /// Qubits of an operand: `q[i]`, every qubit of register `q`, or the
+    /// physical qubit `$i`. Registers follow each other in declaration
+    /// order, so with `qreg a[2]; qreg b[3];` qubit `b[0]` is qubit 2.
+    fn qubit_operand(operand: &str, state: &ParseState) -> Result<Vec<usize>, String> {
+        let operand = operand.trim();
+        if let Some(index) = operand.strip_prefix('$') {
+            let q: usize = index
+                .trim()
+                .parse()
+                .map_err(|_| format!("Invalid qubit operand '{operand}'"))?;
+            if q >= state.num_qubits {
+                return Err(format!(
+                    "Qubit {q} is not declared ({} qubits)",
+                    state.num_qubits
+                ));
+            }
+            return Ok(vec![q]);
+        }
+        if operand.is_empty() {
+            return Err("Missing qubit operand".to_string());
+        }
+        let (name, index) = match operand.split_once('[') {
+            Some((name, rest)) => {
+                let index = rest
+                    .trim_end_matches(']')
+                    .trim()
+                    .parse::<usize>()
+                    .map_err(|_| format!("Invalid qubit operand '{operand}'"))?;
+                (name.trim(), Some(index))
+            }
+            None => (operand, None),
+        };
+        let register = state
+            .qregs
+            .iter()
+            .find(|r| r.name == name)
+            .ok_or_else(|| format!("Unknown quantum register '{name}'"))?;
+        match index {
+            Some(i) if i >= register.size => Err(format!(
+                "Qubit {i} is outside register {name}[{}]",
+                register.size
+            )),
+            Some(i) => Ok(vec![register.offset + i]),
+            None => Ok((register.offset..register.offset + register.size).collect()),
+        }
+    }
+
+    /// Bits of a classical operand: `c[i]`, or every bit of register `c`.
+    fn clbit_operand(operand: &str, state: &ParseState) -> Result<Vec<ClassicalBit>, String> {
+        let operand = operand.trim();
+        let (name, index) = match operand.split_once('[') {
+            Some((name, rest)) => {
+                let index = rest
+                    .trim_end_matches(']')
+                    .trim()
+                    .parse::<usize>()
+                    .map_err(|_| format!("Invalid classical bit operand '{operand}'"))?;
+                (name.trim(), Some(index))
+            }
+            None => (operand, None),
+        };
+        let register = state
+            .cregs
+            .iter()
+            .find(|r| r.name == name)
+            .ok_or_else(|| format!("Unknown classical register '{name}'"))?;
+        match index {
+            Some(i) if i >= register.size => Err(format!(
+                "Bit {i} is outside register {name}[{}]",
+                register.size
+            )),
+            Some(i) => Ok(vec![ClassicalBit::new(name, i)]),
+            None => Ok((0..register.size)
+                .map(|i| ClassicalBit::new(name, i))
+                .collect()),
+        }
+    }
+
+    /// Parses a measurement, rejecting malformed ones as the mode says.
+    fn parse_measure_into(
+        &self,
+        line: &str,
+        state: &mut ParseState,
+        gates: &mut Vec<Gate>,
+    ) -> Result<(), String> {
+        match Self::parse_measure(line, state) {
+            Ok(measures) => gates.extend(measures),
+            Err(e) => self.reject(state, line, e)?,
+        }
+        Ok(())
+    }
+
+    /// One `measure` per qubit of `measure q[0] -> c[0];` (OpenQASM 2),
+    /// `c[0] = measure q[0];` (OpenQASM 3) or their whole-register forms.
+    /// A measurement without a target keeps no bit.
+    fn parse_measure(line: &str, state: &ParseState) -> Result<Vec<Gate>, String> {
+        let statement = line.trim().trim_end_matches(';').trim();
+        let (source, target) = match statement.split_once('=') {
+            Some((target, rest)) => (rest.trim().strip_prefix("measure"), Some(target)),
+            None => match statement.strip_prefix("measure") {
+                Some(rest) => match rest.split_once("->") {
+                    Some((source, target)) => (Some(source), Some(target)),
+                    None => (Some(rest), None),
+                },
+                None => (None, None),
+            },
+        };
+        let source = source.ok_or_else(|| "Malformed measurement".to_string())?;
+        let qubits = Self::qubit_operand(source, state)?;
+        let Some(target) = target else {
+            return Ok(qubits
+                .into_iter()
+                .map(|q| Gate::new("measure", vec![q], vec![]))
+                .collect());
+        };
+        let bits = Self::clbit_operand(target, state)?;
+        if bits.len() != qubits.len() {
+            return Err(format!(
+                "Measurement of {} qubit(s) into {} bit(s)",
+                qubits.len(),
+                bits.len()
+            ));
+        }
+        Ok(qubits
+            .into_iter()
+            .zip(bits)
+            .map(|(q, bit)| Gate::measure(q, bit))
+            .collect())
+    }
+
+    /// `reset` or `delay` gates, one per qubit, or a single `barrier` over
+    /// all operands. A delay's duration is `[100ns]` (OpenQASM 3) or a
+    /// number of `dt` in parentheses.
+    fn parse_directive(line: &str, state: &ParseState) -> Result<Vec<Gate>, String> {
+        let name = line
+            .split(|c: char| "([;".contains(c) || c.is_whitespace())
+            .next()
+            .unwrap_or("");
+        let mut operands = line[name.len()..].trim().trim_end_matches(';');
+        let mut params = Vec::new();
+        if name == "delay" {
+            let (duration, rest) = match operands.strip_prefix('[') {
+                Some(rest) => {
+                    let (duration, rest) =
+                        rest.split_once(']').ok_or("Unterminated delay duration")?;
+                    (Duration::parse(duration)?, rest)
+                }
+                None => {
+                    let (dt, rest) = operands
+                        .strip_prefix('(')
+                        .and_then(|rest| rest.split_once(')'))
+                        .ok_or("Delay without a duration")?;
+                    (Duration::parse(&format!("{}dt", dt.trim()))?, rest)
+                }
+            };
+            params.push(Param::Duration(duration));
+            operands = rest;
+        }
+        let mut qubits = Vec::new();
+        for operand in operands.split(',').filter(|o| !o.trim().is_empty()) {
+            for q in Self::qubit_operand(operand, state)? {
+                if !qubits.contains(&q) {
+                    qubits.push(q);
+                }
+            }
+        }
+        if name == "barrier" {
+            // A bare `barrier;` spans every qubit.
+            if qubits.is_empty() {
+                qubits = (0..state.num_qubits).collect();
+            }
+            return Ok(vec![Gate::new("barrier", qubits, vec![])]);
+        }
+        if qubits.is_empty() {
+            // A bare `delay[d];` idles every qubit.
+            if name != "delay" {
+                return Err("Missing qubit operand".to_string());
+            }
+            qubits = (0..state.num_qubits).collect();
+        }
+        Ok(qubits
+            .into_iter()
+            .map(|q| Gate::new(name, vec![q], params.clone()))
+            .collect())
+    }
+
+    /// Leading identifier of a gate statement, e.g. `rz` in `rz(0.5) q[0];`.
+    fn gate_name(line: &str) -> &str {
+        line.split(|c: char| c == '(' || c.is_whitespace())
+            .next()
+            .unwrap_or("")
+    }
+
+    fn is_supported_gate(name: &str) -> bool {
+        name.starts_with("cx")
+            || name.starts_with("h")
+            || name.starts_with("x")
+            || name.starts_with("y")
+            || name.starts_with("z")
+            || name.starts_with("rz")
+            || name.starts_with("rx")
+            || name.starts_with("ry")
+            || name.starts_with("cp")
+            || name.starts_with("cz")
+            || name.starts_with("cu1")
+            || name.starts_with("ccx")
+            || name.starts_with("swap")
+            || name == "s"
+            || name.starts_with("sdg")
+            || name == "t"
+            || name.starts_with("tdg")
+    }
+
+    /// Splits `if (cond) rest` / `while (cond) rest` into the parsed condition
+    /// and the text after the closing parenthesis.
+    fn split_condition(line: &str) -> Result<(ClassicalExpr, &str), String> {
+        let open = line
+            .find('(')
+            .ok_or_else(|| format!("Missing condition in line: {line}"))?;
+        let mut depth = 0usize;
+        let mut close = None;
+        for (i, c) in line.char_indices().skip(open) {
+            match c {
+                '(' => depth += 1,
+                ')' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        close = Some(i);
+                        break;
+                    }
+                }
+                _ => {}
+            }
+        }
+        let close = close.ok_or_else(|| format!("Unbalanced condition in line: {line}"))?;
+        let condition = ClassicalExpr::parse(&line[open + 1..close])?;
+        Ok((condition, line[close + 1..].trim()))
+    }
+
+    /// Parses the gate `text` of `statement` (the whole statement, or the
+    /// body of a conditional one) and checks it, or skips it with a warning
+    /// in permissive mode if it is malformed.
+    fn parse_checked_gate(
+        &self,
+        text: &str,
+        statement: &str,
+        state: &mut ParseState,
+    ) -> Result<Option<Gate>, String> {
+        match self.parse_gate(text, state) {
+            Ok(g) => {
+                self.check_gate(&g, statement, state)?;
+                Ok(Some(g))
+            }
+            Err(e) => {
+                self.reject(state, statement, format!("{e}; statement skipped"))?;
+                Ok(None)
+            }
+        }
+    }
+
+    fn parse_gate(&self, line: &str, state: &ParseState) -> Result<Gate, String> {
+        // Examples:
+        //   h q[0];
+        //   cx q[0], q[1];
+        //   rz(1.5708) q[0];
+        let name_end = line
+            .find(|c: char| c == '(' || c.is_whitespace())
+            .ok_or_else(|| format!("Failed to parse gate from line: {line}"))?;
+        let name = self.aliases.canonical(&line[..name_end]);
+
+        // Extract optional parameter list, e.g. "rz(1.57)" or "u(0.1, 0.2, 0.3)"
+        let (params, operands) = if line[name_end..].trim_start().starts_with('(') {
+            let open = name_end + line[name_end..].find('(').unwrap_or(0);
+            let close = open
+                + line[open..]
+                    .find(')')
+                    .ok_or_else(|| format!("Unterminated parameter list in line: {line}"))?;
+            let params = line[open + 1..close]
+                .split(',')
+                .map(|a| self.angles.parse(a))
+                .collect::<Result<Vec<_>, _>>()?;
+            (params, &line[close + 1..])
+        } else {
+            (Vec::new(), &line[name_end..])
+        };
+
+        let mut qubits = Vec::new();
+        for operand in operands.trim().trim_end_matches(';').split(',') {
+            match Self::qubit_operand(operand, state)?[..] {
+                [q] => qubits.push(q),
+                _ => return Err(format!("Failed to parse qubits from line: {line}")),
+            }
+        }
+
+        Ok(Gate::new(name, qubits, params))
+    }
+}
+
+// ============================================================================
+// QASM EMITTER
+// ============================================================================
+
+#[cfg(feature = "parser")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QasmVersion {
+    V2,
+    V3,
+}
+
+#[cfg(feature = "parser")]
+pub struct QASMEmitter {
+    pub version: QasmVersion,
+    /// Vendor names to write gates under.
+    pub aliases: GateAliases,
+    /// Unit numeric angles are written in.
+    pub angle_unit: AngleUnit,
+}
+
+#[cfg(feature = "parser")]
+impl QASMEmitter {
+    /// Emitter writing the transpiler's own gate names.
+    pub fn new(version: QasmVersion) -> Self {
+        Self {
+            version,
+            aliases: GateAliases::empty(),
+            angle_unit: AngleUnit::Radians,
+        }
+    }
+
+    pub fn emit(&self, circuit: &QuantumCircuit) -> Result<String, String> {
+        let mut out = String::new();
+        match self.version {
+            QasmVersion::V2 => {
+                // Bit outputs are plain registers; anything else has no OpenQASM 2 form.
+                let signature = &circuit.signature;
+                let mut inexpressible = signature.inputs.iter().chain(
+                    signature
+                        .outputs
+                        .iter()
+                        .filter(|d| d.ty.bit_size().is_none()),
+                );
+                if let Some(decl) = inexpressible.next() {
+                    return Err(format!(
+                        "Declaration of `{}` cannot be expressed in OpenQASM 2",
+                        decl.name
+                    ));
+                }
+                if !circuit.calibrations.is_empty() {
+                    return Err("Pulse calibrations cannot be expressed in OpenQASM 2".to_string());
+                }
+                if let Some(variable) = circuit.variables.first() {
+                    return Err(format!(
+                        "Classical variable `{}` cannot be expressed in OpenQASM 2",
+                        variable.name
+                    ));
+                }
+                out.push_str("OPENQASM 2.0;\ninclude \"qelib1.inc\";\n");
+                out.push_str(&format!("qreg q[{}];\n", circuit.num_qubits));
+                for r in &circuit.cregs {
+                    out.push_str(&format!("creg {}[{}];\n", r.name, r.size));
+                }
+            }
+            QasmVersion::V3 => {
+                out.push_str("OPENQASM 3.0;\ninclude \"stdgates.inc\";\n");
+                for decl in &circuit.signature.inputs {
+                    out.push_str(&format!("input {} {};\n", decl.ty, decl.name));
+                }
+                out.push_str(&format!("qubit[{}] q;\n", circuit.num_qubits));
+                for r in &circuit.cregs {
+                    match circuit.signature.output(&r.name) {
+                        Some(decl) => out.push_str(&format!("output {} {};\n", decl.ty, decl.name)),
+                        None => out.push_str(&format!("bit[{}] {};\n", r.size, r.name)),
+                    }
+                }
+                for decl in circuit
+                    .signature
+                    .outputs
+                    .iter()
+                    .filter(|d| d.ty.bit_size().is_none())
+                {
+                    out.push_str(&format!("output {} {};\n", decl.ty, decl.name));
+                }
+                for variable in &circuit.variables {
+                    out.push_str(&format!("{variable}\n"));
+                }
+                for block in &circuit.calibrations.blocks {
+                    out.push_str(block.text());
+                    out.push('\n');
+                }
+            }
+        }
+
+        let mut defined = HashSet::new();
+        self.emit_definitions(&circuit.gates, &mut defined, &mut out)?;
+        self.emit_statements(&circuit.gates, 0, &mut out)?;
+        Ok(out)
+    }
+
+    /// Emits a `gate` definition for each composite used, innermost first.
+    fn emit_definitions(
+        &self,
+        gates: &[Gate],
+        defined: &mut HashSet<String>,
+        out: &mut String,
+    ) -> Result<(), String> {
+        for g in gates {
+            if let Some(block) = &g.block {
+                for body in block.bodies() {
+                    self.emit_definitions(&body.gates, defined, out)?;
+                }
+            }
+            let Some(composite) = &g.composite else {
+                continue;
+            };
+            if !defined.insert(composite.name.clone()) {
+                continue;
+            }
+            let body = &composite.definition;
+            self.emit_definitions(&body.gates, defined, out)?;
+            let args = (0..body.num_qubits)
+                .map(|q| format!("q{q}"))
+                .collect::<Vec<_>>()
+                .join(", ");
+            out.push_str(&format!("gate {} {args} {{\n", composite.name));
+            for inner in &body.gates {
+                if inner.condition.is_some() || inner.block.is_some() {
+                    return Err(format!(
+                        "Gate definition `{}` contains classical control",
+                        composite.name
+                    ));
+                }
+                let qubits = inner
+                    .qubits
+                    .iter()
+                    .map(|q| format!("q{q}"))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                out.push_str(&format!("    {}\n", self.emit_gate_with(inner, &qubits)));
+            }
+            out.push_str("}\n");
+        }
+        Ok(())
+    }
+
+    fn emit_statements(
+        &self,
+        gates: &[Gate],
+        indent: usize,
+        out: &mut String,
+    ) -> Result<(), String> {
+        let pad = "    ".repeat(indent);
+        for g in gates {
+            if let Some(block) = &g.block {
+                if self.version == QasmVersion::V2 && !block.is_protected() {
+                    return Err(format!(
+                        "Control-flow block `{}` cannot be expressed in OpenQASM 2",
+                        g.name
+                    ));
+                }
+                match block.as_ref() {
+                    ControlFlow::IfElse {
+                        condition,
+                        true_body,
+                        false_body,
+                    } => {
+                        out.push_str(&format!("{pad}if ({condition}) {{\n"));
+                        self.emit_statements(&true_body.gates, indent + 1, out)?;
+                        if let Some(false_body) = false_body {
+                            out.push_str(&format!("{pad}}} else {{\n"));
+                            self.emit_statements(&false_body.gates, indent + 1, out)?;
+                        }
+                    }
+                    ControlFlow::While { condition, body } => {
+                        out.push_str(&format!("{pad}while ({condition}) {{\n"));
+                        self.emit_statements(&body.gates, indent + 1, out)?;
+                    }
+                    ControlFlow::For {
+                        variable,
+                        start,
+                        step,
+                        end,
+                        body,
+                    } => {
+                        let range = if *step == 1 {
+                            format!("[{start}:{end}]")
+                        } else {
+                            format!("[{start}:{step}:{end}]")
+                        };
+                        out.push_str(&format!("{pad}for uint {variable} in {range} {{\n"));
+                        self.emit_statements(&body.gates, indent + 1, out)?;
+                    }
+                    ControlFlow::Protected { body } => {
+                        // Comment directives, so the region reads the same in both versions.
+                        out.push_str(&format!("{pad}{}\n", Pragma::NoOptBegin));
+                        self.emit_statements(&body.gates, indent, out)?;
+                        out.push_str(&format!("{pad}{}\n", Pragma::NoOptEnd));
+                        continue;
+                    }
+                }
+                out.push_str(&format!("{pad}}}\n"));
+                continue;
+            }
+
+            if let (QasmVersion::V2, "delay", [Param::Duration(d)]) =
+                (self.version, g.name.as_str(), &g.params[..])
+            {
+                if d.unit != DurationUnit::Dt {
+                    return Err(format!(
+                        "Delay of {d} cannot be expressed in OpenQASM 2, which only counts dt"
+                    ));
+                }
+            }
+            let stmt = self.emit_gate(g);
+            match (&g.condition, self.version) {
+                (None, _) => out.push_str(&format!("{pad}{stmt}\n")),
+                (Some(cond), QasmVersion::V2) => {
+                    // OpenQASM 2 only supports `if(creg==int)`.
+                    let (reg, value) = cond.as_register_equals().ok_or_else(|| {
+                        format!("Condition `{cond}` cannot be expressed in OpenQASM 2")
+                    })?;
+                    out.push_str(&format!("{pad}if({reg}=={value}) {stmt}\n"));
+                }
+                (Some(cond), QasmVersion::V3) => {
+                    out.push_str(&format!("{pad}if ({cond}) {{ {stmt} }}\n"))
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn emit_gate(&self, g: &Gate) -> String {
+        if let ([q], [bit]) = (&g.qubits[..], &g.clbits[..]) {
+            return match self.version {
+                QasmVersion::V2 => format!("measure q[{q}] -> {bit};"),
+                QasmVersion::V3 => format!("{bit} = measure q[{q}];"),
+            };
+        }
+        let qubits = g
+            .qubits
+            .iter()
+            .map(|q| format!("q[{q}]"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        self.emit_gate_with(g, &qubits)
+    }
+
+    fn emit_gate_with(&self, g: &Gate, qubits: &str) -> String {
+        let name = self.aliases.vendor(&g.name);
+        if let ("delay", [Param::Duration(d)]) = (g.name.as_str(), &g.params[..]) {
+            return match self.version {
+                QasmVersion::V2 => format!("{name}({}) {qubits};", d.value),
+                QasmVersion::V3 => format!("{name}[{d}] {qubits};"),
+            };
+        }
+        if g.params.is_empty() {
+            format!("{name} {qubits};")
+        } else {
+            // Delay parameters are durations, not angles.
+            let unit = if g.name == "delay" {
+                AngleUnit::Radians
+            } else {
+                self.angle_unit
+            };
+            let params = g
+                .params
+                .iter()
+                .map(|p| unit.express(p).to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+            format!("{name}({params}) {qubits};")
+        }
+    }
+}
+
+// ============================================================================
+// SIMPLE ROUTER (MAPS VIRTUAL QUBITS TO PHYSICAL ONES, INSERTS SWAP CHAINS)
+// ============================================================================
+
+/// A circuit over physical qubits together with where each virtual qubit
+/// started and ended up.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone)]
+pub struct RoutedCircuit {
+    pub circuit: PhysicalCircuit,
+    pub initial_layout: Layout,
+    pub final_layout: Layout,
+    pub report: RoutingReport,
+}
+
+#[cfg(feature = "std")]
+impl RoutedCircuit {
+    /// This routing applied to `circuit`, a circuit with the same
+    /// `RoutingSkeleton` as the one routed: parameterized gates take
+    /// `circuit`'s parameters in order. `None` if the parameterized gates
+    /// don't line up, e.g. because the router reordered them.
+    fn with_params_of(&self, circuit: &QuantumCircuit) -> Option<RoutedCircuit> {
+        let mut source = circuit.gates.iter().filter(|g| !g.params.is_empty());
+        let gates = self
+            .circuit
+            .gates
+            .iter()
+            .map(|g| {
+                if g.params.is_empty() {
+                    return Some(g.clone());
+                }
+                let from = source.next().filter(|s| s.name == g.name)?;
+                Some(Gate {
+                    params: from.params.clone(),
+                    ..g.clone()
+                })
+            })
+            .collect::<Option<Vec<_>>>()?;
+        if source.next().is_some() {
+            return None;
+        }
+        let mut routed = circuit.with_gates(gates);
+        routed.num_qubits = self.circuit.num_qubits;
+        routed.num_clbits = self.circuit.num_clbits;
+        routed.cregs = self.circuit.cregs.clone();
+        Some(RoutedCircuit {
+            circuit: PhysicalCircuit::assume_physical(routed),
+            ..self.clone()
+        })
+    }
+}
+
+/// Everything about a circuit that routing depends on, i.e. all but its
+/// gate parameters.
+#[cfg(feature = "std")]
+#[derive(PartialEq)]
+struct RoutingSkeleton {
+    num_qubits: usize,
+    cregs: Vec<ClassicalRegister>,
+    gates: Vec<GateShape>,
+}
+
+/// A gate with its parameters left out, except for how many there are.
+#[cfg(feature = "std")]
+#[derive(PartialEq)]
+struct GateShape {
+    name: String,
+    qubits: Vec<usize>,
+    params: usize,
+    condition: Option<ClassicalExpr>,
+    clbits: Vec<ClassicalBit>,
+}
+
+#[cfg(feature = "std")]
+impl RoutingSkeleton {
+    /// `None` for circuits with control flow or composite gates.
+    fn of(circuit: &QuantumCircuit) -> Option<Self> {
+        let gates = circuit
+            .gates
+            .iter()
+            .map(|g| {
+                (g.block.is_none() && g.composite.is_none()).then(|| GateShape {
+                    name: g.name.clone(),
+                    qubits: g.qubits.clone(),
+                    params: g.params.len(),
+                    condition: g.condition.clone(),
+                    clbits: g.clbits.clone(),
+                })
+            })
+            .collect::<Option<Vec<_>>>()?;
+        Some(Self {
+            num_qubits: circuit.num_qubits,
+            cregs: circuit.cregs.clone(),
+            gates,
+        })
+    }
+}
+
+/// How the router brought the qubits of one two-qubit gate together.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, PartialEq)]
+pub struct SwapDecision {
+    pub gate: String,
+    pub qubits: (VirtualQubit, VirtualQubit),
+    /// Physical qubits the first qubit was swapped through, from where it
+    /// started to where the gate ran.
+    pub path: Vec<PhysicalQubit>,
+    /// Shortest paths that were available.
+    pub alternatives: usize,
+    /// Probability that the chosen swaps or the gate fail.
+    pub error: f64,
+    /// The same for the worst of the alternatives.
+    pub worst_error: f64,
+}
+
+/// A `cx` between qubits two hops apart run as a BRIDGE through the qubit
+/// between them (four CNOTs, layout unchanged) instead of swapping.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, PartialEq)]
+pub struct BridgeDecision {
+    pub gate: String,
+    pub qubits: (VirtualQubit, VirtualQubit),
+    /// Control, middle and target physical qubits.
+    pub path: Vec<PhysicalQubit>,
+    /// Probability that one of the four CNOTs fails.
+    pub error: f64,
+    /// The same for the swap and gate it replaced.
+    pub swap_error: f64,
+}
+
+/// Routing decisions, in the order the gates were routed.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct RoutingReport {
+    pub decisions: Vec<SwapDecision>,
+    pub bridges: Vec<BridgeDecision>,
+}
+
+#[cfg(feature = "std")]
+impl RoutingReport {
+    pub fn swap_count(&self) -> usize {
+        self.decisions.iter().map(|d| d.path.len() - 1).sum()
+    }
+
+    pub fn bridge_count(&self) -> usize {
+        self.bridges.len()
+    }
+}
+
+#[cfg(feature = "std")]
+impl fmt::Display for RoutingReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Routing: {} swap(s) for {} gate(s), {} bridge(s)",
+            self.swap_count(),
+            self.decisions.len(),
+            self.bridge_count()
+        )?;
+        for d in &self.decisions {
+            let path = d
+                .path
+                .iter()
+                .map(|p| p.to_string())
+                .collect::<Vec<_>>()
+                .join(" -> ");
+            write!(
+                f,
+                "\n  {} {} {}: {path} (error {:.4}, best of {} path(s), worst {:.4})",
+                d.gate, d.qubits.0, d.qubits.1, d.error, d.alternatives, d.worst_error
+            )?;
+        }
+        for d in &self.bridges {
+            let path = d
+                .path
+                .iter()
+                .map(|p| p.to_string())
+                .collect::<Vec<_>>()
+                .join(" -> ");
+            write!(
+                f,
+                "\n  {} {} {}: bridge {path} (error {:.4}, swapping {:.4})",
+                d.gate, d.qubits.0, d.qubits.1, d.error, d.swap_error
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// What routing produces besides the gates.
+#[cfg(feature = "std")]
+struct RoutingOutputs {
+    #[cfg(feature = "router")]
+    bridge_bits: Option<ClassicalRegister>,
+    report: RoutingReport,
+}
+
+/// Best and worst accumulated error (as `-ln` of the success probability)
+/// over the shortest paths from a qubit to a neighbour of the target, how
+/// many there are, and the next hop of the best one.
+#[cfg(feature = "router")]
+#[derive(Clone, Copy)]
+struct PathCosts {
+    best: f64,
+    worst: f64,
+    count: usize,
+    next: Option<PhysicalQubit>,
+}
+
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SimpleRouter {
+    /// On backends with dynamic circuits, bridge long-range CNOTs through free
+    /// qubits with measurement and feedforward instead of swapping.
+    pub teleportation: bool,
+    /// Run a `cx` between qubits two hops apart as a BRIDGE when that is
+    /// cheaper than swapping, counting what the swap would do to the gates
+    /// that follow.
+    pub bridge_gates: bool,
+    /// What "cheaper" means when choosing between a bridge and a swap.
+    pub objective: OptimizationObjective,
+}
+
+/// Two-qubit gates looked at when pricing a swap against a bridge.
+#[cfg(feature = "router")]
+const BRIDGE_LOOKAHEAD: usize = 8;
+
+#[cfg(feature = "std")]
+impl SimpleRouter {
+    /// Routes `circuit` starting from the trivial layout.
+    pub fn route(
+        &self,
+        circuit: &QuantumCircuit,
+        backend: &BackendSpec,
+    ) -> Result<RoutedCircuit, String> {
+        if circuit.num_qubits > backend.num_qubits {
+            return Err(format!(
+                "Circuit needs {} qubits but backend {} only has {}",
+                circuit.num_qubits, backend.name, backend.num_qubits
+            ));
+        }
+        let layout = Layout::trivial(circuit.num_qubits, backend.num_qubits)?;
+        self.route_with_layout(circuit, backend, layout)
+    }
+
+    /// Routes `circuit` starting from `initial_layout`. Whenever a two-qubit
+    /// gate acts on uncoupled physical qubits, its first qubit is swapped along
+    /// a shortest path until the two are neighbours.
+    pub fn route_with_layout(
+        &self,
+        circuit: &QuantumCircuit,
+        backend: &BackendSpec,
+        initial_layout: Layout,
+    ) -> Result<RoutedCircuit, String> {
+        let dist = backend.distance_matrix();
+        let mut layout = initial_layout.clone();
+        let mut inserted = Vec::new();
+        let mut outputs = RoutingOutputs {
+            #[cfg(feature = "router")]
+            bridge_bits: (self.teleportation && backend.supports_dynamic_circuits)
+                .then(|| teleport::ancilla_register(circuit)),
+            report: RoutingReport::default(),
+        };
+        let gates = self.route_gates(
+            &circuit.gates,
+            backend,
+            &dist,
+            &mut layout,
+            &mut inserted,
+            &mut outputs,
+        )?;
+        let mut routed = circuit.with_gates(gates);
+        routed.num_qubits = backend.num_qubits;
+        #[cfg(feature = "router")]
+        if let Some(register) = outputs.bridge_bits.filter(|r| r.size > 0) {
+            routed.num_clbits += register.size;
+            routed.cregs.push(register);
+        }
+        Ok(RoutedCircuit {
+            circuit: PhysicalCircuit::assume_physical(routed),
+            initial_layout,
+            final_layout: layout,
+            report: outputs.report,
+        })
+    }
+
+    #[cfg_attr(not(feature = "router"), allow(unused_variables))]
+    fn route_gates(
+        &self,
+        gates: &[Gate],
+        backend: &BackendSpec,
+        dist: &[Vec<usize>],
+        layout: &mut Layout,
+        inserted: &mut Vec<(PhysicalQubit, PhysicalQubit)>,
+        outputs: &mut RoutingOutputs,
+    ) -> Result<Vec<Gate>, String> {
+        let mut out = Vec::new();
+
+        for (i, g) in gates.iter().enumerate() {
+            if let Some(block) = &g.block {
+                // A body may or may not run, so it must leave the layout as it
+                // found it: undo its swaps in reverse before leaving.
+                let mut error = None;
+                let routed = block.map_bodies(&mut |body| {
+                    let mut body_layout = layout.clone();
+                    let mut body_swaps = Vec::new();
+                    match self.route_gates(
+                        &body.gates,
+                        backend,
+                        dist,
+                        &mut body_layout,
+                        &mut body_swaps,
+                        outputs,
+                    ) {
+                        Ok(mut gates) => {
+                            for &(a, b) in body_swaps.iter().rev() {
+                                gates.push(Gate::new("swap", vec![a.0, b.0], vec![]));
+                            }
+                            let mut routed = body.with_gates(gates);
+                            routed.num_qubits = backend.num_qubits;
+                            routed
+                        }
+                        Err(e) => {
+                            error = Some(e);
+                            body.clone()
+                        }
+                    }
+                });
+                if let Some(e) = error {
+                    return Err(e);
+                }
+                out.push(Gate::from_block(routed));
+                continue;
+            }
+
+            let virtuals: Vec<VirtualQubit> = g.qubits.iter().map(|&q| VirtualQubit(q)).collect();
+            if let Some(v) = virtuals.iter().find(|v| v.0 >= layout.num_virtual()) {
+                return Err(format!(
+                    "Gate {} acts on {v}, which is outside the circuit",
+                    g.name
+                ));
+            }
+            if let [a, b] = virtuals[..] {
+                #[cfg(feature = "router")]
+                if let Some(register) = &mut outputs.bridge_bits {
+                    if let Some(bridged) = teleport::bridge_cx(
+                        g,
+                        layout.physical(a),
+                        layout.physical(b),
+                        backend,
+                        dist,
+                        layout,
+                        register,
+                    ) {
+                        out.extend(bridged);
+                        continue;
+                    }
+                }
+                #[cfg(feature = "router")]
+                if self.bridge_gates {
+                    if let Some(bridged) = Self::bridge_gate(
+                        g,
+                        backend,
+                        dist,
+                        layout,
+                        &gates[i + 1..],
+                        &self.objective,
+                        &mut outputs.report,
+                    ) {
+                        out.extend(bridged);
+                        continue;
+                    }
+                }
+                for (p, n) in
+                    Self::bring_adjacent(g, a, b, backend, dist, layout, &mut outputs.report)?
+                {
+                    out.push(Gate::new("swap", vec![p.0, n.0], vec![]));
+                    inserted.push((p, n));
+                }
+            }
+            let mut mapped = g.clone();
+            mapped.qubits = virtuals.iter().map(|&v| layout.physical(v).0).collect();
+            out.push(mapped);
+        }
+
+        Ok(out)
+    }
+
+    /// Without the `router` feature no swaps are inserted: a gate on
+    /// uncoupled qubits is an error.
+    #[cfg(not(feature = "router"))]
+    fn bring_adjacent(
+        g: &Gate,
+        a: VirtualQubit,
+        b: VirtualQubit,
+        _backend: &BackendSpec,
+        dist: &[Vec<usize>],
+        layout: &mut Layout,
+        _report: &mut RoutingReport,
+    ) -> Result<Vec<(PhysicalQubit, PhysicalQubit)>, String> {
+        let start = layout.physical(a);
+        let target = layout.physical(b);
+        match dist[start.0][target.0] {
+            usize::MAX => Err(format!("Physical qubits {start} and {target} are not connected")),
+            0 | 1 => Ok(Vec::new()),
+            _ => Err(format!(
+                "Gate {} acts on uncoupled qubits {start} and {target}; inserting swaps needs the `router` feature",
+                g.name
+            )),
+        }
+    }
+
+    /// Swaps `a` along a shortest path towards `b` until they are coupled,
+    /// returning the swaps applied to `layout`. Of the shortest paths, the
+    /// one whose swaps (three CNOTs each) and final gate accumulate the least
+    /// two-qubit error is taken; ties go to lower-numbered qubits.
+    #[cfg(feature = "router")]
+    fn bring_adjacent(
+        g: &Gate,
+        a: VirtualQubit,
+        b: VirtualQubit,
+        backend: &BackendSpec,
+        dist: &[Vec<usize>],
+        layout: &mut Layout,
+        report: &mut RoutingReport,
+    ) -> Result<Vec<(PhysicalQubit, PhysicalQubit)>, String> {
+        let start = layout.physical(a);
+        let target = layout.physical(b);
+        let d = dist[start.0][target.0];
+        if d == usize::MAX {
+            return Err(format!(
+                "Physical qubits {start} and {target} are not connected"
+            ));
+        }
+        if d <= 1 {
+            return Ok(Vec::new());
+        }
+
+        let mut memo = HashMap::new();
+        let costs = Self::path_costs(start, target, backend, dist, &mut memo)
+            .ok_or_else(|| format!("No path from {start} towards {target}"))?;
+        let mut path = vec![start];
+        let mut swaps = Vec::new();
+        while let Some(next) = memo
+            .get(&path[path.len() - 1])
+            .and_then(|c: &PathCosts| c.next)
+        {
+            let here = path[path.len() - 1];
+            layout.swap_physical(here, next);
+            swaps.push((here, next));
+            path.push(next);
+        }
+        report.decisions.push(SwapDecision {
+            gate: g.name.clone(),
+            qubits: (a, b),
+            path,
+            alternatives: costs.count,
+            error: 1.0 - (-costs.best).exp(),
+            worst_error: 1.0 - (-costs.worst).exp(),
+        });
+        Ok(swaps)
+    }
+
+    /// Runs a `cx` whose qubits are two hops apart as a BRIDGE through the
+    /// best middle qubit unless `objective` prefers swapping. Each side is
+    /// scored by its two-qubit gates, their depth and `-ln` of their success
+    /// probability, and each hop the next `BRIDGE_LOOKAHEAD` two-qubit gates
+    /// would still need after either choice counts as one more swap, so a
+    /// pair that interacts again is swapped together while a one-off
+    /// interaction is bridged.
+    #[cfg(feature = "router")]
+    fn bridge_gate(
+        g: &Gate,
+        backend: &BackendSpec,
+        dist: &[Vec<usize>],
+        layout: &Layout,
+        upcoming: &[Gate],
+        objective: &OptimizationObjective,
+        report: &mut RoutingReport,
+    ) -> Option<Vec<Gate>> {
+        if g.name != "cx" || g.composite.is_some() {
+            return None;
+        }
+        let [a, b] = g.qubits[..] else {
+            return None;
+        };
+        let (a, b) = (VirtualQubit(a), VirtualQubit(b));
+        let (control, target) = (layout.physical(a), layout.physical(b));
+        if dist[control.0][target.0] != 2 {
+            return None;
+        }
+        let loss = |x: PhysicalQubit, y: PhysicalQubit| {
+            -(1.0 - backend.two_qubit_error(x, y))
+                .max(f64::MIN_POSITIVE)
+                .ln()
+        };
+        let (middle, bridge) = (0..backend.num_qubits)
+            .map(PhysicalQubit)
+            .filter(|&m| backend.are_coupled(control, m) && backend.are_coupled(m, target))
+            .map(|m| (m, 2.0 * (loss(control, m) + loss(m, target))))
+            .min_by(|x, y| x.1.total_cmp(&y.1))?;
+        let mut memo = HashMap::new();
+        let swap = Self::path_costs(control, target, backend, dist, &mut memo)?;
+        let hop = memo.get(&control)?.next?;
+        let mut swapped = layout.clone();
+        swapped.swap_physical(control, hop);
+
+        // Hops the upcoming gates still need, up to the next control flow.
+        let remaining = |layout: &Layout| -> usize {
+            upcoming
+                .iter()
+                .take_while(|g| g.block.is_none())
+                .filter_map(|g| match g.qubits[..] {
+                    [x, y] if x < layout.num_virtual() && y < layout.num_virtual() => Some(
+                        dist[layout.physical(VirtualQubit(x)).0]
+                            [layout.physical(VirtualQubit(y)).0],
+                    ),
+                    _ => None,
+                })
+                .take(BRIDGE_LOOKAHEAD)
+                .filter(|&d| d != usize::MAX)
+                .map(|d| d.saturating_sub(1))
+                .sum()
+        };
+        let per_hop = 3.0 * loss(control, hop);
+        // Both sides run four cx in sequence, then three per remaining hop.
+        let metrics = |loss: f64, hops: usize| CircuitMetrics {
+            depth: 4 + 3 * hops,
+            gate_count: 4 + 3 * hops,
+            two_qubit_count: 4 + 3 * hops,
+            estimated_error: 1.0 - (-(loss + per_hop * hops as f64)).exp(),
+        };
+        let bridged = metrics(bridge, remaining(layout));
+        if objective.prefers(&metrics(swap.best, remaining(&swapped)), &bridged) {
+            return None;
+        }
+
+        report.bridges.push(BridgeDecision {
+            gate: g.name.clone(),
+            qubits: (a, b),
+            path: vec![control, middle, target],
+            error: 1.0 - (-bridge).exp(),
+            swap_error: 1.0 - (-swap.best).exp(),
+        });
+        let cx = |x: PhysicalQubit, y: PhysicalQubit| Gate {
+            qubits: vec![x.0, y.0],
+            ..g.clone()
+        };
+        Some(vec![
+            cx(control, middle),
+            cx(middle, target),
+            cx(control, middle),
+            cx(middle, target),
+        ])
+    }
+
+    #[cfg(feature = "router")]
+    fn path_costs(
+        here: PhysicalQubit,
+        target: PhysicalQubit,
+        backend: &BackendSpec,
+        dist: &[Vec<usize>],
+        memo: &mut HashMap<PhysicalQubit, PathCosts>,
+    ) -> Option<PathCosts> {
+        if let Some(costs) = memo.get(&here) {
+            return Some(*costs);
+        }
+        let loss = |x: PhysicalQubit, y: PhysicalQubit| {
+            -(1.0 - backend.two_qubit_error(x, y))
+                .max(f64::MIN_POSITIVE)
+                .ln()
+        };
+        let d = dist[here.0][target.0];
+        let costs = if d == 1 {
+            let final_gate = loss(here, target);
+            PathCosts {
+                best: final_gate,
+                worst: final_gate,
+                count: 1,
+                next: None,
+            }
+        } else {
+            let mut costs: Option<PathCosts> = None;
+            for n in (0..backend.num_qubits).map(PhysicalQubit) {
+                if !backend.are_coupled(here, n) || dist[n.0][target.0] != d - 1 {
+                    continue;
+                }
+                let Some(rest) = Self::path_costs(n, target, backend, dist, memo) else {
+                    continue;
+                };
+                let swap = 3.0 * loss(here, n);
+                costs = Some(match costs {
+                    None => PathCosts {
+                        best: swap + rest.best,
+                        worst: swap + rest.worst,
+                        count: rest.count,
+                        next: Some(n),
+                    },
+                    Some(c) => PathCosts {
+                        best: c.best.min(swap + rest.best),
+                        worst: c.worst.max(swap + rest.worst),
+                        count: c.count.saturating_add(rest.count),
+                        next: if swap + rest.best < c.best - 1e-12 {
+                            Some(n)
+                        } else {
+                            c.next
+                        },
+                    },
+                });
+            }
+            costs?
+        };
+        memo.insert(here, costs);
+        Some(costs)
+    }
+}
+
+// ============================================================================
+// SIMPLE OPTIMIZATION PASSES
+// ============================================================================
+
+pub trait OptimizationPass {
+    fn optimize(&self, circuit: &QuantumCircuit) -> QuantumCircuit;
+
+    /// Name the pass is reported under, e.g. in pass traces; defaults to the
+    /// name of its type.
+    fn name(&self) -> &str {
+        let full = core::any::type_name::<Self>();
+        full.rsplit("::").next().unwrap_or(full)
+    }
+
+    /// Whether the pass is also applied inside control-flow bodies. Passes that
+    /// return false see each block as a single opaque gate.
+    fn recurse_into_blocks(&self) -> bool {
+        true
+    }
+
+    /// Why the pass can't change `circuit`, if a check much cheaper than the
+    /// pass itself shows it; the transpiler then skips the pass and records
+    /// the reason. Only return a reason when the pass would be a no-op.
+    fn skip_reason(&self, _circuit: &QuantumCircuit) -> Option<String> {
+        None
+    }
+
+    /// Optimizes a routed circuit, for passes that ask the backend about
+    /// its qubits; `None`, the default, runs `optimize` instead.
+    #[cfg(feature = "std")]
+    fn optimize_physical(&self, _circuit: &PhysicalCircuit) -> Option<PhysicalCircuit> {
+        None
+    }
+}
+
+/// Number of gates of each name, counting control-flow bodies too, for
+/// `OptimizationPass::skip_reason` checks.
+pub fn gate_name_counts(circuit: &QuantumCircuit) -> BTreeMap<&str, usize> {
+    fn add<'a>(gates: &'a [Gate], counts: &mut BTreeMap<&'a str, usize>) {
+        for g in gates {
+            match &g.block {
+                Some(block) => block
+                    .bodies()
+                    .into_iter()
+                    .for_each(|body| add(&body.gates, counts)),
+                None => *counts.entry(g.name.as_str()).or_insert(0) += 1,
+            }
+        }
+    }
+    let mut counts = BTreeMap::new();
+    add(&circuit.gates, &mut counts);
+    counts
+}
+
+/// Runs `pass` on `circuit`, first descending into control-flow bodies if the
+/// pass asks for it. Protected regions are never descended into, and passes
+/// see them as opaque gates.
+pub fn run_pass(pass: &dyn OptimizationPass, circuit: &QuantumCircuit) -> QuantumCircuit {
+    if !pass.recurse_into_blocks() || circuit.gates.iter().all(|g| g.block.is_none()) {
+        return pass.optimize(circuit);
+    }
+    let gates = circuit
+        .gates
+        .iter()
+        .map(|g| match &g.block {
+            Some(block) if !block.is_protected() => {
+                Gate::from_block(block.map_bodies(&mut |body| run_pass(pass, body)))
+            }
+            _ => g.clone(),
+        })
+        .collect();
+    pass.optimize(&circuit.with_gates(gates))
+}
+
+/// `run_pass` for a routed circuit, running the pass's `optimize_physical`
+/// where it has one. Block bodies of a physical circuit are physical too.
+#[cfg(feature = "std")]
+pub fn run_physical_pass(
+    pass: &dyn OptimizationPass,
+    circuit: &PhysicalCircuit,
+) -> PhysicalCircuit {
+    let optimize = |circuit: &PhysicalCircuit| {
+        pass.optimize_physical(circuit)
+            .unwrap_or_else(|| circuit.map(|c| pass.optimize(c)))
+    };
+    if !pass.recurse_into_blocks() || circuit.gates.iter().all(|g| g.block.is_none()) {
+        return optimize(circuit);
+    }
+    let gates = circuit
+        .gates
+        .iter()
+        .map(|g| match &g.block {
+            Some(block) if !block.is_protected() => {
+                Gate::from_block(block.map_bodies(&mut |body| {
+                    run_physical_pass(pass, &PhysicalCircuit::assume_physical(body.clone()))
+                        .into_circuit()
+                }))
+            }
+            _ => g.clone(),
+        })
+        .collect();
+    optimize(&circuit.map(|c| c.with_gates(gates)))
+}
+
+/// Cancels back‑to‑back self‑inverse gates on same qubits (x/x, h/h, cx/cx).
+pub struct GateCancellationPass;
+
+/// Gates that are their own inverse, so an identical pair cancels.
+const SELF_INVERSE_GATES: &[&str] = &[
+    "id", "x", "y", "z", "h", "cx", "cnot", "cy", "cz", "swap", "ccx",
+];
+
+impl OptimizationPass for GateCancellationPass {
+    fn name(&self) -> &str {
+        "gate-cancellation"
+    }
+
+    fn optimize(&self, circuit: &QuantumCircuit) -> QuantumCircuit {
+        let mut out = Vec::new();
+        let mut i = 0;
+        while i < circuit.gates.len() {
+            if i + 1 < circuit.gates.len() {
+                let g1 = &circuit.gates[i];
+                let g2 = &circuit.gates[i + 1];
+                if g1.name == g2.name
+                    && SELF_INVERSE_GATES.contains(&g1.name.as_str())
+                    && g1.qubits == g2.qubits
+                    && g1.condition == g2.condition
+                    && g1.block.is_none()
+                    && g1.composite.is_none()
+                {
+                    // cancel pair
+                    i += 2;
+                    continue;
+                }
+            }
+            out.push(circuit.gates[i].clone());
+            i += 1;
+        }
+        circuit.with_gates(out)
+    }
+
+    fn skip_reason(&self, circuit: &QuantumCircuit) -> Option<String> {
+        let counts = gate_name_counts(circuit);
+        let repeated = SELF_INVERSE_GATES
+            .iter()
+            .any(|name| counts.get(name).is_some_and(|&n| n > 1));
+        (!repeated).then(|| "no self-inverse gate occurs twice".to_string())
+    }
+}
+
+/// Merges consecutive RZ rotations on same qubit.
+pub struct RotationMergingPass;
+
+impl OptimizationPass for RotationMergingPass {
+    fn name(&self) -> &str {
+        "rotation-merging"
+    }
+
+    fn optimize(&self, circuit: &QuantumCircuit) -> QuantumCircuit {
+        let mut out = Vec::new();
+        let mut i = 0;
+        while i < circuit.gates.len() {
+            let g = &circuit.gates[i];
+            if g.name == "rz"
+                && g.qubits.len() == 1
+                && !g.params.is_empty()
+                && g.condition.is_none()
+            {
+                let q = g.qubits[0];
+                let mut angle = g.params[0].clone();
+                let mut j = i + 1;
+                while j < circuit.gates.len() {
+                    let ng = &circuit.gates[j];
+                    if ng.name == "rz"
+                        && ng.qubits == vec![q]
+                        && !ng.params.is_empty()
+                        && ng.condition.is_none()
+                    {
+                        // Only merge angles that sum to a single parameter.
+                        match angle.add(&ng.params[0]) {
+                            Some(sum) => angle = sum,
+                            None => break,
+                        }
+                        j += 1;
+                    } else {
+                        break;
+                    }
+                }
+                if !angle.is_zero() {
+                    out.push(Gate::new("rz", vec![q], vec![angle]));
+                }
+                i = j;
+            } else {
+                out.push(g.clone());
+                i += 1;
+            }
+        }
+        circuit.with_gates(out)
+    }
+
+    /// Clifford-only circuits without `rz`, for instance, have nothing to merge.
+    fn skip_reason(&self, circuit: &QuantumCircuit) -> Option<String> {
+        (!gate_name_counts(circuit).contains_key("rz")).then(|| "no rz rotations".to_string())
+    }
+}
+
+// ============================================================================
+// TRANSPILER ENGINE
+// ============================================================================
+
+/// Last stage a transpilation runs; the result holds the circuit as that
+/// stage leaves it.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum TranspileStage {
+    /// Composite gates expanded into the gates they are defined by, then
+    /// placed on the initial layout without routing, so two-qubit gates may
+    /// act on uncoupled qubits.
+    Unroll,
+    /// Laid out and routed onto coupled qubits, before any optimization.
+    Routing,
+    /// Routed and optimized, before the scheduling passes.
+    Optimization,
+    /// Every stage, including the scheduling passes.
+    #[default]
+    Scheduling,
+}
+
+#[cfg(feature = "std")]
+impl TranspileStage {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "unroll" => Some(TranspileStage::Unroll),
+            "routing" => Some(TranspileStage::Routing),
+            "optimization" => Some(TranspileStage::Optimization),
+            "scheduling" => Some(TranspileStage::Scheduling),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, PartialEq)]
+pub struct TranspilationStats {
+    pub original_depth: usize,
+    pub final_depth: usize,
+    pub original_gate_count: usize,
+    pub final_gate_count: usize,
+    pub depth_reduction: f64,
+    pub gate_reduction: f64,
+    /// Optimization passes not run because the stopping criterion was met.
+    pub skipped_passes: usize,
+}
+
+#[cfg(feature = "std")]
+pub struct TranspilationResult {
+    pub circuit: PhysicalCircuit,
+    pub stats: TranspilationStats,
+    pub initial_layout: Layout,
+    pub final_layout: Layout,
+    pub routing: RoutingReport,
+    /// Classical bit each logical qubit is read into, taken from the input
+    /// circuit (see `QubitMapping::clbit`).
+    pub readout_clbits: Vec<Option<usize>>,
+    /// One record per optimization pass run, if tracing was enabled.
+    pub trace: Vec<PassRecord>,
+    /// What the parser skipped or guessed at, for circuits read from source.
+    pub warnings: Vec<ParseWarning>,
+}
+
+#[cfg(feature = "std")]
+pub struct UniversalTranspiler {
+    #[cfg(feature = "parser")]
+    parser: QASMParser,
+    router: Arc<dyn RoutingStrategy>,
+    #[cfg(feature = "exact-routing")]
+    exact_router: Option<exact_routing::ExactRouter>,
+    passes: Vec<Box<dyn OptimizationPass>>,
+    scheduling: Vec<Box<dyn SchedulingPass>>,
+    objective: OptimizationObjective,
+    stopping: StoppingCriterion,
+    trace: bool,
+    until: TranspileStage,
+    adaptive: bool,
+}
+
+#[cfg(feature = "std")]
+impl Default for UniversalTranspiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "std")]
+impl UniversalTranspiler {
+    pub fn new() -> Self {
+        Self {
+            #[cfg(feature = "parser")]
+            parser: QASMParser::default(),
+            router: Arc::new(SimpleRouter::default()),
+            #[cfg(feature = "exact-routing")]
+            exact_router: None,
+            passes: vec![
+                Box::new(InitialStateOptimizationPass),
+                Box::new(GateCancellationPass),
+                Box::new(RotationMergingPass),
+            ],
+            scheduling: Vec::new(),
+            objective: OptimizationObjective::default(),
+            stopping: StoppingCriterion::default(),
+            trace: false,
+            until: TranspileStage::default(),
+            adaptive: true,
+        }
+    }
+
+    /// Sets the objective used to accept or reject each optimization pass's
+    /// output.
+    pub fn with_objective(mut self, objective: OptimizationObjective) -> Self {
+        self.objective = objective;
+        self
+    }
+
+    /// Sets how the parser represents angles, e.g. `AngleOptions::exact_pi()`
+    /// to keep multiples of pi exact through optimization.
+    #[cfg(feature = "parser")]
+    pub fn with_angle_options(mut self, angles: AngleOptions) -> Self {
+        self.parser.angles = angles;
+        self
+    }
+
+    /// Sets whether the parser rejects input it cannot represent or skips it
+    /// with a warning in `TranspilationResult::warnings`.
+    #[cfg(feature = "parser")]
+    pub fn with_parse_mode(mut self, mode: ParseMode) -> Self {
+        self.parser.mode = mode;
+        self
+    }
+
+    /// Sets the vendor gate names the parser accepts, e.g. a table read with
+    /// `GateAliases::extend_from_table`.
+    #[cfg(feature = "parser")]
+    pub fn with_gate_aliases(mut self, aliases: GateAliases) -> Self {
+        self.parser.aliases = aliases;
+        self
+    }
+
+    /// Replaces the optimization pipeline, e.g. with one built by a
+    /// `PassRegistry`.
+    pub fn with_passes(mut self, passes: Vec<Box<dyn OptimizationPass>>) -> Self {
+        self.passes = passes;
+        self
+    }
+
+    /// Sets the passes run after optimization to fit the circuit to the
+    /// hardware's timing; there are none by default.
+    pub fn with_scheduling_passes(mut self, passes: Vec<Box<dyn SchedulingPass>>) -> Self {
+        self.scheduling = passes;
+        self
+    }
+
+    /// Replaces both the optimization and the scheduling passes with those
+    /// of a `PassRegistry` pipeline.
+    pub fn with_pipeline(self, pipeline: Pipeline) -> Self {
+        self.with_passes(pipeline.passes)
+            .with_scheduling_passes(pipeline.scheduling)
+    }
+
+    /// Records a `PassRecord` for every optimization pass run, returned in
+    /// `TranspilationResult::trace`.
+    pub fn with_pass_trace(mut self, enabled: bool) -> Self {
+        self.trace = enabled;
+        self
+    }
+
+    /// Sets whether passes whose `skip_reason` says they can't help are
+    /// skipped (the default), or always run, e.g. to profile them.
+    pub fn with_adaptive_skipping(mut self, enabled: bool) -> Self {
+        self.adaptive = enabled;
+        self
+    }
+
+    /// Stops after `stage`, e.g. `TranspileStage::Routing` to get the routed
+    /// circuit before optimization. The stats then compare the input with
+    /// that intermediate circuit.
+    pub fn with_stop_after(mut self, stage: TranspileStage) -> Self {
+        self.until = stage;
+        self
+    }
+
+    /// Sets when to stop optimizing early, e.g. once a target fidelity is
+    /// reached.
+    pub fn with_stopping_criterion(mut self, stopping: StoppingCriterion) -> Self {
+        self.stopping = stopping;
+        self
+    }
+
+    /// Enables teleportation-based routing of long-range CNOTs on backends
+    /// that support dynamic circuits.
+    pub fn with_teleportation_routing(mut self, enabled: bool) -> Self {
+        self.router = Arc::new(SimpleRouter {
+            teleportation: enabled,
+            ..SimpleRouter::default()
+        });
+        self
+    }
+
+    /// Routes with `router`, e.g. one looked up by name in a
+    /// `RouterRegistry`.
+    pub fn with_router(mut self, router: Arc<dyn RoutingStrategy>) -> Self {
+        self.router = router;
+        self
+    }
+
+    /// Routes circuits within `exact`'s limits with the minimum number of
+    /// swaps, choosing the initial layout too; larger circuits, and those
+    /// given a layout, still go through the heuristic router.
+    #[cfg(feature = "exact-routing")]
+    pub fn with_exact_routing(mut self, exact: exact_routing::ExactRouter) -> Self {
+        self.exact_router = Some(exact);
+        self
+    }
+
+    pub fn objective(&self) -> &OptimizationObjective {
+        &self.objective
+    }
+
+    #[cfg(feature = "parser")]
+    pub fn transpile(
+        &self,
+        input: &str,
+        backend: &BackendSpec,
+    ) -> Result<TranspilationResult, String> {
+        let (circ, warnings) = self.parser.parse_with_warnings(input)?;
+        let mut result = self.transpile_circuit(circ, backend)?;
+        result.warnings = warnings;
+        Ok(result)
+    }
+
+    /// Same as `transpile`, for a circuit that is already in memory.
+    pub fn transpile_circuit(
+        &self,
+        circ: QuantumCircuit,
+        backend: &BackendSpec,
+    ) -> Result<TranspilationResult, String> {
+        self.transpile_from(circ, backend, None)
+    }
+
+    /// Same as `transpile_circuit`, routing from `initial_layout` instead of
+    /// the trivial layout.
+    pub fn transpile_circuit_with_layout(
+        &self,
+        circ: QuantumCircuit,
+        backend: &BackendSpec,
+        initial_layout: Layout,
+    ) -> Result<TranspilationResult, String> {
+        self.transpile_from(circ, backend, Some(initial_layout))
+    }
+
+    /// Transpiles circuits that differ only in their gate parameters, such
+    /// as the points of a parameter sweep, choosing the layout and swaps once
+    /// per distinct structure. A circuit whose unrolled gates match an
+    /// earlier one's in everything but parameters reuses its routing with
+    /// its own parameters substituted, so the sweep keeps one qubit
+    /// assignment; circuits with control flow are routed on their own.
+    /// Results are in input order.
+    pub fn transpile_batch_with_shared_layout(
+        &self,
+        circuits: Vec<QuantumCircuit>,
+        backend: &BackendSpec,
+    ) -> Result<Vec<TranspilationResult>, String> {
+        let mut shared: Vec<(RoutingSkeleton, RoutedCircuit)> = Vec::new();
+        circuits
+            .into_iter()
+            .map(|circ| {
+                let original = (Self::calculate_depth(&circ), circ.gates.len());
+                let circ = run_pass(&UnrollPass, &circ);
+                let skeleton = RoutingSkeleton::of(&circ);
+                let reused = skeleton
+                    .as_ref()
+                    .and_then(|s| shared.iter().find(|(other, _)| other == s))
+                    .and_then(|(_, template)| template.with_params_of(&circ));
+                let routed = match reused {
+                    Some(routed) => routed,
+                    None => {
+                        let routed = self.route(&circ, backend, None)?;
+                        if let Some(skeleton) = skeleton {
+                            shared.push((skeleton, routed.clone()));
+                        }
+                        routed
+                    }
+                };
+                self.finish(circ, routed, backend, original)
+            })
+            .collect()
+    }
+
+    fn transpile_from(
+        &self,
+        circ: QuantumCircuit,
+        backend: &BackendSpec,
+        initial_layout: Option<Layout>,
+    ) -> Result<TranspilationResult, String> {
+        let original = (Self::calculate_depth(&circ), circ.gates.len());
+
+        // Expand composite gates so the router sees real interactions
+        let circ = run_pass(&UnrollPass, &circ);
+        let routed = self.route(&circ, backend, initial_layout)?;
+        self.finish(circ, routed, backend, original)
+    }
+
+    fn route(
+        &self,
+        circ: &QuantumCircuit,
+        backend: &BackendSpec,
+        initial_layout: Option<Layout>,
+    ) -> Result<RoutedCircuit, String> {
+        if self.until == TranspileStage::Unroll {
+            let layout = match initial_layout {
+                Some(layout) => layout,
+                None => Layout::trivial(circ.num_qubits, backend.num_qubits)?,
+            };
+            return Ok(RoutedCircuit {
+                circuit: circ.apply_layout(&layout)?,
+                initial_layout: layout.clone(),
+                final_layout: layout,
+                report: RoutingReport::default(),
+            });
+        }
+        #[cfg(feature = "exact-routing")]
+        let exact = match (&self.exact_router, &initial_layout) {
+            (Some(exact), None) => exact.route(circ, backend)?,
+            _ => None,
+        };
+        #[cfg(not(feature = "exact-routing"))]
+        let exact = None;
+        match (exact, initial_layout) {
+            (Some(routed), _) => Ok(routed),
+            (None, layout) => self.router.route(circ, backend, layout, &self.objective),
+        }
+    }
+
+    /// Optimizes a routed circuit; `original` is the depth and gate count of
+    /// the circuit as given.
+    fn finish(
+        &self,
+        circ: QuantumCircuit,
+        routed: RoutedCircuit,
+        backend: &BackendSpec,
+        original: (usize, usize),
+    ) -> Result<TranspilationResult, String> {
+        let (original_depth, original_gate_count) = original;
+        let fixed_timing = circ.timing == Timing::Fixed;
+        if fixed_timing
+            && (!routed.report.decisions.is_empty()
+                || routed.circuit.gates.len() != circ.gates.len())
+        {
+            return Err(format!(
+                "Circuit with fixed timing needs routing on backend {}; place interacting qubits on coupled ones",
+                backend.name
+            ));
+        }
+        let readout_clbits = circ.readout_clbits();
+        let mut circ = routed.circuit;
+
+        // Optimize, keeping a pass's output only if the objective doesn't regress,
+        // until the stopping criterion says further passes aren't worth it.
+        // Circuits with fixed timing are left as they are, as are those
+        // transpiled only up to an earlier stage.
+        let mut metrics = CircuitMetrics::on(&circ, backend);
+        let mut skipped_passes = 0;
+        let mut trace = Vec::new();
+        let optimize = !fixed_timing && self.until >= TranspileStage::Optimization;
+        for (i, p) in self.passes.iter().enumerate().filter(|_| optimize) {
+            if self.stopping.reached(&metrics) {
+                skipped_passes = self.passes.len() - i;
+                break;
+            }
+            let started = std::time::Instant::now();
+            if let Some(reason) = p.skip_reason(&circ).filter(|_| self.adaptive) {
+                if self.trace {
+                    let elapsed = started.elapsed().as_secs_f64();
+                    trace.push(PassRecord::skipped(
+                        p.name(),
+                        &circ,
+                        elapsed * 1000.0,
+                        reason,
+                    ));
+                }
+                continue;
+            }
+            let candidate = run_physical_pass(p.as_ref(), &circ);
+            let elapsed = started.elapsed().as_secs_f64();
+            let candidate_metrics = CircuitMetrics::on(&candidate, backend);
+            let before = metrics;
+            let accepted = self.objective.accepts(&metrics, &candidate_metrics);
+            if self.trace {
+                trace.push(PassRecord::new(
+                    p.name(),
+                    &circ,
+                    &candidate,
+                    elapsed * 1000.0,
+                    accepted,
+                ));
+            }
+            if accepted {
+                circ = candidate;
+                metrics = candidate_metrics;
+            }
+            if self.stopping.diminishing(&before, &metrics, elapsed) {
+                skipped_passes = self.passes.len() - i - 1;
+                break;
+            }
+        }
+
+        // Fit the circuit to the hardware's timing. The objective has no say
+        // here: the delays make the circuit runnable, which no metric rewards.
+        let schedule = !fixed_timing && self.until >= TranspileStage::Scheduling;
+        for p in self.scheduling.iter().filter(|_| schedule) {
+            let started = std::time::Instant::now();
+            let scheduled = p
+                .schedule(&circ)
+                .map_err(|e| format!("Scheduling pass {}: {e}", p.name()))?;
+            if self.trace {
+                let elapsed = started.elapsed().as_secs_f64();
+                trace.push(PassRecord::new(
+                    p.name(),
+                    &circ,
+                    &scheduled,
+                    elapsed * 1000.0,
+                    true,
+                ));
+            }
+            circ = scheduled;
+        }
+
+        let final_depth = Self::calculate_depth(&circ);
+        let final_gate_count = circ.gates.len();
+
+        let depth_reduction = if original_depth == 0 {
+            0.0
+        } else {
+            (original_depth.saturating_sub(final_depth)) as f64 / original_depth as f64 * 100.0
+        };
+
+        let gate_reduction = if original_gate_count == 0 {
+            0.0
+        } else {
+            (original_gate_count.saturating_sub(final_gate_count)) as f64
+                / original_gate_count as f64
+                * 100.0
+        };
+
+        Ok(TranspilationResult {
+            circuit: circ,
+            stats: TranspilationStats {
+                original_depth,
+                final_depth,
+                original_gate_count,
+                final_gate_count,
+                depth_reduction,
+                gate_reduction,
+                skipped_passes,
+            },
+            initial_layout: routed.initial_layout,
+            final_layout: routed.final_layout,
+            routing: routed.report,
+            readout_clbits,
+            trace,
+            warnings: Vec::new(),
+        })
+    }
+
+    pub fn calculate_depth(circuit: &QuantumCircuit) -> usize {
+        if circuit.num_qubits == 0 {
+            return 0;
+        }
+        circuit
+            .moment_indices()
+            .into_iter()
+            .max()
+            .map_or(0, |m| m + 1)
+    }
+}
+
+#[cfg(all(test, any(feature = "parser", feature = "router")))]
+mod tests {
+    use super::*;
+
+    /// Line 0-1-2 where edge 1-2 is much worse than edge 0-1.
+    #[cfg(feature = "router")]
+    fn lopsided_line() -> BackendSpec {
+        BackendSpec {
+            name: "lopsided".to_string(),
+            num_qubits: 3,
+            coupling_map: vec![(0, 1), (1, 2)],
+            native_gates: ["cx", "swap"].iter().map(|s| s.to_string()).collect(),
+            calibration: Some(
+                CalibrationSnapshot::new(0)
+                    .with_two_qubit_error(0, 1, 0.001)
+                    .with_two_qubit_error(1, 2, 0.05),
+            ),
+            ..Default::default()
+        }
+    }
+
+    #[cfg(feature = "router")]
+    fn route_with_bridges(objective: OptimizationObjective, gates: Vec<Gate>) -> RoutedCircuit {
+        let router = SimpleRouter {
+            bridge_gates: true,
+            objective,
+            ..SimpleRouter::default()
+        };
+        let circuit = QuantumCircuit::new(3, 0).with_gates(gates);
+        RoutingStrategy::route(&router, &circuit, &lopsided_line(), None, &objective).unwrap()
+    }
+
+    #[cfg(feature = "router")]
+    fn bridges(objective: OptimizationObjective) -> usize {
+        let gates = vec![Gate::new("cx", vec![0, 2], vec![])];
+        route_with_bridges(objective, gates).report.bridges.len()
+    }
+
+    #[cfg(feature = "router")]
+    #[test]
+    fn bridge_or_swap_follows_the_objective() {
+        // Both need four cx, so counting gates keeps the bridge...
+        assert_eq!(bridges(OptimizationObjective::min_two_qubit_count()), 1);
+        // ...but the bridge runs twice on the bad edge where the swap runs once.
+        assert_eq!(bridges(OptimizationObjective::min_error()), 0);
+    }
+
+    #[cfg(feature = "router")]
+    #[test]
+    fn a_bridge_is_the_cx_it_replaces() {
+        let cx = Gate::new("cx", vec![0, 2], vec![]);
+        let routed = route_with_bridges(
+            OptimizationObjective::min_two_qubit_count(),
+            vec![cx.clone()],
+        );
+        assert_eq!(routed.report.bridges[0].path, [0, 1, 2].map(PhysicalQubit));
+        assert_eq!(routed.final_layout, routed.initial_layout);
+        let unitary = |gates: &[Gate]| {
+            gates.iter().fold(linalg::Matrix::identity(8), |u, g| {
+                linalg::gate_matrix(g).unwrap().embed(&g.qubits, 3).mul(&u)
+            })
+        };
+        assert!(unitary(&routed.circuit.gates).approx_eq(&unitary(&[cx]), 1e-9));
+    }
+
+    #[cfg(feature = "router")]
+    #[test]
+    fn pairs_that_interact_again_are_swapped_together() {
+        let cx = Gate::new("cx", vec![0, 2], vec![]);
+        let routed = route_with_bridges(
+            OptimizationObjective::min_two_qubit_count(),
+            vec![cx.clone(), cx.clone(), cx],
+        );
+        assert!(routed.report.bridges.is_empty());
+        assert_eq!(routed.report.decisions.len(), 1);
+    }
+
+    #[cfg(feature = "parser")]
+    fn parser(mode: ParseMode) -> QASMParser {
+        QASMParser {
+            mode,
+            ..QASMParser::default()
+        }
+    }
+
+    #[cfg(feature = "parser")]
+    #[test]
+    fn unknown_gates_in_conditional_bodies_are_checked() {
+        let source = "qreg q[1];\ncreg c[1];\nif (c == 1) frob q[0];\nif (c == 1) x q[0];\n";
+        assert!(parser(ParseMode::Strict).parse(source).is_err());
+        let (circuit, warnings) = parser(ParseMode::Permissive)
+            .parse_with_warnings(source)
+            .unwrap();
+        assert_eq!(circuit.gates.len(), 1);
+        assert_eq!(circuit.gates[0].name, "x");
+        assert!(circuit.gates[0].condition.is_some());
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].message.contains("frob"));
+    }
+
+    #[cfg(feature = "parser")]
+    #[test]
+    fn conditional_resets_and_measurements_are_kept() {
+        let source =
+            "qreg q[2];\ncreg c[2];\nif (c == 1) reset q[1];\nif (c == 2) measure q[0] -> c[1];\n";
+        let circuit = parser(ParseMode::Strict).parse(source).unwrap();
+        let names: Vec<(&str, &[usize])> = circuit
+            .gates
+            .iter()
+            .map(|g| (g.name.as_str(), &g.qubits[..]))
+            .collect();
+        assert_eq!(names, [("reset", &[1][..]), ("measure", &[0][..])]);
+        assert!(circuit.gates.iter().all(|g| g.condition.is_some()));
+    }
+
+    #[cfg(feature = "parser")]
+    #[test]
+    fn registers_are_placed_one_after_another() {
+        let source = "qreg a[2];\nqubit[3] b;\ncx a[1], b[0];\nh b;\nmeasure b[2];\n";
+        let error = parser(ParseMode::Strict).parse(source).unwrap_err();
+        // `h b;` broadcasts over a register, which the parser does not do.
+        assert!(error.contains("h b"));
+
+        let source = "qreg a[2];\nqubit[3] b;\ncx a[1], b[0];\nbarrier b;\nmeasure b[2];\n";
+        let circuit = parser(ParseMode::Strict).parse(source).unwrap();
+        assert_eq!(circuit.num_qubits, 5);
+        let qubits: Vec<&[usize]> = circuit.gates.iter().map(|g| &g.qubits[..]).collect();
+        assert_eq!(qubits, [&[1, 2][..], &[2, 3, 4][..], &[4][..]]);
+
+        assert!(parser(ParseMode::Strict)
+            .parse("qreg a[2];\nh a[2];\n")
+            .is_err());
+        assert!(parser(ParseMode::Strict)
+            .parse("qreg a[2];\nqreg a[1];\n")
+            .is_err());
+        let (circuit, warnings) = parser(ParseMode::Permissive)
+            .parse_with_warnings("qreg a[2];\nqreg a[1];\nh a[1];\n")
+            .unwrap();
+        assert_eq!(circuit.num_qubits, 2);
+        assert_eq!(warnings.len(), 1);
+    }
+
+    #[cfg(feature = "parser")]
+    #[test]
+    fn malformed_parameters_are_skipped_in_permissive_mode() {
+        let source = "qreg q[1];\nrz(1 2) q[0];\nrz(0.5) q[0];\n";
+        assert!(parser(ParseMode::Strict).parse(source).is_err());
+        let (circuit, warnings) = parser(ParseMode::Permissive)
+            .parse_with_warnings(source)
+            .unwrap();
+        assert_eq!(circuit.gates.len(), 1);
+        assert_eq!(circuit.gates[0].params, [Param::Value(0.5)]);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].statement, "rz(1 2) q[0];");
+    }
+}
@@ -0,0 +1,304 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::f64::consts::PI;
+
+use crate::layout::PhysicalQubit;
+use crate::{BackendSpec, Gate, Param, QuantumCircuit};
+
+// ============================================================================
+// CIRCUIT LIBRARY
+// ============================================================================
+
+/// Builds a hardware-efficient variational ansatz whose entangling gates sit
+/// exactly on the backend's coupling map, so the router never has to touch it.
+///
+/// Each layer applies `ry(theta[k]) rz(theta[k + 1])` to every qubit followed
+/// by one `cx` per coupling edge; a final rotation layer closes the circuit.
+/// Parameters are left symbolic and named `theta[0]`, `theta[1]`, ...
+pub fn hardware_efficient_ansatz(backend: &BackendSpec, layers: usize) -> QuantumCircuit {
+    let mut gates = Vec::new();
+    let mut next_param = 0usize;
+
+    let mut rotation_layer = |gates: &mut Vec<Gate>| {
+        for q in 0..backend.num_qubits {
+            for name in ["ry", "rz"] {
+                gates.push(Gate::new(
+                    name,
+                    vec![q],
+                    vec![Param::symbol(&format!("theta[{next_param}]"))],
+                ));
+                next_param += 1;
+            }
+        }
+    };
+
+    // Undirected edges, in coupling map order, each entangled once per layer.
+    let mut seen = HashSet::new();
+    let edges: Vec<(usize, usize)> = backend
+        .coupling_map
+        .iter()
+        .filter(|&&(a, b)| a != b && seen.insert((a.min(b), a.max(b))))
+        .cloned()
+        .collect();
+
+    for _ in 0..layers {
+        rotation_layer(&mut gates);
+        for &(a, b) in &edges {
+            gates.push(Gate::new("cx", vec![a, b], vec![]));
+        }
+    }
+    rotation_layer(&mut gates);
+
+    QuantumCircuit {
+        gates,
+        ..QuantumCircuit::new(backend.num_qubits, 0)
+    }
+}
+
+/// Textbook quantum Fourier transform on `num_qubits` qubits, built from `h`
+/// and controlled-phase (`cp`) gates, with the final bit-reversal swaps if
+/// `do_swaps` is set.
+pub fn qft(num_qubits: usize, do_swaps: bool) -> QuantumCircuit {
+    let mut gates = Vec::new();
+    for j in 0..num_qubits {
+        gates.push(Gate::new("h", vec![j], vec![]));
+        for k in j + 1..num_qubits {
+            let angle = PI / (1u64 << (k - j)) as f64;
+            gates.push(Gate::new("cp", vec![k, j], vec![Param::Value(angle)]));
+        }
+    }
+    if do_swaps {
+        for j in 0..num_qubits / 2 {
+            gates.push(Gate::new("swap", vec![j, num_qubits - 1 - j], vec![]));
+        }
+    }
+    QuantumCircuit {
+        gates,
+        ..QuantumCircuit::new(num_qubits, 0)
+    }
+}
+
+/// Textbook GHZ preparation: `h` on qubit 0, then a chain of `cx` from each
+/// qubit to the next. Its depth grows linearly; see `ghz_on_backend` for one
+/// built around the coupling map.
+pub fn ghz(num_qubits: usize) -> QuantumCircuit {
+    let mut gates = Vec::new();
+    if num_qubits > 0 {
+        gates.push(Gate::new("h", vec![0], vec![]));
+    }
+    for q in 1..num_qubits {
+        gates.push(Gate::new("cx", vec![q - 1, q], vec![]));
+    }
+    QuantumCircuit {
+        gates,
+        ..QuantumCircuit::new(num_qubits, 0)
+    }
+}
+
+// ============================================================================
+// STATE PREPARATION ON THE COUPLING MAP
+// ============================================================================
+
+/// A circuit over a backend's physical qubits preparing a state on some of
+/// them, with every two-qubit gate on a coupled pair.
+#[derive(Debug, Clone)]
+pub struct StatePreparation {
+    pub circuit: QuantumCircuit,
+    /// Qubits holding the state, in the order they were entangled for a GHZ
+    /// state and in increasing order for a graph state.
+    pub qubits: Vec<PhysicalQubit>,
+    /// Layers of two-qubit gates after the initial Hadamards.
+    pub entangling_depth: usize,
+}
+
+/// Undirected neighbours of every physical qubit, sorted.
+fn neighbors(backend: &BackendSpec) -> Vec<BTreeSet<usize>> {
+    let mut neighbors = vec![BTreeSet::new(); backend.num_qubits];
+    for &(a, b) in &backend.coupling_map {
+        if a != b && a < backend.num_qubits && b < backend.num_qubits {
+            neighbors[a].insert(b);
+            neighbors[b].insert(a);
+        }
+    }
+    neighbors
+}
+
+/// Prepares a GHZ state on `num_qubits` coupled physical qubits by fanning
+/// out CNOTs along a spanning tree of the coupling map instead of a chain.
+///
+/// For every root, the tree is a breadth-first tree over the `num_qubits`
+/// qubits closest to it, and each qubit feeds its children in decreasing
+/// order of the rounds their subtrees still need, which is the fastest
+/// schedule on that tree. The root and tree needing the fewest CNOT rounds
+/// are kept, ties going to the lower-numbered root.
+pub fn ghz_on_backend(
+    backend: &BackendSpec,
+    num_qubits: usize,
+) -> Result<StatePreparation, String> {
+    if num_qubits == 0 || num_qubits > backend.num_qubits {
+        return Err(format!(
+            "Cannot prepare a {num_qubits}-qubit GHZ state on backend {} with {} qubits",
+            backend.name, backend.num_qubits
+        ));
+    }
+    let neighbors = neighbors(backend);
+    let (root, (rounds, schedule)) = (0..backend.num_qubits)
+        .filter_map(|root| {
+            bfs_tree(&neighbors, root, num_qubits)
+                .map(|tree| (root, broadcast_schedule(root, &tree)))
+        })
+        .min_by_key(|&(root, (rounds, _))| (rounds, root))
+        .ok_or_else(|| {
+            format!(
+                "Backend {} has no {num_qubits} connected qubits",
+                backend.name
+            )
+        })?;
+
+    let mut gates = vec![Gate::new("h", vec![root], vec![])];
+    let mut qubits = vec![PhysicalQubit(root)];
+    for &(_, parent, child) in &schedule {
+        gates.push(Gate::new("cx", vec![parent, child], vec![]));
+        qubits.push(PhysicalQubit(child));
+    }
+    Ok(StatePreparation {
+        circuit: QuantumCircuit {
+            gates,
+            ..QuantumCircuit::new(backend.num_qubits, 0)
+        },
+        qubits,
+        entangling_depth: rounds,
+    })
+}
+
+/// `(qubit, parent)` for the first `size` qubits reached breadth-first from
+/// `root`, or `None` if fewer are connected to it.
+fn bfs_tree(
+    neighbors: &[BTreeSet<usize>],
+    root: usize,
+    size: usize,
+) -> Option<Vec<(usize, Option<usize>)>> {
+    let mut seen = vec![false; neighbors.len()];
+    seen[root] = true;
+    let mut queue = VecDeque::from([root]);
+    let mut tree = vec![(root, None)];
+    while let Some(q) = queue.pop_front() {
+        for &n in &neighbors[q] {
+            if tree.len() == size {
+                return Some(tree);
+            }
+            if !seen[n] {
+                seen[n] = true;
+                tree.push((n, Some(q)));
+                queue.push_back(n);
+            }
+        }
+    }
+    (tree.len() == size).then_some(tree)
+}
+
+/// Rounds needed to spread a value from `root` over the tree, one child per
+/// qubit per round, and the `(round, parent, child)` CNOTs doing it, sorted
+/// by round.
+fn broadcast_schedule(
+    root: usize,
+    tree: &[(usize, Option<usize>)],
+) -> (usize, Vec<(usize, usize, usize)>) {
+    // Rounds each subtree needs once its root holds the value, filled in
+    // from the leaves since the tree is in breadth-first order.
+    let mut children: Vec<Vec<usize>> = vec![Vec::new(); tree.len()];
+    let index: HashMap<usize, usize> = tree.iter().enumerate().map(|(i, &(q, _))| (q, i)).collect();
+    for (i, &(_, parent)) in tree.iter().enumerate() {
+        if let Some(p) = parent {
+            children[index[&p]].push(i);
+        }
+    }
+    let mut needs = vec![0; tree.len()];
+    for i in (0..tree.len()).rev() {
+        children[i].sort_by_key(|&c| (Reverse(needs[c]), tree[c].0));
+        needs[i] = children[i]
+            .iter()
+            .enumerate()
+            .map(|(k, &c)| k + 1 + needs[c])
+            .max()
+            .unwrap_or(0);
+    }
+
+    let mut schedule = Vec::new();
+    let mut reached = vec![(index[&root], 0)];
+    while let Some((i, round)) = reached.pop() {
+        for (k, &c) in children[i].iter().enumerate() {
+            schedule.push((round + k + 1, tree[i].0, tree[c].0));
+            reached.push((c, round + k + 1));
+        }
+    }
+    schedule.sort();
+    (needs[index[&root]], schedule)
+}
+
+/// Prepares the graph state with the given edges, all of which must be
+/// coupled pairs: `h` on every vertex, then one `cz` per edge. The CZs
+/// commute, so they are packed into as few layers as a greedy edge colouring
+/// finds, busiest vertices first.
+pub fn graph_state_on_backend(
+    backend: &BackendSpec,
+    edges: &[(PhysicalQubit, PhysicalQubit)],
+) -> Result<StatePreparation, String> {
+    let mut unique = BTreeSet::new();
+    for &(a, b) in edges {
+        if a == b
+            || a.0 >= backend.num_qubits
+            || b.0 >= backend.num_qubits
+            || !backend.are_coupled(a, b)
+        {
+            return Err(format!(
+                "Graph state edge {a}-{b} is not a coupled pair of backend {}",
+                backend.name
+            ));
+        }
+        unique.insert((a.min(b), a.max(b)));
+    }
+    let mut degree = vec![0; backend.num_qubits];
+    for &(a, b) in &unique {
+        degree[a.0] += 1;
+        degree[b.0] += 1;
+    }
+    let mut ordered: Vec<(PhysicalQubit, PhysicalQubit)> = unique.into_iter().collect();
+    ordered.sort_by_key(|&(a, b)| Reverse(degree[a.0].max(degree[b.0])));
+
+    let mut layers: Vec<Vec<(PhysicalQubit, PhysicalQubit)>> = Vec::new();
+    for (a, b) in ordered {
+        match layers.iter_mut().find(|layer| {
+            layer
+                .iter()
+                .all(|&(x, y)| ![x, y].contains(&a) && ![x, y].contains(&b))
+        }) {
+            Some(layer) => layer.push((a, b)),
+            None => layers.push(vec![(a, b)]),
+        }
+    }
+
+    let qubits: Vec<PhysicalQubit> = (0..backend.num_qubits)
+        .filter(|&q| degree[q] > 0)
+        .map(PhysicalQubit)
+        .collect();
+    let mut gates: Vec<Gate> = qubits
+        .iter()
+        .map(|q| Gate::new("h", vec![q.0], vec![]))
+        .collect();
+    for layer in &layers {
+        gates.extend(
+            layer
+                .iter()
+                .map(|&(a, b)| Gate::new("cz", vec![a.0, b.0], vec![])),
+        );
+    }
+    Ok(StatePreparation {
+        circuit: QuantumCircuit {
+            gates,
+            ..QuantumCircuit::new(backend.num_qubits, 0)
+        },
+        qubits,
+        entangling_depth: layers.len(),
+    })
+}
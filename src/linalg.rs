@@ -0,0 +1,591 @@
+use std::f64::consts::FRAC_1_SQRT_2;
+use std::ops::{Add, Mul, Neg, Sub};
+use std::sync::OnceLock;
+
+use crate::{Gate, Param};
+
+// ============================================================================
+// COMPLEX NUMBERS AND SMALL DENSE MATRICES
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Complex {
+    pub re: f64,
+    pub im: f64,
+}
+
+impl Complex {
+    pub const ZERO: Complex = Complex { re: 0.0, im: 0.0 };
+    pub const ONE: Complex = Complex { re: 1.0, im: 0.0 };
+    pub const I: Complex = Complex { re: 0.0, im: 1.0 };
+
+    pub fn new(re: f64, im: f64) -> Self {
+        Self { re, im }
+    }
+
+    /// `e^{i theta}`.
+    pub fn from_phase(theta: f64) -> Self {
+        Self::new(theta.cos(), theta.sin())
+    }
+
+    pub fn conj(self) -> Self {
+        Self::new(self.re, -self.im)
+    }
+
+    pub fn norm_sqr(self) -> f64 {
+        self.re * self.re + self.im * self.im
+    }
+
+    pub fn abs(self) -> f64 {
+        self.norm_sqr().sqrt()
+    }
+
+    pub fn scale(self, k: f64) -> Self {
+        Self::new(self.re * k, self.im * k)
+    }
+}
+
+impl Add for Complex {
+    type Output = Complex;
+    fn add(self, o: Complex) -> Complex {
+        Complex::new(self.re + o.re, self.im + o.im)
+    }
+}
+
+impl Sub for Complex {
+    type Output = Complex;
+    fn sub(self, o: Complex) -> Complex {
+        Complex::new(self.re - o.re, self.im - o.im)
+    }
+}
+
+impl Mul for Complex {
+    type Output = Complex;
+    fn mul(self, o: Complex) -> Complex {
+        Complex::new(
+            self.re * o.re - self.im * o.im,
+            self.re * o.im + self.im * o.re,
+        )
+    }
+}
+
+impl Neg for Complex {
+    type Output = Complex;
+    fn neg(self) -> Complex {
+        Complex::new(-self.re, -self.im)
+    }
+}
+
+/// Square complex matrix in row-major order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Matrix {
+    pub dim: usize,
+    pub data: Vec<Complex>,
+}
+
+impl Matrix {
+    pub fn identity(dim: usize) -> Self {
+        let mut data = vec![Complex::ZERO; dim * dim];
+        for i in 0..dim {
+            data[i * dim + i] = Complex::ONE;
+        }
+        Self { dim, data }
+    }
+
+    pub fn from_rows(rows: &[&[Complex]]) -> Self {
+        let dim = rows.len();
+        Self {
+            dim,
+            data: rows.iter().flat_map(|r| r.iter().copied()).collect(),
+        }
+    }
+
+    pub fn get(&self, row: usize, col: usize) -> Complex {
+        self.data[row * self.dim + col]
+    }
+
+    /// Matrix product, computed by the installed `LinalgBackend`.
+    pub fn mul(&self, other: &Matrix) -> Matrix {
+        Matrix {
+            dim: self.dim,
+            data: backend().matmul(self.dim, &self.data, &other.data),
+        }
+    }
+
+    /// Applies this unitary to `qubits` of `state` (bit `i` of the matrix
+    /// index is `qubits[i]`, little-endian), using the installed backend.
+    pub fn apply_to_state(&self, qubits: &[usize], state: &mut [Complex]) {
+        backend().apply(self, qubits, state);
+    }
+
+    pub fn adjoint(&self) -> Matrix {
+        let n = self.dim;
+        let mut data = vec![Complex::ZERO; n * n];
+        for i in 0..n {
+            for j in 0..n {
+                data[j * n + i] = self.data[i * n + j].conj();
+            }
+        }
+        Matrix { dim: n, data }
+    }
+
+    pub fn approx_eq(&self, other: &Matrix, tol: f64) -> bool {
+        self.dim == other.dim
+            && self
+                .data
+                .iter()
+                .zip(&other.data)
+                .all(|(a, b)| (*a - *b).abs() <= tol)
+    }
+
+    /// True if the matrices agree up to a global phase.
+    pub fn approx_eq_up_to_phase(&self, other: &Matrix, tol: f64) -> bool {
+        if self.dim != other.dim {
+            return false;
+        }
+        let Some(k) = other.data.iter().position(|z| z.abs() > tol) else {
+            return self.data.iter().all(|z| z.abs() <= tol);
+        };
+        if self.data[k].abs() <= tol {
+            return false;
+        }
+        // phase = self[k] / other[k], normalized
+        let ratio = self.data[k] * other.data[k].conj();
+        let phase = ratio.scale(1.0 / ratio.abs());
+        self.data
+            .iter()
+            .zip(&other.data)
+            .all(|(a, b)| (*a - phase * *b).abs() <= tol)
+    }
+
+    /// Extends a matrix acting on `positions` (bit `i` of its index is
+    /// `positions[i]`) to `num_qubits` qubits, little-endian.
+    pub fn embed(&self, positions: &[usize], num_qubits: usize) -> Matrix {
+        let dim = 1usize << num_qubits;
+        let mask: usize = positions.iter().map(|&p| 1 << p).sum();
+        let local = |x: usize| -> usize {
+            positions
+                .iter()
+                .enumerate()
+                .map(|(i, &p)| ((x >> p) & 1) << i)
+                .sum()
+        };
+        let mut data = vec![Complex::ZERO; dim * dim];
+        for row in 0..dim {
+            for col in 0..dim {
+                if row & !mask == col & !mask {
+                    data[row * dim + col] = self.get(local(row), local(col));
+                }
+            }
+        }
+        Matrix { dim, data }
+    }
+
+    pub fn is_diagonal(&self, tol: f64) -> bool {
+        (0..self.dim).all(|i| (0..self.dim).all(|j| i == j || self.get(i, j).abs() <= tol))
+    }
+}
+
+// ============================================================================
+// STANDARD GATE MATRICES
+// ============================================================================
+
+/// Unitary of a standard gate, with `qubits[i]` as bit `i` of the index
+/// (little-endian, as in OpenQASM). `None` for unknown gates, symbolic
+/// parameters and non-unitary operations.
+pub fn gate_matrix(gate: &Gate) -> Option<Matrix> {
+    if gate.block.is_some() || gate.composite.is_some() {
+        return None;
+    }
+    let params: Vec<f64> = gate
+        .params
+        .iter()
+        .map(Param::value)
+        .collect::<Option<_>>()?;
+    let p = |i: usize| params.get(i).copied();
+    let c = |re: f64, im: f64| Complex::new(re, im);
+    let (o, l) = (Complex::ZERO, Complex::ONE);
+
+    let m = match gate.name.as_str() {
+        "id" | "i" => Matrix::identity(2),
+        "x" => Matrix::from_rows(&[&[o, l], &[l, o]]),
+        "y" => Matrix::from_rows(&[&[o, -Complex::I], &[Complex::I, o]]),
+        "z" => Matrix::from_rows(&[&[l, o], &[o, -l]]),
+        "h" => {
+            let s = c(FRAC_1_SQRT_2, 0.0);
+            Matrix::from_rows(&[&[s, s], &[s, -s]])
+        }
+        "s" => phase_gate(std::f64::consts::FRAC_PI_2),
+        "sdg" => phase_gate(-std::f64::consts::FRAC_PI_2),
+        "t" => phase_gate(std::f64::consts::FRAC_PI_4),
+        "tdg" => phase_gate(-std::f64::consts::FRAC_PI_4),
+        "sx" => Matrix::from_rows(&[&[c(0.5, 0.5), c(0.5, -0.5)], &[c(0.5, -0.5), c(0.5, 0.5)]]),
+        "p" | "u1" => phase_gate(p(0)?),
+        "rz" => {
+            let t = p(0)? / 2.0;
+            Matrix::from_rows(&[&[Complex::from_phase(-t), o], &[o, Complex::from_phase(t)]])
+        }
+        "rx" => {
+            let t = p(0)? / 2.0;
+            Matrix::from_rows(&[
+                &[c(t.cos(), 0.0), c(0.0, -t.sin())],
+                &[c(0.0, -t.sin()), c(t.cos(), 0.0)],
+            ])
+        }
+        "ry" => {
+            let t = p(0)? / 2.0;
+            Matrix::from_rows(&[
+                &[c(t.cos(), 0.0), c(-t.sin(), 0.0)],
+                &[c(t.sin(), 0.0), c(t.cos(), 0.0)],
+            ])
+        }
+        "u" | "u3" => u3(p(0)?, p(1)?, p(2)?),
+        "u2" => u3(std::f64::consts::FRAC_PI_2, p(0)?, p(1)?),
+        "cx" | "cnot" => controlled(&Matrix::from_rows(&[&[o, l], &[l, o]])),
+        "cy" => controlled(&Matrix::from_rows(&[&[o, -Complex::I], &[Complex::I, o]])),
+        "cz" => controlled(&Matrix::from_rows(&[&[l, o], &[o, -l]])),
+        "cp" | "cu1" => controlled(&phase_gate(p(0)?)),
+        "crz" => {
+            let t = p(0)? / 2.0;
+            controlled(&Matrix::from_rows(&[
+                &[Complex::from_phase(-t), o],
+                &[o, Complex::from_phase(t)],
+            ]))
+        }
+        "swap" => {
+            let mut m = Matrix::identity(4);
+            m.data = vec![o; 16];
+            for (r, col) in [(0, 0), (1, 2), (2, 1), (3, 3)] {
+                m.data[r * 4 + col] = l;
+            }
+            m
+        }
+        "rzz" => {
+            let t = p(0)? / 2.0;
+            let (a, b) = (Complex::from_phase(-t), Complex::from_phase(t));
+            let mut m = Matrix::identity(4);
+            m.data = vec![o; 16];
+            for (i, v) in [a, b, b, a].into_iter().enumerate() {
+                m.data[i * 4 + i] = v;
+            }
+            m
+        }
+        "ccx" => {
+            let mut m = Matrix::identity(8);
+            // controls are bits 0 and 1, target bit 2: swap |011> and |111>
+            m.data[3 * 8 + 3] = o;
+            m.data[7 * 8 + 7] = o;
+            m.data[3 * 8 + 7] = l;
+            m.data[7 * 8 + 3] = l;
+            m
+        }
+        _ => return None,
+    };
+    (m.dim == 1 << gate.qubits.len()).then_some(m)
+}
+
+fn phase_gate(theta: f64) -> Matrix {
+    Matrix::from_rows(&[
+        &[Complex::ONE, Complex::ZERO],
+        &[Complex::ZERO, Complex::from_phase(theta)],
+    ])
+}
+
+fn u3(theta: f64, phi: f64, lambda: f64) -> Matrix {
+    let (c, s) = ((theta / 2.0).cos(), (theta / 2.0).sin());
+    Matrix::from_rows(&[
+        &[Complex::new(c, 0.0), -Complex::from_phase(lambda).scale(s)],
+        &[
+            Complex::from_phase(phi).scale(s),
+            Complex::from_phase(phi + lambda).scale(c),
+        ],
+    ])
+}
+
+/// Two-qubit controlled-`u` with the control on bit 0 and target on bit 1.
+fn controlled(u: &Matrix) -> Matrix {
+    let mut m = Matrix::identity(4);
+    // Indices with the control bit set: 1 (target 0) and 3 (target 1).
+    for (r, &row) in [1usize, 3].iter().enumerate() {
+        for (col_i, &col) in [1usize, 3].iter().enumerate() {
+            m.data[row * 4 + col] = u.get(r, col_i);
+        }
+    }
+    m
+}
+
+// ============================================================================
+// LINEAR ALGEBRA BACKENDS
+// ============================================================================
+
+/// Dense kernels behind `Matrix`. One backend is installed per process with
+/// `set_backend`; `BlockedBackend` is used if none is. The `nalgebra`
+/// feature provides `NalgebraBackend`, and a crate depending on this one can
+/// wrap another library, such as faer, behind it:
+///
+/// ```
+/// use transpiler_arch::linalg::{self, Complex, LinalgBackend};
+///
+/// /// Textbook triple loop, standing in for a call into faer.
+/// struct Naive;
+///
+/// impl LinalgBackend for Naive {
+///     fn name(&self) -> &'static str {
+///         "naive"
+///     }
+///
+///     fn matmul(&self, n: usize, a: &[Complex], b: &[Complex]) -> Vec<Complex> {
+///         let mut out = vec![Complex::ZERO; n * n];
+///         for i in 0..n {
+///             for k in 0..n {
+///                 for j in 0..n {
+///                     out[i * n + j] = out[i * n + j] + a[i * n + k] * b[k * n + j];
+///                 }
+///             }
+///         }
+///         out
+///     }
+/// }
+///
+/// linalg::set_backend(Box::new(Naive)).unwrap();
+/// assert_eq!(linalg::backend().name(), "naive");
+/// ```
+pub trait LinalgBackend: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Product of two `n` x `n` row-major matrices.
+    fn matmul(&self, n: usize, a: &[Complex], b: &[Complex]) -> Vec<Complex>;
+
+    /// Applies the `2^k` x `2^k` matrix `u` to the `k` `qubits` of a state
+    /// vector in place.
+    fn apply(&self, u: &Matrix, qubits: &[usize], state: &mut [Complex]) {
+        let mask: usize = qubits.iter().map(|&q| 1 << q).sum();
+        let offsets: Vec<usize> = (0..u.dim)
+            .map(|local| {
+                qubits
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &q)| ((local >> i) & 1) << q)
+                    .sum()
+            })
+            .collect();
+        let mut amplitudes = vec![Complex::ZERO; u.dim];
+        for base in (0..state.len()).filter(|i| i & mask == 0) {
+            for (a, &offset) in amplitudes.iter_mut().zip(&offsets) {
+                *a = state[base + offset];
+            }
+            for (row, &offset) in offsets.iter().enumerate() {
+                let coefficients = &u.data[row * u.dim..(row + 1) * u.dim];
+                state[base + offset] = coefficients
+                    .iter()
+                    .zip(&amplitudes)
+                    .fold(Complex::ZERO, |acc, (&c, &a)| acc + c * a);
+            }
+        }
+    }
+}
+
+/// Triple loop over `Complex` values, skipping zero entries of `a`, which
+/// makes it fast for the sparse matrices of embedded gates.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NaiveBackend;
+
+impl LinalgBackend for NaiveBackend {
+    fn name(&self) -> &'static str {
+        "naive"
+    }
+
+    fn matmul(&self, n: usize, a: &[Complex], b: &[Complex]) -> Vec<Complex> {
+        let mut out = vec![Complex::ZERO; n * n];
+        for i in 0..n {
+            for k in 0..n {
+                let x = a[i * n + k];
+                if x == Complex::ZERO {
+                    continue;
+                }
+                for j in 0..n {
+                    out[i * n + j] = out[i * n + j] + x * b[k * n + j];
+                }
+            }
+        }
+        out
+    }
+}
+
+/// Cache-blocked product on separate real and imaginary planes, whose inner
+/// loops the compiler turns into SIMD instructions. Matrices smaller than
+/// `min_dim` go through `NaiveBackend`, where splitting isn't worth it.
+#[derive(Debug, Clone, Copy)]
+pub struct BlockedBackend {
+    /// Tile edge, in elements.
+    pub block: usize,
+    pub min_dim: usize,
+}
+
+impl Default for BlockedBackend {
+    fn default() -> Self {
+        Self {
+            block: 64,
+            min_dim: 32,
+        }
+    }
+}
+
+impl LinalgBackend for BlockedBackend {
+    fn name(&self) -> &'static str {
+        "blocked"
+    }
+
+    fn matmul(&self, n: usize, a: &[Complex], b: &[Complex]) -> Vec<Complex> {
+        if n < self.min_dim {
+            return NaiveBackend.matmul(n, a, b);
+        }
+        let planes = |m: &[Complex]| -> (Vec<f64>, Vec<f64>) {
+            (
+                m.iter().map(|z| z.re).collect(),
+                m.iter().map(|z| z.im).collect(),
+            )
+        };
+        let (a_re, a_im) = planes(a);
+        let (b_re, b_im) = planes(b);
+        let mut re = vec![0.0; n * n];
+        let mut im = vec![0.0; n * n];
+        let block = self.block.max(1);
+        for jj in (0..n).step_by(block) {
+            let j_end = (jj + block).min(n);
+            for kk in (0..n).step_by(block) {
+                let k_end = (kk + block).min(n);
+                for i in 0..n {
+                    let out_re = &mut re[i * n + jj..i * n + j_end];
+                    let out_im = &mut im[i * n + jj..i * n + j_end];
+                    for k in kk..k_end {
+                        let (x_re, x_im) = (a_re[i * n + k], a_im[i * n + k]);
+                        if x_re == 0.0 && x_im == 0.0 {
+                            continue;
+                        }
+                        let row_re = &b_re[k * n + jj..k * n + j_end];
+                        let row_im = &b_im[k * n + jj..k * n + j_end];
+                        for (((o_re, o_im), &y_re), &y_im) in out_re
+                            .iter_mut()
+                            .zip(out_im.iter_mut())
+                            .zip(row_re)
+                            .zip(row_im)
+                        {
+                            *o_re += x_re * y_re - x_im * y_im;
+                            *o_im += x_re * y_im + x_im * y_re;
+                        }
+                    }
+                }
+            }
+        }
+        re.into_iter()
+            .zip(im)
+            .map(|(re, im)| Complex::new(re, im))
+            .collect()
+    }
+}
+
+/// Product through nalgebra: the real and imaginary planes are multiplied
+/// as four real matrix products, which nalgebra hands to its SIMD `gemm`
+/// kernels. Matrices smaller than `min_dim` go through `NaiveBackend`.
+#[cfg(feature = "nalgebra")]
+#[derive(Debug, Clone, Copy)]
+pub struct NalgebraBackend {
+    pub min_dim: usize,
+}
+
+#[cfg(feature = "nalgebra")]
+impl Default for NalgebraBackend {
+    fn default() -> Self {
+        Self { min_dim: 32 }
+    }
+}
+
+#[cfg(feature = "nalgebra")]
+impl LinalgBackend for NalgebraBackend {
+    fn name(&self) -> &'static str {
+        "nalgebra"
+    }
+
+    fn matmul(&self, n: usize, a: &[Complex], b: &[Complex]) -> Vec<Complex> {
+        use nalgebra::DMatrix;
+
+        if n < self.min_dim {
+            return NaiveBackend.matmul(n, a, b);
+        }
+        let planes = |m: &[Complex]| {
+            (
+                DMatrix::from_row_iterator(n, n, m.iter().map(|z| z.re)),
+                DMatrix::from_row_iterator(n, n, m.iter().map(|z| z.im)),
+            )
+        };
+        let (a_re, a_im) = planes(a);
+        let (b_re, b_im) = planes(b);
+        let re = &a_re * &b_re - &a_im * &b_im;
+        let im = &a_re * &b_im + &a_im * &b_re;
+        // nalgebra stores columns contiguously; walk the result by rows.
+        (0..n)
+            .flat_map(|i| (0..n).map(move |j| (i, j)))
+            .map(|(i, j)| Complex::new(re[(i, j)], im[(i, j)]))
+            .collect()
+    }
+}
+
+static BACKEND: OnceLock<Box<dyn LinalgBackend>> = OnceLock::new();
+
+/// Installs the backend for all matrix math in this process, e.g. one from
+/// another crate wrapping faer, nalgebra or a BLAS. Fails once any matrix
+/// math has run or a backend was already installed.
+pub fn set_backend(backend: Box<dyn LinalgBackend>) -> Result<(), String> {
+    let name = backend.name();
+    BACKEND.set(backend).map_err(|_| {
+        format!(
+            "Cannot install linear algebra backend {name}: {} is already in use",
+            self::backend().name()
+        )
+    })
+}
+
+/// The installed backend.
+pub fn backend() -> &'static dyn LinalgBackend {
+    BACKEND
+        .get_or_init(|| Box::new(BlockedBackend::default()))
+        .as_ref()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Dense `n` x `n` matrix with entries spread over the unit square.
+    fn dense(n: usize, seed: u64) -> Vec<Complex> {
+        let mut x = seed;
+        let mut next = move || {
+            x = x
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (x >> 11) as f64 / (1u64 << 53) as f64 - 0.5
+        };
+        (0..n * n).map(|_| Complex::new(next(), next())).collect()
+    }
+
+    fn assert_close(a: &[Complex], b: &[Complex]) {
+        for (x, y) in a.iter().zip(b) {
+            assert!((*x - *y).abs() < 1e-9, "{x:?} != {y:?}");
+        }
+    }
+
+    #[test]
+    fn backends_agree_with_the_triple_loop() {
+        let n = 40;
+        let (a, b) = (dense(n, 1), dense(n, 2));
+        let expected = NaiveBackend.matmul(n, &a, &b);
+        let blocked = BlockedBackend {
+            block: 16,
+            min_dim: 1,
+        };
+        assert_close(&blocked.matmul(n, &a, &b), &expected);
+        #[cfg(feature = "nalgebra")]
+        assert_close(&NalgebraBackend { min_dim: 1 }.matmul(n, &a, &b), &expected);
+    }
+}
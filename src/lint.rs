@@ -0,0 +1,415 @@
+use std::collections::HashSet;
+use std::f64::consts::TAU;
+use std::fmt;
+
+use crate::angle::Rational;
+use crate::classical::{ClassicalExpr, ClassicalRegister};
+use crate::control_flow::ControlFlow;
+use crate::{Gate, Param, QuantumCircuit};
+
+// ============================================================================
+// CIRCUIT LINTS
+// ============================================================================
+
+/// A suspicious pattern found in a circuit.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lint {
+    pub rule: &'static str,
+    /// Where the pattern was found, e.g. `gate 3`, `gate 2, body 0, gate 1`
+    /// or `qubit 4`.
+    pub location: String,
+    pub message: String,
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} [{}]", self.location, self.message, self.rule)
+    }
+}
+
+/// One check run by the `Linter`.
+pub trait LintRule {
+    /// Name used to report and allow the rule, e.g. `small-angle`.
+    fn name(&self) -> &'static str;
+
+    fn description(&self) -> &'static str;
+
+    fn check(&self, circuit: &QuantumCircuit) -> Vec<Lint>;
+}
+
+/// Runs a set of rules, skipping the allowed ones.
+pub struct Linter {
+    pub rules: Vec<Box<dyn LintRule>>,
+    pub allowed: HashSet<String>,
+}
+
+impl Default for Linter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Linter {
+    /// Linter running every built-in rule.
+    pub fn new() -> Self {
+        Self {
+            rules: vec![
+                Box::new(AngleInDegrees),
+                Box::new(SmallAngle::default()),
+                Box::new(GateAfterMeasure),
+                Box::new(UnusedClbit),
+                Box::new(UnmeasuredQubit),
+                Box::new(UnreachableBranch),
+            ],
+            allowed: HashSet::new(),
+        }
+    }
+
+    pub fn with_rule(mut self, rule: Box<dyn LintRule>) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    /// Stops reporting the rule `name`.
+    pub fn allow(mut self, name: &str) -> Result<Self, String> {
+        if !self.rules.iter().any(|r| r.name() == name) {
+            let known = self
+                .rules
+                .iter()
+                .map(|r| r.name())
+                .collect::<Vec<_>>()
+                .join(", ");
+            return Err(format!("Unknown lint rule '{name}' (known rules: {known})"));
+        }
+        self.allowed.insert(name.to_string());
+        Ok(self)
+    }
+
+    pub fn check(&self, circuit: &QuantumCircuit) -> Vec<Lint> {
+        self.rules
+            .iter()
+            .filter(|r| !self.allowed.contains(r.name()))
+            .flat_map(|r| r.check(circuit))
+            .collect()
+    }
+}
+
+/// Calls `f` with each gate and its location, block gates before the gates
+/// of their bodies.
+fn visit(gates: &[Gate], prefix: &str, f: &mut dyn FnMut(&Gate, &str)) {
+    for (i, g) in gates.iter().enumerate() {
+        let location = format!("{prefix}gate {i}");
+        f(g, &location);
+        if let Some(block) = &g.block {
+            for (b, body) in block.bodies().into_iter().enumerate() {
+                visit(&body.gates, &format!("{location}, body {b}, "), f);
+            }
+        }
+    }
+}
+
+/// Angles above a full turn that are whole multiples of 15, e.g. `rz(90)`,
+/// which were most likely written in degrees.
+pub struct AngleInDegrees;
+
+impl LintRule for AngleInDegrees {
+    fn name(&self) -> &'static str {
+        "angle-in-degrees"
+    }
+
+    fn description(&self) -> &'static str {
+        "angle that looks like it was written in degrees"
+    }
+
+    fn check(&self, circuit: &QuantumCircuit) -> Vec<Lint> {
+        let mut lints = Vec::new();
+        visit(&circuit.gates, "", &mut |g, location| {
+            for p in g.params.iter().filter(|_| g.name != "delay") {
+                let Param::Value(v) = p else {
+                    continue;
+                };
+                let fifteens = v / 15.0;
+                if v.abs() <= TAU || (fifteens - fifteens.round()).abs() > 1e-9 {
+                    continue;
+                }
+                if let Some(radians) = Rational::new(v.round() as i64, 180).map(Param::Pi) {
+                    lints.push(Lint {
+                        rule: self.name(),
+                        location: location.to_string(),
+                        message: format!(
+                            "`{}` angle {p} looks like degrees; did you mean {radians}?",
+                            g.name
+                        ),
+                    });
+                }
+            }
+        });
+        lints
+    }
+}
+
+/// Nonzero rotations too small to have any effect on hardware.
+pub struct SmallAngle {
+    pub threshold: f64,
+}
+
+impl Default for SmallAngle {
+    fn default() -> Self {
+        Self { threshold: 1e-6 }
+    }
+}
+
+impl LintRule for SmallAngle {
+    fn name(&self) -> &'static str {
+        "small-angle"
+    }
+
+    fn description(&self) -> &'static str {
+        "rotation by a tiny nonzero angle"
+    }
+
+    fn check(&self, circuit: &QuantumCircuit) -> Vec<Lint> {
+        let mut lints = Vec::new();
+        visit(&circuit.gates, "", &mut |g, location| {
+            let tiny = g
+                .params
+                .iter()
+                .filter_map(Param::value)
+                .find(|v| *v != 0.0 && v.abs() < self.threshold);
+            if let (Some(v), false) = (tiny, g.name == "delay") {
+                lints.push(Lint {
+                    rule: self.name(),
+                    location: location.to_string(),
+                    message: format!(
+                        "`{}` angle {v:e} is below {:e}; remove the gate?",
+                        g.name, self.threshold
+                    ),
+                });
+            }
+        });
+        lints
+    }
+}
+
+/// Unconditional gates on a qubit that was already measured and not reset.
+/// Conditional gates are the usual feedforward and aren't reported.
+pub struct GateAfterMeasure;
+
+impl GateAfterMeasure {
+    fn check_body(&self, circuit: &QuantumCircuit, prefix: &str, lints: &mut Vec<Lint>) {
+        let mut measured = HashSet::new();
+        for (i, g) in circuit.gates.iter().enumerate() {
+            let location = format!("{prefix}gate {i}");
+            if let Some(block) = &g.block {
+                for (b, body) in block.bodies().into_iter().enumerate() {
+                    self.check_body(body, &format!("{location}, body {b}, "), lints);
+                }
+                continue;
+            }
+            match g.name.as_str() {
+                "measure" => measured.extend(g.qubits.iter().copied()),
+                "reset" => g.qubits.iter().for_each(|q| {
+                    measured.remove(q);
+                }),
+                "barrier" | "delay" => {}
+                _ if g.condition.is_none() => {
+                    if let Some(q) = g.qubits.iter().find(|q| measured.contains(*q)) {
+                        lints.push(Lint {
+                            rule: self.name(),
+                            location,
+                            message: format!(
+                                "`{}` acts on qubit {q} after it was measured",
+                                g.name
+                            ),
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+impl LintRule for GateAfterMeasure {
+    fn name(&self) -> &'static str {
+        "gate-after-measure"
+    }
+
+    fn description(&self) -> &'static str {
+        "operation on a qubit after its measurement"
+    }
+
+    fn check(&self, circuit: &QuantumCircuit) -> Vec<Lint> {
+        let mut lints = Vec::new();
+        self.check_body(circuit, "", &mut lints);
+        lints
+    }
+}
+
+/// Classical bits nothing is measured into and no condition reads.
+pub struct UnusedClbit;
+
+/// Condition of a gate or block, if any.
+fn condition(g: &Gate) -> Option<&ClassicalExpr> {
+    match g.block.as_deref() {
+        Some(ControlFlow::IfElse { condition, .. } | ControlFlow::While { condition, .. }) => {
+            Some(condition)
+        }
+        _ => g.condition.as_ref(),
+    }
+}
+
+impl LintRule for UnusedClbit {
+    fn name(&self) -> &'static str {
+        "unused-clbit"
+    }
+
+    fn description(&self) -> &'static str {
+        "classical bit that is never written or read"
+    }
+
+    fn check(&self, circuit: &QuantumCircuit) -> Vec<Lint> {
+        let mut used = Vec::new();
+        visit(&circuit.gates, "", &mut |g, _| {
+            used.extend(g.clbits.iter().map(|b| (b.register.clone(), Some(b.index))));
+            if let Some(cond) = condition(g) {
+                used.extend(cond.references());
+            }
+        });
+        circuit
+            .cregs
+            .iter()
+            .filter_map(|r| {
+                let unused = (0..r.size)
+                    .filter(|&i| {
+                        !used
+                            .iter()
+                            .any(|(name, bit)| *name == r.name && bit.is_none_or(|b| b == i))
+                    })
+                    .map(|i| format!("{}[{i}]", r.name))
+                    .collect::<Vec<_>>();
+                (!unused.is_empty()).then(|| Lint {
+                    rule: self.name(),
+                    location: format!("register {}", r.name),
+                    message: format!("{} never measured into or read", unused.join(", ")),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Qubits acted on but never measured, in circuits that measure some qubits.
+/// Their gates cannot affect the results.
+pub struct UnmeasuredQubit;
+
+impl LintRule for UnmeasuredQubit {
+    fn name(&self) -> &'static str {
+        "unmeasured-qubit"
+    }
+
+    fn description(&self) -> &'static str {
+        "gates on a qubit that is never measured"
+    }
+
+    fn check(&self, circuit: &QuantumCircuit) -> Vec<Lint> {
+        let mut touched = vec![false; circuit.num_qubits];
+        let mut measured = vec![false; circuit.num_qubits];
+        visit(&circuit.gates, "", &mut |g, _| {
+            let flags = match g.name.as_str() {
+                "measure" => &mut measured,
+                "barrier" | "delay" => return,
+                _ if g.block.is_some() => return,
+                _ => &mut touched,
+            };
+            for &q in &g.qubits {
+                if let Some(flag) = flags.get_mut(q) {
+                    *flag = true;
+                }
+            }
+        });
+        if !measured.contains(&true) {
+            return Vec::new();
+        }
+        (0..circuit.num_qubits)
+            .filter(|&q| touched[q] && !measured[q])
+            .map(|q| Lint {
+                rule: self.name(),
+                location: format!("qubit {q}"),
+                message: "has gates but is never measured".to_string(),
+            })
+            .collect()
+    }
+}
+
+/// Conditions that are never true, or `if`/`else` conditions that are always
+/// true so the `else` branch never runs.
+pub struct UnreachableBranch;
+
+/// Most condition bits enumerated to decide reachability.
+const MAX_CONDITION_BITS: usize = 16;
+
+impl UnreachableBranch {
+    /// Whether `expr` can be false and can be true, or `None` if it reads
+    /// unknown or too many bits.
+    fn outcomes(expr: &ClassicalExpr, cregs: &[ClassicalRegister]) -> Option<(bool, bool)> {
+        let mut registers: Vec<ClassicalRegister> = Vec::new();
+        for (name, _) in expr.references() {
+            if !registers.iter().any(|r| r.name == name) {
+                registers.push(cregs.iter().find(|r| r.name == name)?.clone());
+            }
+        }
+        let width: usize = registers.iter().map(|r| r.size).sum();
+        if width > MAX_CONDITION_BITS {
+            return None;
+        }
+        let (mut can_be_false, mut can_be_true) = (false, false);
+        for assignment in 0u32..1 << width {
+            let bits: Vec<bool> = (0..width).map(|i| assignment >> i & 1 == 1).collect();
+            match expr.evaluate(&registers, &bits)? {
+                0 => can_be_false = true,
+                _ => can_be_true = true,
+            }
+        }
+        Some((can_be_false, can_be_true))
+    }
+}
+
+impl LintRule for UnreachableBranch {
+    fn name(&self) -> &'static str {
+        "unreachable-branch"
+    }
+
+    fn description(&self) -> &'static str {
+        "conditional code that can never run"
+    }
+
+    fn check(&self, circuit: &QuantumCircuit) -> Vec<Lint> {
+        let mut lints = Vec::new();
+        visit(&circuit.gates, "", &mut |g, location| {
+            let Some(cond) = condition(g) else {
+                return;
+            };
+            let message = match Self::outcomes(cond, &circuit.cregs) {
+                Some((_, false)) => format!("condition `{cond}` is never true"),
+                Some((false, true))
+                    if matches!(
+                        g.block.as_deref(),
+                        Some(ControlFlow::IfElse {
+                            false_body: Some(_),
+                            ..
+                        })
+                    ) =>
+                {
+                    format!("condition `{cond}` is always true, so the else branch never runs")
+                }
+                _ => return,
+            };
+            lints.push(Lint {
+                rule: self.name(),
+                location: location.to_string(),
+                message,
+            });
+        });
+        lints
+    }
+}
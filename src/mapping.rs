@@ -0,0 +1,135 @@
+use crate::json::JsonValue;
+use crate::layout::{PhysicalQubit, VirtualQubit};
+use crate::{BackendSpec, QuantumCircuit, TranspilationResult};
+
+// ============================================================================
+// QUBIT MAPPING EXPORT (CLASSICAL BIT <-> PHYSICAL QUBIT)
+// ============================================================================
+
+/// Where one logical qubit of the input program ended up, and which
+/// classical bit holds its measurement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QubitMapping {
+    pub logical_qubit: VirtualQubit,
+    /// Classical bit the qubit is read into: that of its final measurement
+    /// in the input program if it measures anything, else bit `i` for qubit
+    /// `i`, so qubits beyond the classical register have none.
+    pub clbit: Option<usize>,
+    pub initial_physical: PhysicalQubit,
+    /// Physical qubit holding the logical qubit after routing's swaps, i.e.
+    /// the one actually measured at the end.
+    pub final_physical: PhysicalQubit,
+}
+
+impl QuantumCircuit {
+    /// Flat index of the classical bit each qubit is read into, as
+    /// `QubitMapping::clbit` describes. Qubits are matched to measurements by
+    /// the circuit's own indices, so on a routed circuit, where swaps move
+    /// qubits between measurements, call it on the input instead.
+    pub fn readout_clbits(&self) -> Vec<Option<usize>> {
+        if !self.gates.iter().any(|g| g.name == "measure") {
+            return (0..self.num_qubits)
+                .map(|q| (q < self.num_clbits).then_some(q))
+                .collect();
+        }
+        let mut clbits = vec![None; self.num_qubits];
+        for g in self.gates.iter().filter(|g| g.name == "measure") {
+            for (&q, bit) in g.qubits.iter().zip(&g.clbits) {
+                if let Some(slot) = clbits.get_mut(q) {
+                    *slot = self.clbit_index(bit);
+                }
+            }
+        }
+        clbits
+    }
+}
+
+impl TranspilationResult {
+    /// One entry per logical qubit of the input circuit, in qubit order.
+    pub fn qubit_mapping(&self) -> Vec<QubitMapping> {
+        self.initial_layout
+            .iter()
+            .map(|(v, p)| QubitMapping {
+                logical_qubit: v,
+                clbit: self.readout_clbits.get(v.0).copied().flatten(),
+                initial_physical: p,
+                final_physical: self.final_layout.physical(v),
+            })
+            .collect()
+    }
+
+    /// Machine-readable mapping file for interpreting hardware bitstrings.
+    pub fn mapping_json(&self, backend: &BackendSpec) -> JsonValue {
+        let entries = self
+            .qubit_mapping()
+            .into_iter()
+            .map(|m| {
+                JsonValue::object([
+                    ("logical_qubit", m.logical_qubit.0.into()),
+                    ("clbit", m.clbit.map_or(JsonValue::Null, Into::into)),
+                    ("initial_physical_qubit", m.initial_physical.0.into()),
+                    ("final_physical_qubit", m.final_physical.0.into()),
+                ])
+            })
+            .collect();
+        JsonValue::object([
+            ("backend", JsonValue::string(backend.name.as_str())),
+            ("num_physical_qubits", backend.num_qubits.into()),
+            ("num_clbits", self.circuit.num_clbits.into()),
+            (
+                "bit_order",
+                "bit 0 is the rightmost character of a bitstring".into(),
+            ),
+            ("qubits", JsonValue::Array(entries)),
+        ])
+    }
+}
+
+#[cfg(all(test, feature = "router"))]
+mod tests {
+    use super::*;
+    use crate::classical::ClassicalBit;
+    use crate::{Gate, UniversalTranspiler};
+
+    fn line() -> BackendSpec {
+        BackendSpec {
+            name: "line".to_string(),
+            num_qubits: 3,
+            coupling_map: vec![(0, 1), (1, 2)],
+            native_gates: ["cx", "swap", "measure"]
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn measure(qubit: usize) -> Gate {
+        Gate::measure(qubit, ClassicalBit::new("c", qubit))
+    }
+
+    #[test]
+    fn qubits_measured_before_a_swap_keep_their_bit() {
+        // Qubit 1 is measured, then swapped away to bring 0 and 2 together.
+        let circuit = QuantumCircuit::new(3, 3).with_gates(vec![
+            measure(1),
+            Gate::new("cx", vec![0, 2], vec![]),
+            measure(0),
+            measure(2),
+        ]);
+        let result = UniversalTranspiler::new()
+            .transpile_circuit(circuit, &line())
+            .unwrap();
+        assert_eq!(result.routing.swap_count(), 1);
+        let mapping = result.qubit_mapping();
+        let clbits: Vec<Option<usize>> = mapping.iter().map(|m| m.clbit).collect();
+        assert_eq!(clbits, [Some(0), Some(1), Some(2)]);
+        assert_ne!(mapping[1].initial_physical, mapping[1].final_physical);
+    }
+
+    #[test]
+    fn unmeasured_circuits_read_qubit_i_into_bit_i() {
+        let circuit = QuantumCircuit::new(3, 2);
+        assert_eq!(circuit.readout_clbits(), [Some(0), Some(1), None]);
+    }
+}
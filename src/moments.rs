@@ -0,0 +1,199 @@
+use crate::control_flow::ControlFlow;
+use crate::{Gate, QuantumCircuit};
+
+// ============================================================================
+// MOMENTS (MAXIMAL PARALLEL LAYERS)
+// ============================================================================
+
+/// Classical bits an instruction reads and writes, as a register (or
+/// variable) name with the bit, or `None` for the whole register.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClassicalAccess {
+    pub reads: Vec<(String, Option<usize>)>,
+    pub writes: Vec<(String, Option<usize>)>,
+}
+
+impl ClassicalAccess {
+    /// Whether the two can't run in the same layer: one writes a bit the
+    /// other reads or writes.
+    pub fn conflicts_with(&self, other: &ClassicalAccess) -> bool {
+        let overlap = |a: &[(String, Option<usize>)], b: &[(String, Option<usize>)]| {
+            a.iter().any(|(r, i)| {
+                b.iter()
+                    .any(|(s, j)| r == s && (i.is_none() || j.is_none() || i == j))
+            })
+        };
+        overlap(&self.writes, &other.reads)
+            || overlap(&self.writes, &other.writes)
+            || overlap(&other.writes, &self.reads)
+    }
+}
+
+impl Gate {
+    /// Bits read by the gate's condition and written by its measurement,
+    /// including everything inside a control-flow block.
+    pub fn classical_access(&self) -> ClassicalAccess {
+        let mut access = ClassicalAccess::default();
+        if let Some(condition) = &self.condition {
+            access.reads.extend(condition.references());
+        }
+        access.writes.extend(
+            self.clbits
+                .iter()
+                .map(|b| (b.register.clone(), Some(b.index))),
+        );
+        if let Some(block) = &self.block {
+            if let ControlFlow::IfElse { condition, .. } | ControlFlow::While { condition, .. } =
+                block.as_ref()
+            {
+                access.reads.extend(condition.references());
+            }
+            for body in block.bodies() {
+                for g in &body.gates {
+                    let inner = g.classical_access();
+                    access.reads.extend(inner.reads);
+                    access.writes.extend(inner.writes);
+                }
+            }
+        }
+        access
+    }
+}
+
+/// Gates that act on pairwise disjoint qubits, none writing a classical bit
+/// another one uses, so they can run simultaneously.
+#[derive(Debug, Clone, Default)]
+pub struct Moment {
+    pub gates: Vec<Gate>,
+}
+
+impl Moment {
+    pub fn acts_on(&self, qubit: usize) -> bool {
+        self.gates.iter().any(|g| g.qubits.contains(&qubit))
+    }
+
+    /// Sorted qubits touched by this moment.
+    pub fn qubits(&self) -> Vec<usize> {
+        let mut qubits: Vec<usize> = self
+            .gates
+            .iter()
+            .flat_map(|g| g.qubits.iter().copied())
+            .collect();
+        qubits.sort_unstable();
+        qubits
+    }
+
+    /// Adds `gate` if it doesn't overlap the moment, on qubits or on classical
+    /// bits one of them writes; otherwise hands it back.
+    #[allow(clippy::result_large_err)]
+    pub fn try_add(&mut self, gate: Gate) -> Result<(), Gate> {
+        let access = gate.classical_access();
+        if gate.qubits.iter().any(|&q| self.acts_on(q))
+            || self
+                .gates
+                .iter()
+                .any(|g| g.classical_access().conflicts_with(&access))
+        {
+            return Err(gate);
+        }
+        self.gates.push(gate);
+        Ok(())
+    }
+}
+
+impl QuantumCircuit {
+    /// Flat indices (as in `clbit_index`) of the register bits in `refs`;
+    /// names that aren't registers are skipped.
+    pub fn clbit_indices(&self, refs: &[(String, Option<usize>)]) -> Vec<usize> {
+        let mut indices = Vec::new();
+        let mut offset = 0;
+        for r in &self.cregs {
+            for (_, bit) in refs.iter().filter(|(name, _)| *name == r.name) {
+                match bit {
+                    Some(i) if *i < r.size => indices.push(offset + i),
+                    Some(_) => {}
+                    None => indices.extend(offset..offset + r.size),
+                }
+            }
+            offset += r.size;
+        }
+        indices
+    }
+
+    /// Moment index of every gate under as-soon-as-possible layering. Besides
+    /// waiting for its qubits, a gate reading a classical bit comes after the
+    /// measurement writing it, and a measurement after earlier readers and
+    /// writers of its bits, so feed-forward keeps its order.
+    pub fn moment_indices(&self) -> Vec<usize> {
+        let num_bits = self.cregs.iter().map(|r| r.size).sum();
+        let mut qubit_time = vec![0usize; self.num_qubits];
+        // Earliest layer that may read, and that may write, each bit.
+        let mut read_time = vec![0usize; num_bits];
+        let mut write_time = vec![0usize; num_bits];
+        self.gates
+            .iter()
+            .map(|g| {
+                let access = g.classical_access();
+                let (reads, writes) = (
+                    self.clbit_indices(&access.reads),
+                    self.clbit_indices(&access.writes),
+                );
+                let layer = g
+                    .qubits
+                    .iter()
+                    .map(|&q| qubit_time.get(q).copied().unwrap_or(0))
+                    .chain(reads.iter().map(|&b| read_time[b]))
+                    .chain(writes.iter().map(|&b| write_time[b]))
+                    .max()
+                    .unwrap_or(0);
+                for &q in &g.qubits {
+                    if q < qubit_time.len() {
+                        qubit_time[q] = layer + 1;
+                    }
+                }
+                for &b in &reads {
+                    write_time[b] = write_time[b].max(layer + 1);
+                }
+                for &b in &writes {
+                    read_time[b] = layer + 1;
+                    write_time[b] = layer + 1;
+                }
+                layer
+            })
+            .collect()
+    }
+
+    /// The circuit as a sequence of moments, each gate placed as early as its
+    /// qubits allow. Gate order within a moment follows the gate list.
+    pub fn moments(&self) -> Vec<Moment> {
+        let indices = self.moment_indices();
+        let mut moments = vec![Moment::default(); indices.iter().max().map_or(0, |m| m + 1)];
+        for (g, &layer) in self.gates.iter().zip(&indices) {
+            moments[layer].gates.push(g.clone());
+        }
+        moments
+    }
+
+    /// Copy of this circuit with its gates replaced by `moments`, flattened in order.
+    pub fn with_moments(&self, moments: &[Moment]) -> QuantumCircuit {
+        self.with_gates(
+            moments
+                .iter()
+                .flat_map(|m| m.gates.iter().cloned())
+                .collect(),
+        )
+    }
+
+    /// Rebuilds the circuit layer by layer; `f` receives each moment with its
+    /// index and returns the moments to put in its place (none to delete it,
+    /// several to insert layers around it).
+    pub fn map_moments(&self, mut f: impl FnMut(usize, &Moment) -> Vec<Moment>) -> QuantumCircuit {
+        let mapped: Vec<Moment> = self
+            .moments()
+            .iter()
+            .enumerate()
+            .flat_map(|(i, m)| f(i, m))
+            .collect();
+        self.with_moments(&mapped)
+    }
+}
@@ -0,0 +1,190 @@
+use crate::layout::PhysicalCircuit;
+use crate::{BackendSpec, Gate, QuantumCircuit, UniversalTranspiler};
+
+// ============================================================================
+// OPTIMIZATION OBJECTIVE
+// ============================================================================
+
+/// Default error rates used when a backend carries no calibration data.
+pub const DEFAULT_SINGLE_QUBIT_ERROR: f64 = 1e-4;
+pub const DEFAULT_TWO_QUBIT_ERROR: f64 = 1e-2;
+
+/// The quantities an objective trades off against each other.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CircuitMetrics {
+    pub depth: usize,
+    pub gate_count: usize,
+    /// Two-qubit interactions, counting a `swap` as three.
+    pub two_qubit_count: usize,
+    /// Probability that at least one gate fails, assuming independent errors.
+    pub estimated_error: f64,
+}
+
+impl CircuitMetrics {
+    /// Metrics of `circuit` with the default error rates.
+    pub fn of(circuit: &QuantumCircuit) -> Self {
+        Self::with_errors(
+            circuit,
+            |_| DEFAULT_SINGLE_QUBIT_ERROR,
+            |_| DEFAULT_TWO_QUBIT_ERROR,
+        )
+    }
+
+    /// Metrics of `circuit` with `backend`'s calibrated error rates.
+    pub fn on(circuit: &PhysicalCircuit, backend: &BackendSpec) -> Self {
+        Self::with_errors(
+            circuit,
+            |g| backend.single_qubit_error(circuit.gate_qubits(g)[0]),
+            |g| {
+                let qubits = circuit.gate_qubits(g);
+                backend.two_qubit_error(qubits[0], qubits[1])
+            },
+        )
+    }
+
+    fn with_errors(
+        circuit: &QuantumCircuit,
+        single_qubit_error: impl Fn(&Gate) -> f64,
+        two_qubit_error: impl Fn(&Gate) -> f64,
+    ) -> Self {
+        let mut two_qubit_count = 0;
+        let mut success = 1.0;
+        for g in &circuit.gates {
+            // Idling is not a gate: delays and barriers carry no gate error.
+            let idle = matches!(g.name.as_str(), "delay" | "barrier");
+            let (interactions, error) = match g.qubits.len() {
+                n if n == 0 || idle => (0, 0.0),
+                1 => (0, single_qubit_error(g)),
+                _ => {
+                    let n = if g.name == "swap" { 3 } else { 1 };
+                    let e = two_qubit_error(g);
+                    (n, 1.0 - (1.0 - e).powi(n as i32))
+                }
+            };
+            two_qubit_count += interactions;
+            success *= 1.0 - error;
+        }
+        Self {
+            depth: UniversalTranspiler::calculate_depth(circuit),
+            gate_count: circuit.gates.len(),
+            two_qubit_count,
+            estimated_error: 1.0 - success,
+        }
+    }
+}
+
+/// Weights for what "better" means when passes have a choice: superconducting
+/// devices mostly care about depth, ion traps about two-qubit gate count.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OptimizationObjective {
+    pub depth_weight: f64,
+    pub two_qubit_weight: f64,
+    pub error_weight: f64,
+}
+
+impl Default for OptimizationObjective {
+    fn default() -> Self {
+        Self {
+            depth_weight: 1.0,
+            two_qubit_weight: 1.0,
+            error_weight: 0.0,
+        }
+    }
+}
+
+impl OptimizationObjective {
+    pub fn min_depth() -> Self {
+        Self {
+            depth_weight: 1.0,
+            two_qubit_weight: 0.1,
+            error_weight: 0.0,
+        }
+    }
+
+    pub fn min_two_qubit_count() -> Self {
+        Self {
+            depth_weight: 0.1,
+            two_qubit_weight: 1.0,
+            error_weight: 0.0,
+        }
+    }
+
+    pub fn min_error() -> Self {
+        Self {
+            depth_weight: 0.0,
+            two_qubit_weight: 0.0,
+            error_weight: 1.0,
+        }
+    }
+
+    /// Weighted cost of `candidate` with each term normalized by `reference`,
+    /// so the weights are unitless. `reference` itself scores the weight sum.
+    pub fn relative_cost(&self, candidate: &CircuitMetrics, reference: &CircuitMetrics) -> f64 {
+        let ratio = |c: f64, r: f64| if r > 0.0 { c / r } else { 1.0 + c };
+        self.depth_weight * ratio(candidate.depth as f64, reference.depth as f64)
+            + self.two_qubit_weight
+                * ratio(
+                    candidate.two_qubit_count as f64,
+                    reference.two_qubit_count as f64,
+                )
+            + self.error_weight * ratio(candidate.estimated_error, reference.estimated_error)
+    }
+
+    /// True if `candidate` scores strictly better than `reference`.
+    pub fn prefers(&self, candidate: &CircuitMetrics, reference: &CircuitMetrics) -> bool {
+        self.relative_cost(candidate, reference) < self.relative_cost(reference, reference) - 1e-12
+    }
+
+    /// True unless `after` scores worse than `before`.
+    pub fn accepts(&self, before: &CircuitMetrics, after: &CircuitMetrics) -> bool {
+        !self.prefers(before, after)
+    }
+}
+
+/// When to stop running optimization passes before the pipeline is done.
+/// Fidelity is `1 - estimated_error` of the routed circuit on the backend.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct StoppingCriterion {
+    /// Skip the remaining passes once the circuit is at least this good.
+    pub target_fidelity: Option<f64>,
+    /// Skip the remaining passes once a pass improves fidelity by less than
+    /// this much per second it ran, since later (heavier) passes are unlikely
+    /// to do better.
+    pub min_fidelity_gain_per_second: Option<f64>,
+}
+
+impl StoppingCriterion {
+    pub fn target_fidelity(fidelity: f64) -> Self {
+        Self {
+            target_fidelity: Some(fidelity),
+            ..Self::default()
+        }
+    }
+
+    pub fn min_gain_per_second(rate: f64) -> Self {
+        Self {
+            min_fidelity_gain_per_second: Some(rate),
+            ..Self::default()
+        }
+    }
+
+    /// True if the circuit is already good enough to stop.
+    pub fn reached(&self, metrics: &CircuitMetrics) -> bool {
+        self.target_fidelity
+            .is_some_and(|t| 1.0 - metrics.estimated_error >= t)
+    }
+
+    /// True if a pass that moved the circuit from `before` to `after` in
+    /// `seconds` was too slow a gain to keep going.
+    pub fn diminishing(
+        &self,
+        before: &CircuitMetrics,
+        after: &CircuitMetrics,
+        seconds: f64,
+    ) -> bool {
+        self.min_fidelity_gain_per_second.is_some_and(|min| {
+            let gain = before.estimated_error - after.estimated_error;
+            gain / seconds.max(1e-9) < min
+        })
+    }
+}
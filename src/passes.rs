@@ -0,0 +1,223 @@
+use crate::canonical::CanonicalOrderPass;
+use crate::initial_state::InitialStateOptimizationPass;
+use crate::objective::OptimizationObjective;
+use crate::pauli_frame::PauliFramePass;
+use crate::qft::QftResynthesisPass;
+use crate::scheduling::{ConstrainTimingPass, CrosstalkAwareSchedulingPass, SchedulingPass};
+use crate::unobservable::UnobservableGateRemovalPass;
+use crate::{BackendSpec, GateCancellationPass, OptimizationPass, RotationMergingPass};
+
+// ============================================================================
+// OPTIMIZATION PASS REGISTRY
+// ============================================================================
+
+/// Builds a pass for the backend the circuit is transpiled to, weighing its
+/// choices by the transpiler's objective.
+pub type PassBuilder = fn(&BackendSpec, &OptimizationObjective) -> Box<dyn OptimizationPass>;
+
+/// Builds a scheduling pass for the backend the circuit is transpiled to.
+pub type SchedulingPassBuilder = fn(&BackendSpec) -> Box<dyn SchedulingPass>;
+
+/// How a registered pass is built, which also decides the stage it runs in.
+#[derive(Clone, Copy)]
+pub enum PassBuild {
+    Optimization(PassBuilder),
+    Scheduling(SchedulingPassBuilder),
+}
+
+/// A pass selectable by name.
+#[derive(Clone)]
+pub struct PassEntry {
+    pub name: String,
+    pub description: String,
+    /// Whether the default pipeline runs it.
+    pub default: bool,
+    pub build: PassBuild,
+}
+
+/// The passes of a pipeline, by the stage they run in.
+#[derive(Default)]
+pub struct Pipeline {
+    pub passes: Vec<Box<dyn OptimizationPass>>,
+    pub scheduling: Vec<Box<dyn SchedulingPass>>,
+}
+
+/// Passes by name, in the order a pipeline runs them unless told otherwise;
+/// scheduling passes always run after the optimization ones.
+#[derive(Clone)]
+pub struct PassRegistry {
+    passes: Vec<PassEntry>,
+}
+
+impl Default for PassRegistry {
+    /// The passes this crate provides; the default ones are the pipeline of
+    /// `UniversalTranspiler::new`.
+    fn default() -> Self {
+        Self::empty()
+            .with_pass(
+                "initial-state",
+                "drop gates that act trivially on qubits still in |0>",
+                true,
+                |_, _| Box::new(InitialStateOptimizationPass),
+            )
+            .with_pass(
+                "gate-cancellation",
+                "cancel adjacent identical self-inverse gates",
+                true,
+                |_, _| Box::new(GateCancellationPass),
+            )
+            .with_pass(
+                "rotation-merging",
+                "merge consecutive rz rotations",
+                true,
+                |_, _| Box::new(RotationMergingPass),
+            )
+            .with_pass(
+                "unobservable-removal",
+                "remove gates no measurement can see",
+                false,
+                |_, _| Box::new(UnobservableGateRemovalPass),
+            )
+            .with_pass(
+                "pauli-frame",
+                "push Pauli gates to the end of the circuit and merge them",
+                false,
+                |_, _| Box::new(PauliFramePass),
+            )
+            .with_pass(
+                "qft-resynthesis",
+                "resynthesize recognized QFTs, with a swap network on qubit lines",
+                false,
+                |backend, objective| {
+                    Box::new(QftResynthesisPass {
+                        approximation_threshold: 0.0,
+                        backend: Some(backend.clone()),
+                        objective: *objective,
+                    })
+                },
+            )
+            .with_pass(
+                "canonical-order",
+                "order commuting gates deterministically",
+                false,
+                |_, _| Box::new(CanonicalOrderPass),
+            )
+            .with_scheduling_pass(
+                "crosstalk-scheduling",
+                "delay gates on crosstalk-paired edges so they don't overlap",
+                false,
+                |backend| {
+                    Box::new(CrosstalkAwareSchedulingPass {
+                        backend: backend.clone(),
+                    })
+                },
+            )
+            .with_scheduling_pass(
+                "constrain-timing",
+                "pad and stretch delays to the backend's timing constraints",
+                false,
+                |backend| {
+                    Box::new(ConstrainTimingPass {
+                        backend: backend.clone(),
+                    })
+                },
+            )
+    }
+}
+
+impl PassRegistry {
+    pub fn empty() -> Self {
+        Self { passes: Vec::new() }
+    }
+
+    /// Adds a pass at the end, or replaces the pass of that name in place.
+    pub fn with_pass(
+        self,
+        name: &str,
+        description: &str,
+        default: bool,
+        build: PassBuilder,
+    ) -> Self {
+        self.with_entry(name, description, default, PassBuild::Optimization(build))
+    }
+
+    /// Same as `with_pass`, for a pass of the scheduling stage.
+    pub fn with_scheduling_pass(
+        self,
+        name: &str,
+        description: &str,
+        default: bool,
+        build: SchedulingPassBuilder,
+    ) -> Self {
+        self.with_entry(name, description, default, PassBuild::Scheduling(build))
+    }
+
+    fn with_entry(
+        mut self,
+        name: &str,
+        description: &str,
+        default: bool,
+        build: PassBuild,
+    ) -> Self {
+        let entry = PassEntry {
+            name: name.to_string(),
+            description: description.to_string(),
+            default,
+            build,
+        };
+        match self.passes.iter_mut().find(|p| p.name == name) {
+            Some(existing) => *existing = entry,
+            None => self.passes.push(entry),
+        }
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Result<&PassEntry, String> {
+        self.passes.iter().find(|p| p.name == name).ok_or_else(|| {
+            format!(
+                "Unknown pass '{name}'; available: {}",
+                self.names().join(", ")
+            )
+        })
+    }
+
+    /// Registered names, in pipeline order.
+    pub fn names(&self) -> Vec<&str> {
+        self.passes.iter().map(|p| p.name.as_str()).collect()
+    }
+
+    pub fn entries(&self) -> &[PassEntry] {
+        &self.passes
+    }
+
+    /// Builds a pipeline for `backend` and `objective`: the passes named in
+    /// `only`, in that order within each stage, or else the default ones,
+    /// minus those in `skip`. Unknown names are an error, so a misspelt pass
+    /// is never silently run or kept.
+    pub fn pipeline(
+        &self,
+        backend: &BackendSpec,
+        objective: &OptimizationObjective,
+        only: Option<&[String]>,
+        skip: &[String],
+    ) -> Result<Pipeline, String> {
+        for name in skip {
+            self.get(name)?;
+        }
+        let selected: Vec<&PassEntry> = match only {
+            Some(names) => names
+                .iter()
+                .map(|name| self.get(name))
+                .collect::<Result<_, _>>()?,
+            None => self.passes.iter().filter(|p| p.default).collect(),
+        };
+        let mut pipeline = Pipeline::default();
+        for p in selected.into_iter().filter(|p| !skip.contains(&p.name)) {
+            match p.build {
+                PassBuild::Optimization(build) => pipeline.passes.push(build(backend, objective)),
+                PassBuild::Scheduling(build) => pipeline.scheduling.push(build(backend)),
+            }
+        }
+        Ok(pipeline)
+    }
+}
@@ -0,0 +1,304 @@
+use crate::commutation::commute;
+use crate::{gate_name_counts, Counts, Gate, OptimizationPass, QuantumCircuit};
+
+// ============================================================================
+// PAULI FRAME TRACKING
+// ============================================================================
+
+/// Pauli operator pending on each qubit, ignoring global phase: `x[q]` and
+/// `z[q]` both set means Y. `flips[b]` records that the outcome written to
+/// classical bit `b` (flat index, as in `QuantumCircuit::clbit_index`) must be
+/// inverted, because an X reached the measurement that wrote it.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct PauliFrame {
+    pub x: Vec<bool>,
+    pub z: Vec<bool>,
+    pub flips: Vec<bool>,
+}
+
+impl PauliFrame {
+    pub fn new(num_qubits: usize, num_clbits: usize) -> Self {
+        Self {
+            x: vec![false; num_qubits],
+            z: vec![false; num_qubits],
+            flips: vec![false; num_clbits],
+        }
+    }
+
+    pub fn is_identity(&self) -> bool {
+        !self.x.iter().chain(&self.z).chain(&self.flips).any(|&b| b)
+    }
+
+    /// Classical bits whose recorded outcome the frame flips.
+    pub fn flipped_clbits(&self) -> Vec<usize> {
+        (0..self.flips.len()).filter(|&b| self.flips[b]).collect()
+    }
+
+    /// Applies the frame to a bitstring over all classical bits (bit 0
+    /// rightmost), as the simulators and backends report them.
+    pub fn correct_bitstring(&self, bitstring: &str) -> String {
+        let n = bitstring.len();
+        bitstring
+            .chars()
+            .enumerate()
+            .map(|(i, c)| match (self.flips.get(n - 1 - i), c) {
+                (Some(true), '0') => '1',
+                (Some(true), '1') => '0',
+                _ => c,
+            })
+            .collect()
+    }
+
+    /// Counts as they would have been had the frame been applied on hardware.
+    pub fn correct_counts(&self, counts: &Counts) -> Counts {
+        let mut out = Counts::new();
+        for (bits, &n) in counts {
+            *out.entry(self.correct_bitstring(bits)).or_insert(0) += n;
+        }
+        out
+    }
+
+    /// The Pauli gate pending on `q`, if any.
+    fn gate_on(&self, q: usize) -> Option<Gate> {
+        let name = match (self.x[q], self.z[q]) {
+            (false, false) => return None,
+            (true, false) => "x",
+            (false, true) => "z",
+            (true, true) => "y",
+        };
+        Some(Gate::new(name, vec![q], vec![]))
+    }
+
+    fn clear(&mut self, q: usize) {
+        self.x[q] = false;
+        self.z[q] = false;
+    }
+
+    /// Absorbs a Pauli gate into the frame. Returns false for other gates.
+    fn absorb(&mut self, g: &Gate) -> bool {
+        if g.qubits.len() != 1 || g.condition.is_some() {
+            return false;
+        }
+        let q = g.qubits[0];
+        match g.name.as_str() {
+            "x" => self.x[q] ^= true,
+            "z" => self.z[q] ^= true,
+            "y" => {
+                self.x[q] ^= true;
+                self.z[q] ^= true;
+            }
+            "id" | "i" => {}
+            _ => return false,
+        }
+        true
+    }
+
+    /// Moves the frame past a Clifford gate `g`: applying P then `g` equals
+    /// applying `g` then P' = g P g†. Returns false for any other gate.
+    fn conjugate(&mut self, g: &Gate) -> bool {
+        if g.condition.is_some() || g.block.is_some() || g.composite.is_some() {
+            return false;
+        }
+        match (g.name.as_str(), g.qubits.as_slice()) {
+            ("h", &[q]) => {
+                std::mem::swap(&mut self.x[q], &mut self.z[q]);
+            }
+            ("s" | "sdg", &[q]) => self.z[q] ^= self.x[q],
+            ("cx" | "cnot", &[c, t]) => {
+                self.x[t] ^= self.x[c];
+                self.z[c] ^= self.z[t];
+            }
+            ("cz", &[a, b]) => {
+                self.z[b] ^= self.x[a];
+                self.z[a] ^= self.x[b];
+            }
+            ("swap", &[a, b]) => {
+                self.x.swap(a, b);
+                self.z.swap(a, b);
+            }
+            _ => return false,
+        }
+        true
+    }
+}
+
+/// Pushes Pauli gates (twirling layers, teleportation byproducts, measurement
+/// corrections) through Clifford gates towards the end of the circuit.
+///
+/// As an `OptimizationPass` the Paulis are re-emitted in front of the first
+/// gate they don't commute with (measurements included) or at the end,
+/// merged into at most one per qubit. `extract` instead turns the X part of
+/// a Pauli that reaches a measurement into a flip of the measured bit, drops
+/// the Z part, and returns the flips as a `PauliFrame` to be applied to the
+/// measured bitstrings in post-processing.
+pub struct PauliFramePass;
+
+impl PauliFramePass {
+    /// Circuit with the Paulis removed that post-processing can stand in
+    /// for, plus the frame to apply classically to its outcomes. Paulis left
+    /// in the frame's `x` and `z` act after the last gate on their qubits.
+    ///
+    /// Only bits the circuit itself never reads, and writes by unconditional
+    /// measurements alone, are flipped classically; before any other
+    /// measurement the pending Pauli is put back into the circuit.
+    pub fn extract(&self, circuit: &QuantumCircuit) -> (QuantumCircuit, PauliFrame) {
+        self.propagate(circuit, true)
+    }
+
+    fn propagate(
+        &self,
+        circuit: &QuantumCircuit,
+        fold_measurements: bool,
+    ) -> (QuantumCircuit, PauliFrame) {
+        let num_clbits = circuit.cregs.iter().map(|r| r.size).sum();
+        let mut frame = PauliFrame::new(circuit.num_qubits, num_clbits);
+        let mut trackable = vec![fold_measurements; num_clbits];
+        for g in &circuit.gates {
+            let access = g.classical_access();
+            let mut untracked = circuit.clbit_indices(&access.reads);
+            if g.condition.is_some() || g.block.is_some() {
+                untracked.extend(circuit.clbit_indices(&access.writes));
+            }
+            untracked.into_iter().for_each(|b| trackable[b] = false);
+        }
+
+        let mut out = Vec::with_capacity(circuit.gates.len());
+        for g in &circuit.gates {
+            if frame.absorb(g) {
+                continue;
+            }
+            if g.condition.is_none() && g.block.is_none() {
+                match g.name.as_str() {
+                    "measure" => {
+                        let bits: Option<Vec<usize>> =
+                            g.clbits.iter().map(|b| circuit.clbit_index(b)).collect();
+                        if let Some(bits) = bits.filter(|b| {
+                            b.len() == g.qubits.len() && b.iter().all(|&b| trackable[b])
+                        }) {
+                            // X P_m = P_(m^1) X: the outcome flips and X stays
+                            // pending; Z on a basis state is a global phase.
+                            for (&q, b) in g.qubits.iter().zip(bits) {
+                                frame.flips[b] = frame.x[q];
+                                frame.z[q] = false;
+                            }
+                            out.push(g.clone());
+                            continue;
+                        }
+                    }
+                    "reset" => {
+                        g.qubits.iter().for_each(|&q| frame.clear(q));
+                        out.push(g.clone());
+                        continue;
+                    }
+                    _ => {}
+                }
+            }
+            if !frame.conjugate(g) {
+                // Not a Clifford the frame can move through: keep only the
+                // parts that commute with `g` and materialize the rest.
+                for &q in &g.qubits {
+                    if let Some(p) = frame.gate_on(q) {
+                        if matches!(g.name.as_str(), "measure" | "reset") || !commute(&p, g) {
+                            out.push(p);
+                            frame.clear(q);
+                        }
+                    }
+                }
+            }
+            out.push(g.clone());
+        }
+        (circuit.with_gates(out), frame)
+    }
+}
+
+impl OptimizationPass for PauliFramePass {
+    fn name(&self) -> &str {
+        "pauli-frame"
+    }
+
+    fn optimize(&self, circuit: &QuantumCircuit) -> QuantumCircuit {
+        let (extracted, frame) = self.propagate(circuit, false);
+        let mut gates = extracted.gates;
+        gates.extend((0..circuit.num_qubits).filter_map(|q| frame.gate_on(q)));
+        circuit.with_gates(gates)
+    }
+
+    fn skip_reason(&self, circuit: &QuantumCircuit) -> Option<String> {
+        let counts = gate_name_counts(circuit);
+        let paulis = ["x", "y", "z", "id", "i"]
+            .iter()
+            .any(|name| counts.contains_key(name));
+        (!paulis).then(|| "no Pauli gates".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::classical::{ClassicalBit, ClassicalExpr};
+
+    fn gate(name: &str, qubits: Vec<usize>) -> Gate {
+        Gate::new(name, qubits, vec![])
+    }
+
+    fn names(circuit: &QuantumCircuit) -> Vec<&str> {
+        circuit.gates.iter().map(|g| g.name.as_str()).collect()
+    }
+
+    #[test]
+    fn x_through_a_cnot_flips_both_measured_bits() {
+        let circuit = QuantumCircuit::new(2, 2).with_gates(vec![
+            gate("x", vec![0]),
+            gate("cx", vec![0, 1]),
+            Gate::measure(0, ClassicalBit::new("c", 0)),
+            Gate::measure(1, ClassicalBit::new("c", 1)),
+        ]);
+        let (extracted, frame) = PauliFramePass.extract(&circuit);
+        assert_eq!(names(&extracted), ["cx", "measure", "measure"]);
+        assert_eq!(frame.flipped_clbits(), [0, 1]);
+        assert_eq!(frame.correct_bitstring("00"), "11");
+        let counts = Counts::from([("00".to_string(), 3), ("11".to_string(), 1)]);
+        let corrected = frame.correct_counts(&counts);
+        assert_eq!(corrected["11"], 3);
+        assert_eq!(corrected["00"], 1);
+    }
+
+    #[test]
+    fn z_reaching_a_measurement_is_dropped() {
+        // H turns the X into a Z, which only changes the phase.
+        let circuit = QuantumCircuit::new(1, 1).with_gates(vec![
+            gate("x", vec![0]),
+            gate("h", vec![0]),
+            Gate::measure(0, ClassicalBit::new("c", 0)),
+        ]);
+        let (extracted, frame) = PauliFramePass.extract(&circuit);
+        assert_eq!(names(&extracted), ["h", "measure"]);
+        assert!(frame.is_identity());
+    }
+
+    #[test]
+    fn bits_the_circuit_reads_are_not_flipped_classically() {
+        let circuit = QuantumCircuit::new(2, 1).with_gates(vec![
+            gate("x", vec![0]),
+            Gate::measure(0, ClassicalBit::new("c", 0)),
+            gate("x", vec![1]).with_condition(ClassicalExpr::parse("c == 1").unwrap()),
+        ]);
+        let (extracted, frame) = PauliFramePass.extract(&circuit);
+        assert_eq!(names(&extracted), ["x", "measure", "x"]);
+        assert!(frame.flipped_clbits().is_empty());
+    }
+
+    #[test]
+    fn the_pass_moves_paulis_past_the_gates_they_commute_with() {
+        let circuit = QuantumCircuit::new(1, 0).with_gates(vec![
+            gate("x", vec![0]),
+            gate("h", vec![0]),
+            gate("t", vec![0]),
+            gate("x", vec![0]),
+        ]);
+        // X becomes Z past H, passes T, and merges with the last X into Y.
+        let optimized = PauliFramePass.optimize(&circuit);
+        assert_eq!(names(&optimized), ["h", "t", "y"]);
+        assert_eq!(optimized.gates[2].qubits, [0]);
+    }
+}
@@ -0,0 +1,152 @@
+use alloc::collections::BTreeSet;
+
+use crate::prelude::*;
+use crate::Gate;
+
+// ============================================================================
+// OPENPULSE CALIBRATION PASSTHROUGH
+// ============================================================================
+
+/// A pulse-level section of an OpenQASM 3 program, kept verbatim.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CalibrationBlock {
+    /// `defcalgrammar "openpulse";`
+    Grammar(String),
+    /// `cal { ... }`, declarations shared by the defcals.
+    Cal(String),
+    /// `defcal name(params) qubits { ... }`, the pulses implementing `gate`
+    /// on `qubits` (as written, e.g. `$0` or an identifier for any qubit).
+    Defcal {
+        gate: String,
+        qubits: Vec<String>,
+        text: String,
+    },
+}
+
+impl CalibrationBlock {
+    pub fn text(&self) -> &str {
+        match self {
+            CalibrationBlock::Grammar(text)
+            | CalibrationBlock::Cal(text)
+            | CalibrationBlock::Defcal { text, .. } => text,
+        }
+    }
+}
+
+/// Calibration sections of a program in source order. The transpiler never
+/// looks inside them; they are emitted again as they were read.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct PulseCalibrations {
+    pub blocks: Vec<CalibrationBlock>,
+}
+
+impl PulseCalibrations {
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+
+    /// Names of the gates some defcal implements.
+    pub fn gate_names(&self) -> BTreeSet<String> {
+        self.blocks
+            .iter()
+            .filter_map(|b| match b {
+                CalibrationBlock::Defcal { gate, .. } => Some(gate.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The defcal implementing `g`: one for its name whose physical qubits
+    /// (`$n`) are the gate's, or whose operands are identifiers matching any
+    /// qubit.
+    pub fn defcal_for(&self, g: &Gate) -> Option<&CalibrationBlock> {
+        self.blocks.iter().find(|b| match b {
+            CalibrationBlock::Defcal { gate, qubits, .. } => {
+                *gate == g.name
+                    && qubits.len() == g.qubits.len()
+                    && qubits.iter().zip(&g.qubits).all(|(operand, &q)| {
+                        match operand.strip_prefix('$') {
+                            Some(n) => n.parse() == Ok(q),
+                            None => true,
+                        }
+                    })
+            }
+            _ => false,
+        })
+    }
+}
+
+/// Removes the calibration sections from `source`, returning the rest of the
+/// program (with blank lines in their place, so line numbers are kept) and
+/// the sections themselves.
+pub fn extract_calibrations(source: &str) -> Result<(String, PulseCalibrations), String> {
+    let mut rest = Vec::new();
+    let mut calibrations = PulseCalibrations::default();
+    let mut lines = source.lines();
+    while let Some(line) = lines.next() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("defcalgrammar") {
+            calibrations
+                .blocks
+                .push(CalibrationBlock::Grammar(line.to_string()));
+            rest.push("");
+            continue;
+        }
+        let is_cal = trimmed.starts_with("cal ") || trimmed.starts_with("cal{");
+        if !is_cal && !trimmed.starts_with("defcal ") {
+            rest.push(line);
+            continue;
+        }
+
+        // Take lines until the braces balance.
+        let mut text = vec![line];
+        let mut depth = brace_balance(line);
+        let mut opened = line.contains('{');
+        while !opened || depth > 0 {
+            let next = lines
+                .next()
+                .ok_or_else(|| format!("Unterminated calibration block: {}", trimmed))?;
+            depth += brace_balance(next);
+            opened |= next.contains('{');
+            text.push(next);
+        }
+        rest.extend(core::iter::repeat_n("", text.len()));
+        let text = text.join("\n");
+        calibrations.blocks.push(if is_cal {
+            CalibrationBlock::Cal(text)
+        } else {
+            let (gate, qubits) = parse_defcal_header(trimmed)?;
+            CalibrationBlock::Defcal { gate, qubits, text }
+        });
+    }
+    Ok((rest.join("\n"), calibrations))
+}
+
+/// Opening minus closing braces on a line, ignoring `//` comments.
+fn brace_balance(line: &str) -> i64 {
+    let code = line.split("//").next().unwrap_or("");
+    code.matches('{').count() as i64 - code.matches('}').count() as i64
+}
+
+/// Gate name and qubit operands of `defcal name(params) q0, q1 {`.
+fn parse_defcal_header(header: &str) -> Result<(String, Vec<String>), String> {
+    let rest = header["defcal".len()..].trim_start();
+    let name_end = rest
+        .find(|c: char| c == '(' || c.is_whitespace() || c == '{')
+        .ok_or_else(|| format!("Malformed defcal: {header}"))?;
+    let mut operands = rest[name_end..].trim_start();
+    if operands.starts_with('(') {
+        let close = operands
+            .find(')')
+            .ok_or_else(|| format!("Unterminated defcal parameters: {header}"))?;
+        operands = &operands[close + 1..];
+    }
+    // A return type (`-> bit`) may follow the operands.
+    let operands = operands.split(['{', '-']).next().unwrap_or("");
+    let qubits = operands
+        .split([',', ' ', '\t'])
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+        .collect();
+    Ok((rest[..name_end].to_string(), qubits))
+}
@@ -0,0 +1,306 @@
+use std::collections::HashMap;
+
+use crate::classical::ClassicalBit;
+use crate::layout::PhysicalCircuit;
+use crate::{
+    BackendSpec, Counts, Gate, Param, QuantumCircuit, TranspilationResult, UniversalTranspiler,
+};
+
+// ============================================================================
+// QAOA WORKFLOW
+// ============================================================================
+
+/// Weighted, undirected graph for MaxCut-style QAOA problems.
+#[derive(Debug, Clone)]
+pub struct WeightedGraph {
+    pub num_nodes: usize,
+    pub edges: Vec<(usize, usize, f64)>,
+}
+
+impl WeightedGraph {
+    /// Total weight of the edges cut by `bitstring`, whose bit `i` (bit 0
+    /// rightmost) is the side of node `i`.
+    pub fn cut_value(&self, bitstring: &str) -> f64 {
+        let bits: Vec<char> = bitstring.chars().rev().collect();
+        let side = |q: usize| bits.get(q).copied().unwrap_or('0');
+        self.edges
+            .iter()
+            .filter(|&&(a, b, _)| side(a) != side(b))
+            .map(|&(_, _, w)| w)
+            .sum()
+    }
+}
+
+/// Builds the depth-`p` QAOA circuit for `graph` with symbolic angles
+/// `gamma[l]` (cost layers) and `beta[l]` (mixer layers), measuring node `i`
+/// into `c[i]` so the outcome keeps its node through routing.
+pub fn qaoa_circuit(graph: &WeightedGraph, p: usize) -> QuantumCircuit {
+    let gate = Gate::new;
+    let mut gates: Vec<Gate> = (0..graph.num_nodes)
+        .map(|q| gate("h", vec![q], vec![]))
+        .collect();
+    for layer in 0..p {
+        for &(a, b, w) in &graph.edges {
+            // exp(-i gamma w Z_a Z_b) as cx . rz(2 w gamma) . cx
+            gates.push(gate("cx", vec![a, b], vec![]));
+            gates.push(gate(
+                "rz",
+                vec![b],
+                vec![Param::Symbol {
+                    name: format!("gamma[{layer}]"),
+                    scale: 2.0 * w,
+                }],
+            ));
+            gates.push(gate("cx", vec![a, b], vec![]));
+        }
+        for q in 0..graph.num_nodes {
+            gates.push(gate(
+                "rx",
+                vec![q],
+                vec![Param::Symbol {
+                    name: format!("beta[{layer}]"),
+                    scale: 2.0,
+                }],
+            ));
+        }
+    }
+    gates.extend((0..graph.num_nodes).map(|q| Gate::measure(q, ClassicalBit::new("c", q))));
+
+    QuantumCircuit {
+        gates,
+        ..QuantumCircuit::new(graph.num_nodes, graph.num_nodes)
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Angle {
+    Gamma(usize),
+    Beta(usize),
+}
+
+/// Location of a symbolic parameter inside the transpiled circuit.
+struct ParamSlot {
+    gate: usize,
+    param: usize,
+    angle: Angle,
+    scale: f64,
+}
+
+/// A QAOA problem transpiled once for a backend; each optimizer iteration
+/// then only pays for `bind`.
+pub struct QaoaWorkflow {
+    pub graph: WeightedGraph,
+    pub p: usize,
+    pub transpiled: TranspilationResult,
+    slots: Vec<ParamSlot>,
+}
+
+impl QaoaWorkflow {
+    pub fn new(
+        graph: WeightedGraph,
+        p: usize,
+        transpiler: &UniversalTranspiler,
+        backend: &BackendSpec,
+    ) -> Result<Self, String> {
+        let transpiled = transpiler.transpile_circuit(qaoa_circuit(&graph, p), backend)?;
+
+        let symbols: HashMap<String, Angle> = (0..p)
+            .flat_map(|l| {
+                [
+                    (format!("gamma[{l}]"), Angle::Gamma(l)),
+                    (format!("beta[{l}]"), Angle::Beta(l)),
+                ]
+            })
+            .collect();
+        let mut slots = Vec::new();
+        for (gi, g) in transpiled.circuit.gates.iter().enumerate() {
+            for (pi, param) in g.params.iter().enumerate() {
+                if let Param::Symbol { name, scale } = param {
+                    let angle = *symbols.get(name).ok_or_else(|| {
+                        format!("Unexpected parameter {name} after transpilation")
+                    })?;
+                    slots.push(ParamSlot {
+                        gate: gi,
+                        param: pi,
+                        angle,
+                        scale: *scale,
+                    });
+                }
+            }
+        }
+
+        Ok(Self {
+            graph,
+            p,
+            transpiled,
+            slots,
+        })
+    }
+
+    /// Returns the transpiled circuit with concrete angles for every layer.
+    pub fn bind(&self, gammas: &[f64], betas: &[f64]) -> Result<PhysicalCircuit, String> {
+        if gammas.len() != self.p || betas.len() != self.p {
+            return Err(format!(
+                "Expected {} gammas and betas, got {} and {}",
+                self.p,
+                gammas.len(),
+                betas.len()
+            ));
+        }
+        Ok(self.transpiled.circuit.map(|circuit| {
+            let mut circuit = circuit.clone();
+            for slot in &self.slots {
+                let value = match slot.angle {
+                    Angle::Gamma(l) => gammas[l],
+                    Angle::Beta(l) => betas[l],
+                };
+                circuit.gates[slot.gate].params[slot.param] = Param::Value(slot.scale * value);
+            }
+            circuit
+        }))
+    }
+
+    /// Shot-weighted mean cut value, the quantity the classical optimizer
+    /// maximizes.
+    pub fn expected_cut(&self, counts: &Counts) -> f64 {
+        let shots: usize = counts.values().sum();
+        if shots == 0 {
+            return 0.0;
+        }
+        let total: f64 = counts
+            .iter()
+            .map(|(bits, &n)| self.graph.cut_value(bits) * n as f64)
+            .sum();
+        total / shots as f64
+    }
+
+    /// Most frequently sampled bitstring and its cut value.
+    pub fn best_sample(&self, counts: &Counts) -> Option<(String, f64)> {
+        counts
+            .iter()
+            .max_by(|a, b| a.1.cmp(b.1).then_with(|| b.0.cmp(a.0)))
+            .map(|(bits, _)| (bits.clone(), self.graph.cut_value(bits)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Weighted triangle, which can't be placed on a line without a swap.
+    fn triangle() -> WeightedGraph {
+        WeightedGraph {
+            num_nodes: 3,
+            edges: vec![(0, 1, 1.0), (1, 2, 2.0), (0, 2, 3.0)],
+        }
+    }
+
+    #[cfg(feature = "router")]
+    fn line(num_qubits: usize) -> BackendSpec {
+        BackendSpec {
+            name: "line".to_string(),
+            num_qubits,
+            coupling_map: (1..num_qubits).map(|q| (q - 1, q)).collect(),
+            native_gates: ["x", "h", "cx", "rz"]
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn cut_value_reads_node_i_from_bit_i() {
+        let graph = triangle();
+        // Nodes 0 and 1 against node 2 cut (1,2) and (0,2).
+        assert_eq!(graph.cut_value("011"), 5.0);
+        assert_eq!(graph.cut_value("101"), 3.0);
+        assert_eq!(graph.cut_value("000"), 0.0);
+    }
+
+    #[cfg(feature = "router")]
+    #[test]
+    fn routed_triangle_measures_each_node_into_its_bit() {
+        use crate::layout::VirtualQubit;
+
+        let workflow =
+            QaoaWorkflow::new(triangle(), 1, &UniversalTranspiler::new(), &line(5)).unwrap();
+        let transpiled = &workflow.transpiled;
+        assert!(transpiled.routing.swap_count() > 0);
+
+        let measures: Vec<&Gate> = transpiled
+            .circuit
+            .gates
+            .iter()
+            .filter(|g| g.name == "measure")
+            .collect();
+        assert_eq!(measures.len(), 3);
+        for g in measures {
+            let node = g.clbits[0].index;
+            assert_eq!(
+                g.qubits,
+                vec![transpiled.final_layout.physical(VirtualQubit(node)).0]
+            );
+        }
+
+        let counts: Counts = [("011".to_string(), 1), ("101".to_string(), 3)]
+            .into_iter()
+            .collect();
+        assert_eq!(workflow.expected_cut(&counts), (5.0 + 3.0 * 3.0) / 4.0);
+        assert_eq!(
+            workflow.best_sample(&counts),
+            Some(("101".to_string(), 3.0))
+        );
+    }
+
+    /// Exact outcome distribution of `circuit`, scaled to a million shots.
+    #[cfg(all(feature = "simulator", feature = "router"))]
+    fn exact_counts(circuit: &QuantumCircuit) -> Counts {
+        use crate::simulator::{SimulatorBackend, StateVectorSimulator};
+
+        let state = StateVectorSimulator::default()
+            .statevector(circuit)
+            .unwrap();
+        let measured: Vec<(usize, usize)> = circuit
+            .gates
+            .iter()
+            .filter(|g| g.name == "measure")
+            .map(|g| (g.qubits[0], circuit.clbit_index(&g.clbits[0]).unwrap()))
+            .collect();
+        let mut counts = Counts::new();
+        for (index, amplitude) in state.iter().enumerate() {
+            let mut bits = vec!['0'; circuit.num_clbits];
+            for &(qubit, clbit) in &measured {
+                if index >> qubit & 1 == 1 {
+                    bits[circuit.num_clbits - 1 - clbit] = '1';
+                }
+            }
+            *counts.entry(bits.into_iter().collect()).or_insert(0) +=
+                (amplitude.norm_sqr() * 1e6).round() as usize;
+        }
+        counts
+    }
+
+    #[cfg(all(feature = "simulator", feature = "router"))]
+    #[test]
+    fn routed_triangle_expected_cut_matches_the_logical_circuit() {
+        let graph = triangle();
+        let workflow =
+            QaoaWorkflow::new(graph.clone(), 1, &UniversalTranspiler::new(), &line(5)).unwrap();
+        let (gamma, beta) = (0.4, 0.3);
+        let values: std::collections::BTreeMap<String, f64> = [
+            ("gamma[0]".to_string(), gamma),
+            ("beta[0]".to_string(), beta),
+        ]
+        .into_iter()
+        .collect();
+        let logical = qaoa_circuit(&graph, 1).bind_parameters(&values).unwrap();
+        let routed = workflow.bind(&[gamma], &[beta]).unwrap();
+
+        let expected = workflow.expected_cut(&exact_counts(&logical));
+        let actual = workflow.expected_cut(&exact_counts(&routed));
+        assert!((expected - actual).abs() < 1e-4, "{expected} vs {actual}");
+        // Far from the 3.0 of uniform outcomes, so a node mix-up would show.
+        assert!((expected - 3.0).abs() > 0.1, "{expected}");
+    }
+}
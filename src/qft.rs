@@ -0,0 +1,353 @@
+use std::f64::consts::PI;
+
+use crate::layout::{PhysicalCircuit, PhysicalQubit};
+use crate::objective::{CircuitMetrics, OptimizationObjective};
+use crate::{gate_name_counts, BackendSpec, Gate, OptimizationPass, Param, QuantumCircuit};
+
+// ============================================================================
+// QFT RECOGNITION AND RESYNTHESIS
+// ============================================================================
+
+/// A textbook QFT found in a gate list.
+#[derive(Debug, Clone)]
+pub struct QftMatch {
+    /// Qubits in QFT order: `qubits[0]` receives the first Hadamard.
+    pub qubits: Vec<usize>,
+    pub start: usize,
+    /// One past the last gate of the match.
+    pub end: usize,
+    /// Whether the final bit-reversal swaps are part of the match.
+    pub swaps: bool,
+}
+
+fn is_plain(g: &Gate, name: &str) -> bool {
+    g.name == name && g.condition.is_none() && g.block.is_none() && g.composite.is_none()
+}
+
+fn is_cp(g: &Gate) -> bool {
+    (is_plain(g, "cp") || is_plain(g, "cu1")) && g.qubits.len() == 2
+}
+
+/// True if `g` is `cp(pi / 2^distance)` between `a` and `b`, in either order.
+fn is_qft_rotation(g: &Gate, a: usize, b: usize, distance: usize) -> bool {
+    let expected = PI / (1u64 << distance.min(63)) as f64;
+    is_cp(g)
+        && (g.qubits == [a, b] || g.qubits == [b, a])
+        && g.params
+            .first()
+            .and_then(Param::value)
+            .is_some_and(|v| (v - expected).abs() < 1e-9)
+}
+
+/// Matches a textbook QFT (see `library::qft`) beginning at `gates[start]`.
+pub fn match_qft(gates: &[Gate], start: usize) -> Option<QftMatch> {
+    let first = gates.get(start)?;
+    if !is_plain(first, "h") || first.qubits.len() != 1 {
+        return None;
+    }
+
+    // The first row of rotations fixes the qubit order.
+    let mut qubits = vec![first.qubits[0]];
+    let mut i = start + 1;
+    while let Some(g) = gates.get(i) {
+        if !is_cp(g) || !g.qubits.contains(&qubits[0]) {
+            break;
+        }
+        let other = if g.qubits[0] == qubits[0] {
+            g.qubits[1]
+        } else {
+            g.qubits[0]
+        };
+        if qubits.contains(&other) || !is_qft_rotation(g, qubits[0], other, qubits.len()) {
+            break;
+        }
+        qubits.push(other);
+        i += 1;
+    }
+    let n = qubits.len();
+    if n < 2 {
+        return None;
+    }
+
+    for j in 1..n {
+        let g = gates.get(i)?;
+        if !is_plain(g, "h") || g.qubits != [qubits[j]] {
+            return None;
+        }
+        i += 1;
+        for k in j + 1..n {
+            if !is_qft_rotation(gates.get(i)?, qubits[j], qubits[k], k - j) {
+                return None;
+            }
+            i += 1;
+        }
+    }
+
+    let swaps = (0..n / 2).all(|j| {
+        gates.get(i + j).is_some_and(|g| {
+            let (a, b) = (qubits[j], qubits[n - 1 - j]);
+            is_plain(g, "swap") && (g.qubits == [a, b] || g.qubits == [b, a])
+        })
+    });
+    if swaps {
+        i += n / 2;
+    }
+
+    Some(QftMatch {
+        qubits,
+        start,
+        end: i,
+        swaps,
+    })
+}
+
+/// Replaces recognized QFTs by an approximate QFT that drops controlled-phase
+/// rotations with `|angle| < approximation_threshold`. When a backend is given
+/// and the QFT's qubits form a path in its coupling map, a nearest-neighbour
+/// swap network that needs no routing is also considered, and `objective`
+/// decides between the two. The backend is only consulted for a routed
+/// circuit, through `optimize_physical`.
+pub struct QftResynthesisPass {
+    pub approximation_threshold: f64,
+    pub backend: Option<BackendSpec>,
+    pub objective: OptimizationObjective,
+}
+
+impl QftResynthesisPass {
+    fn is_line(backend: &BackendSpec, qubits: &[PhysicalQubit]) -> bool {
+        qubits.windows(2).all(|w| backend.are_coupled(w[0], w[1]))
+    }
+
+    /// Metrics of `gates` including a rough routing estimate: each two-qubit
+    /// gate between qubits at distance `d` needs `d - 1` swaps there and back.
+    fn routed_metrics(
+        backend: &BackendSpec,
+        circuit: &PhysicalCircuit,
+        gates: Vec<Gate>,
+    ) -> CircuitMetrics {
+        let candidate = circuit.map(|c| c.with_gates(gates));
+        let mut metrics = CircuitMetrics::on(&candidate, backend);
+        let dist = backend.distance_matrix();
+        for g in candidate.gates.iter().filter(|g| g.qubits.len() == 2) {
+            let qubits = candidate.gate_qubits(g);
+            let d = dist
+                .get(qubits[0].0)
+                .and_then(|row| row.get(qubits[1].0))
+                .copied()
+                .unwrap_or(1);
+            if d > 1 && d != usize::MAX {
+                let swaps = 2 * (d - 1);
+                metrics.two_qubit_count += 3 * swaps;
+                metrics.depth += 3 * swaps;
+                metrics.gate_count += swaps;
+                let e = backend.two_qubit_error(qubits[0], qubits[1]);
+                metrics.estimated_error =
+                    1.0 - (1.0 - metrics.estimated_error) * (1.0 - e).powi(3 * swaps as i32);
+            }
+        }
+        metrics
+    }
+
+    /// Resynthesizes every QFT of `circuit`, which is `routed` when there is
+    /// a backend to place a swap network on.
+    fn resynthesize(
+        &self,
+        circuit: &QuantumCircuit,
+        routed: Option<(&PhysicalCircuit, &BackendSpec)>,
+    ) -> QuantumCircuit {
+        let mut out = Vec::with_capacity(circuit.gates.len());
+        let mut i = 0;
+        while i < circuit.gates.len() {
+            let Some(m) = match_qft(&circuit.gates, i) else {
+                out.push(circuit.gates[i].clone());
+                i += 1;
+                continue;
+            };
+            let textbook = self.textbook(&m);
+            let line = routed.filter(|(physical, backend)| {
+                let qubits: Vec<PhysicalQubit> =
+                    m.qubits.iter().map(|&q| physical.qubit(q)).collect();
+                m.swaps && Self::is_line(backend, &qubits)
+            });
+            match line {
+                Some((physical, backend)) => {
+                    let lnn = self.linear_nearest_neighbour(&m);
+                    let lnn_metrics = Self::routed_metrics(backend, physical, lnn.clone());
+                    let textbook_metrics =
+                        Self::routed_metrics(backend, physical, textbook.clone());
+                    if self.objective.prefers(&lnn_metrics, &textbook_metrics) {
+                        out.extend(lnn);
+                    } else {
+                        out.extend(textbook);
+                    }
+                }
+                None => out.extend(textbook),
+            }
+            i = m.end;
+        }
+        circuit.with_gates(out)
+    }
+
+    fn rotation(&self, control: usize, target: usize, distance: usize) -> Option<Gate> {
+        let angle = PI / (1u64 << distance.min(63)) as f64;
+        (angle >= self.approximation_threshold)
+            .then(|| Gate::new("cp", vec![control, target], vec![Param::Value(angle)]))
+    }
+
+    fn textbook(&self, m: &QftMatch) -> Vec<Gate> {
+        let q = &m.qubits;
+        let mut out = Vec::new();
+        for j in 0..q.len() {
+            out.push(Gate::new("h", vec![q[j]], vec![]));
+            for k in j + 1..q.len() {
+                out.extend(self.rotation(q[k], q[j], k - j));
+            }
+        }
+        if m.swaps {
+            for j in 0..q.len() / 2 {
+                out.push(Gate::new("swap", vec![q[j], q[q.len() - 1 - j]], vec![]));
+            }
+        }
+        out
+    }
+
+    /// Each qubit in turn is bubbled to the far end of the line, interacting
+    /// with every later qubit as it passes. The network leaves the qubit order
+    /// reversed, which is exactly the QFT's final bit reversal.
+    fn linear_nearest_neighbour(&self, m: &QftMatch) -> Vec<Gate> {
+        let wires = &m.qubits;
+        let n = wires.len();
+        let mut out = Vec::new();
+        for j in 0..n {
+            out.push(Gate::new("h", vec![wires[0]], vec![]));
+            for step in 1..n - j {
+                let (a, b) = (wires[step - 1], wires[step]);
+                out.extend(self.rotation(b, a, step));
+                out.push(Gate::new("swap", vec![a, b], vec![]));
+            }
+        }
+        out
+    }
+}
+
+impl OptimizationPass for QftResynthesisPass {
+    fn name(&self) -> &str {
+        "qft-resynthesis"
+    }
+
+    fn optimize(&self, circuit: &QuantumCircuit) -> QuantumCircuit {
+        self.resynthesize(circuit, None)
+    }
+
+    fn optimize_physical(&self, circuit: &PhysicalCircuit) -> Option<PhysicalCircuit> {
+        let routed = self.backend.as_ref().map(|backend| (circuit, backend));
+        Some(circuit.map(|c| self.resynthesize(c, routed)))
+    }
+
+    /// Saves matching at every Hadamard of circuits with no controlled phases.
+    fn skip_reason(&self, circuit: &QuantumCircuit) -> Option<String> {
+        let counts = gate_name_counts(circuit);
+        let phases = ["cp", "cu1"].iter().any(|name| counts.contains_key(name));
+        (!phases || !counts.contains_key("h"))
+            .then(|| "no Hadamard and controlled-phase gates".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::library::qft;
+    use crate::linalg::{gate_matrix, Matrix};
+
+    fn pass(approximation_threshold: f64, backend: Option<BackendSpec>) -> QftResynthesisPass {
+        QftResynthesisPass {
+            approximation_threshold,
+            backend,
+            objective: OptimizationObjective::min_two_qubit_count(),
+        }
+    }
+
+    fn unitary(gates: &[Gate], num_qubits: usize) -> Matrix {
+        gates
+            .iter()
+            .fold(Matrix::identity(1 << num_qubits), |u, g| {
+                gate_matrix(g).unwrap().embed(&g.qubits, num_qubits).mul(&u)
+            })
+    }
+
+    fn line(num_qubits: usize) -> BackendSpec {
+        BackendSpec {
+            name: "line".to_string(),
+            num_qubits,
+            coupling_map: (1..num_qubits).map(|q| (q - 1, q)).collect(),
+            native_gates: ["h", "cp", "swap"].iter().map(|s| s.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn library_qfts_are_matched_whole() {
+        let mut gates = vec![Gate::new("x", vec![0], vec![])];
+        gates.extend(qft(4, true).gates);
+        gates.push(Gate::new("h", vec![2], vec![]));
+        let m = match_qft(&gates, 1).unwrap();
+        assert_eq!(m.qubits, [0, 1, 2, 3]);
+        assert_eq!((m.start, m.end), (1, gates.len() - 1));
+        assert!(m.swaps);
+        assert!(match_qft(&gates, 0).is_none());
+
+        let m = match_qft(&qft(3, false).gates, 0).unwrap();
+        assert!(!m.swaps);
+        assert_eq!(m.end, 6);
+    }
+
+    #[test]
+    fn wrong_angles_are_not_a_qft() {
+        let mut gates = qft(3, false).gates;
+        gates[4].params = vec![Param::Value(PI / 4.0)];
+        assert!(match_qft(&gates, 0).is_none());
+    }
+
+    #[test]
+    fn small_rotations_are_dropped() {
+        let circuit = qft(5, true);
+        let exact = pass(0.0, None).optimize(&circuit);
+        assert_eq!(exact.gates.len(), circuit.gates.len());
+        // pi/8 and pi/16 go; pi/2 and pi/4 stay.
+        let approximate = pass(0.5, None).optimize(&circuit);
+        let rotations = approximate.gates.iter().filter(|g| g.name == "cp").count();
+        assert_eq!(rotations, 4 + 3);
+    }
+
+    #[test]
+    fn the_swap_network_is_the_same_qft() {
+        for n in 2..=4 {
+            let m = match_qft(&qft(n, true).gates, 0).unwrap();
+            let textbook = unitary(&pass(0.0, None).textbook(&m), n);
+            let lnn = unitary(&pass(0.0, None).linear_nearest_neighbour(&m), n);
+            assert!(lnn.approx_eq(&textbook, 1e-9), "{n} qubits");
+        }
+    }
+
+    #[test]
+    fn qfts_along_a_line_use_the_swap_network() {
+        let backend = line(4);
+        let circuit = PhysicalCircuit::assume_physical(qft(4, true));
+        let optimized = pass(0.0, Some(backend.clone()))
+            .optimize_physical(&circuit)
+            .unwrap();
+        for g in optimized.gates.iter().filter(|g| g.qubits.len() == 2) {
+            let qubits = optimized.gate_qubits(g);
+            assert!(backend.are_coupled(qubits[0], qubits[1]), "{g:?}");
+        }
+        assert!(unitary(&optimized.gates, 4).approx_eq(&unitary(&circuit.gates, 4), 1e-9));
+
+        // Without the final swaps the network's reversal isn't wanted.
+        let circuit = PhysicalCircuit::assume_physical(qft(4, false));
+        let optimized = pass(0.0, Some(backend))
+            .optimize_physical(&circuit)
+            .unwrap();
+        assert_eq!(optimized.gates.len(), circuit.gates.len());
+        assert!(optimized.gates.iter().all(|g| g.name != "swap"));
+    }
+}
@@ -0,0 +1,199 @@
+use std::collections::HashSet;
+
+use crate::control_flow::ControlFlow;
+use crate::layout::{Layout, PhysicalCircuit};
+use crate::{Gate, QuantumCircuit};
+
+// ============================================================================
+// QUBIT RELABELING AND REGISTER RENAMING
+// ============================================================================
+
+impl QuantumCircuit {
+    /// Relabels qubit `q` as `mapping[q]`. The mapping must cover every qubit
+    /// and be injective; the result is widened if it targets higher indices.
+    pub fn remap_qubits(&self, mapping: &[usize]) -> Result<QuantumCircuit, String> {
+        if mapping.len() != self.num_qubits {
+            return Err(format!(
+                "Qubit mapping has {} entries but the circuit has {} qubits",
+                mapping.len(),
+                self.num_qubits
+            ));
+        }
+        let mut seen = HashSet::new();
+        if let Some(dup) = mapping.iter().find(|&&q| !seen.insert(q)) {
+            return Err(format!("Qubit mapping sends two qubits to {dup}"));
+        }
+        let width = mapping
+            .iter()
+            .map(|&q| q + 1)
+            .max()
+            .unwrap_or(0)
+            .max(self.num_qubits);
+        let mut out = self.relabel_gates(mapping, width)?;
+        out.num_qubits = width;
+        Ok(out)
+    }
+
+    /// Reorders the qubits: qubit `q` becomes `perm[q]`. `perm` must be a
+    /// permutation of `0..num_qubits`.
+    pub fn permute(&self, perm: &[usize]) -> Result<QuantumCircuit, String> {
+        if let Some(&q) = perm.iter().find(|&&q| q >= self.num_qubits) {
+            return Err(format!(
+                "Permutation entry {q} is out of range for {} qubits",
+                self.num_qubits
+            ));
+        }
+        self.remap_qubits(perm)
+    }
+
+    /// Places the circuit on hardware: virtual qubit `v` becomes the physical
+    /// qubit `layout` assigns it, over all of the device's qubits.
+    pub fn apply_layout(&self, layout: &Layout) -> Result<PhysicalCircuit, String> {
+        let mapping: Vec<usize> = layout.iter().map(|(_, p)| p.0).collect();
+        let mut out = self.remap_qubits(&mapping)?;
+        out.num_qubits = out.num_qubits.max(layout.num_physical());
+        Ok(PhysicalCircuit::assume_physical(out))
+    }
+
+    fn relabel_gates(&self, mapping: &[usize], width: usize) -> Result<QuantumCircuit, String> {
+        let mut gates = Vec::with_capacity(self.gates.len());
+        for g in &self.gates {
+            if let Some(block) = &g.block {
+                let mut error = None;
+                let relabeled =
+                    block.map_bodies(&mut |body| match body.relabel_gates(mapping, width) {
+                        Ok(mut b) => {
+                            b.num_qubits = width;
+                            b
+                        }
+                        Err(e) => {
+                            error = Some(e);
+                            body.clone()
+                        }
+                    });
+                if let Some(e) = error {
+                    return Err(e);
+                }
+                gates.push(Gate {
+                    condition: g.condition.clone(),
+                    ..Gate::from_block(relabeled)
+                });
+                continue;
+            }
+            let qubits = g
+                .qubits
+                .iter()
+                .map(|&q| {
+                    mapping.get(q).copied().ok_or_else(|| {
+                        format!("Gate {} acts on qubit {q}, outside the mapping", g.name)
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            gates.push(Gate {
+                qubits,
+                ..g.clone()
+            });
+        }
+        Ok(self.with_gates(gates))
+    }
+
+    /// Renames classical register `old` to `new`, updating every condition
+    /// that refers to it.
+    pub fn rename_creg(&self, old: &str, new: &str) -> Result<QuantumCircuit, String> {
+        if !self.cregs.iter().any(|r| r.name == old) {
+            return Err(format!("No classical register named {old}"));
+        }
+        if old != new && self.cregs.iter().any(|r| r.name == new) {
+            return Err(format!("A classical register named {new} already exists"));
+        }
+        let valid = new
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_alphabetic() || c == '_')
+            && new.chars().all(|c| c.is_alphanumeric() || c == '_');
+        if !valid {
+            return Err(format!("Invalid register name: {new}"));
+        }
+        Ok(self.rename_creg_in_gates(old, new))
+    }
+
+    fn rename_creg_in_gates(&self, old: &str, new: &str) -> QuantumCircuit {
+        let gates = self
+            .gates
+            .iter()
+            .map(|g| {
+                let mut g = g.clone();
+                g.condition = g.condition.map(|c| c.rename_register(old, new));
+                for bit in &mut g.clbits {
+                    if bit.register == old {
+                        bit.register = new.to_string();
+                    }
+                }
+                if let Some(block) = &g.block {
+                    let renamed = block.map_bodies(&mut |body| body.rename_creg_in_gates(old, new));
+                    let renamed = match renamed {
+                        ControlFlow::IfElse {
+                            condition,
+                            true_body,
+                            false_body,
+                        } => ControlFlow::IfElse {
+                            condition: condition.rename_register(old, new),
+                            true_body,
+                            false_body,
+                        },
+                        ControlFlow::While { condition, body } => ControlFlow::While {
+                            condition: condition.rename_register(old, new),
+                            body,
+                        },
+                        other => other,
+                    };
+                    g.block = Some(Box::new(renamed));
+                }
+                g
+            })
+            .collect();
+        let mut out = self.with_gates(gates);
+        for r in &mut out.cregs {
+            if r.name == old {
+                r.name = new.to_string();
+            }
+        }
+        for decl in &mut out.signature.outputs {
+            if decl.name == old {
+                decl.name = new.to_string();
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::PhysicalQubit;
+
+    #[test]
+    fn apply_layout_puts_gates_on_physical_qubits() {
+        let circuit = QuantumCircuit::new(2, 0).with_gates(vec![
+            Gate::new("h", vec![0], vec![]),
+            Gate::new("cx", vec![0, 1], vec![]),
+        ]);
+        let layout = Layout::from_physical(vec![PhysicalQubit(3), PhysicalQubit(1)], 4).unwrap();
+        let placed = circuit.apply_layout(&layout).unwrap();
+        assert_eq!(placed.num_qubits, 4);
+        assert_eq!(placed.gate_qubits(&placed.gates[0]), vec![PhysicalQubit(3)]);
+        assert_eq!(
+            placed.gate_qubits(&placed.gates[1]),
+            vec![PhysicalQubit(3), PhysicalQubit(1)]
+        );
+    }
+
+    #[test]
+    fn layouts_must_fit_the_device() {
+        let circuit = QuantumCircuit::new(2, 0);
+        assert!(Layout::from_physical(vec![PhysicalQubit(0), PhysicalQubit(0)], 2).is_err());
+        let layout = Layout::trivial(2, 2).unwrap();
+        assert!(circuit.apply_layout(&layout).is_ok());
+        assert!(Layout::trivial(3, 2).is_err());
+    }
+}
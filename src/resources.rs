@@ -0,0 +1,345 @@
+use std::f64::consts::FRAC_PI_4;
+use std::fmt;
+
+use crate::json::JsonValue;
+use crate::{Gate, Param, QuantumCircuit};
+
+// ============================================================================
+// FAULT-TOLERANT RESOURCE ESTIMATION
+// ============================================================================
+
+/// Magic-state distillation unit producing T states.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TFactory {
+    /// T states delivered by one run of the factory.
+    pub t_states_per_run: usize,
+    /// Length of one run, in logical cycles.
+    pub run_cycles: usize,
+    pub physical_qubits: usize,
+}
+
+/// Surface-code architecture a logical circuit is estimated against.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FtProfile {
+    pub name: String,
+    /// Code distance of every logical qubit; a logical cycle is this many
+    /// syndrome-extraction rounds.
+    pub code_distance: usize,
+    /// Duration of one syndrome-extraction round.
+    pub syndrome_cycle_ns: f64,
+    pub t_factory: TFactory,
+    /// Most factories that fit beside the algorithm; `None` builds as many as
+    /// needed for T states never to stall it.
+    pub max_t_factories: Option<usize>,
+    /// Allowed synthesis error of each arbitrary-angle rotation.
+    pub rotation_precision: f64,
+}
+
+impl FtProfile {
+    /// Built-in profiles with illustrative figures for a fast
+    /// (superconducting) and a slow (trapped-ion) physical layer. Real
+    /// designs differ; override fields for planning.
+    pub fn presets() -> Vec<FtProfile> {
+        let t_factory = TFactory {
+            t_states_per_run: 1,
+            run_cycles: 11,
+            physical_qubits: 6_000,
+        };
+        vec![
+            FtProfile {
+                name: "superconducting".to_string(),
+                code_distance: 15,
+                syndrome_cycle_ns: 400.0,
+                t_factory: t_factory.clone(),
+                max_t_factories: None,
+                rotation_precision: 1e-9,
+            },
+            FtProfile {
+                name: "trapped-ion".to_string(),
+                code_distance: 13,
+                syndrome_cycle_ns: 600_000.0,
+                t_factory,
+                max_t_factories: None,
+                rotation_precision: 1e-9,
+            },
+        ]
+    }
+
+    pub fn preset(name: &str) -> Option<FtProfile> {
+        Self::presets().into_iter().find(|p| p.name == name)
+    }
+
+    /// T gates needed to synthesize one arbitrary rotation to the profile's
+    /// precision (Clifford+T synthesis scales as 0.53·log2(1/ε) + 5.3).
+    pub fn t_per_rotation(&self) -> usize {
+        (0.53 * (1.0 / self.rotation_precision).log2() + 5.3)
+            .ceil()
+            .max(1.0) as usize
+    }
+
+    /// Resources to run `circuit`, read as a logical (not yet error-corrected)
+    /// circuit.
+    pub fn estimate(&self, circuit: &QuantumCircuit) -> ResourceEstimate {
+        let counts = LogicalCounts::of(circuit, self.t_per_rotation());
+        let t_states =
+            counts.t_gates + 7 * counts.toffolis + counts.rotations * self.t_per_rotation();
+
+        // Enough factories to supply T states as fast as the algorithm
+        // consumes them, unless capped, in which case the algorithm waits.
+        let factory = &self.t_factory;
+        let per_run = factory.t_states_per_run.max(1);
+        let depth = counts.depth.max(1);
+        let needed = if t_states == 0 {
+            0
+        } else {
+            (t_states * factory.run_cycles)
+                .div_ceil(per_run * depth)
+                .max(1)
+        };
+        let t_factories = self
+            .max_t_factories
+            .map_or(needed, |max| needed.min(max.max(1)));
+        let runtime_cycles = if t_factories == 0 {
+            counts.depth
+        } else {
+            counts
+                .depth
+                .max(t_states.div_ceil(t_factories * per_run) * factory.run_cycles)
+        };
+
+        // Algorithmic qubits plus the routing bus lattice surgery needs.
+        let q = circuit.num_qubits;
+        let logical_qubits = if q == 0 {
+            0
+        } else {
+            2 * q + (8.0 * q as f64).sqrt().ceil() as usize + 1
+        };
+        let per_logical = 2 * self.code_distance * self.code_distance;
+        ResourceEstimate {
+            profile: self.name.clone(),
+            code_distance: self.code_distance,
+            algorithmic_qubits: q,
+            logical_qubits,
+            t_gates: counts.t_gates,
+            toffolis: counts.toffolis,
+            rotations: counts.rotations,
+            measurements: counts.measurements,
+            t_states,
+            logical_depth: counts.depth,
+            t_factories,
+            physical_qubits: logical_qubits * per_logical + t_factories * factory.physical_qubits,
+            runtime_cycles,
+            runtime_ns: runtime_cycles as f64 * self.code_distance as f64 * self.syndrome_cycle_ns,
+        }
+    }
+}
+
+/// Requirements of a circuit on a fault-tolerant profile.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResourceEstimate {
+    pub profile: String,
+    pub code_distance: usize,
+    /// Qubits of the input circuit.
+    pub algorithmic_qubits: usize,
+    /// Logical qubits laid out, including routing space.
+    pub logical_qubits: usize,
+    /// `t`/`tdg` gates, and rotations by odd multiples of pi/4.
+    pub t_gates: usize,
+    pub toffolis: usize,
+    /// Arbitrary-angle rotations, each synthesized from several T gates.
+    pub rotations: usize,
+    pub measurements: usize,
+    /// T states consumed in total.
+    pub t_states: usize,
+    /// Logical cycles the algorithm takes if T states never run out.
+    pub logical_depth: usize,
+    pub t_factories: usize,
+    pub physical_qubits: usize,
+    /// Logical cycles including any wait for T states.
+    pub runtime_cycles: usize,
+    pub runtime_ns: f64,
+}
+
+impl ResourceEstimate {
+    pub fn to_json(&self) -> JsonValue {
+        JsonValue::object([
+            ("profile", JsonValue::string(self.profile.as_str())),
+            ("code_distance", self.code_distance.into()),
+            ("algorithmic_qubits", self.algorithmic_qubits.into()),
+            ("logical_qubits", self.logical_qubits.into()),
+            ("t_gates", self.t_gates.into()),
+            ("toffolis", self.toffolis.into()),
+            ("rotations", self.rotations.into()),
+            ("measurements", self.measurements.into()),
+            ("t_states", self.t_states.into()),
+            ("logical_depth", self.logical_depth.into()),
+            ("t_factories", self.t_factories.into()),
+            ("physical_qubits", self.physical_qubits.into()),
+            ("runtime_cycles", self.runtime_cycles.into()),
+            ("runtime_ns", self.runtime_ns.into()),
+        ])
+    }
+}
+
+impl fmt::Display for ResourceEstimate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Resource estimate ({} profile, code distance {}):",
+            self.profile, self.code_distance
+        )?;
+        writeln!(
+            f,
+            "  logical qubits:  {} ({} algorithmic)",
+            self.logical_qubits, self.algorithmic_qubits
+        )?;
+        writeln!(
+            f,
+            "  T states:        {} ({} T, {} Toffoli, {} rotations)",
+            self.t_states, self.t_gates, self.toffolis, self.rotations
+        )?;
+        writeln!(f, "  T factories:     {}", self.t_factories)?;
+        writeln!(f, "  physical qubits: {}", self.physical_qubits)?;
+        writeln!(f, "  logical depth:   {} cycles", self.logical_depth)?;
+        write!(
+            f,
+            "  runtime:         {} cycles, {:.6} s",
+            self.runtime_cycles,
+            self.runtime_ns * 1e-9
+        )
+    }
+}
+
+/// How expensive a single logical operation is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OpKind {
+    Clifford,
+    T,
+    Rotation,
+    Toffoli,
+    Measurement,
+    Free,
+}
+
+/// Kind of a rotation by `angle`: multiples of pi/2 are Clifford, odd
+/// multiples of pi/4 a single T, anything else (or symbolic) needs synthesis.
+fn rotation_kind(angle: &Param) -> OpKind {
+    let quarter_turns = match angle {
+        Param::Pi(r) if 4 % r.den == 0 => Some(r.num * (4 / r.den)),
+        Param::Pi(_) | Param::Symbol { .. } | Param::Duration(_) => None,
+        Param::Value(v) => {
+            let k = v / FRAC_PI_4;
+            ((k - k.round()).abs() < 1e-9).then(|| k.round() as i64)
+        }
+    };
+    match quarter_turns {
+        Some(k) if k % 2 == 0 => OpKind::Clifford,
+        Some(_) => OpKind::T,
+        None => OpKind::Rotation,
+    }
+}
+
+/// Logical operations a gate amounts to.
+fn decompose(g: &Gate) -> Vec<OpKind> {
+    let half = |p: &Param| match p.value() {
+        Some(v) => rotation_kind(&Param::Value(v / 2.0)),
+        None => OpKind::Rotation,
+    };
+    match (g.name.as_str(), g.params.as_slice()) {
+        ("barrier" | "delay", _) => vec![OpKind::Free],
+        ("measure", _) => vec![OpKind::Measurement],
+        ("t" | "tdg", _) => vec![OpKind::T],
+        ("ccx" | "ccz", _) => vec![OpKind::Toffoli],
+        ("rz" | "rx" | "ry" | "p" | "u1" | "rzz", [angle]) => vec![rotation_kind(angle)],
+        // Controlled phases split into three rotations by half the angle,
+        // controlled rz into two.
+        ("cp" | "cu1", [angle]) => vec![OpKind::Clifford, half(angle), half(angle), half(angle)],
+        ("crz", [angle]) => vec![OpKind::Clifford, half(angle), half(angle)],
+        ("u" | "u3" | "u2", angles) => angles.iter().map(rotation_kind).collect(),
+        _ => vec![OpKind::Clifford],
+    }
+}
+
+#[derive(Debug, Default)]
+struct LogicalCounts {
+    t_gates: usize,
+    toffolis: usize,
+    rotations: usize,
+    measurements: usize,
+    depth: usize,
+}
+
+impl LogicalCounts {
+    /// Counts operations (loop bodies once per iteration, the costlier branch
+    /// of an if/else) and the logical depth, where Clifford operations and T
+    /// gates take a cycle each, a Toffoli three and a rotation one per T.
+    fn of(circuit: &QuantumCircuit, t_per_rotation: usize) -> LogicalCounts {
+        let mut counts = LogicalCounts::default();
+        let width = circuit
+            .gates
+            .iter()
+            .flat_map(|g| g.qubits.iter().map(|&q| q + 1))
+            .max()
+            .unwrap_or(0);
+        let mut clock = vec![0usize; circuit.num_qubits.max(width)];
+        for g in &circuit.gates {
+            let start = g.qubits.iter().map(|&q| clock[q]).max().unwrap_or(0);
+            let cycles = if let Some(block) = &g.block {
+                let repeats = block.iteration_count().unwrap_or(1);
+                let body = block
+                    .bodies()
+                    .into_iter()
+                    .map(|b| LogicalCounts::of(b, t_per_rotation))
+                    .max_by_key(|c| {
+                        (
+                            c.t_gates + 7 * c.toffolis + c.rotations * t_per_rotation,
+                            c.depth,
+                        )
+                    })
+                    .unwrap_or_default();
+                counts.t_gates = counts
+                    .t_gates
+                    .saturating_add(body.t_gates.saturating_mul(repeats));
+                counts.toffolis = counts
+                    .toffolis
+                    .saturating_add(body.toffolis.saturating_mul(repeats));
+                counts.rotations = counts
+                    .rotations
+                    .saturating_add(body.rotations.saturating_mul(repeats));
+                counts.measurements = counts
+                    .measurements
+                    .saturating_add(body.measurements.saturating_mul(repeats));
+                body.depth.saturating_mul(repeats)
+            } else {
+                decompose(g)
+                    .into_iter()
+                    .map(|op| match op {
+                        OpKind::Free => 0,
+                        OpKind::Clifford => 1,
+                        OpKind::Measurement => {
+                            counts.measurements += 1;
+                            1
+                        }
+                        OpKind::T => {
+                            counts.t_gates += 1;
+                            1
+                        }
+                        OpKind::Toffoli => {
+                            counts.toffolis += 1;
+                            3
+                        }
+                        OpKind::Rotation => {
+                            counts.rotations += 1;
+                            t_per_rotation
+                        }
+                    })
+                    .sum()
+            };
+            for &q in &g.qubits {
+                clock[q] = start.saturating_add(cycles);
+            }
+        }
+        counts.depth = clock.into_iter().max().unwrap_or(0);
+        counts
+    }
+}
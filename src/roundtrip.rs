@@ -0,0 +1,257 @@
+use crate::classical::ClassicalExpr;
+use crate::composite::UnrollPass;
+use crate::control_flow::ControlFlow;
+use crate::{run_pass, Gate, Param, QASMEmitter, QASMParser, QasmVersion, QuantumCircuit};
+
+// ============================================================================
+// ROUND-TRIP CHECKS (PARSE -> EMIT -> PARSE)
+// ============================================================================
+
+/// Numeric parameters closer than this compare equal.
+const PARAM_TOLERANCE: f64 = 1e-9;
+
+/// Form in which circuits are compared: composite instances expanded, since
+/// OpenQASM gate definitions are read back as their bodies.
+pub fn canonicalize(circuit: &QuantumCircuit) -> QuantumCircuit {
+    run_pass(&UnrollPass, circuit)
+}
+
+/// Emits `circuit` as OpenQASM `version` and parses the text back.
+pub fn roundtrip(circuit: &QuantumCircuit, version: QasmVersion) -> Result<QuantumCircuit, String> {
+    let text = QASMEmitter::new(version).emit(circuit)?;
+    QASMParser::default().parse(&text)
+}
+
+/// Checks that `parse(emit(circuit))` is structurally equal to `circuit`
+/// after canonicalization. The error names the first difference.
+pub fn check_roundtrip(circuit: &QuantumCircuit, version: QasmVersion) -> Result<(), String> {
+    let canonical = canonicalize(circuit);
+    let back = roundtrip(&canonical, version)?;
+    match structural_diff(&canonical, &back) {
+        None => Ok(()),
+        Some(diff) => Err(format!(
+            "Round trip through {version:?} changed the circuit: {diff}"
+        )),
+    }
+}
+
+/// First structural difference between two circuits, or `None` if they have
+/// the same registers and gate sequence. Numeric parameters are compared
+/// by value, so `pi/2` equals `1.5707963267948966`.
+pub fn structural_diff(a: &QuantumCircuit, b: &QuantumCircuit) -> Option<String> {
+    if a.num_qubits != b.num_qubits {
+        return Some(format!("{} qubits vs {}", a.num_qubits, b.num_qubits));
+    }
+    if a.cregs != b.cregs {
+        return Some(format!(
+            "classical registers {:?} vs {:?}",
+            a.cregs, b.cregs
+        ));
+    }
+    if a.variables != b.variables {
+        return Some(format!(
+            "classical variables {:?} vs {:?}",
+            a.variables, b.variables
+        ));
+    }
+    if a.signature != b.signature {
+        return Some(format!("signature {:?} vs {:?}", a.signature, b.signature));
+    }
+    if a.calibrations != b.calibrations {
+        return Some("pulse calibrations differ".to_string());
+    }
+    gates_diff(&a.gates, &b.gates)
+}
+
+fn gates_diff(a: &[Gate], b: &[Gate]) -> Option<String> {
+    for (i, (ga, gb)) in a.iter().zip(b).enumerate() {
+        if let Some(d) = gate_diff(ga, gb) {
+            return Some(format!("gate {i}: {d}"));
+        }
+    }
+    match a.len().cmp(&b.len()) {
+        std::cmp::Ordering::Equal => None,
+        std::cmp::Ordering::Less => Some(format!(
+            "unexpected extra gate {} `{}`",
+            a.len(),
+            b[a.len()].name
+        )),
+        std::cmp::Ordering::Greater => {
+            Some(format!("gate {} `{}` is missing", b.len(), a[b.len()].name))
+        }
+    }
+}
+
+fn gate_diff(a: &Gate, b: &Gate) -> Option<String> {
+    if a.name != b.name {
+        return Some(format!("`{}` vs `{}`", a.name, b.name));
+    }
+    if a.qubits != b.qubits {
+        return Some(format!(
+            "`{}` on qubits {:?} vs {:?}",
+            a.name, a.qubits, b.qubits
+        ));
+    }
+    if a.clbits != b.clbits {
+        return Some(format!(
+            "`{}` into clbits {:?} vs {:?}",
+            a.name, a.clbits, b.clbits
+        ));
+    }
+    if a.params.len() != b.params.len()
+        || !a
+            .params
+            .iter()
+            .zip(&b.params)
+            .all(|(x, y)| params_equal(x, y))
+    {
+        let show = |ps: &[Param]| {
+            ps.iter()
+                .map(|p| p.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        return Some(format!(
+            "`{}` params ({}) vs ({})",
+            a.name,
+            show(&a.params),
+            show(&b.params)
+        ));
+    }
+    if a.condition != b.condition {
+        let show = |c: &Option<ClassicalExpr>| {
+            c.as_ref()
+                .map_or("none".to_string(), ClassicalExpr::to_string)
+        };
+        return Some(format!(
+            "`{}` condition {} vs {}",
+            a.name,
+            show(&a.condition),
+            show(&b.condition)
+        ));
+    }
+    match (&a.composite, &b.composite) {
+        (None, None) => {}
+        (Some(x), Some(y)) => {
+            if let Some(d) = structural_diff(&x.definition, &y.definition) {
+                return Some(format!("`{}` definition: {d}", a.name));
+            }
+        }
+        _ => return Some(format!("`{}` is composite on one side only", a.name)),
+    }
+    match (&a.block, &b.block) {
+        (None, None) => None,
+        (Some(x), Some(y)) => block_diff(x, y).map(|d| format!("`{}` {d}", a.name)),
+        _ => Some(format!("`{}` block presence differs", a.name)),
+    }
+}
+
+fn params_equal(a: &Param, b: &Param) -> bool {
+    match (a.value(), b.value()) {
+        (Some(x), Some(y)) => (x - y).abs() <= PARAM_TOLERANCE,
+        _ => a == b,
+    }
+}
+
+fn block_diff(a: &ControlFlow, b: &ControlFlow) -> Option<String> {
+    let header_equal = match (a, b) {
+        (ControlFlow::IfElse { condition: c1, .. }, ControlFlow::IfElse { condition: c2, .. })
+        | (ControlFlow::While { condition: c1, .. }, ControlFlow::While { condition: c2, .. }) => {
+            c1 == c2
+        }
+        (
+            ControlFlow::For {
+                variable: v1,
+                start: s1,
+                step: t1,
+                end: e1,
+                ..
+            },
+            ControlFlow::For {
+                variable: v2,
+                start: s2,
+                step: t2,
+                end: e2,
+                ..
+            },
+        ) => (v1, s1, t1, e1) == (v2, s2, t2, e2),
+        (ControlFlow::Protected { .. }, ControlFlow::Protected { .. }) => true,
+        _ => false,
+    };
+    if !header_equal {
+        return Some("header differs".to_string());
+    }
+    let (bodies_a, bodies_b) = (a.bodies(), b.bodies());
+    if bodies_a.len() != bodies_b.len() {
+        return Some("number of branches differs".to_string());
+    }
+    bodies_a
+        .iter()
+        .zip(&bodies_b)
+        .enumerate()
+        .find_map(|(i, (x, y))| gates_diff(&x.gates, &y.gates).map(|d| format!("body {i}: {d}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> QuantumCircuit {
+        QASMParser::default().parse(text).unwrap()
+    }
+
+    #[test]
+    fn qasm3_control_flow_and_signature_round_trip() {
+        let circuit = parse(
+            "OPENQASM 3.0;
+include \"stdgates.inc\";
+input float theta;
+output bit[2] result;
+qubit[3] q;
+bit[2] c;
+uint[4] k = 2;
+rx(theta) q[0];
+c[0] = measure q[0];
+if (c[0] == 1) {
+  x q[1];
+} else {
+  h q[1];
+}
+while (c[0] == 0) {
+  h q[0];
+  c[0] = measure q[0];
+}
+for int i in [0:3] {
+  cx q[1], q[2];
+}
+result[0] = measure q[1];
+result[1] = measure q[2];
+",
+        );
+        assert_eq!(check_roundtrip(&circuit, QasmVersion::V3), Ok(()));
+    }
+
+    #[test]
+    fn qasm2_registers_and_conditions_round_trip() {
+        let circuit = parse(
+            "OPENQASM 2.0;
+include \"qelib1.inc\";
+qreg q[2];
+creg a[1];
+creg b[2];
+h q[0];
+measure q[0] -> a[0];
+if (a == 1) x q[1];
+measure q[1] -> b[1];
+",
+        );
+        assert_eq!(check_roundtrip(&circuit, QasmVersion::V2), Ok(()));
+    }
+
+    #[test]
+    fn measurements_into_different_bits_differ() {
+        let a = parse("OPENQASM 3.0;\nqubit[1] q;\nbit[2] c;\nc[0] = measure q[0];\n");
+        let b = parse("OPENQASM 3.0;\nqubit[1] q;\nbit[2] c;\nc[1] = measure q[0];\n");
+        assert!(structural_diff(&a, &b).is_some());
+    }
+}
@@ -0,0 +1,151 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use crate::layout::Layout;
+use crate::objective::OptimizationObjective;
+use crate::{BackendSpec, QuantumCircuit, RoutedCircuit, SimpleRouter};
+
+// ============================================================================
+// ROUTING STRATEGIES AND THEIR REGISTRY
+// ============================================================================
+
+/// A way of placing a circuit on a backend and making its two-qubit gates
+/// act on coupled qubits. Implement this in another crate and register it to
+/// make a router selectable by name:
+///
+/// ```
+/// use std::sync::Arc;
+///
+/// use transpiler_arch::layout::Layout;
+/// use transpiler_arch::objective::OptimizationObjective;
+/// use transpiler_arch::routing::{RouterRegistry, RoutingStrategy};
+/// use transpiler_arch::{BackendSpec, QuantumCircuit, RoutedCircuit, SimpleRouter, UniversalTranspiler};
+///
+/// /// Swap-chain routing that always starts from the trivial layout.
+/// struct TrivialStart;
+///
+/// impl RoutingStrategy for TrivialStart {
+///     fn name(&self) -> &str {
+///         "trivial-start"
+///     }
+///
+///     fn route(
+///         &self,
+///         circuit: &QuantumCircuit,
+///         backend: &BackendSpec,
+///         _: Option<Layout>,
+///         objective: &OptimizationObjective,
+///     ) -> Result<RoutedCircuit, String> {
+///         let layout = Layout::trivial(circuit.num_qubits, backend.num_qubits)?;
+///         let router = SimpleRouter {
+///             objective: *objective,
+///             ..SimpleRouter::default()
+///         };
+///         router.route_with_layout(circuit, backend, layout)
+///     }
+/// }
+///
+/// let registry = RouterRegistry::default().with_router(Arc::new(TrivialStart));
+/// let transpiler = UniversalTranspiler::new().with_router(registry.get("trivial-start").unwrap());
+/// # let _ = transpiler;
+/// ```
+pub trait RoutingStrategy: Send + Sync {
+    /// Name the strategy is registered and selected under.
+    fn name(&self) -> &str;
+
+    /// Routes `circuit` from `initial_layout`, or from a layout of the
+    /// strategy's choosing if `None`. Where the strategy has a choice, e.g.
+    /// between swapping and bridging, `objective` says which result is
+    /// better.
+    fn route(
+        &self,
+        circuit: &QuantumCircuit,
+        backend: &BackendSpec,
+        initial_layout: Option<Layout>,
+        objective: &OptimizationObjective,
+    ) -> Result<RoutedCircuit, String>;
+}
+
+impl RoutingStrategy for SimpleRouter {
+    fn name(&self) -> &str {
+        match (self.teleportation, self.bridge_gates) {
+            (false, false) => "swap-chain",
+            (false, true) => "bridge",
+            (true, false) => "teleport",
+            (true, true) => "teleport-bridge",
+        }
+    }
+
+    fn route(
+        &self,
+        circuit: &QuantumCircuit,
+        backend: &BackendSpec,
+        initial_layout: Option<Layout>,
+        objective: &OptimizationObjective,
+    ) -> Result<RoutedCircuit, String> {
+        let router = SimpleRouter {
+            objective: *objective,
+            ..*self
+        };
+        match initial_layout {
+            Some(layout) => router.route_with_layout(circuit, backend, layout),
+            None => SimpleRouter::route(&router, circuit, backend),
+        }
+    }
+}
+
+/// Routing strategies by name.
+#[derive(Clone)]
+pub struct RouterRegistry {
+    routers: BTreeMap<String, Arc<dyn RoutingStrategy>>,
+}
+
+impl Default for RouterRegistry {
+    /// The routers this crate provides: `swap-chain` (the default, which
+    /// only checks coupling without the `router` feature), `teleport` and
+    /// `bridge` with `router`, and `exact` with `exact-routing`.
+    fn default() -> Self {
+        let registry = Self::empty().with_router(Arc::new(SimpleRouter::default()));
+        #[cfg(feature = "router")]
+        let registry = registry
+            .with_router(Arc::new(SimpleRouter {
+                teleportation: true,
+                ..SimpleRouter::default()
+            }))
+            .with_router(Arc::new(SimpleRouter {
+                bridge_gates: true,
+                ..SimpleRouter::default()
+            }));
+        #[cfg(feature = "exact-routing")]
+        let registry = registry.with_router(Arc::new(crate::exact_routing::ExactRouter::default()));
+        registry
+    }
+}
+
+impl RouterRegistry {
+    pub fn empty() -> Self {
+        Self {
+            routers: BTreeMap::new(),
+        }
+    }
+
+    /// Adds `router` under its name, replacing any router of that name.
+    pub fn with_router(mut self, router: Arc<dyn RoutingStrategy>) -> Self {
+        self.routers.insert(router.name().to_string(), router);
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Result<Arc<dyn RoutingStrategy>, String> {
+        self.routers.get(name).cloned().ok_or_else(|| {
+            format!(
+                "Unknown router '{name}'; available: {}",
+                self.names().join(", ")
+            )
+        })
+    }
+
+    /// Registered names, sorted.
+    pub fn names(&self) -> Vec<&str> {
+        self.routers.keys().map(String::as_str).collect()
+    }
+}
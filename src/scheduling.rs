@@ -0,0 +1,594 @@
+use crate::duration::Duration;
+use crate::layout::PhysicalCircuit;
+use crate::{BackendSpec, Gate, Param, QuantumCircuit};
+
+// ============================================================================
+// SCHEDULING AND TIMING CONSTRAINTS
+// ============================================================================
+
+/// Pulse-level timing rules imposed by the control electronics. All values
+/// are in units of the backend's sample time `dt`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimingConstraints {
+    /// Every pulse (and delay) duration must be a multiple of this.
+    pub granularity: u64,
+    /// Shortest non-zero pulse or delay the hardware accepts.
+    pub min_length: u64,
+    /// Gate start times must be multiples of this.
+    pub pulse_alignment: u64,
+    /// Measurement start times must be multiples of this.
+    pub acquire_alignment: u64,
+}
+
+impl Default for TimingConstraints {
+    fn default() -> Self {
+        Self {
+            granularity: 1,
+            min_length: 1,
+            pulse_alignment: 1,
+            acquire_alignment: 1,
+        }
+    }
+}
+
+fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+/// Rounds `value` up to the next multiple of `multiple`.
+fn align_up(value: u64, multiple: u64) -> u64 {
+    if multiple <= 1 {
+        value
+    } else {
+        value.div_ceil(multiple) * multiple
+    }
+}
+
+impl BackendSpec {
+    /// Duration of `gate` in `dt`, or `None` if the backend doesn't know it.
+    /// Delays carry their duration as their parameter, converted with the
+    /// backend's `dt` if given in time units; a plain number counts `dt`.
+    pub fn gate_duration(&self, gate: &Gate) -> Option<u64> {
+        match gate.name.as_str() {
+            "delay" => match gate.params.first()? {
+                Param::Duration(d) => d.to_dt(self.dt),
+                p => p.value().map(|d| d.max(0.0).round() as u64),
+            },
+            "barrier" => Some(0),
+            name => self.gate_durations.get(name).copied(),
+        }
+    }
+
+    /// Whether two-qubit gates on edges `a` and `b` suffer crosstalk when
+    /// they overlap in time. Edge direction doesn't matter.
+    pub fn crosstalk_between(&self, a: (usize, usize), b: (usize, usize)) -> bool {
+        let edge = |(x, y): (usize, usize)| (x.min(y), x.max(y));
+        let (a, b) = (edge(a), edge(b));
+        self.crosstalk_pairs
+            .iter()
+            .any(|&(p, q)| (edge(p) == a && edge(q) == b) || (edge(p) == b && edge(q) == a))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScheduledGate {
+    /// Index into the scheduled circuit's gate list.
+    pub index: usize,
+    pub start: u64,
+    pub duration: u64,
+}
+
+impl ScheduledGate {
+    pub fn end(&self) -> u64 {
+        self.start + self.duration
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Schedule {
+    pub gates: Vec<ScheduledGate>,
+    pub total_duration: u64,
+}
+
+/// As-soon-as-possible schedule of a routed circuit, in `dt`.
+pub fn schedule_asap(circuit: &PhysicalCircuit, backend: &BackendSpec) -> Result<Schedule, String> {
+    schedule(circuit, backend, false)
+}
+
+/// As-soon-as-possible schedule that never overlaps two-qubit gates on edges
+/// the backend lists as crosstalk pairs: a gate that would is pushed back
+/// until the conflicting gate has finished.
+pub fn schedule_crosstalk_aware(
+    circuit: &PhysicalCircuit,
+    backend: &BackendSpec,
+) -> Result<Schedule, String> {
+    schedule(circuit, backend, true)
+}
+
+/// `backend.gate_duration(gate)`, or why there is none.
+fn duration(backend: &BackendSpec, gate: &Gate) -> Result<u64, String> {
+    backend
+        .gate_duration(gate)
+        .ok_or_else(|| match &gate.params[..] {
+            [Param::Duration(d)] if gate.name == "delay" => {
+                format!(
+                    "Backend {} has no dt to convert the {d} delay with",
+                    backend.name
+                )
+            }
+            _ => format!(
+                "Backend {} has no duration for gate {}",
+                backend.name, gate.name
+            ),
+        })
+}
+
+/// Places gates moment by moment, so under crosstalk the earlier layer wins
+/// the edge. A gate reading a classical bit starts once the measurement
+/// writing it has finished, and a measurement doesn't start before earlier
+/// readers of its bits.
+fn schedule(
+    circuit: &PhysicalCircuit,
+    backend: &BackendSpec,
+    avoid_crosstalk: bool,
+) -> Result<Schedule, String> {
+    let mut qubit_time = vec![0u64; circuit.num_qubits.max(backend.num_qubits)];
+    let num_bits = circuit.cregs.iter().map(|r| r.size).sum();
+    // Earliest start for a gate reading, and for one writing, each bit.
+    let mut read_time = vec![0u64; num_bits];
+    let mut write_time = vec![0u64; num_bits];
+    let mut gates = Vec::with_capacity(circuit.gates.len());
+    // (edge, start, end) of every two-qubit gate placed so far.
+    let mut two_qubit: Vec<((usize, usize), u64, u64)> = Vec::new();
+    let layers = circuit.moment_indices();
+    let mut order: Vec<usize> = (0..circuit.gates.len()).collect();
+    order.sort_by_key(|&i| layers[i]);
+    for index in order {
+        let g = &circuit.gates[index];
+        if g.block.is_some() {
+            return Err(format!("Cannot schedule control-flow block {}", g.name));
+        }
+        let duration = duration(backend, g)?;
+        let access = g.classical_access();
+        let (reads, writes) = (
+            circuit.clbit_indices(&access.reads),
+            circuit.clbit_indices(&access.writes),
+        );
+        let mut start = g
+            .qubits
+            .iter()
+            .map(|&q| qubit_time[q])
+            .chain(reads.iter().map(|&b| read_time[b]))
+            .chain(writes.iter().map(|&b| write_time[b]))
+            .max()
+            .unwrap_or(0);
+        if let [a, b] = g.qubits[..] {
+            if avoid_crosstalk && duration > 0 {
+                while let Some(&(_, _, end)) = two_qubit.iter().find(|&&(e, s, end)| {
+                    s < start + duration && start < end && backend.crosstalk_between(e, (a, b))
+                }) {
+                    start = end;
+                }
+            }
+            two_qubit.push(((a, b), start, start + duration));
+        }
+        for &q in &g.qubits {
+            qubit_time[q] = start + duration;
+        }
+        for &b in &reads {
+            write_time[b] = write_time[b].max(start);
+        }
+        for &b in &writes {
+            read_time[b] = start + duration;
+            write_time[b] = start + duration;
+        }
+        gates.push(ScheduledGate {
+            index,
+            start,
+            duration,
+        });
+    }
+    Ok(Schedule {
+        total_duration: qubit_time.into_iter().max().unwrap_or(0),
+        gates,
+    })
+}
+
+/// A pass that places gates in time for the hardware. These run after
+/// optimization, on the routed circuit, and their output is kept whatever
+/// the objective makes of it: the delays they add make a circuit legal to
+/// run rather than better.
+pub trait SchedulingPass {
+    fn name(&self) -> &str;
+
+    /// `circuit` scheduled, or why it can't be.
+    fn schedule(&self, circuit: &PhysicalCircuit) -> Result<PhysicalCircuit, String>;
+}
+
+/// Pads the circuit with delays so that, run as soon as possible, no two
+/// two-qubit gates on crosstalk-paired edges overlap (see
+/// `schedule_crosstalk_aware`).
+pub struct CrosstalkAwareSchedulingPass {
+    pub backend: BackendSpec,
+}
+
+impl SchedulingPass for CrosstalkAwareSchedulingPass {
+    fn name(&self) -> &str {
+        "crosstalk-scheduling"
+    }
+
+    fn schedule(&self, circuit: &PhysicalCircuit) -> Result<PhysicalCircuit, String> {
+        let schedule = schedule_crosstalk_aware(circuit, &self.backend)?;
+        let mut qubit_time = vec![0u64; circuit.num_qubits.max(self.backend.num_qubits)];
+        let mut out = Vec::with_capacity(circuit.gates.len());
+        for sg in &schedule.gates {
+            let g = &circuit.gates[sg.index];
+            let earliest = g.qubits.iter().map(|&q| qubit_time[q]).max().unwrap_or(0);
+            for &q in &g.qubits {
+                let gap = sg.start - qubit_time[q];
+                if sg.start > earliest && gap > 0 {
+                    out.push(Gate::new(
+                        "delay",
+                        vec![q],
+                        vec![Param::Duration(Duration::dt(gap))],
+                    ));
+                }
+                qubit_time[q] = sg.end();
+            }
+            out.push(g.clone());
+        }
+        Ok(circuit.map(|c| c.with_gates(out)))
+    }
+}
+
+/// Makes a scheduled circuit satisfy the backend's `TimingConstraints`:
+/// delays are stretched to the granularity and minimum length, and gates that
+/// would start off the pulse (or acquire) alignment grid get padding delays
+/// in front of them. It fails when a gate has no aligned start that every
+/// padding delay can legally reach, e.g. after a gate whose duration is off
+/// the granularity.
+pub struct ConstrainTimingPass {
+    pub backend: BackendSpec,
+}
+
+impl SchedulingPass for ConstrainTimingPass {
+    fn name(&self) -> &str {
+        "constrain-timing"
+    }
+
+    fn schedule(&self, circuit: &PhysicalCircuit) -> Result<PhysicalCircuit, String> {
+        let constraints = self.backend.timing_constraints;
+        let granularity = constraints.granularity.max(1);
+        let legal_delay = |d: u64| {
+            if d == 0 {
+                0
+            } else {
+                align_up(d.max(constraints.min_length), granularity)
+            }
+        };
+
+        let mut qubit_time = vec![0u64; circuit.num_qubits.max(self.backend.num_qubits)];
+        let mut out = Vec::with_capacity(circuit.gates.len());
+        for g in &circuit.gates {
+            if g.block.is_some() {
+                return Err(format!("Cannot schedule control-flow block {}", g.name));
+            }
+            let mut g = g.clone();
+            if g.name == "delay" {
+                let d = duration(&self.backend, &g)?;
+                g.params = vec![Param::Duration(Duration::dt(legal_delay(d)))];
+            }
+            let duration = duration(&self.backend, &g)?;
+
+            let alignment = if g.name == "measure" {
+                constraints.acquire_alignment
+            } else {
+                constraints.pulse_alignment
+            }
+            .max(1);
+            let earliest = g.qubits.iter().map(|&q| qubit_time[q]).max().unwrap_or(0);
+            let mut start = earliest;
+            if duration > 0 && g.name != "delay" && align_up(earliest, alignment) != earliest {
+                // Move the start along the alignment grid until every padding
+                // delay is itself a legal duration. Once past the minimum
+                // length, whether a start works repeats every
+                // lcm(alignment, granularity), so one such period decides.
+                let fits = |start: u64| {
+                    g.qubits.iter().all(|&q| {
+                        let gap = start - qubit_time[q];
+                        gap == 0 || legal_delay(gap) == gap
+                    })
+                };
+                let first = align_up(earliest, alignment);
+                let period = granularity / gcd(alignment, granularity);
+                let tries = constraints.min_length.div_ceil(alignment) + period;
+                start = (0..tries)
+                    .map(|k| first + k * alignment)
+                    .find(|&s| fits(s))
+                    .ok_or_else(|| {
+                        format!(
+                            "Gate {} on qubits {:?} has no start on the {alignment} dt alignment \
+                             that delays of {granularity} dt granularity and at least {} dt reach",
+                            g.name, g.qubits, constraints.min_length
+                        )
+                    })?;
+                for &q in &g.qubits {
+                    let pad = start - qubit_time[q];
+                    if pad > 0 {
+                        out.push(Gate::new(
+                            "delay",
+                            vec![q],
+                            vec![Param::Duration(Duration::dt(pad))],
+                        ));
+                    }
+                }
+            }
+            for &q in &g.qubits {
+                qubit_time[q] = start + duration;
+            }
+            out.push(g);
+        }
+        Ok(circuit.map(|c| c.with_gates(out)))
+    }
+}
+
+/// One gate on the critical path.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CriticalStep {
+    pub index: usize,
+    pub name: String,
+    pub qubits: Vec<usize>,
+    pub start: u64,
+    pub end: u64,
+    /// Qubit through which this gate delays the next step, if any.
+    pub via: Option<usize>,
+}
+
+/// Longest dependency chain of a schedule: the gates that determine the
+/// total duration, in execution order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CriticalPath {
+    pub steps: Vec<CriticalStep>,
+    pub total_duration: u64,
+    /// Qubits the chain runs along, sorted.
+    pub bounding_qubits: Vec<usize>,
+}
+
+/// Extracts the critical path of `schedule`, which must come from
+/// `schedule_asap(circuit, ..)`.
+pub fn critical_path(circuit: &QuantumCircuit, schedule: &Schedule) -> CriticalPath {
+    // Replay the schedule, remembering which earlier gate (and over which
+    // qubit) each gate had to wait for.
+    let mut last_on_qubit: Vec<Option<usize>> = vec![None; circuit.num_qubits];
+    let mut predecessor: Vec<Option<(usize, usize)>> = vec![None; schedule.gates.len()];
+    for (i, sg) in schedule.gates.iter().enumerate() {
+        let g = &circuit.gates[sg.index];
+        predecessor[i] = g
+            .qubits
+            .iter()
+            .filter_map(|&q| last_on_qubit.get(q).copied().flatten().map(|p| (p, q)))
+            .filter(|&(p, _)| schedule.gates[p].end() == sg.start && sg.start > 0)
+            .max_by_key(|&(p, _)| p);
+        for &q in &g.qubits {
+            if let Some(slot) = last_on_qubit.get_mut(q) {
+                *slot = Some(i);
+            }
+        }
+    }
+
+    let mut steps = Vec::new();
+    let mut cursor = schedule
+        .gates
+        .iter()
+        .enumerate()
+        .max_by_key(|(i, sg)| (sg.end(), std::cmp::Reverse(*i)))
+        .map(|(i, _)| i);
+    let mut via = None;
+    while let Some(i) = cursor {
+        let sg = schedule.gates[i];
+        let g = &circuit.gates[sg.index];
+        steps.push(CriticalStep {
+            index: sg.index,
+            name: g.name.clone(),
+            qubits: g.qubits.clone(),
+            start: sg.start,
+            end: sg.end(),
+            via,
+        });
+        via = predecessor[i].map(|(_, q)| q);
+        cursor = predecessor[i].map(|(p, _)| p);
+    }
+    steps.reverse();
+
+    let mut bounding_qubits: Vec<usize> = steps.iter().filter_map(|s| s.via).collect();
+    if bounding_qubits.is_empty() {
+        bounding_qubits = steps
+            .iter()
+            .flat_map(|s| s.qubits.iter().copied())
+            .collect();
+    }
+    bounding_qubits.sort_unstable();
+    bounding_qubits.dedup();
+
+    CriticalPath {
+        steps,
+        total_duration: schedule.total_duration,
+        bounding_qubits,
+    }
+}
+
+impl std::fmt::Display for CriticalPath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "Critical path: {} gates, {} dt, bound by qubits {:?}",
+            self.steps.len(),
+            self.total_duration,
+            self.bounding_qubits
+        )?;
+        for s in &self.steps {
+            writeln!(
+                f,
+                "  [{:>6} - {:>6}] {:8} qubits={:?}",
+                s.start, s.end, s.name, s.qubits
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objective::{CircuitMetrics, OptimizationObjective};
+    use crate::passes::PassRegistry;
+    use crate::UniversalTranspiler;
+
+    fn backend(x_duration: u64, constraints: TimingConstraints) -> BackendSpec {
+        BackendSpec {
+            name: "timed".to_string(),
+            num_qubits: 1,
+            gate_durations: [("x", x_duration), ("h", 16), ("measure", 64)]
+                .iter()
+                .map(|&(name, d)| (name.to_string(), d))
+                .collect(),
+            timing_constraints: constraints,
+            ..Default::default()
+        }
+    }
+
+    fn circuit(gates: Vec<Gate>) -> PhysicalCircuit {
+        PhysicalCircuit::assume_physical(QuantumCircuit::new(1, 0).with_gates(gates))
+    }
+
+    fn delays(circuit: &QuantumCircuit) -> Vec<Param> {
+        circuit
+            .gates
+            .iter()
+            .filter(|g| g.name == "delay")
+            .map(|g| g.params[0].clone())
+            .collect()
+    }
+
+    #[test]
+    fn gates_are_padded_onto_the_alignment_grid() {
+        let constraints = TimingConstraints {
+            granularity: 16,
+            min_length: 16,
+            pulse_alignment: 32,
+            ..Default::default()
+        };
+        let pass = ConstrainTimingPass {
+            backend: backend(16, constraints),
+        };
+        let x_then_h = circuit(vec![
+            Gate::new("x", vec![0], vec![]),
+            Gate::new("h", vec![0], vec![]),
+        ]);
+        let constrained = pass.schedule(&x_then_h).unwrap();
+        let names: Vec<&str> = constrained.gates.iter().map(|g| g.name.as_str()).collect();
+        assert_eq!(names, ["x", "delay", "h"]);
+        assert_eq!(delays(&constrained), [Param::Duration(Duration::dt(16))]);
+    }
+
+    #[test]
+    fn delays_are_stretched_to_legal_lengths() {
+        let constraints = TimingConstraints {
+            granularity: 16,
+            min_length: 64,
+            ..Default::default()
+        };
+        let pass = ConstrainTimingPass {
+            backend: backend(16, constraints),
+        };
+        let delay = Gate::new("delay", vec![0], vec![Param::Duration(Duration::dt(10))]);
+        let constrained = pass.schedule(&circuit(vec![delay])).unwrap();
+        assert_eq!(delays(&constrained), [Param::Duration(Duration::dt(64))]);
+    }
+
+    #[test]
+    fn unreachable_alignment_is_an_error() {
+        // After a 10 dt gate every aligned start is 6 dt off the granularity,
+        // so no padding delay is legal.
+        let constraints = TimingConstraints {
+            granularity: 16,
+            min_length: 64,
+            pulse_alignment: 16,
+            ..Default::default()
+        };
+        let pass = ConstrainTimingPass {
+            backend: backend(10, constraints),
+        };
+        let x_then_h = circuit(vec![
+            Gate::new("x", vec![0], vec![]),
+            Gate::new("h", vec![0], vec![]),
+        ]);
+        assert!(pass.schedule(&x_then_h).is_err());
+    }
+
+    #[test]
+    fn the_transpiler_keeps_scheduling_output_the_objective_would_reject() {
+        let constraints = TimingConstraints {
+            granularity: 16,
+            min_length: 16,
+            pulse_alignment: 32,
+            ..Default::default()
+        };
+        let backend = BackendSpec {
+            native_gates: ["x", "h"].iter().map(|s| s.to_string()).collect(),
+            ..backend(16, constraints)
+        };
+        let only = ["constrain-timing".to_string()];
+        let pipeline = PassRegistry::default()
+            .pipeline(
+                &backend,
+                &OptimizationObjective::default(),
+                Some(&only),
+                &[],
+            )
+            .unwrap();
+        let transpiler = UniversalTranspiler::new().with_pipeline(pipeline);
+        let x_then_h = QuantumCircuit::new(1, 0).with_gates(vec![
+            Gate::new("x", vec![0], vec![]),
+            Gate::new("h", vec![0], vec![]),
+        ]);
+        let result = transpiler.transpile_circuit(x_then_h, &backend).unwrap();
+        assert_eq!(delays(&result.circuit), [Param::Duration(Duration::dt(16))]);
+    }
+
+    #[test]
+    fn crosstalk_paired_gates_are_serialized() {
+        let backend = BackendSpec {
+            name: "paired".to_string(),
+            num_qubits: 4,
+            coupling_map: vec![(0, 1), (1, 2), (2, 3)],
+            gate_durations: [("cx".to_string(), 100)].into_iter().collect(),
+            crosstalk_pairs: vec![((0, 1), (3, 2))],
+            ..Default::default()
+        };
+        let pass = CrosstalkAwareSchedulingPass { backend };
+        let parallel =
+            PhysicalCircuit::assume_physical(QuantumCircuit::new(4, 0).with_gates(vec![
+                Gate::new("cx", vec![0, 1], vec![]),
+                Gate::new("cx", vec![2, 3], vec![]),
+            ]));
+        let scheduled = pass.schedule(&parallel).unwrap();
+        let names: Vec<&str> = scheduled.gates.iter().map(|g| g.name.as_str()).collect();
+        assert_eq!(names, ["cx", "delay", "delay", "cx"]);
+        assert_eq!(
+            delays(&scheduled),
+            [
+                Param::Duration(Duration::dt(100)),
+                Param::Duration(Duration::dt(100))
+            ]
+        );
+        // The padding costs depth but carries no gate error.
+        let before = CircuitMetrics::of(&parallel);
+        let after = CircuitMetrics::of(&scheduled);
+        assert!(after.depth > before.depth);
+        assert_eq!(after.estimated_error, before.estimated_error);
+    }
+}
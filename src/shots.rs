@@ -0,0 +1,186 @@
+// ============================================================================
+// SHOT ALLOCATION
+// ============================================================================
+
+/// One circuit of a batch whose results are combined into a single estimate,
+/// e.g. a measurement group of a Hamiltonian: the batch estimates
+/// `sum_i weight_i * mean_i`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShotRequest {
+    pub label: String,
+    /// Variance of the circuit's estimator from a single shot.
+    pub single_shot_variance: f64,
+    pub weight: f64,
+    /// Fewest shots the circuit may receive (0 lets it be skipped).
+    pub min_shots: usize,
+}
+
+impl ShotRequest {
+    pub fn new(label: &str, single_shot_variance: f64, weight: f64) -> Self {
+        Self {
+            label: label.to_string(),
+            single_shot_variance,
+            weight,
+            min_shots: 1,
+        }
+    }
+
+    /// `|weight| * sigma`, the quantity optimal allocations are proportional to.
+    fn importance(&self) -> f64 {
+        self.weight.abs() * self.single_shot_variance.max(0.0).sqrt()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShotAllocation {
+    /// Shots per request, in request order.
+    pub shots: Vec<usize>,
+    pub total_shots: usize,
+    /// Variance of the combined estimate, `sum_i w_i^2 sigma_i^2 / n_i`.
+    /// Infinite if a request with non-zero importance got no shots.
+    pub estimated_variance: f64,
+}
+
+impl ShotAllocation {
+    fn new(requests: &[ShotRequest], shots: Vec<usize>) -> Self {
+        let estimated_variance = requests
+            .iter()
+            .zip(&shots)
+            .map(|(r, &n)| {
+                let v = r.weight * r.weight * r.single_shot_variance;
+                match (n, v > 0.0) {
+                    (_, false) => 0.0,
+                    (0, true) => f64::INFINITY,
+                    (n, true) => v / n as f64,
+                }
+            })
+            .sum();
+        Self {
+            total_shots: shots.iter().sum(),
+            shots,
+            estimated_variance,
+        }
+    }
+}
+
+/// Splits `budget` shots across `requests` to minimize the variance of the
+/// combined estimate. Without minimums the optimum gives each circuit shots
+/// in proportion to `|weight| * sigma`; requests whose share falls below
+/// their minimum are pinned to it and the rest is re-split.
+pub fn allocate_shots(requests: &[ShotRequest], budget: usize) -> Result<ShotAllocation, String> {
+    validate(requests)?;
+    let required: usize = requests.iter().map(|r| r.min_shots).sum();
+    if required > budget {
+        return Err(format!(
+            "Shot budget {budget} is below the {required} shots the minimums require"
+        ));
+    }
+
+    let mut pinned = vec![false; requests.len()];
+    let ideal = loop {
+        let free_budget = budget
+            - requests
+                .iter()
+                .zip(&pinned)
+                .filter(|(_, &p)| p)
+                .map(|(r, _)| r.min_shots)
+                .sum::<usize>();
+        let free_importance: f64 = requests
+            .iter()
+            .zip(&pinned)
+            .filter(|(_, &p)| !p)
+            .map(|(r, _)| r.importance())
+            .sum();
+        let share: Vec<f64> = requests
+            .iter()
+            .zip(&pinned)
+            .map(|(r, &p)| {
+                if p {
+                    r.min_shots as f64
+                } else if free_importance > 0.0 {
+                    free_budget as f64 * r.importance() / free_importance
+                } else {
+                    0.0
+                }
+            })
+            .collect();
+        let mut changed = false;
+        for (i, r) in requests.iter().enumerate() {
+            if !pinned[i] && share[i] < r.min_shots as f64 {
+                pinned[i] = true;
+                changed = true;
+            }
+        }
+        if !changed {
+            break share;
+        }
+    };
+    Ok(ShotAllocation::new(
+        requests,
+        round_to_total(&ideal, budget, requests),
+    ))
+}
+
+/// Fewest total shots whose combined estimate reaches `target_variance`,
+/// allocated optimally. Minimums are honored on top of the optimum.
+pub fn shots_for_target_variance(
+    requests: &[ShotRequest],
+    target_variance: f64,
+) -> Result<ShotAllocation, String> {
+    validate(requests)?;
+    if target_variance <= 0.0 {
+        return Err(format!(
+            "Target variance must be positive, got {target_variance}"
+        ));
+    }
+    // With n_i = k * importance_i the variance is (sum importance)^2 / k.
+    let total_importance: f64 = requests.iter().map(ShotRequest::importance).sum();
+    let k = total_importance / target_variance;
+    let shots = requests
+        .iter()
+        .map(|r| ((k * r.importance()).ceil() as usize).max(r.min_shots))
+        .collect();
+    Ok(ShotAllocation::new(requests, shots))
+}
+
+fn validate(requests: &[ShotRequest]) -> Result<(), String> {
+    for r in requests {
+        if !r.single_shot_variance.is_finite() || r.single_shot_variance < 0.0 {
+            return Err(format!(
+                "Request {} has invalid variance {}",
+                r.label, r.single_shot_variance
+            ));
+        }
+        if !r.weight.is_finite() {
+            return Err(format!(
+                "Request {} has invalid weight {}",
+                r.label, r.weight
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Rounds fractional shares down, then hands the leftover shots to the
+/// largest remainders so the result sums to `total`.
+fn round_to_total(ideal: &[f64], total: usize, requests: &[ShotRequest]) -> Vec<usize> {
+    let mut shots: Vec<usize> = ideal
+        .iter()
+        .zip(requests)
+        .map(|(&x, r)| (x.floor() as usize).max(r.min_shots))
+        .collect();
+    let mut order: Vec<usize> = (0..ideal.len()).collect();
+    order.sort_by(|&a, &b| {
+        let (ra, rb) = (ideal[a] - ideal[a].floor(), ideal[b] - ideal[b].floor());
+        rb.total_cmp(&ra).then(a.cmp(&b))
+    });
+    let mut assigned: usize = shots.iter().sum();
+    // Cycle in remainder order in case rounding left more than one shot each.
+    let mut i = 0;
+    while assigned < total && !order.is_empty() {
+        shots[order[i % order.len()]] += 1;
+        assigned += 1;
+        i += 1;
+    }
+    shots
+}
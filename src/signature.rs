@@ -0,0 +1,226 @@
+use alloc::collections::BTreeMap;
+use core::fmt;
+
+use crate::classical::ClassicalRegister;
+use crate::prelude::*;
+use crate::QuantumCircuit;
+
+// ============================================================================
+// CIRCUIT SIGNATURES (OPENQASM 3 INPUT / OUTPUT DECLARATIONS)
+// ============================================================================
+
+/// Type of an `input`/`output` variable. Widths are `None` when omitted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClassicalType {
+    Float(Option<u32>),
+    Angle(Option<u32>),
+    Int(Option<u32>),
+    Uint(Option<u32>),
+    Bool,
+    /// `bit` (a single bit) or `bit[n]`.
+    Bit(Option<usize>),
+}
+
+impl ClassicalType {
+    /// Parses `float`, `float[64]`, `angle[20]`, `bit[3]`, `bool`, ...
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let text = text.trim();
+        let (base, width) = match text.split_once('[') {
+            Some((base, rest)) => {
+                let width = rest
+                    .trim_end_matches(']')
+                    .trim()
+                    .parse::<u32>()
+                    .map_err(|_| format!("Invalid width in type {text}"))?;
+                if width == 0 {
+                    return Err(format!("Type {text} must be at least one bit wide"));
+                }
+                (base.trim(), Some(width))
+            }
+            None => (text, None),
+        };
+        Ok(match (base, width) {
+            ("float", w) => ClassicalType::Float(w),
+            ("angle", w) => ClassicalType::Angle(w),
+            ("int", w) => ClassicalType::Int(w),
+            ("uint", w) => ClassicalType::Uint(w),
+            ("bool", None) => ClassicalType::Bool,
+            ("bit", w) => ClassicalType::Bit(w.map(|w| w as usize)),
+            _ => return Err(format!("Unsupported input/output type {text}")),
+        })
+    }
+
+    /// Number of classical bits if this is a bit type.
+    pub fn bit_size(&self) -> Option<usize> {
+        match self {
+            ClassicalType::Bit(size) => Some(size.unwrap_or(1)),
+            _ => None,
+        }
+    }
+
+    /// True if any value is acceptable, rather than only whole numbers.
+    fn accepts_fractions(&self) -> bool {
+        matches!(self, ClassicalType::Float(_) | ClassicalType::Angle(_))
+    }
+}
+
+impl fmt::Display for ClassicalType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (base, width) = match self {
+            ClassicalType::Float(w) => ("float", w.map(|w| w as usize)),
+            ClassicalType::Angle(w) => ("angle", w.map(|w| w as usize)),
+            ClassicalType::Int(w) => ("int", w.map(|w| w as usize)),
+            ClassicalType::Uint(w) => ("uint", w.map(|w| w as usize)),
+            ClassicalType::Bool => ("bool", None),
+            ClassicalType::Bit(w) => ("bit", *w),
+        };
+        match width {
+            Some(w) => write!(f, "{base}[{w}]"),
+            None => write!(f, "{base}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct IoDeclaration {
+    pub name: String,
+    pub ty: ClassicalType,
+}
+
+/// Named, typed inputs and outputs of a circuit. Inputs are bound by name
+/// via `QuantumCircuit::bind_inputs`; bit outputs are classical registers
+/// whose values `QuantumCircuit::read_outputs` extracts from a result.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct CircuitSignature {
+    pub inputs: Vec<IoDeclaration>,
+    pub outputs: Vec<IoDeclaration>,
+}
+
+impl CircuitSignature {
+    pub fn is_empty(&self) -> bool {
+        self.inputs.is_empty() && self.outputs.is_empty()
+    }
+
+    pub fn input(&self, name: &str) -> Option<&IoDeclaration> {
+        self.inputs.iter().find(|d| d.name == name)
+    }
+
+    pub fn output(&self, name: &str) -> Option<&IoDeclaration> {
+        self.outputs.iter().find(|d| d.name == name)
+    }
+
+    /// Parses `input float theta;` or `output bit[2] result;`, returning
+    /// whether it is an input along with the declaration.
+    pub fn parse_declaration(line: &str) -> Result<(bool, IoDeclaration), String> {
+        let (is_input, rest) = if let Some(rest) = line.strip_prefix("input ") {
+            (true, rest)
+        } else if let Some(rest) = line.strip_prefix("output ") {
+            (false, rest)
+        } else {
+            return Err(format!("Not an input/output declaration: {line}"));
+        };
+        let rest = rest.trim().trim_end_matches(';').trim();
+        let split = rest
+            .rfind(|c: char| c.is_whitespace() || c == ']')
+            .ok_or_else(|| format!("Malformed declaration: {line}"))?;
+        let (ty, name) = (rest[..=split].trim(), rest[split + 1..].trim());
+        if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+            return Err(format!("Malformed declaration: {line}"));
+        }
+        Ok((
+            is_input,
+            IoDeclaration {
+                name: name.to_string(),
+                ty: ClassicalType::parse(ty)?,
+            },
+        ))
+    }
+
+    /// Adds a parsed declaration, rejecting duplicate names. Bit outputs are
+    /// returned as the classical register they declare.
+    pub fn declare(
+        &mut self,
+        is_input: bool,
+        decl: IoDeclaration,
+    ) -> Result<Option<ClassicalRegister>, String> {
+        if self.input(&decl.name).is_some() || self.output(&decl.name).is_some() {
+            return Err(format!("{} is declared more than once", decl.name));
+        }
+        let register = match (is_input, decl.ty.bit_size()) {
+            (false, Some(size)) => Some(ClassicalRegister {
+                name: decl.name.clone(),
+                size,
+            }),
+            _ => None,
+        };
+        if is_input {
+            self.inputs.push(decl);
+        } else {
+            self.outputs.push(decl);
+        }
+        Ok(register)
+    }
+}
+
+impl QuantumCircuit {
+    /// Binds the circuit's declared inputs by name. Every input must be given
+    /// a value, integer-typed inputs need whole numbers, and names that
+    /// aren't declared inputs are rejected. Bound inputs leave the signature.
+    pub fn bind_inputs(&self, values: &BTreeMap<String, f64>) -> Result<QuantumCircuit, String> {
+        for name in values.keys() {
+            if self.signature.input(name).is_none() {
+                return Err(format!("{name} is not an input of this circuit"));
+            }
+        }
+        for decl in &self.signature.inputs {
+            let value = values
+                .get(&decl.name)
+                .ok_or_else(|| format!("No value given for input {}", decl.name))?;
+            if !decl.ty.accepts_fractions() && value % 1.0 != 0.0 {
+                return Err(format!(
+                    "Input {} of type {} needs a whole number, got {value}",
+                    decl.name, decl.ty
+                ));
+            }
+        }
+        let mut bound = self.bind_parameters(values)?;
+        bound.signature.inputs.clear();
+        Ok(bound)
+    }
+
+    /// Values of the bit outputs, read from a bitstring over all classical
+    /// bits in register declaration order with bit 0 rightmost.
+    pub fn read_outputs(&self, clbits: &str) -> Result<BTreeMap<String, u64>, String> {
+        if clbits.len() != self.num_clbits {
+            return Err(format!(
+                "Expected {} classical bits, got {} in {clbits}",
+                self.num_clbits,
+                clbits.len()
+            ));
+        }
+        let bits: Vec<char> = clbits.chars().rev().collect();
+        let mut values = BTreeMap::new();
+        for decl in &self.signature.outputs {
+            let Some(size) = decl.ty.bit_size() else {
+                continue;
+            };
+            let start = self
+                .cregs
+                .iter()
+                .take_while(|r| r.name != decl.name)
+                .map(|r| r.size)
+                .sum::<usize>();
+            let mut value = 0u64;
+            for i in (0..size).rev() {
+                let bit = match bits.get(start + i) {
+                    Some('0') => 0,
+                    Some('1') => 1,
+                    _ => return Err(format!("Invalid bitstring {clbits}")),
+                };
+                value = (value << 1) | bit;
+            }
+            values.insert(decl.name.clone(), value);
+        }
+        Ok(values)
+    }
+}
@@ -0,0 +1,154 @@
+use crate::characterization::SplitMix64;
+use crate::composite::UnrollPass;
+use crate::control_flow::ControlFlow;
+use crate::linalg::{gate_matrix, Complex, Matrix};
+use crate::{run_pass, Counts, Gate, QuantumCircuit};
+
+// ============================================================================
+// STATE-VECTOR SIMULATION
+// ============================================================================
+
+/// Something that runs circuits and reports their outcomes, used to check
+/// transpiled circuits against their source. Hardware-accelerated simulators
+/// implement this next to the CPU `StateVectorSimulator`.
+pub trait SimulatorBackend {
+    fn name(&self) -> &'static str;
+
+    /// Most qubits the backend can simulate.
+    fn max_qubits(&self) -> usize;
+
+    /// Amplitudes after running the unitary part of `circuit` from |0...0>,
+    /// with qubit 0 as bit 0 of the index. Measurements are ignored.
+    fn statevector(&self, circuit: &QuantumCircuit) -> Result<Vec<Complex>, String>;
+
+    /// Outcomes of `shots` runs, as bitstrings over all classical bits with
+    /// bit 0 rightmost. Measurements must come after every other gate on
+    /// their qubits.
+    fn sample(&self, circuit: &QuantumCircuit, shots: usize, seed: u64) -> Result<Counts, String> {
+        let measured = terminal_measurements(circuit)?;
+        let state = self.statevector(circuit)?;
+        let mut cumulative = Vec::with_capacity(state.len());
+        let mut total = 0.0;
+        for amplitude in &state {
+            total += amplitude.norm_sqr();
+            cumulative.push(total);
+        }
+        let mut rng = SplitMix64(seed);
+        let mut counts = Counts::new();
+        for _ in 0..shots {
+            let r = rng.unit() * total;
+            let index = cumulative.partition_point(|&c| c <= r).min(state.len() - 1);
+            let mut bits = vec!['0'; circuit.num_clbits];
+            for &(qubit, clbit) in &measured {
+                if index >> qubit & 1 == 1 {
+                    bits[circuit.num_clbits - 1 - clbit] = '1';
+                }
+            }
+            *counts.entry(bits.into_iter().collect()).or_insert(0) += 1;
+        }
+        Ok(counts)
+    }
+}
+
+/// `(qubit, flat clbit index)` of every measurement, checking that no gate
+/// follows one on the same qubit.
+fn terminal_measurements(circuit: &QuantumCircuit) -> Result<Vec<(usize, usize)>, String> {
+    let mut measured = Vec::new();
+    for (i, g) in circuit.gates.iter().enumerate() {
+        if g.name != "measure" {
+            continue;
+        }
+        if circuit.gates[i + 1..]
+            .iter()
+            .any(|later| later.qubits.iter().any(|q| g.qubits.contains(q)))
+        {
+            return Err(format!(
+                "Measurement of qubit {:?} is followed by other operations",
+                g.qubits
+            ));
+        }
+        for (&qubit, bit) in g.qubits.iter().zip(&g.clbits) {
+            let index = circuit
+                .clbit_index(bit)
+                .ok_or_else(|| format!("Unknown classical bit {}[{}]", bit.register, bit.index))?;
+            measured.push((qubit, index));
+        }
+    }
+    Ok(measured)
+}
+
+/// Dense state-vector simulation on the CPU, applying gates through the
+/// installed `LinalgBackend`. Memory grows as 16 bytes times 2^qubits.
+#[derive(Debug, Clone, Copy)]
+pub struct StateVectorSimulator {
+    pub max_qubits: usize,
+}
+
+impl Default for StateVectorSimulator {
+    /// Up to 24 qubits, a 256 MiB state.
+    fn default() -> Self {
+        Self { max_qubits: 24 }
+    }
+}
+
+/// Calls `f` with the matrix and qubits of each gate of `gates` in turn,
+/// the way the simulators run them: measurements, barriers and delays are
+/// skipped and protected blocks run inline; other control flow, classical
+/// conditions and gates without a known matrix are errors.
+pub fn for_each_unitary(
+    gates: &[Gate],
+    f: &mut impl FnMut(&Matrix, &[usize]),
+) -> Result<(), String> {
+    for g in gates {
+        if let Some(block) = &g.block {
+            match block.as_ref() {
+                ControlFlow::Protected { body } => for_each_unitary(&body.gates, f)?,
+                _ => return Err(format!("Cannot simulate control-flow block {}", g.name)),
+            }
+            continue;
+        }
+        if g.condition.is_some() {
+            return Err(format!(
+                "Cannot simulate classically conditioned {}",
+                g.name
+            ));
+        }
+        match g.name.as_str() {
+            "measure" | "barrier" | "delay" => {}
+            name => {
+                let u =
+                    gate_matrix(g).ok_or_else(|| format!("No unitary known for gate {name}"))?;
+                f(&u, &g.qubits);
+            }
+        }
+    }
+    Ok(())
+}
+
+impl SimulatorBackend for StateVectorSimulator {
+    fn name(&self) -> &'static str {
+        "statevector"
+    }
+
+    fn max_qubits(&self) -> usize {
+        self.max_qubits
+    }
+
+    fn statevector(&self, circuit: &QuantumCircuit) -> Result<Vec<Complex>, String> {
+        if circuit.num_qubits > self.max_qubits {
+            return Err(format!(
+                "Circuit has {} qubits; the {} simulator handles at most {}",
+                circuit.num_qubits,
+                self.name(),
+                self.max_qubits
+            ));
+        }
+        let circuit = run_pass(&UnrollPass, circuit);
+        let mut state = vec![Complex::ZERO; 1 << circuit.num_qubits];
+        state[0] = Complex::ONE;
+        for_each_unitary(&circuit.gates, &mut |u, qubits| {
+            u.apply_to_state(qubits, &mut state)
+        })?;
+        Ok(state)
+    }
+}
@@ -0,0 +1,122 @@
+use std::collections::VecDeque;
+
+use crate::classical::{ClassicalBit, ClassicalExpr, ClassicalOp, ClassicalRegister};
+use crate::layout::{Layout, PhysicalQubit};
+use crate::{BackendSpec, Gate, QuantumCircuit};
+
+// ============================================================================
+// TELEPORTATION-BASED ROUTING (MEASUREMENT-BASED CNOT BRIDGING)
+// ============================================================================
+
+/// Register holding the ancilla measurement outcomes of bridged CNOTs, named
+/// so it doesn't clash with the circuit's own registers. It starts empty and
+/// grows to the longest bridge; bridges reuse its bits.
+pub fn ancilla_register(circuit: &QuantumCircuit) -> ClassicalRegister {
+    let taken = |name: &str| {
+        circuit.cregs.iter().any(|r| r.name == name) || circuit.signature.input(name).is_some()
+    };
+    let mut name = "bridge".to_string();
+    let mut suffix = 0;
+    while taken(&name) {
+        suffix += 1;
+        name = format!("bridge{suffix}");
+    }
+    ClassicalRegister { name, size: 0 }
+}
+
+/// Shortest chain of unoccupied physical qubits linking `from` to `to`,
+/// excluding the endpoints. `None` if no such chain exists.
+fn free_path(
+    from: PhysicalQubit,
+    to: PhysicalQubit,
+    backend: &BackendSpec,
+    layout: &Layout,
+) -> Option<Vec<PhysicalQubit>> {
+    let n = backend.num_qubits;
+    let mut parent = vec![None; n];
+    let mut visited = vec![false; n];
+    visited[from.0] = true;
+    let mut queue = VecDeque::from([from]);
+    while let Some(u) = queue.pop_front() {
+        if u != from && backend.are_coupled(u, to) {
+            let mut path = vec![u];
+            while let Some(p) = parent[path[path.len() - 1].0] {
+                if p == from {
+                    break;
+                }
+                path.push(p);
+            }
+            path.reverse();
+            return Some(path);
+        }
+        for v in (0..n).map(PhysicalQubit) {
+            if !visited[v.0]
+                && v != to
+                && layout.virtual_at(v).is_none()
+                && backend.are_coupled(u, v)
+            {
+                visited[v.0] = true;
+                parent[v.0] = Some(u);
+                queue.push_back(v);
+            }
+        }
+    }
+    None
+}
+
+/// Replaces a long-range `cx` by a measurement-based bridge when a chain of
+/// free qubits links control and target and the bridge needs no more CNOTs
+/// than the SWAP chain it replaces. Returns `None` to fall back to swapping.
+///
+/// The control's value is fanned out along the chain (`cx` from each qubit to
+/// the next, ending on the target), then every ancilla is measured in the X
+/// basis and a `1` outcome is corrected by `z` on the control. Ancillas are
+/// reset afterwards, so free qubits stay in |0>, and the layout is unchanged.
+pub fn bridge_cx(
+    g: &Gate,
+    control: PhysicalQubit,
+    target: PhysicalQubit,
+    backend: &BackendSpec,
+    dist: &[Vec<usize>],
+    layout: &Layout,
+    register: &mut ClassicalRegister,
+) -> Option<Vec<Gate>> {
+    if g.name != "cx"
+        || g.condition.is_some()
+        || g.composite.is_some()
+        || backend.are_coupled(control, target)
+    {
+        return None;
+    }
+    let d = dist[control.0][target.0];
+    if d == usize::MAX {
+        return None;
+    }
+    let ancillas = free_path(control, target, backend, layout)?;
+    // A SWAP chain costs three CNOTs per hop; the bridge one per qubit.
+    if ancillas.len() > 3 * (d - 1) {
+        return None;
+    }
+
+    register.size = register.size.max(ancillas.len());
+    let mut chain = vec![control];
+    chain.extend(&ancillas);
+    chain.push(target);
+    let mut out: Vec<Gate> = chain
+        .windows(2)
+        .map(|w| Gate::new("cx", vec![w[0].0, w[1].0], vec![]))
+        .collect();
+    for (i, a) in ancillas.iter().enumerate() {
+        let bit = ClassicalBit::new(&register.name, i);
+        let flipped = ClassicalExpr::Binary(
+            ClassicalOp::Eq,
+            Box::new(ClassicalExpr::Bit(bit.register.clone(), i)),
+            Box::new(ClassicalExpr::Int(1)),
+        );
+        out.push(Gate::new("h", vec![a.0], vec![]));
+        out.push(Gate::measure(a.0, bit));
+        out.push(Gate::new("z", vec![control.0], vec![]).with_condition(flipped));
+        out.push(Gate::new("reset", vec![a.0], vec![]));
+    }
+    Some(out)
+}
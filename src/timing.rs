@@ -0,0 +1,122 @@
+#[cfg(feature = "std")]
+use crate::duration::Duration;
+#[cfg(feature = "std")]
+use crate::prelude::*;
+#[cfg(feature = "std")]
+use crate::{BackendSpec, Gate, Param, QuantumCircuit};
+
+// ============================================================================
+// TIMED EXPERIMENT CIRCUITS (FIXED DELAYS AND ALIGNMENT)
+// ============================================================================
+
+/// Whether the transpiler may move gates in time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Timing {
+    /// Gates may be reordered, merged or removed.
+    #[default]
+    Flexible,
+    /// Timing is part of the experiment, as in spectroscopy or T1/T2
+    /// sequences: gates are only expanded and mapped to physical qubits,
+    /// never reordered or removed, and no swaps are inserted.
+    Fixed,
+}
+
+/// Builds a `Timing::Fixed` circuit gate by gate while tracking when each
+/// qubit becomes free, in `dt` of `backend`, with gates starting as soon as
+/// possible as `schedule_asap` places them.
+#[cfg(feature = "std")]
+pub struct TimedCircuitBuilder {
+    backend: BackendSpec,
+    circuit: QuantumCircuit,
+    clock: Vec<u64>,
+}
+
+#[cfg(feature = "std")]
+impl TimedCircuitBuilder {
+    pub fn new(num_qubits: usize, num_clbits: usize, backend: &BackendSpec) -> Self {
+        let mut circuit = QuantumCircuit::new(num_qubits, num_clbits);
+        circuit.timing = Timing::Fixed;
+        Self {
+            backend: backend.clone(),
+            circuit,
+            clock: vec![0; num_qubits],
+        }
+    }
+
+    /// When `qubit` is next free.
+    pub fn time(&self, qubit: usize) -> u64 {
+        self.clock.get(qubit).copied().unwrap_or(0)
+    }
+
+    fn check_qubits(&self, qubits: &[usize]) -> Result<(), String> {
+        match qubits.iter().find(|&&q| q >= self.clock.len()) {
+            Some(q) => Err(format!(
+                "Qubit {q} is outside the {}-qubit circuit",
+                self.clock.len()
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Appends `gate`, starting once all its qubits are free.
+    pub fn gate(mut self, gate: Gate) -> Result<Self, String> {
+        self.check_qubits(&gate.qubits)?;
+        if gate.block.is_some() {
+            return Err(format!("Cannot time control-flow block {}", gate.name));
+        }
+        let duration = self.backend.gate_duration(&gate).ok_or_else(|| {
+            format!(
+                "Backend {} has no duration for gate {}",
+                self.backend.name, gate.name
+            )
+        })?;
+        let end = gate
+            .qubits
+            .iter()
+            .map(|&q| self.clock[q])
+            .max()
+            .unwrap_or(0)
+            + duration;
+        gate.qubits.iter().for_each(|&q| self.clock[q] = end);
+        self.circuit.gates.push(gate);
+        Ok(self)
+    }
+
+    /// Idles `qubit` for `duration` dt.
+    pub fn delay(self, qubit: usize, duration: u64) -> Result<Self, String> {
+        if duration == 0 {
+            return self.check_qubits(&[qubit]).map(|_| self);
+        }
+        self.gate(Gate::new(
+            "delay",
+            vec![qubit],
+            vec![Param::Duration(Duration::dt(duration))],
+        ))
+    }
+
+    /// Pads `qubits` with delays until all are free at the same time, so the
+    /// next gates on them start together.
+    pub fn align(mut self, qubits: &[usize]) -> Result<Self, String> {
+        self.check_qubits(qubits)?;
+        let latest = qubits.iter().map(|&q| self.clock[q]).max().unwrap_or(0);
+        for &q in qubits {
+            let slack = latest - self.clock[q];
+            self = self.delay(q, slack)?;
+        }
+        Ok(self)
+    }
+
+    /// Aligns every qubit, then idles them all for `duration` dt.
+    pub fn delay_all(mut self, duration: u64) -> Result<Self, String> {
+        let all: Vec<usize> = (0..self.clock.len()).collect();
+        self = self.align(&all)?;
+        for q in all {
+            self = self.delay(q, duration)?;
+        }
+        Ok(self)
+    }
+
+    pub fn build(self) -> QuantumCircuit {
+        self.circuit
+    }
+}
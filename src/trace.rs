@@ -0,0 +1,140 @@
+use std::collections::BTreeMap;
+
+use crate::json::JsonValue;
+use crate::{QuantumCircuit, TranspilationResult, UniversalTranspiler};
+
+// ============================================================================
+// PASS TRACE (ONE JSON RECORD PER OPTIMIZATION PASS)
+// ============================================================================
+
+/// What one optimization pass did to the circuit.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PassRecord {
+    pub pass: String,
+    pub wall_time_ms: f64,
+    /// Whether the objective kept the pass's output.
+    pub accepted: bool,
+    pub depth_before: usize,
+    pub gate_count_before: usize,
+    /// Depth, gate count and gates by name of the pass's output.
+    pub depth: usize,
+    pub gate_count: usize,
+    pub gate_counts: BTreeMap<String, usize>,
+    /// Change in the count of each gate name the pass touched.
+    pub changes: BTreeMap<String, i64>,
+    /// Why the pass was skipped without running, if it was; the time is
+    /// then that of the check.
+    pub skipped: Option<String>,
+}
+
+/// Top-level gates by name; a control-flow block counts as one gate.
+fn gate_counts(circuit: &QuantumCircuit) -> BTreeMap<String, usize> {
+    let mut counts = BTreeMap::new();
+    for g in &circuit.gates {
+        *counts.entry(g.name.clone()).or_insert(0) += 1;
+    }
+    counts
+}
+
+impl PassRecord {
+    /// Record of a pass that turned `before` into `after`.
+    pub fn new(
+        pass: &str,
+        before: &QuantumCircuit,
+        after: &QuantumCircuit,
+        wall_time_ms: f64,
+        accepted: bool,
+    ) -> Self {
+        let counts_before = gate_counts(before);
+        let gate_counts = gate_counts(after);
+        let mut changes = BTreeMap::new();
+        for name in counts_before.keys().chain(gate_counts.keys()) {
+            let delta = *gate_counts.get(name).unwrap_or(&0) as i64
+                - *counts_before.get(name).unwrap_or(&0) as i64;
+            if delta != 0 {
+                changes.insert(name.clone(), delta);
+            }
+        }
+        Self {
+            pass: pass.to_string(),
+            wall_time_ms,
+            accepted,
+            depth_before: UniversalTranspiler::calculate_depth(before),
+            gate_count_before: before.gates.len(),
+            depth: UniversalTranspiler::calculate_depth(after),
+            gate_count: after.gates.len(),
+            gate_counts,
+            changes,
+            skipped: None,
+        }
+    }
+
+    /// Record of a pass skipped on `circuit` for `reason`.
+    pub fn skipped(
+        pass: &str,
+        circuit: &QuantumCircuit,
+        wall_time_ms: f64,
+        reason: String,
+    ) -> Self {
+        Self {
+            skipped: Some(reason),
+            ..Self::new(pass, circuit, circuit, wall_time_ms, false)
+        }
+    }
+
+    pub fn to_json(&self) -> JsonValue {
+        JsonValue::Object(self.fields())
+    }
+
+    fn fields(&self) -> Vec<(String, JsonValue)> {
+        let counts = |map: &BTreeMap<String, usize>| {
+            JsonValue::object(map.iter().map(|(k, &v)| (k.as_str(), v.into())))
+        };
+        [
+            ("pass", JsonValue::string(self.pass.as_str())),
+            ("wall_time_ms", self.wall_time_ms.into()),
+            ("accepted", self.accepted.into()),
+            ("depth_before", self.depth_before.into()),
+            ("depth", self.depth.into()),
+            ("gate_count_before", self.gate_count_before.into()),
+            ("gate_count", self.gate_count.into()),
+            ("gate_counts", counts(&self.gate_counts)),
+            (
+                "changes",
+                JsonValue::object(
+                    self.changes
+                        .iter()
+                        .map(|(k, &v)| (k.as_str(), (v as f64).into())),
+                ),
+            ),
+            (
+                "skipped",
+                self.skipped
+                    .as_deref()
+                    .map_or(JsonValue::Null, JsonValue::string),
+            ),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v))
+        .collect()
+    }
+}
+
+impl TranspilationResult {
+    /// The pass trace as JSON lines, one per pass, each starting with the
+    /// `run` fields (e.g. the file and backend) so traces of many runs can
+    /// be concatenated. Empty unless the transpiler recorded a trace.
+    pub fn trace_jsonl(&self, run: &[(&str, JsonValue)]) -> String {
+        self.trace
+            .iter()
+            .map(|record| {
+                let line = JsonValue::object(
+                    run.iter()
+                        .map(|(k, v)| (k.to_string(), v.clone()))
+                        .chain(record.fields()),
+                );
+                format!("{line}\n")
+            })
+            .collect()
+    }
+}
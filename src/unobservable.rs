@@ -0,0 +1,105 @@
+use crate::commutation::DIAGONAL_GATES;
+use crate::{Gate, OptimizationPass, QuantumCircuit};
+
+// ============================================================================
+// REMOVAL OF GATES MEASUREMENTS CANNOT SEE
+// ============================================================================
+
+/// Removes gates that cannot change any measurement outcome: diagonal gates
+/// whose qubits are next measured in the Z basis (they commute with the
+/// measurement and only change phases), and gates on qubits that are never
+/// measured afterwards, nor interact with a qubit that is. Circuits without
+/// any measurement are left alone, since all their qubits may be outputs.
+pub struct UnobservableGateRemovalPass;
+
+/// What the rest of the circuit does with a qubit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Future {
+    /// Nothing observable depends on its state.
+    Unobserved,
+    /// Only its Z-basis populations are observed.
+    ZMeasured,
+    Observed,
+}
+
+/// Qubits a gate acts on, including those used inside its block bodies.
+fn touched_qubits(g: &Gate, out: &mut Vec<usize>) {
+    out.extend(&g.qubits);
+    if let Some(block) = &g.block {
+        for body in block.bodies() {
+            body.gates
+                .iter()
+                .for_each(|inner| touched_qubits(inner, out));
+        }
+    }
+}
+
+fn measures(gates: &[Gate]) -> bool {
+    gates.iter().any(|g| {
+        g.name == "measure"
+            || g.block
+                .as_ref()
+                .is_some_and(|b| b.bodies().iter().any(|body| measures(&body.gates)))
+    })
+}
+
+impl OptimizationPass for UnobservableGateRemovalPass {
+    fn name(&self) -> &str {
+        "unobservable-removal"
+    }
+
+    fn optimize(&self, circuit: &QuantumCircuit) -> QuantumCircuit {
+        if !measures(&circuit.gates) {
+            return circuit.clone();
+        }
+        let width = circuit
+            .gates
+            .iter()
+            .flat_map(|g| g.qubits.iter().map(|&q| q + 1))
+            .max()
+            .unwrap_or(0);
+        let mut future = vec![Future::Unobserved; circuit.num_qubits.max(width)];
+        let mut kept = Vec::with_capacity(circuit.gates.len());
+        // Walk backwards, so each gate knows what happens to its qubits later.
+        for g in circuit.gates.iter().rev() {
+            if g.block.is_some() || g.composite.is_some() {
+                let mut qubits = Vec::new();
+                touched_qubits(g, &mut qubits);
+                qubits
+                    .into_iter()
+                    .for_each(|q| future[q] = Future::Observed);
+                kept.push(g.clone());
+                continue;
+            }
+            match g.name.as_str() {
+                "measure" => g.qubits.iter().for_each(|&q| future[q] = Future::ZMeasured),
+                "reset" if g.condition.is_none() => g
+                    .qubits
+                    .iter()
+                    .for_each(|&q| future[q] = Future::Unobserved),
+                "barrier" | "delay" => {}
+                name => {
+                    let diagonal = DIAGONAL_GATES.contains(&name);
+                    let unobservable = g.qubits.iter().all(|&q| match future[q] {
+                        Future::Unobserved => true,
+                        Future::ZMeasured => diagonal,
+                        Future::Observed => false,
+                    });
+                    if unobservable {
+                        continue;
+                    }
+                    g.qubits.iter().for_each(|&q| future[q] = Future::Observed);
+                }
+            }
+            kept.push(g.clone());
+        }
+        kept.reverse();
+        circuit.with_gates(kept)
+    }
+
+    /// Whether a body's gates are observable depends on what follows the
+    /// block.
+    fn recurse_into_blocks(&self) -> bool {
+        false
+    }
+}
@@ -0,0 +1,157 @@
+use crate::prelude::*;
+use crate::{Gate, QuantumCircuit};
+
+// ============================================================================
+// TEXT CIRCUIT DRAWINGS
+// ============================================================================
+
+/// Number of leading control qubits of the standard controlled gates.
+fn controls(name: &str) -> usize {
+    match name {
+        "cx" | "cy" | "cz" | "ch" | "crx" | "cry" | "crz" | "cp" | "cu1" | "cu3" | "cswap" => 1,
+        "ccx" => 2,
+        _ => 0,
+    }
+}
+
+/// What `gate` shows on its `position`-th qubit: `*` for a control, `x` for
+/// either end of a swap, else the gate's name with its parameters.
+fn label(gate: &Gate, position: usize) -> String {
+    let c = controls(&gate.name);
+    if position < c {
+        return "*".to_string();
+    }
+    if gate.name.ends_with("swap") {
+        return "x".to_string();
+    }
+    let name = gate.name[c..].to_uppercase();
+    if gate.params.is_empty() {
+        return name;
+    }
+    let params: Vec<String> = gate.params.iter().map(|p| p.to_string()).collect();
+    format!("{name}({})", params.join(","))
+}
+
+/// `text` centred in `width` characters of `fill`.
+fn centred(text: &str, width: usize, fill: char) -> String {
+    let len = text.chars().count();
+    let left = (width - len) / 2;
+    let mut out: String = core::iter::repeat_n(fill, left).collect();
+    out.push_str(text);
+    out.extend(core::iter::repeat_n(fill, width - len - left));
+    out
+}
+
+/// Draws `circuit` as text, one wire per qubit and gates as early as the
+/// wires they cross allow. A vertical line joins the qubits of a multi-qubit
+/// gate; gates without qubits, such as a global barrier, cross every wire.
+///
+/// ```text
+/// q0: -H--*-
+///         |
+/// q1: ----X-
+/// ```
+pub fn draw(circuit: &QuantumCircuit) -> String {
+    let n = circuit.num_qubits;
+    if n == 0 {
+        return String::new();
+    }
+
+    // Columns of gates, each gate in the first column free on every wire
+    // from its lowest qubit to its highest.
+    let mut next_free = vec![0usize; n];
+    let mut columns: Vec<Vec<&Gate>> = Vec::new();
+    for g in &circuit.gates {
+        let lo = g.qubits.iter().copied().min().unwrap_or(0).min(n - 1);
+        let hi = g.qubits.iter().copied().max().unwrap_or(n - 1).min(n - 1);
+        let column = next_free[lo..=hi].iter().copied().max().unwrap_or(0);
+        next_free[lo..=hi].fill(column + 1);
+        if column == columns.len() {
+            columns.push(Vec::new());
+        }
+        columns[column].push(g);
+    }
+
+    let prefix: Vec<String> = (0..n).map(|q| format!("q{q}: ")).collect();
+    let indent = prefix.iter().map(String::len).max().unwrap_or(0);
+    let mut wires: Vec<String> = prefix.iter().map(|p| format!("{p:indent$}")).collect();
+    let mut gaps: Vec<String> = vec![" ".repeat(indent); n - 1];
+    for column in &columns {
+        let mut cells: Vec<Option<String>> = vec![None; n];
+        let mut crossed = vec![false; n];
+        for g in column {
+            let lo = g.qubits.iter().copied().min().unwrap_or(0).min(n - 1);
+            let hi = g.qubits.iter().copied().max().unwrap_or(n - 1).min(n - 1);
+            for q in lo..=hi {
+                match g.qubits.iter().position(|&p| p == q) {
+                    Some(position) => cells[q] = Some(label(g, position)),
+                    None => crossed[q] = true,
+                }
+            }
+        }
+        let width = cells
+            .iter()
+            .flatten()
+            .map(|c| c.chars().count())
+            .max()
+            .unwrap_or(1);
+        for (q, wire) in wires.iter_mut().enumerate() {
+            let cell = match &cells[q] {
+                Some(label) => label.as_str(),
+                None if crossed[q] => "|",
+                None => "-",
+            };
+            wire.push('-');
+            wire.push_str(&centred(cell, width, '-'));
+            wire.push('-');
+        }
+        for (q, gap) in gaps.iter_mut().enumerate() {
+            let joined = column.iter().any(|g| {
+                let lo = g.qubits.iter().copied().min().unwrap_or(0);
+                let hi = g.qubits.iter().copied().max().unwrap_or(n - 1);
+                lo <= q && q < hi
+            });
+            gap.push(' ');
+            gap.push_str(&centred(if joined { "|" } else { " " }, width, ' '));
+            gap.push(' ');
+        }
+    }
+
+    let mut lines = Vec::with_capacity(2 * n - 1);
+    for (q, wire) in wires.into_iter().enumerate() {
+        if q > 0 {
+            lines.push(gaps[q - 1].trim_end().to_string());
+        }
+        lines.push(wire);
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Param;
+
+    #[test]
+    fn bell_pair() {
+        let circuit = QuantumCircuit::new(2, 0).with_gates(vec![
+            Gate::new("h", vec![0], vec![]),
+            Gate::new("cx", vec![0, 1], vec![]),
+        ]);
+        assert_eq!(draw(&circuit), "q0: -H--*-\n        |\nq1: ----X-");
+    }
+
+    #[test]
+    fn gates_spanning_a_wire_cross_it_in_their_own_column() {
+        let circuit = QuantumCircuit::new(3, 0).with_gates(vec![
+            Gate::new("rz", vec![1], vec![Param::Value(0.5)]),
+            Gate::new("cz", vec![2, 0], vec![]),
+        ]);
+        let drawing = draw(&circuit);
+        let lines: Vec<&str> = drawing.lines().collect();
+        assert_eq!(lines.len(), 5);
+        assert_eq!(lines[0], "q0: ----------Z-");
+        assert_eq!(lines[2], "q1: -RZ(0.5)--|-");
+        assert_eq!(lines[4], "q2: ----------*-");
+    }
+}